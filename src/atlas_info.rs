@@ -1,3 +1,4 @@
+use crate::telemetry::{self, log};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
@@ -35,16 +36,28 @@ pub struct AtlasInfo {
 }
 
 impl AtlasInfo {
+    fn read_frames() -> io::Result<FrameJson> {
+        let s = fs::read_to_string(Path::new("assets/atlas.json"))?;
+        serde_json::from_str(&s).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Without the file every sprite is drawn from the corner of the atlas
     pub fn load_from_file() -> AtlasInfo {
-        let s = fs::read_to_string(Path::new("assets/atlas.json")).unwrap();
-        let file_content: FrameJson = serde_json::from_str(&s)
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
-            .unwrap();
+        let file_content = Self::read_frames().unwrap_or_else(|error| {
+            telemetry::record_asset_warning(format!("Could not load assets/atlas.json: {}", error));
+            FrameJson { frames: Vec::new() }
+        });
 
         let mut mapper = HashMap::new();
 
         for frame in file_content.frames {
-            let without_ext = frame.file_name.strip_suffix(".aseprite").unwrap();
+            let Some(without_ext) = frame.file_name.strip_suffix(".aseprite") else {
+                telemetry::record_asset_warning(format!(
+                    "Unknown frame in assets/atlas.json: {}",
+                    frame.file_name
+                ));
+                continue;
+            };
 
             let (name, number) = if without_ext.contains(" ") {
                 without_ext.rsplit_once(' ').unwrap()
@@ -56,7 +69,13 @@ impl AtlasInfo {
             // let (name, number) = without_ext.rsplit_once(' ').unwrap();
             // println!("name: {}, number: {}", name, number);
 
-            let index = number.parse::<i32>().ok().unwrap();
+            let Ok(index) = number.parse::<i32>() else {
+                telemetry::record_asset_warning(format!(
+                    "Unknown frame in assets/atlas.json: {}",
+                    frame.file_name
+                ));
+                continue;
+            };
             mapper.insert(
                 (name.to_string(), index),
                 AtlasRect {
@@ -77,8 +96,8 @@ impl AtlasInfo {
         if let Some(xy) = result {
             xy
         } else {
-            println!("Sprite not found {} {}", sprite, frame_i);
-            println!("Potential sprites {:?}", self.mapper.keys());
+            log!("Sprite not found {} {}", sprite, frame_i);
+            log!("Potential sprites {:?}", self.mapper.keys());
             &AtlasRect {
                 x: 0,
                 y: 0,
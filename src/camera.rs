@@ -1,3 +1,30 @@
+use crate::render::TILE_SIZE;
+use crate::state::{BoundingBox, Pos};
+use rand::Rng;
+
+/// How the camera moves after what it follows, see Camera::follow
+#[derive(Clone, Copy)]
+pub struct FollowParams {
+    // Part of the way to the target that is moved every update, 1.0 snaps to it
+    pub lerp: f32,
+    // Tiles looked ahead per tile per update of horizontal speed, up to max_lookahead tiles
+    pub lookahead: f32,
+    pub max_lookahead: f32,
+    // The target can move this many tiles up and down without the camera following
+    pub dead_zone_height: f32,
+}
+
+impl Default for FollowParams {
+    fn default() -> Self {
+        FollowParams {
+            lerp: 0.1,
+            lookahead: 20.0,
+            max_lookahead: 3.0,
+            dead_zone_height: 3.0,
+        }
+    }
+}
+
 pub struct Camera {
     pub x: f32,
     pub y: f32,
@@ -6,6 +33,15 @@ pub struct Camera {
     pub max_zoom: f32,
     pub screen_w: f32,
     pub screen_h: f32,
+    pub follow_params: FollowParams,
+    // The view jumps around by up to shake_amount tiles, fading out over the shake frames
+    shake_amount: f32,
+    shake_frames: u32,
+    shake_frames_total: u32,
+    // During a slide the view eases from slide_from (x, y, zoom) to the target in slide_frames
+    slide_from: (f32, f32, f32),
+    slide_frame: u32,
+    slide_frames: u32,
 }
 
 #[derive(Debug)]
@@ -30,6 +66,13 @@ impl Camera {
             max_zoom: 16.0,
             screen_w: width,
             screen_h: height,
+            follow_params: FollowParams::default(),
+            shake_amount: 0.0,
+            shake_frames: 0,
+            shake_frames_total: 0,
+            slide_from: (x, y, zoom),
+            slide_frame: 0,
+            slide_frames: 0,
         }
     }
 
@@ -40,10 +83,150 @@ impl Camera {
 
     pub fn slowly_follow(&mut self, x: f32, y: f32, zoom: f32) {
         let target_zoom = zoom.clamp(self.min_zoom, self.max_zoom);
+        if self.slide_frame < self.slide_frames {
+            self.slide_frame += 1;
+            let t = self.slide_frame as f32 / self.slide_frames as f32;
+            let t = t * t * (3.0 - 2.0 * t);
+            let (from_x, from_y, from_zoom) = self.slide_from;
+            self.x = from_x + (x - from_x) * t;
+            self.y = from_y + (y - from_y) * t;
+            self.zoom = from_zoom + (target_zoom - from_zoom) * t;
+            return;
+        }
+        let lerp = self.follow_params.lerp;
+
+        self.x += (x - self.x) * lerp;
+        self.y += (y - self.y) * lerp;
+        self.zoom += (target_zoom - self.zoom) * lerp;
+    }
+
+    /// Follows something moving at vx, looking ahead to where it is going. Small moves up and
+    /// down (like jumps) stay inside the dead zone and do not move the camera. The view is kept
+    /// inside the bounds, when they change the camera moves over smoothly.
+    pub fn follow(&mut self, x: f32, y: f32, vx: f32, zoom: f32, bounds: Option<&BoundingBox>) {
+        let params = self.follow_params;
+        let target_x =
+            x + (vx * params.lookahead).clamp(-params.max_lookahead, params.max_lookahead);
+        let half_dead_zone = params.dead_zone_height * 0.5;
+        let target_y = y.clamp(self.y - half_dead_zone, self.y + half_dead_zone);
+        let target_y = self.y + (y - target_y);
+
+        let zoom = zoom.clamp(self.min_zoom, self.max_zoom);
+        let (target_x, target_y) = match bounds {
+            Some(bounds) => self.clamp_view(target_x, target_y, zoom, bounds),
+            None => (target_x, target_y),
+        };
+        self.slowly_follow(target_x, target_y, zoom);
+    }
+
+    /// Frames all the targets (like the player and a boss) with a margin of tiles around them.
+    /// Zooms out as far as needed to fit them but not closer than max_zoom, the view is kept
+    /// inside the bounds.
+    pub fn follow_targets(
+        &mut self,
+        targets: &[Pos],
+        margin: f32,
+        max_zoom: f32,
+        bounds: Option<&BoundingBox>,
+    ) {
+        let Some(first) = targets.first() else {
+            return;
+        };
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (first.x, first.y, first.x, first.y);
+        for target in &targets[1..] {
+            min_x = min_x.min(target.x);
+            min_y = min_y.min(target.y);
+            max_x = max_x.max(target.x);
+            max_y = max_y.max(target.y);
+        }
+        let (w, h) = (max_x - min_x + margin * 2.0, max_y - min_y + margin * 2.0);
+        let zoom = (self.screen_w / (w * TILE_SIZE))
+            .min(self.screen_h / (h * TILE_SIZE))
+            .min(max_zoom)
+            .clamp(self.min_zoom, self.max_zoom);
+        let (x, y) = ((min_x + max_x) * 0.5, (min_y + max_y) * 0.5);
+        let (x, y) = match bounds {
+            Some(bounds) => self.clamp_view(x, y, zoom, bounds),
+            None => (x, y),
+        };
+        self.slowly_follow(x, y, zoom);
+    }
+
+    // The closest center for the view at the zoom that keeps it inside the bounds, centered on
+    // an axis where the bounds are smaller than the view
+    fn clamp_view(&self, x: f32, y: f32, zoom: f32, bounds: &BoundingBox) -> (f32, f32) {
+        let half_w = self.screen_w * 0.5 / zoom / TILE_SIZE;
+        let half_h = self.screen_h * 0.5 / zoom / TILE_SIZE;
+        let clamp_axis = |center: f32, min: f32, size: f32, half_view: f32| {
+            if size <= half_view * 2.0 {
+                min + size * 0.5
+            } else {
+                center.clamp(min + half_view, min + size - half_view)
+            }
+        };
+        (
+            clamp_axis(x, bounds.x, bounds.w, half_w),
+            clamp_axis(y, bounds.y, bounds.h, half_h),
+        )
+    }
+
+    /// The camera eases over to what it follows in the given frames instead of lerping, for
+    /// when the target jumps like when moving to another room
+    pub fn slide(&mut self, frames: u32) {
+        self.slide_from = (self.x, self.y, self.zoom);
+        self.slide_frame = 0;
+        self.slide_frames = frames;
+    }
+
+    // Moves the camera on its own, dx and dy are -1, 0 or 1
+    pub fn free_fly(&mut self, dx: f32, dy: f32, tiles_per_second: f32, dt: f32) {
+        self.x += dx * tiles_per_second * dt;
+        self.y += dy * tiles_per_second * dt;
+    }
+
+    /// Shakes the view by up to amount tiles for the frames, a weaker shake does not cut a
+    /// stronger one short
+    pub fn shake(&mut self, amount: f32, frames: u32) {
+        if amount >= self.current_shake_amount() {
+            self.shake_amount = amount;
+            self.shake_frames = frames;
+            self.shake_frames_total = frames;
+        }
+    }
 
-        self.x += (x - self.x) * 0.10;
-        self.y += (y - self.y) * 0.10;
-        self.zoom += (target_zoom - self.zoom) * 0.10;
+    /// Call once per game update
+    pub fn update_shake(&mut self) {
+        self.shake_frames = self.shake_frames.saturating_sub(1);
+    }
+
+    fn current_shake_amount(&self) -> f32 {
+        if self.shake_frames == 0 {
+            return 0.0;
+        }
+        self.shake_amount * self.shake_frames as f32 / self.shake_frames_total as f32
+    }
+
+    /// Random offset in tiles to add to the view while shaking
+    pub fn shake_offset(&self) -> (f32, f32) {
+        let amount = self.current_shake_amount();
+        if amount == 0.0 {
+            return (0.0, 0.0);
+        }
+        let mut rng = rand::rng();
+        (
+            rng.random_range(-amount..amount),
+            rng.random_range(-amount..amount),
+        )
+    }
+
+    /// If any of the bounding box is on the screen, or within margin tiles of it
+    pub fn sees(&self, bb: &BoundingBox, margin: f32) -> bool {
+        let half_w = self.screen_w * 0.5 / self.zoom / TILE_SIZE + margin;
+        let half_h = self.screen_h * 0.5 / self.zoom / TILE_SIZE + margin;
+        bb.x + bb.w > self.x - half_w
+            && bb.x < self.x + half_w
+            && bb.y + bb.h > self.y - half_h
+            && bb.y < self.y + half_h
     }
 
     pub fn set_zoom(&mut self, zoom: f32) {
@@ -78,6 +261,19 @@ impl Camera {
         MouseCoords { x: tx, y: ty }
     }
 
+    pub fn tile_to_screen(&self, x: f32, y: f32) -> (f32, f32) {
+        // Keep this in sync with TILE_SIZE used in rendering.
+        const TILE_SIZE: f32 = 16.0;
+
+        // The View transform used in rendering, the inverse of screen_to_tile
+        let snapped_cx = (self.x * TILE_SIZE * self.zoom).round() / self.zoom;
+        let snapped_cy = (self.y * TILE_SIZE * self.zoom).round() / self.zoom;
+        (
+            (x * TILE_SIZE - snapped_cx) * self.zoom + self.screen_w * 0.5,
+            (y * TILE_SIZE - snapped_cy) * self.zoom + self.screen_h * 0.5,
+        )
+    }
+
     pub fn zoom_to_fit_horizontal_tiles(&self, tiles: u32) -> f32 {
         // Keep this in sync with TILE_SIZE used in rendering.
         const TILE_SIZE: f32 = 16.0;
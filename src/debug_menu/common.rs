@@ -1,8 +1,19 @@
+use super::console::ConsoleCommand;
+use super::thumbnails::RoomThumbnails;
+use crate::net::LockstepSession;
+use crate::sound_handler::{SoundCategory, SoundHandler};
 use crate::state::GameState;
+use crate::state::InputState;
+use crate::state::abilities::Ability;
+use crate::state::bot::Bot;
+use crate::state::desync::DesyncChecker;
+use crate::state::game_state::Game;
 use crate::state::map_like::Room;
+use crate::tuning::{self, tuning_mut};
 use egui::Ui;
+use miniquad::date;
 
-use crate::camera::MouseCoords;
+use crate::camera::{Camera, MouseCoords};
 
 #[derive(Debug, Eq, PartialEq)]
 pub enum TileSelection {
@@ -13,6 +24,29 @@ pub enum TileSelection {
     Ladder,
     Platform,
     StartDoor,
+    Spikes,
+    HiddenWall,
+    SlopeUpRight,
+    SlopeUpLeft,
+    Cracked,
+    LockedDoor,
+    DashBarrier,
+    BombWall,
+    Torch,
+    Grass,
+    Vine,
+}
+
+// How the selected tile is painted
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum TileTool {
+    // Every tile the mouse is dragged over
+    Brush,
+    // The tiles between where the drag started and where it is released
+    Rectangle,
+    Line,
+    // The tiles like the clicked one that are connected to it
+    Fill,
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -22,14 +56,32 @@ pub enum EnemySelection {
     Slime,
     Worm,
     Burrower,
+    BatFlock,
+    ShieldBearer,
+    Archer,
+    Boss,
+    Wisp,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum ItemSelection {
+    Remove,
+    Box,
+    Vase,
+    ShopSlot,
+    Checkpoint,
+    Key,
+    Ability(Ability),
+    Coin,
+    SmallStone,
+    LargeStone,
+    TrainingSwitch,
 }
 
 #[derive(Debug, Eq, PartialEq)]
 pub enum DoorSelection {
-    Left,
-    Right,
-    Up,
-    Down,
+    // The direction comes from the edge of the room the door is placed on
+    Door,
     LevelStart,
     LevelEnd,
     Remove,
@@ -38,12 +90,16 @@ pub enum DoorSelection {
 pub enum EditorSelection {
     Tiles {
         selection: TileSelection,
+        tool: TileTool,
     },
     Enemies {
         snap_bottom: bool,
         snap_top: bool,
         selection: EnemySelection,
     },
+    Items {
+        selection: ItemSelection,
+    },
     PlayerPos,
     Doors {
         selection: DoorSelection,
@@ -53,12 +109,49 @@ pub enum EditorSelection {
 pub struct DebugMenu {
     pub editor_selection: EditorSelection,
     pub all_rooms: Vec<(String, Room)>,
+    pub room_thumbnails: RoomThumbnails,
     pub current_editor_room_index: u32,
     pub is_game: bool,
     pub zoom_show_full: bool,
     pub show_dark: bool,
+    // Fixed simulation rate and how fast the simulation runs compared to real time
+    pub updates_per_second: f64,
+    pub time_scale: f64,
+    // The camera is detached from the player and moved with the arrow keys or WASD
+    pub free_camera: bool,
+    pub free_camera_speed: f32,
+    // The next click in the game moves the player there instead of pinging
+    pub teleport_on_click: bool,
+    // Index in to the spawn palette, clicking in the game spawns it while one is picked
+    pub spawn_selection: Option<usize>,
+    pub spawn_count: u32,
+    pub spawn_elite: bool,
+    // Saved copy of the whole game, restored as many times as needed
+    pub game_snapshot: Option<Box<Game>>,
+    pub desync_checker: Option<DesyncChecker>,
+    // Plays the game instead of the keyboard, for leaving the game running in soak tests
+    pub bot: Option<Bot>,
+    pub net_session: Option<LockstepSession>,
+    pub net_port: u16,
+    pub net_address: String,
+    pub net_error: Option<String>,
+    // Index in to the doors of the edited room, its matches in the other rooms are listed
+    pub selected_door: Option<usize>,
+    // How many of the attempts of the map generator the generation view shows
+    pub generation_step: usize,
+    // Result of the last write of the tuning file
+    pub tuning_status: Option<String>,
+    // Lists the latest sounds and mutes groups of them, for finding sounds that play too often
+    // or have no files
+    pub show_sound_overlay: bool,
+    // Short message shown at the bottom of the screen and when it goes away
+    toast: Option<(String, f64)>,
 }
 
+pub const FAST_FORWARD_TIME_SCALE: f64 = 4.0;
+const TOAST_SECONDS: f64 = 3.0;
+pub const SLOW_MOTION_TIME_SCALE: f64 = 0.25;
+
 impl DebugMenu {
     pub fn new() -> DebugMenu {
         let all_rooms = Room::load_rooms_from_folder();
@@ -66,18 +159,285 @@ impl DebugMenu {
         DebugMenu {
             editor_selection: EditorSelection::Tiles {
                 selection: TileSelection::Clear,
+                tool: TileTool::Brush,
             },
             all_rooms,
+            room_thumbnails: RoomThumbnails::default(),
             current_editor_room_index: 0,
             is_game: true,
             zoom_show_full: true,
             show_dark: true,
+            updates_per_second: 60.0,
+            time_scale: 1.0,
+            free_camera: false,
+            free_camera_speed: 30.0,
+            teleport_on_click: false,
+            spawn_selection: None,
+            spawn_count: 1,
+            spawn_elite: false,
+            game_snapshot: None,
+            desync_checker: None,
+            bot: None,
+            net_session: None,
+            net_port: 7777,
+            net_address: String::from("127.0.0.1:7777"),
+            net_error: None,
+            selected_door: None,
+            generation_step: 0,
+            tuning_status: None,
+            show_sound_overlay: false,
+            toast: None,
+        }
+    }
+
+    pub fn show_toast(&mut self, message: impl Into<String>) {
+        self.toast = Some((message.into(), date::now() + TOAST_SECONDS));
+    }
+
+    pub fn render_toast(&mut self, egui_ctx: &egui::Context) {
+        let Some((message, until)) = &self.toast else {
+            return;
+        };
+        if date::now() > *until {
+            self.toast = None;
+            return;
+        }
+        egui::Area::new(egui::Id::new("toast"))
+            .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -24.0])
+            .interactable(false)
+            .show(egui_ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(message.as_str());
+                });
+            });
+    }
+
+    pub fn render_sound_overlay(&mut self, egui_ctx: &egui::Context, sound_handler: &SoundHandler) {
+        if !self.show_sound_overlay {
+            return;
+        }
+        let now = date::now();
+        egui::Window::new("Sounds")
+            .open(&mut self.show_sound_overlay)
+            .default_width(360.0)
+            .show(egui_ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Mute:");
+                    for category in SoundCategory::ALL {
+                        let mut muted = sound_handler.is_category_muted(category);
+                        if ui.checkbox(&mut muted, format!("{:?}", category)).changed() {
+                            sound_handler.set_category_muted(category, muted);
+                        }
+                    }
+                });
+                ui.separator();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("recent_sounds")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for played in sound_handler.recent_sounds().iter().rev() {
+                                ui.label(format!("{:.2}s ago", now - played.time));
+                                if played.count > 1 {
+                                    ui.label(format!("{:?} x{}", played.sound, played.count));
+                                } else {
+                                    ui.label(format!("{:?}", played.sound));
+                                }
+                                match played.variant {
+                                    Some((index, count)) => {
+                                        ui.label(format!("variant {}/{}", index + 1, count))
+                                    }
+                                    None => ui.colored_label(egui::Color32::YELLOW, "no files"),
+                                };
+                                ui.label(format!("volume {:.2}", played.volume));
+                                match played.pos {
+                                    Some(pos) => ui.label(format!("({:.1}, {:.1})", pos.x, pos.y)),
+                                    None => ui.label(""),
+                                };
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+    }
+
+    // Pressing the hotkey of the active time scale again goes back to normal speed
+    pub fn toggle_time_scale(&mut self, time_scale: f64) {
+        if self.time_scale == time_scale {
+            self.time_scale = 1.0;
+        } else {
+            self.time_scale = time_scale;
         }
     }
+
+    pub fn render_camera_ui(&mut self, ui: &mut Ui, camera: &mut Camera) {
+        ui.horizontal(|ui| {
+            ui.add(egui::Checkbox::new(&mut self.free_camera, "Free camera"));
+            ui.add(egui::Label::new("Speed:"));
+            ui.add(
+                egui::DragValue::new(&mut self.free_camera_speed)
+                    .range(1.0..=200.0)
+                    .suffix(" tiles/s"),
+            );
+        });
+        egui::CollapsingHeader::new("Camera follow").show(ui, |ui| {
+            let params = &mut camera.follow_params;
+            ui.add(egui::Slider::new(&mut params.lerp, 0.01..=1.0).text("Lerp"));
+            ui.add(egui::Slider::new(&mut params.lookahead, 0.0..=60.0).text("Lookahead"));
+            ui.add(egui::Slider::new(&mut params.max_lookahead, 0.0..=10.0).text("Max lookahead"));
+            ui.add(
+                egui::Slider::new(&mut params.dead_zone_height, 0.0..=10.0)
+                    .text("Vertical dead zone"),
+            );
+        });
+    }
+
+    // Hosting or joining a lockstep session, both restart the game once the peer is there
+    pub fn render_network_ui(&mut self, ui: &mut Ui) {
+        if let Some(status) = self.net_session.as_ref().map(|session| session.status()) {
+            ui.horizontal(|ui| {
+                ui.add(egui::Label::new(status));
+                if ui.add(egui::Button::new("Disconnect")).clicked() {
+                    self.net_session = None;
+                }
+            });
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            ui.add(egui::Label::new("Port:"));
+            ui.add(egui::DragValue::new(&mut self.net_port));
+            if ui.add(egui::Button::new("Host")).clicked() {
+                match LockstepSession::host(self.net_port) {
+                    Ok(session) => {
+                        self.net_session = Some(session);
+                        self.net_error = None;
+                    }
+                    Err(error) => self.net_error = Some(error.to_string()),
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.net_address).desired_width(120.0));
+            if ui.add(egui::Button::new("Join")).clicked() {
+                match LockstepSession::join(&self.net_address) {
+                    Ok(session) => {
+                        self.net_session = Some(session);
+                        self.net_error = None;
+                    }
+                    Err(error) => self.net_error = Some(error.to_string()),
+                }
+            }
+        });
+        if let Some(error) = &self.net_error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+    }
+
+    // Sliders for the values in crate::tuning, they take effect on the next update
+    pub fn render_tuning_ui(&mut self, ui: &mut Ui) {
+        egui::CollapsingHeader::new("Tuning").show(ui, |ui| {
+            {
+                let mut tuning = tuning_mut();
+                ui.add(
+                    egui::Slider::new(&mut tuning.gravity, 0.001..=0.02)
+                        .text("Gravity")
+                        .max_decimals(4),
+                );
+                ui.add(
+                    egui::Slider::new(&mut tuning.terminal_velocity, 0.1..=2.0)
+                        .text("Terminal velocity"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut tuning.jump_velocity, 0.05..=0.3)
+                        .text("Jump velocity")
+                        .max_decimals(3),
+                );
+                ui.add(
+                    egui::Slider::new(&mut tuning.hang_epsilon, 0.0001..=0.05)
+                        .logarithmic(true)
+                        .text("Hang epsilon")
+                        .max_decimals(4),
+                );
+                ui.add(egui::Checkbox::new(
+                    &mut tuning.hang_needs_grab_key,
+                    "Hang only while holding grab (C)",
+                ));
+                ui.add(
+                    egui::Slider::new(&mut tuning.slime_idle_frames, 0..=600)
+                        .text("Slime idle frames"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut tuning.slime_alerted_idle_frames, 0..=200)
+                        .text("Slime alerted idle frames"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut tuning.slime_jump_velocity, 0.05..=0.5)
+                        .text("Slime jump velocity"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut tuning.slime_jump_speed, 0.0..=0.2)
+                        .text("Slime jump speed"),
+                );
+            }
+            ui.horizontal(|ui| {
+                if ui.add(egui::Button::new("Write to config file")).clicked() {
+                    self.tuning_status = Some(match tuning::save() {
+                        Ok(()) => String::from("Written"),
+                        Err(error) => error.to_string(),
+                    });
+                }
+                if ui.add(egui::Button::new("Reset")).clicked() {
+                    tuning::reset();
+                }
+                if let Some(status) = &self.tuning_status {
+                    ui.label(status);
+                }
+            });
+        });
+    }
+
+    pub fn render_simulation_ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.add(egui::Label::new("Updates per second:"));
+            ui.add(egui::DragValue::new(&mut self.updates_per_second).range(1.0..=240.0));
+        });
+        ui.horizontal(|ui| {
+            ui.add(egui::Label::new("Speed:"));
+            for (time_scale, label) in [
+                (SLOW_MOTION_TIME_SCALE, "0.25x (G)"),
+                (1.0, "1x"),
+                (FAST_FORWARD_TIME_SCALE, "4x (F)"),
+            ] {
+                if ui
+                    .add(egui::Button::new(label).selected(self.time_scale == time_scale))
+                    .clicked()
+                {
+                    self.time_scale = time_scale;
+                }
+            }
+        });
+    }
 }
 
 pub trait GameStateDebugMenu: GameState {
     fn mouse_button_event(&mut self, coords: MouseCoords, stage: &mut DebugMenu);
     fn render_ui(&mut self, ui: &mut Ui, stage: &mut DebugMenu);
     fn mouse_drawing(&mut self, coords: MouseCoords, debug_menu: &DebugMenu);
+    fn mouse_released(&mut self, debug_menu: &DebugMenu);
+    fn teleport_player(&mut self, coords: MouseCoords);
+    // The input from the bot when it is playing
+    fn bot_input(&self, debug_menu: &mut DebugMenu) -> Option<InputState>;
+    // Called after every update with the input the update got
+    fn check_desync(
+        &self,
+        input: &InputState,
+        sound_handler: &SoundHandler,
+        debug_menu: &mut DebugMenu,
+    );
+    // Returns what is printed in the console
+    fn run_console_command(
+        &mut self,
+        command: ConsoleCommand,
+        debug_menu: &mut DebugMenu,
+    ) -> Result<String, String>;
 }
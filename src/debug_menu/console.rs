@@ -0,0 +1,262 @@
+use super::common::{DebugMenu, GameStateDebugMenu};
+use super::game::{MAX_SPAWN_COUNT, spawnable_names};
+use egui::text::{CCursor, CCursorRange};
+use egui::{Key, Modifiers};
+
+// Lines of output kept, the oldest are dropped
+const OUTPUT_LINES: usize = 200;
+const COMMANDS: &[&str] = &[
+    "help",
+    "clear",
+    "give",
+    "spawn",
+    "goto",
+    "seed",
+    "timescale",
+    "noclip",
+    "god",
+];
+const HELP: &[&str] = &[
+    "give coins <amount>",
+    "spawn <name> [count]",
+    "goto <room number or file name>, like room_0007",
+    "seed <seed>, restarts the game from the seed",
+    "timescale <scale>",
+    "noclip, god",
+    "clear",
+];
+
+/// A console command that is run by the game state, see GameStateDebugMenu::run_console_command
+pub enum ConsoleCommand {
+    GiveCoins(u32),
+    Spawn { name: String, count: u32 },
+    // A room index in the map or the file name of a room placed in it
+    Goto(String),
+    Seed(u64),
+    Noclip,
+    God,
+}
+
+enum Command {
+    Help,
+    Clear,
+    TimeScale(f64),
+    State(ConsoleCommand),
+}
+
+fn parse_arg<T: std::str::FromStr>(arg: Option<&str>, what: &str) -> Result<T, String> {
+    let arg = arg.ok_or_else(|| format!("Missing the {}", what))?;
+    arg.parse()
+        .map_err(|_| format!("{} is not a valid {}", arg, what))
+}
+
+fn parse(line: &str) -> Result<Command, String> {
+    let mut words = line.split_whitespace();
+    let command = match words.next().unwrap_or("") {
+        "help" => Command::Help,
+        "clear" => Command::Clear,
+        "timescale" => {
+            let time_scale: f64 = parse_arg(words.next(), "time scale")?;
+            // NaN would get through the clamp and stop the game from updating
+            if !time_scale.is_finite() {
+                return Err(format!("{} is not a valid time scale", time_scale));
+            }
+            Command::TimeScale(time_scale)
+        }
+        "give" => match words.next() {
+            Some("coins") => Command::State(ConsoleCommand::GiveCoins(parse_arg(
+                words.next(),
+                "amount",
+            )?)),
+            _ => return Err(String::from("Only coins can be given")),
+        },
+        "spawn" => {
+            let name = parse_arg(words.next(), "name")?;
+            let count = match words.next() {
+                Some(count) => parse_arg::<u32>(Some(count), "count")?.clamp(1, MAX_SPAWN_COUNT),
+                None => 1,
+            };
+            Command::State(ConsoleCommand::Spawn { name, count })
+        }
+        "goto" => Command::State(ConsoleCommand::Goto(parse_arg(words.next(), "room")?)),
+        "seed" => Command::State(ConsoleCommand::Seed(parse_arg(words.next(), "seed")?)),
+        "noclip" => Command::State(ConsoleCommand::Noclip),
+        "god" => Command::State(ConsoleCommand::God),
+        other => return Err(format!("Unknown command {}, try help", other)),
+    };
+    match words.next() {
+        Some(extra) => Err(format!("Unexpected {}", extra)),
+        None => Ok(command),
+    }
+}
+
+// The longest start that all of them share
+fn common_prefix(words: &[String]) -> String {
+    let mut prefix = words[0].clone();
+    for word in &words[1..] {
+        while !word.starts_with(&prefix) {
+            prefix.pop();
+        }
+    }
+    prefix
+}
+
+// Toggled with the key under escape. Commands go through the same game and debug menu state as
+// the debug window, tab completes the word being typed and up and down go through the history.
+#[derive(Default)]
+pub struct Console {
+    pub open: bool,
+    input: String,
+    output: Vec<String>,
+    history: Vec<String>,
+    // Set while going through the history with the arrow keys
+    history_index: Option<usize>,
+    focus: bool,
+}
+
+impl Console {
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.focus = self.open;
+    }
+
+    fn print(&mut self, line: String) {
+        if self.output.len() == OUTPUT_LINES {
+            self.output.remove(0);
+        }
+        self.output.push(line);
+    }
+
+    fn complete(&mut self) {
+        let words: Vec<&str> = self.input.split(' ').collect();
+        let (typed, candidates): (&str, Vec<String>) = match words.as_slice() {
+            [command] => (command, COMMANDS.iter().map(|c| c.to_string()).collect()),
+            ["spawn", name] => (name, spawnable_names().collect()),
+            ["give", what] => (what, vec![String::from("coins")]),
+            _ => return,
+        };
+        let matches: Vec<String> = candidates
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(typed))
+            .collect();
+        if matches.is_empty() {
+            return;
+        }
+        let start = self.input.len() - typed.len();
+        self.input.truncate(start);
+        if let [word] = matches.as_slice() {
+            self.input.push_str(word);
+            self.input.push(' ');
+        } else {
+            self.input.push_str(&common_prefix(&matches));
+            self.print(matches.join("  "));
+        }
+    }
+
+    fn browse_history(&mut self, back: bool) {
+        let index = match (self.history_index, back) {
+            (None, true) => self.history.len().checked_sub(1),
+            (None, false) => None,
+            (Some(index), true) => Some(index.saturating_sub(1)),
+            (Some(index), false) => Some(index + 1).filter(|&index| index < self.history.len()),
+        };
+        self.history_index = index;
+        self.input = index.map_or(String::new(), |index| self.history[index].clone());
+    }
+
+    fn run<S: GameStateDebugMenu + ?Sized>(&mut self, state: &mut S, debug_menu: &mut DebugMenu) {
+        let line = std::mem::take(&mut self.input).trim().to_string();
+        self.history_index = None;
+        if line.is_empty() {
+            return;
+        }
+        self.print(format!("> {}", line));
+        if self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+        let result = match parse(&line) {
+            Ok(Command::Help) => {
+                for help in HELP {
+                    self.print(help.to_string());
+                }
+                Ok(String::new())
+            }
+            Ok(Command::Clear) => {
+                self.output.clear();
+                Ok(String::new())
+            }
+            Ok(Command::TimeScale(time_scale)) => {
+                debug_menu.time_scale = time_scale.clamp(0.01, 16.0);
+                Ok(format!("Time scale {}", debug_menu.time_scale))
+            }
+            Ok(Command::State(command)) => state.run_console_command(command, debug_menu),
+            Err(error) => Err(error),
+        };
+        match result {
+            Ok(message) if message.is_empty() => {}
+            Ok(message) => self.print(message),
+            Err(error) => self.print(format!("Error: {}", error)),
+        }
+    }
+
+    pub fn show<S: GameStateDebugMenu + ?Sized>(
+        &mut self,
+        egui_ctx: &egui::Context,
+        state: &mut S,
+        debug_menu: &mut DebugMenu,
+    ) {
+        if !self.open {
+            return;
+        }
+        egui::TopBottomPanel::top("console").show(egui_ctx, |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(200.0)
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    ui.set_width(ui.available_width());
+                    for line in &self.output {
+                        ui.monospace(line);
+                    }
+                });
+
+            let id = egui::Id::new("console_input");
+            // Taken before the text field sees them
+            if ui.memory(|memory| memory.has_focus(id)) {
+                let input_before = self.input.clone();
+                if ui.input_mut(|input| input.consume_key(Modifiers::NONE, Key::Tab)) {
+                    self.complete();
+                }
+                if ui.input_mut(|input| input.consume_key(Modifiers::NONE, Key::ArrowUp)) {
+                    self.browse_history(true);
+                }
+                if ui.input_mut(|input| input.consume_key(Modifiers::NONE, Key::ArrowDown)) {
+                    self.browse_history(false);
+                }
+                if self.input != input_before
+                    && let Some(mut text_state) = egui::TextEdit::load_state(ui.ctx(), id)
+                {
+                    let end = CCursor::new(self.input.chars().count());
+                    text_state
+                        .cursor
+                        .set_char_range(Some(CCursorRange::one(end)));
+                    text_state.store(ui.ctx(), id);
+                }
+            }
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.input)
+                    .id(id)
+                    .font(egui::TextStyle::Monospace)
+                    // Keeps tab from moving the focus, it completes instead
+                    .lock_focus(true)
+                    .desired_width(f32::INFINITY),
+            );
+            if response.lost_focus() && ui.input(|input| input.key_pressed(Key::Enter)) {
+                self.run(state, debug_menu);
+                self.focus = true;
+            }
+            if std::mem::take(&mut self.focus) {
+                response.request_focus();
+            }
+        });
+    }
+}
@@ -1,71 +1,76 @@
 use super::common::{
-    DebugMenu, DoorSelection, EditorSelection, EnemySelection, GameStateDebugMenu, TileSelection,
+    DebugMenu, DoorSelection, EditorSelection, EnemySelection, GameStateDebugMenu, ItemSelection,
+    TileSelection, TileTool,
 };
+use super::console::ConsoleCommand;
 use crate::physics::EPS;
+use crate::render::BIOME_BACKGROUNDS;
+use crate::sound_handler::SoundHandler;
+use crate::state::abilities::Ability;
 use crate::state::game_state::{Editor, GameState};
-use crate::state::map_like::{DoorDir, MapLike, ObjectTemplate, ObjectTemplateType, Room};
-use crate::state::{BaseTile, OverlayTile};
+use crate::state::map_like::{
+    ItemTemplate, ItemTemplateType, MapLike, ObjectTemplate, ObjectTemplateType, Room,
+};
+use crate::state::{BaseTile, InputState, OverlayTile, Pos};
 use egui::Ui;
+use std::collections::HashSet;
 use std::fs;
 use std::path::Path;
 
 use crate::camera::MouseCoords;
 
 impl GameStateDebugMenu for Editor {
-    fn mouse_drawing(&mut self, coords: MouseCoords, debug_menu: &DebugMenu) {
-        let coords = coords.as_i();
-        if let EditorSelection::Tiles { selection } = &debug_menu.editor_selection {
-            match &selection {
-                TileSelection::NotPartOf => {
-                    self.map_mut()
-                        .set_base(coords.0, coords.1, BaseTile::NotPartOfRoom);
-                    self.map_mut()
-                        .set_overlay(coords.0, coords.1, OverlayTile::None);
-                }
-                TileSelection::Clear => {
-                    self.map_mut().set_base(coords.0, coords.1, BaseTile::Empty);
-                    self.map_mut()
-                        .set_overlay(coords.0, coords.1, OverlayTile::None);
-                }
-                TileSelection::Ladder => {
-                    self.map_mut().set_base(coords.0, coords.1, BaseTile::Empty);
+    fn teleport_player(&mut self, _coords: MouseCoords) {}
 
-                    let tile_to_set = match self.map().get_at(coords.0, coords.1) {
-                        (_, OverlayTile::Platform) => OverlayTile::LadderPlatform,
-                        _ => OverlayTile::Ladder,
-                    };
+    fn bot_input(&self, _debug_menu: &mut DebugMenu) -> Option<InputState> {
+        None
+    }
 
-                    self.map_mut().set_overlay(coords.0, coords.1, tile_to_set);
-                }
-                TileSelection::Platform => {
-                    self.map_mut().set_base(coords.0, coords.1, BaseTile::Empty);
+    fn check_desync(
+        &self,
+        _input: &InputState,
+        _sound_handler: &SoundHandler,
+        _debug_menu: &mut DebugMenu,
+    ) {
+    }
 
-                    let tile_to_set = match self.map().get_at(coords.0, coords.1) {
-                        (_, OverlayTile::Ladder) => OverlayTile::LadderPlatform,
-                        _ => OverlayTile::Platform,
-                    };
+    fn run_console_command(
+        &mut self,
+        _command: ConsoleCommand,
+        _debug_menu: &mut DebugMenu,
+    ) -> Result<String, String> {
+        Err(String::from("Only works in the game"))
+    }
 
-                    self.map_mut().set_overlay(coords.0, coords.1, tile_to_set);
-                }
-                TileSelection::Stone => {
-                    self.map_mut().set_base(coords.0, coords.1, BaseTile::Stone);
-                    self.map_mut()
-                        .set_overlay(coords.0, coords.1, OverlayTile::None);
-                }
-                TileSelection::Wood => {
-                    self.map_mut().set_base(coords.0, coords.1, BaseTile::Wood);
-                    self.map_mut()
-                        .set_overlay(coords.0, coords.1, OverlayTile::None);
-                }
-                TileSelection::StartDoor => {
-                    self.map_mut().set_base(coords.0, coords.1, BaseTile::Empty);
-                    self.map_mut()
-                        .set_overlay(coords.0, coords.1, OverlayTile::StartDoor);
+    fn mouse_drawing(&mut self, coords: MouseCoords, debug_menu: &DebugMenu) {
+        let coords = coords.as_i();
+        if let EditorSelection::Tiles { selection, tool } = &debug_menu.editor_selection {
+            match tool {
+                TileTool::Brush => self.paint_tile(selection, coords.0, coords.1),
+                TileTool::Rectangle | TileTool::Line | TileTool::Fill => {
+                    let start = *self.tile_drag_start.get_or_insert(coords);
+                    self.tile_preview = match tool {
+                        TileTool::Rectangle => rectangle_tiles(start, coords),
+                        TileTool::Line => line_tiles(start, coords),
+                        _ => self.fill_tiles(coords),
+                    };
                 }
             }
         }
     }
 
+    fn mouse_released(&mut self, debug_menu: &DebugMenu) {
+        if self.tile_drag_start.take().is_none() {
+            return;
+        }
+        let tiles = std::mem::take(&mut self.tile_preview);
+        if let EditorSelection::Tiles { selection, .. } = &debug_menu.editor_selection {
+            for (x, y) in tiles {
+                self.paint_tile(selection, x, y);
+            }
+        }
+    }
+
     fn mouse_button_event(&mut self, coords: MouseCoords, stage: &mut DebugMenu) {
         match &stage.editor_selection {
             EditorSelection::PlayerPos => {
@@ -92,6 +97,11 @@ impl GameStateDebugMenu for Editor {
                     EnemySelection::Slime => ObjectTemplateType::Slime,
                     EnemySelection::Worm => ObjectTemplateType::Worm,
                     EnemySelection::Burrower => ObjectTemplateType::Burrower,
+                    EnemySelection::BatFlock => ObjectTemplateType::BatFlock,
+                    EnemySelection::ShieldBearer => ObjectTemplateType::ShieldBearer,
+                    EnemySelection::Archer => ObjectTemplateType::Archer,
+                    EnemySelection::Boss => ObjectTemplateType::Boss,
+                    EnemySelection::Wisp => ObjectTemplateType::Wisp,
                 };
 
                 let template = ObjectTemplate::new(coords.x, coords.y, template_type.clone());
@@ -138,20 +148,44 @@ impl GameStateDebugMenu for Editor {
                     }
                 }
             }
+            EditorSelection::Items { selection } => {
+                let template_type = match selection {
+                    ItemSelection::Remove => {
+                        self.room.item_templates.retain(|template| {
+                            !template.bb().point_inside(&Pos::new(coords.x, coords.y))
+                        });
+                        return;
+                    }
+                    ItemSelection::Box => ItemTemplateType::Box,
+                    ItemSelection::Vase => ItemTemplateType::Vase,
+                    ItemSelection::ShopSlot => ItemTemplateType::ShopSlot,
+                    ItemSelection::Checkpoint => ItemTemplateType::Checkpoint,
+                    ItemSelection::Key => ItemTemplateType::Key,
+                    ItemSelection::Ability(ability) => ItemTemplateType::pickup_of(*ability),
+                    ItemSelection::Coin => ItemTemplateType::Coin,
+                    ItemSelection::SmallStone => ItemTemplateType::SmallStone,
+                    ItemSelection::LargeStone => ItemTemplateType::LargeStone,
+                    ItemSelection::TrainingSwitch => ItemTemplateType::TrainingSwitch,
+                };
+                self.room
+                    .item_templates
+                    .push(ItemTemplate::new(coords.x, coords.y, template_type));
+            }
             EditorSelection::Doors { selection } => {
                 let coords = coords.as_i();
-                for (sel, direction) in [
-                    (DoorSelection::Up, DoorDir::Up),
-                    (DoorSelection::Down, DoorDir::Down),
-                    (DoorSelection::Right, DoorDir::Right),
-                    (DoorSelection::Left, DoorDir::Left),
-                ] {
-                    if *selection == sel {
-                        self.room.set_door(coords.0, coords.1, direction);
-                    }
-                }
                 if *selection == DoorSelection::Remove {
                     self.room.remove_door(coords.0, coords.1);
+                    stage.selected_door = None;
+                } else if *selection == DoorSelection::Door {
+                    match self.room.door_dir_at(coords.0, coords.1) {
+                        Some(direction) => {
+                            self.room.set_door(coords.0, coords.1, direction);
+                            stage.selected_door = Some(self.room.get_doors().len() - 1);
+                        }
+                        None => stage.show_toast(
+                            "Doors go on an edge of the room, not inside it or on a corner",
+                        ),
+                    }
                 }
             }
             EditorSelection::Tiles { .. } => {} // This one is handled in the drawing function
@@ -170,6 +204,7 @@ impl GameStateDebugMenu for Editor {
         {
             stage.editor_selection = EditorSelection::Tiles {
                 selection: TileSelection::Clear,
+                tool: TileTool::Brush,
             };
         }
         if ui
@@ -185,6 +220,17 @@ impl GameStateDebugMenu for Editor {
                 selection: EnemySelection::Bat,
             };
         }
+        if ui
+            .add(egui::RadioButton::new(
+                matches!(stage.editor_selection, EditorSelection::Items { .. }),
+                "Items",
+            ))
+            .clicked()
+        {
+            stage.editor_selection = EditorSelection::Items {
+                selection: ItemSelection::Box,
+            };
+        }
         if ui
             .add(egui::RadioButton::new(
                 matches!(stage.editor_selection, EditorSelection::PlayerPos),
@@ -202,14 +248,30 @@ impl GameStateDebugMenu for Editor {
             .clicked()
         {
             stage.editor_selection = EditorSelection::Doors {
-                selection: DoorSelection::Left,
+                selection: DoorSelection::Door,
             };
         }
 
         let mut new_selection: Option<EditorSelection> = None;
+        let mut open_room: Option<usize> = None;
 
         match &mut stage.editor_selection {
-            EditorSelection::Tiles { selection } => {
+            EditorSelection::Tiles { selection, tool } => {
+                ui.horizontal(|ui| {
+                    for (candidate, label) in [
+                        (TileTool::Brush, "Brush"),
+                        (TileTool::Rectangle, "Rectangle"),
+                        (TileTool::Line, "Line"),
+                        (TileTool::Fill, "Fill"),
+                    ] {
+                        if ui
+                            .add(egui::Button::new(label).selected(*tool == candidate))
+                            .clicked()
+                        {
+                            *tool = candidate;
+                        }
+                    }
+                });
                 ui.add(egui::Label::new("Tile:"));
 
                 ui.horizontal_wrapped(|ui| {
@@ -242,6 +304,14 @@ impl GameStateDebugMenu for Editor {
                             TileSelection::StartDoor,
                             egui::include_image!("../../assets/ui_sprites/start_door.png"),
                         ),
+                        (
+                            TileSelection::Spikes,
+                            egui::include_image!("../../assets/ui_sprites/spikes.png"),
+                        ),
+                        (
+                            TileSelection::HiddenWall,
+                            egui::include_image!("../../assets/ui_sprites/hidden_wall.png"),
+                        ),
                     ] {
                         let image = egui::Image::new(image_source)
                             .fit_to_exact_size(egui::Vec2::new(20.0, 20.0));
@@ -251,6 +321,30 @@ impl GameStateDebugMenu for Editor {
                         {
                             new_selection = Some(EditorSelection::Tiles {
                                 selection: candidate,
+                                tool: *tool,
+                            });
+                        }
+                    }
+                    // The slopes, cracked tiles, locks and decorations do not have images of their
+                    // own
+                    for (candidate, label) in [
+                        (TileSelection::SlopeUpRight, "◢"),
+                        (TileSelection::SlopeUpLeft, "◣"),
+                        (TileSelection::Cracked, "Cracked"),
+                        (TileSelection::LockedDoor, "Locked door"),
+                        (TileSelection::DashBarrier, "Dash barrier"),
+                        (TileSelection::BombWall, "Bomb wall"),
+                        (TileSelection::Torch, "Torch"),
+                        (TileSelection::Grass, "Grass"),
+                        (TileSelection::Vine, "Vine"),
+                    ] {
+                        if ui
+                            .add(egui::Button::new(label).selected(*selection == candidate))
+                            .clicked()
+                        {
+                            new_selection = Some(EditorSelection::Tiles {
+                                selection: candidate,
+                                tool: *tool,
                             });
                         }
                     }
@@ -285,6 +379,14 @@ impl GameStateDebugMenu for Editor {
                             EnemySelection::Burrower,
                             egui::include_image!("../../assets/ui_sprites/burrower.png"),
                         ),
+                        (
+                            EnemySelection::BatFlock,
+                            egui::include_image!("../../assets/ui_sprites/bat_flock.png"),
+                        ),
+                        (
+                            EnemySelection::ShieldBearer,
+                            egui::include_image!("../../assets/ui_sprites/shield_bearer.png"),
+                        ),
                     ] {
                         let image = egui::Image::new(image_source)
                             .fit_to_exact_size(egui::Vec2::new(20.0, 20.0));
@@ -299,11 +401,86 @@ impl GameStateDebugMenu for Editor {
                             });
                         }
                     }
+                    // The enemies drawn without a sprite do not have images of their own
+                    for (candidate, text) in [
+                        (EnemySelection::Archer, "Archer"),
+                        (EnemySelection::Boss, "Boss"),
+                        (EnemySelection::Wisp, "Wisp"),
+                    ] {
+                        if ui
+                            .add(egui::Button::new(text).selected(*selection == candidate))
+                            .clicked()
+                        {
+                            new_selection = Some(EditorSelection::Enemies {
+                                selection: candidate,
+                                snap_bottom: *snap_bottom,
+                                snap_top: *snap_top,
+                            });
+                        }
+                    }
                 });
 
                 ui.add(egui::Checkbox::new(snap_bottom, "Snap bottom"));
                 ui.add(egui::Checkbox::new(snap_top, "Snap top"));
             }
+            EditorSelection::Items { selection } => {
+                ui.add(egui::Label::new("Item:"));
+
+                ui.horizontal_wrapped(|ui| {
+                    for (candidate, image_source) in [
+                        (
+                            ItemSelection::Remove,
+                            egui::include_image!("../../assets/ui_sprites/remove.png"),
+                        ),
+                        (
+                            ItemSelection::Box,
+                            egui::include_image!("../../assets/ui_sprites/box.png"),
+                        ),
+                        (
+                            ItemSelection::Vase,
+                            egui::include_image!("../../assets/ui_sprites/vase.png"),
+                        ),
+                        (
+                            ItemSelection::ShopSlot,
+                            egui::include_image!("../../assets/ui_sprites/shop_slot.png"),
+                        ),
+                    ] {
+                        let image = egui::Image::new(image_source)
+                            .fit_to_exact_size(egui::Vec2::new(20.0, 20.0));
+                        if ui
+                            .add(egui::Button::image(image).selected(*selection == candidate))
+                            .clicked()
+                        {
+                            new_selection = Some(EditorSelection::Items {
+                                selection: candidate,
+                            });
+                        }
+                    }
+                    // The rest do not have images of their own
+                    let pickups = Ability::ALL
+                        .map(|ability| (ItemSelection::Ability(ability), ability.name()));
+                    for (candidate, label) in [
+                        (ItemSelection::Coin, "Coin"),
+                        (ItemSelection::SmallStone, "Small stone"),
+                        (ItemSelection::LargeStone, "Large stone"),
+                        (ItemSelection::Checkpoint, "Checkpoint"),
+                        (ItemSelection::Key, "Key"),
+                        (ItemSelection::TrainingSwitch, "Training switch"),
+                    ]
+                    .into_iter()
+                    .chain(pickups)
+                    {
+                        if ui
+                            .add(egui::Button::new(label).selected(*selection == candidate))
+                            .clicked()
+                        {
+                            new_selection = Some(EditorSelection::Items {
+                                selection: candidate,
+                            });
+                        }
+                    }
+                });
+            }
             EditorSelection::PlayerPos => {
                 ui.add(egui::Label::new("Click to set player pos"));
             }
@@ -311,10 +488,7 @@ impl GameStateDebugMenu for Editor {
                 ui.add(egui::Label::new("Door:"));
 
                 for door_type in [
-                    DoorSelection::Right,
-                    DoorSelection::Left,
-                    DoorSelection::Up,
-                    DoorSelection::Down,
+                    DoorSelection::Door,
                     DoorSelection::LevelStart,
                     DoorSelection::LevelEnd,
                     DoorSelection::Remove,
@@ -331,9 +505,69 @@ impl GameStateDebugMenu for Editor {
                         });
                     }
                 }
+
+                // Generation connects a door to the doors of the opposite direction in the
+                // rooms without a start door
+                ui.add(egui::Label::new("Doors of the room:"));
+                for (door_index, door) in self.room.get_doors().iter().enumerate() {
+                    let (x, y) = self.room.rel_to_abs((door.x, door.y));
+                    if ui
+                        .selectable_label(
+                            stage.selected_door == Some(door_index),
+                            format!("{:?} at ({} {})", door.dir, x, y),
+                        )
+                        .clicked()
+                    {
+                        stage.selected_door = Some(door_index);
+                    }
+                }
+                if let Some(door) = stage
+                    .selected_door
+                    .and_then(|door_index| self.room.get_doors().get(door_index))
+                {
+                    let matching_dir = door.dir.opposite();
+                    let matches: Vec<(usize, &String)> = stage
+                        .all_rooms
+                        .iter()
+                        .enumerate()
+                        .filter(|(room_index, (_, room))| {
+                            *room_index != stage.current_editor_room_index as usize
+                                && room.get_start_pos().is_none()
+                                && room.get_doors().iter().any(|d| d.dir == matching_dir)
+                        })
+                        .map(|(room_index, (file_name, _))| (room_index, file_name))
+                        .collect();
+                    if matches.is_empty() {
+                        ui.colored_label(
+                            egui::Color32::YELLOW,
+                            format!("No room has a {:?} door to connect to", matching_dir),
+                        );
+                    } else {
+                        ui.add(egui::Label::new(format!(
+                            "Connects to {} rooms with a {:?} door:",
+                            matches.len(),
+                            matching_dir
+                        )));
+                    }
+                    ui.horizontal_wrapped(|ui| {
+                        for (room_index, file_name) in matches {
+                            if ui.add(egui::Link::new(file_name)).clicked() {
+                                open_room = Some(room_index);
+                            }
+                        }
+                    });
+                }
             }
         }
 
+        if let Some(room_index) = open_room {
+            self.room = stage.all_rooms[room_index].1.clone();
+            stage.current_editor_room_index = room_index as u32;
+            stage.selected_door = None;
+            self.player_mut().bb.x = self.room.get_center().0;
+            self.player_mut().bb.y = self.room.get_center().1;
+        }
+
         if let Some(selection) = new_selection {
             stage.editor_selection = selection;
         }
@@ -344,19 +578,13 @@ impl GameStateDebugMenu for Editor {
                 stage.all_rooms.sort_by(|a, b| a.0.cmp(&b.0));
             }
             if ui.add(egui::Button::new("Enemies")).clicked() {
-                stage
-                    .all_rooms
-                    .sort_by(|a, b| a.1.has_enemies().cmp(&b.1.has_enemies()));
+                stage.all_rooms.sort_by_key(|a| a.1.has_enemies());
             }
             if ui.add(egui::Button::new("InDoor")).clicked() {
-                stage
-                    .all_rooms
-                    .sort_by(|a, b| a.1.has_start_door().cmp(&b.1.has_start_door()));
+                stage.all_rooms.sort_by_key(|a| a.1.has_start_door());
             }
             if ui.add(egui::Button::new("Active")).clicked() {
-                stage
-                    .all_rooms
-                    .sort_by(|a, b| a.1.disabled.cmp(&b.1.disabled));
+                stage.all_rooms.sort_by_key(|a| a.1.disabled);
             }
         });
 
@@ -401,10 +629,16 @@ impl GameStateDebugMenu for Editor {
 
                 // Change room link
                 ui.horizontal(|ui| {
-                    if ui.add(egui::Link::new(file_name.clone())).clicked() {
+                    stage.room_thumbnails.show(ui, file_name, room);
+                    let label = match &room.name {
+                        Some(name) => format!("{} ({})", file_name, name),
+                        None => file_name.clone(),
+                    };
+                    if ui.add(egui::Link::new(label)).clicked() {
                         println!("Clicked a link");
                         self.room = Room::clone(room);
                         stage.current_editor_room_index = room_index as u32;
+                        stage.selected_door = None;
                         self.player_mut().bb.x = self.room.get_center().0;
                         self.player_mut().bb.y = self.room.get_center().1;
                     }
@@ -417,6 +651,15 @@ impl GameStateDebugMenu for Editor {
                 if room.has_enemies() {
                     ui.add(egui::Label::new("\u{1F432}"));
                 }
+                if room.is_encounter {
+                    ui.add(egui::Label::new("\u{2694}"));
+                }
+                if room.is_escape {
+                    ui.add(egui::Label::new("\u{1F525}"));
+                }
+                if room.is_dark {
+                    ui.add(egui::Label::new("\u{1F311}"));
+                }
 
                 if is_current_room {
                     // Save / Delete buttons
@@ -443,6 +686,77 @@ impl GameStateDebugMenu for Editor {
                         room.get_pos().0 - room.w as i32,
                         room.get_pos().1 - room.h as i32
                     )));
+                    ui.end_row();
+                    ui.add(egui::Label::new(""));
+                    ui.add(egui::Checkbox::new(
+                        &mut self.room.is_encounter,
+                        "Encounter",
+                    ));
+                    ui.end_row();
+                    ui.add(egui::Label::new(""));
+                    ui.add(egui::Checkbox::new(&mut self.room.is_escape, "Escape"));
+                    ui.end_row();
+                    ui.add(egui::Label::new(""));
+                    ui.add(egui::Checkbox::new(&mut self.room.is_dark, "Dark"));
+                    ui.end_row();
+                    ui.add(egui::Label::new(""));
+                    ui.horizontal(|ui| {
+                        ui.add(egui::Label::new("Difficulty:"));
+                        ui.add(egui::DragValue::new(&mut self.room.difficulty).range(0..=20));
+                    });
+                    ui.end_row();
+                    ui.add(egui::Label::new(""));
+                    ui.horizontal(|ui| {
+                        ui.add(egui::Label::new("Par time (s, 0 for none):"));
+                        ui.add(egui::DragValue::new(&mut self.room.par_seconds).range(0..=600));
+                    });
+                    ui.end_row();
+                    ui.add(egui::Label::new(""));
+                    ui.horizontal(|ui| {
+                        ui.add(egui::Label::new("Name:"));
+                        let mut name = self.room.name.clone().unwrap_or_default();
+                        let edit = egui::TextEdit::singleline(&mut name).desired_width(120.0);
+                        if ui.add(edit).changed() {
+                            self.room.name = Some(name).filter(|name| !name.is_empty());
+                        }
+                    });
+                    ui.end_row();
+                    ui.add(egui::Label::new(""));
+                    ui.horizontal(|ui| {
+                        ui.add(egui::Label::new("Biome:"));
+                        let mut biome = self.room.biome();
+                        let max_biome = BIOME_BACKGROUNDS.len() as u32 - 1;
+                        if ui
+                            .add(egui::DragValue::new(&mut biome).range(0..=max_biome))
+                            .changed()
+                        {
+                            self.room.set_biome(biome);
+                        }
+                    });
+
+                    // Ladders that do not lead down to anything are drawn broken
+                    let unsupported_ladders = self
+                        .room
+                        .get_overlays()
+                        .iter()
+                        .filter(|info| {
+                            self.room.is_ladder_at(info.x, info.y)
+                                && !self.room.is_ladder_supported(info.x, info.y)
+                        })
+                        .count();
+                    if unsupported_ladders > 0 {
+                        ui.end_row();
+                        ui.add(egui::Label::new(""));
+                        ui.horizontal(|ui| {
+                            ui.add(egui::Label::new(format!(
+                                "Unsupported ladders: {}",
+                                unsupported_ladders
+                            )));
+                            if ui.add(egui::Button::new("Fix ladders")).clicked() {
+                                self.room.fix_unsupported_ladders();
+                            }
+                        });
+                    }
                 }
                 ui.end_row();
             }
@@ -480,6 +794,157 @@ impl GameStateDebugMenu for Editor {
 
         if reload_rooms {
             stage.all_rooms = Room::load_rooms_from_folder();
+            stage.room_thumbnails.clear();
+        }
+    }
+}
+
+impl Editor {
+    fn paint_tile(&mut self, selection: &TileSelection, x: i32, y: i32) {
+        match selection {
+            TileSelection::NotPartOf => {
+                self.map_mut().set_base(x, y, BaseTile::NotPartOfRoom);
+                self.map_mut().set_overlay(x, y, OverlayTile::None);
+            }
+            TileSelection::Clear => {
+                self.map_mut().set_base(x, y, BaseTile::Empty);
+                self.map_mut().set_overlay(x, y, OverlayTile::None);
+            }
+            TileSelection::Ladder => {
+                self.map_mut().set_base(x, y, BaseTile::Empty);
+
+                let tile_to_set = match self.map().get_at(x, y) {
+                    (_, OverlayTile::Platform) => OverlayTile::LadderPlatform,
+                    _ => OverlayTile::Ladder,
+                };
+
+                self.map_mut().set_overlay(x, y, tile_to_set);
+            }
+            TileSelection::Platform => {
+                self.map_mut().set_base(x, y, BaseTile::Empty);
+
+                let tile_to_set = match self.map().get_at(x, y) {
+                    (_, OverlayTile::Ladder) => OverlayTile::LadderPlatform,
+                    _ => OverlayTile::Platform,
+                };
+
+                self.map_mut().set_overlay(x, y, tile_to_set);
+            }
+            TileSelection::Stone => {
+                self.map_mut().set_base(x, y, BaseTile::Stone);
+                self.map_mut().set_overlay(x, y, OverlayTile::None);
+            }
+            TileSelection::HiddenWall => {
+                self.map_mut().set_base(x, y, BaseTile::HiddenWall);
+                self.map_mut().set_overlay(x, y, OverlayTile::None);
+            }
+            TileSelection::Wood => {
+                self.map_mut().set_base(x, y, BaseTile::Wood);
+                self.map_mut().set_overlay(x, y, OverlayTile::None);
+            }
+            TileSelection::SlopeUpRight => {
+                self.map_mut().set_base(x, y, BaseTile::SlopeUpRight);
+                self.map_mut().set_overlay(x, y, OverlayTile::None);
+            }
+            TileSelection::SlopeUpLeft => {
+                self.map_mut().set_base(x, y, BaseTile::SlopeUpLeft);
+                self.map_mut().set_overlay(x, y, OverlayTile::None);
+            }
+            TileSelection::Cracked => {
+                self.map_mut().set_base(x, y, BaseTile::Cracked);
+                self.map_mut().set_overlay(x, y, OverlayTile::None);
+            }
+            TileSelection::LockedDoor => {
+                self.map_mut().set_base(x, y, BaseTile::LockedDoor);
+                self.map_mut().set_overlay(x, y, OverlayTile::None);
+            }
+            TileSelection::DashBarrier => {
+                self.map_mut().set_base(x, y, BaseTile::DashBarrier);
+                self.map_mut().set_overlay(x, y, OverlayTile::None);
+            }
+            TileSelection::BombWall => {
+                self.map_mut().set_base(x, y, BaseTile::BombWall);
+                self.map_mut().set_overlay(x, y, OverlayTile::None);
+            }
+            TileSelection::StartDoor => {
+                self.map_mut().set_base(x, y, BaseTile::Empty);
+                self.map_mut().set_overlay(x, y, OverlayTile::StartDoor);
+            }
+            TileSelection::Spikes => {
+                self.map_mut().set_base(x, y, BaseTile::Empty);
+                self.map_mut().set_overlay(x, y, OverlayTile::Spikes);
+            }
+            TileSelection::Torch => {
+                self.map_mut().set_base(x, y, BaseTile::Empty);
+                self.map_mut().set_overlay(x, y, OverlayTile::Torch);
+            }
+            TileSelection::Grass => {
+                self.map_mut().set_base(x, y, BaseTile::Empty);
+                self.map_mut().set_overlay(x, y, OverlayTile::Grass);
+            }
+            TileSelection::Vine => {
+                self.map_mut().set_base(x, y, BaseTile::Empty);
+                self.map_mut().set_overlay(x, y, OverlayTile::Vine);
+            }
+        }
+    }
+
+    // The tiles connected to the one at the coords that have the same base and overlay, within
+    // the room
+    fn fill_tiles(&self, coords: (i32, i32)) -> Vec<(i32, i32)> {
+        let (room_x, room_y, room_w, room_h) = self.room.get_bounds();
+        let in_room = |(x, y): (i32, i32)| {
+            x >= room_x && y >= room_y && x < room_x + room_w as i32 && y < room_y + room_h as i32
+        };
+        if !in_room(coords) {
+            return Vec::new();
+        }
+        let (base, overlay) = self.room.get_at(coords.0, coords.1);
+        let mut seen = HashSet::from([coords]);
+        let mut stack = vec![coords];
+        while let Some((x, y)) = stack.pop() {
+            for next in [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)] {
+                if !in_room(next) || seen.contains(&next) {
+                    continue;
+                }
+                let (next_base, next_overlay) = self.room.get_at(next.0, next.1);
+                if next_base == base && next_overlay as u8 == overlay as u8 {
+                    seen.insert(next);
+                    stack.push(next);
+                }
+            }
+        }
+        seen.into_iter().collect()
+    }
+}
+
+fn rectangle_tiles(start: (i32, i32), end: (i32, i32)) -> Vec<(i32, i32)> {
+    let mut tiles = Vec::new();
+    for x in start.0.min(end.0)..=start.0.max(end.0) {
+        for y in start.1.min(end.1)..=start.1.max(end.1) {
+            tiles.push((x, y));
+        }
+    }
+    tiles
+}
+
+// Bresenham's line, the tiles touch each other at least by a corner
+fn line_tiles(start: (i32, i32), end: (i32, i32)) -> Vec<(i32, i32)> {
+    let (dx, dy) = ((end.0 - start.0).abs(), -(end.1 - start.1).abs());
+    let (step_x, step_y) = ((end.0 - start.0).signum(), (end.1 - start.1).signum());
+    let mut error = dx + dy;
+    let (mut x, mut y) = start;
+    let mut tiles = vec![(x, y)];
+    while (x, y) != end {
+        if error * 2 >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if error * 2 <= dx {
+            error += dx;
+            y += step_y;
         }
+        tiles.push((x, y));
     }
+    tiles
 }
@@ -1,22 +1,317 @@
 use super::common::{DebugMenu, GameStateDebugMenu};
+use super::console::ConsoleCommand;
+use super::generation::render_generation_ui;
+use crate::sound_handler::SoundHandler;
+use crate::state::abilities::Ability;
+use crate::state::bot::Bot;
+use crate::state::desync::DesyncChecker;
+use crate::state::enemies::Elite;
 use crate::state::game_state::Game;
+use crate::state::item::{Item, ItemType};
+use crate::state::map_like::{MapLike, ObjectTemplate, ObjectTemplateType};
+use crate::state::{InputState, Pos};
 use egui::Ui;
 
 use crate::camera::MouseCoords;
 
+// Distance between the things when spawning many at once
+const SPAWN_SPACING: f32 = 0.8;
+// The most things spawned at once, from the debug window and the console alike
+pub const MAX_SPAWN_COUNT: u32 = 20;
+
+enum Spawnable {
+    Enemy(ObjectTemplateType),
+    Item(ItemType),
+}
+
+const SPAWN_PALETTE: &[(&str, Spawnable)] = &[
+    ("Bat", Spawnable::Enemy(ObjectTemplateType::Bat)),
+    ("Slime", Spawnable::Enemy(ObjectTemplateType::Slime)),
+    ("Worm", Spawnable::Enemy(ObjectTemplateType::Worm)),
+    ("Burrower", Spawnable::Enemy(ObjectTemplateType::Burrower)),
+    ("Bat flock", Spawnable::Enemy(ObjectTemplateType::BatFlock)),
+    (
+        "Shield bearer",
+        Spawnable::Enemy(ObjectTemplateType::ShieldBearer),
+    ),
+    ("Archer", Spawnable::Enemy(ObjectTemplateType::Archer)),
+    ("Boss", Spawnable::Enemy(ObjectTemplateType::Boss)),
+    ("Wisp", Spawnable::Enemy(ObjectTemplateType::Wisp)),
+    ("Coin", Spawnable::Item(ItemType::Coin)),
+    ("Small stone", Spawnable::Item(ItemType::SmallStone)),
+    ("Large stone", Spawnable::Item(ItemType::LargeStone)),
+    ("Box", Spawnable::Item(ItemType::Box)),
+    ("Vase", Spawnable::Item(ItemType::Vase)),
+    ("One up", Spawnable::Item(ItemType::OneUp)),
+    ("Heart", Spawnable::Item(ItemType::Heart)),
+    ("Key", Spawnable::Item(ItemType::Key)),
+    (
+        "Double jump",
+        Spawnable::Item(ItemType::Ability(Ability::DoubleJump)),
+    ),
+    ("Dash", Spawnable::Item(ItemType::Ability(Ability::Dash))),
+    ("Bomb", Spawnable::Item(ItemType::Ability(Ability::Bomb))),
+];
+
+// The names of the palette for the console, "Bat flock" is bat_flock
+pub(super) fn spawnable_names() -> impl Iterator<Item = String> {
+    SPAWN_PALETTE
+        .iter()
+        .map(|(label, _)| label.to_lowercase().replace(' ', "_"))
+}
+
+impl Game {
+    // Centered on the spot, many of them are spread out in a row
+    fn spawn(&mut self, spawnable: &Spawnable, coords: MouseCoords, count: u32, elite: bool) {
+        for i in 0..count {
+            let x = coords.x + (i as f32 - (count - 1) as f32 / 2.0) * SPAWN_SPACING;
+            let y = coords.y;
+            match spawnable {
+                Spawnable::Enemy(object_type) => {
+                    let bb = ObjectTemplate::new(x, y, object_type.clone()).get_bb();
+                    let mut enemy =
+                        ObjectTemplate::new(x - bb.w / 2.0, y - bb.h / 2.0, object_type.clone())
+                            .as_object();
+                    if elite {
+                        enemy = Box::new(Elite::new(enemy));
+                    }
+                    self.enemies.push(enemy);
+                }
+                Spawnable::Item(item_type) => self.items.push(Item::new(x, y, *item_type)),
+            }
+        }
+    }
+}
+
 impl GameStateDebugMenu for Game {
-    fn mouse_button_event(&mut self, _coords: MouseCoords, _stage: &mut DebugMenu) {}
+    // Clicking in the game pings the spot for the compass
+    fn mouse_button_event(&mut self, coords: MouseCoords, stage: &mut DebugMenu) {
+        if stage.teleport_on_click {
+            stage.teleport_on_click = false;
+            self.teleport_player(coords);
+            return;
+        }
+        if let Some(index) = stage.spawn_selection {
+            self.spawn(
+                &SPAWN_PALETTE[index].1,
+                coords,
+                stage.spawn_count,
+                stage.spawn_elite,
+            );
+            return;
+        }
+        self.compass.ping(Pos::new(coords.x, coords.y));
+    }
+
+    // Stands the player on the bottom of the tile, solid tiles are not teleported into
+    fn teleport_player(&mut self, coords: MouseCoords) {
+        let (x, y) = coords.as_i();
+        if self.map.is_solid_at_tile(x, y) {
+            return;
+        }
+        self.player.teleport(coords.x, y as f32 + 1.0);
+    }
+
+    fn bot_input(&self, debug_menu: &mut DebugMenu) -> Option<InputState> {
+        debug_menu.bot.as_mut().map(|bot| bot.input(self))
+    }
+
+    fn check_desync(
+        &self,
+        input: &InputState,
+        sound_handler: &SoundHandler,
+        debug_menu: &mut DebugMenu,
+    ) {
+        if let Some(checker) = &mut debug_menu.desync_checker {
+            checker.update(self, input, sound_handler);
+        }
+    }
+
+    fn run_console_command(
+        &mut self,
+        command: ConsoleCommand,
+        debug_menu: &mut DebugMenu,
+    ) -> Result<String, String> {
+        match command {
+            ConsoleCommand::GiveCoins(amount) => {
+                self.wallet.add(amount);
+                Ok(format!("{} coins", self.wallet.balance()))
+            }
+            ConsoleCommand::Spawn { name, count } => {
+                let index = spawnable_names()
+                    .position(|spawnable| spawnable == name)
+                    .ok_or_else(|| format!("Nothing called {} to spawn", name))?;
+                // Above the player so that they do not land on the player at once
+                let center = self.player.bb.center();
+                let coords = MouseCoords {
+                    x: center.x,
+                    y: center.y - 2.0,
+                };
+                self.spawn(
+                    &SPAWN_PALETTE[index].1,
+                    coords,
+                    count,
+                    debug_menu.spawn_elite,
+                );
+                Ok(format!("Spawned {} {}", count, name))
+            }
+            ConsoleCommand::Goto(room) => {
+                let room_index = match room.parse::<usize>() {
+                    Ok(room_index) => room_index,
+                    // The file name with or without the .json
+                    Err(_) => self
+                        .map
+                        .rooms
+                        .iter()
+                        .position(|placed| {
+                            placed.file_name.as_deref().is_some_and(|file_name| {
+                                file_name == room || file_name.strip_suffix(".json") == Some(&room)
+                            })
+                        })
+                        .ok_or_else(|| format!("{} is not placed in this map", room))?,
+                };
+                let (x, y) = self
+                    .map
+                    .standable_tile_in_room(room_index)
+                    .ok_or_else(|| format!("No room {} to go to", room))?;
+                self.player.teleport(x as f32 + 0.5, y as f32 + 1.0);
+                Ok(format!("Went to room {}", room))
+            }
+            ConsoleCommand::Seed(seed) => {
+                *self = Game::new_with_seed(seed);
+                debug_menu.desync_checker = None;
+                Ok(format!("New game from seed {}", seed))
+            }
+            ConsoleCommand::Noclip => {
+                let cheats = &mut self.player.cheats;
+                cheats.noclip = !cheats.noclip;
+                Ok(format!(
+                    "Noclip {}",
+                    if cheats.noclip { "on" } else { "off" }
+                ))
+            }
+            ConsoleCommand::God => {
+                let cheats = &mut self.player.cheats;
+                cheats.god_mode = !cheats.god_mode;
+                Ok(format!(
+                    "God mode {}",
+                    if cheats.god_mode { "on" } else { "off" }
+                ))
+            }
+        }
+    }
+
     fn mouse_drawing(&mut self, _coords: MouseCoords, _debug_menu: &DebugMenu) {}
 
+    fn mouse_released(&mut self, _debug_menu: &DebugMenu) {}
+
     fn render_ui(&mut self, ui: &mut Ui, stage: &mut DebugMenu) {
         if ui.add(egui::Button::new("Regenerate map")).clicked() {
             *self = Game::new();
+            stage.desync_checker = None;
         }
+        ui.horizontal(|ui| {
+            if ui.add(egui::Button::new("Snapshot")).clicked() {
+                stage.game_snapshot = Some(Box::new(self.clone()));
+            }
+            if ui
+                .add_enabled(stage.game_snapshot.is_some(), egui::Button::new("Restore"))
+                .clicked()
+                && let Some(snapshot) = &stage.game_snapshot
+            {
+                *self = (**snapshot).clone();
+                stage.desync_checker = None;
+            }
+        });
+        ui.horizontal(|ui| {
+            // Restarts the game from a new seed with a second game running next to it
+            if stage.desync_checker.is_none() {
+                if ui.add(egui::Button::new("Start desync check")).clicked() {
+                    let seed = rand::random();
+                    *self = Game::new_with_seed(seed);
+                    stage.desync_checker = Some(DesyncChecker::new(seed));
+                }
+            } else if ui.add(egui::Button::new("Stop desync check")).clicked() {
+                stage.desync_checker = None;
+            }
+            if let Some(checker) = &stage.desync_checker {
+                match checker.desync_frame() {
+                    Some(frame) => {
+                        ui.colored_label(egui::Color32::RED, format!("Desync at update {}", frame))
+                    }
+                    None => ui.label(format!("In sync for {} updates", checker.frame())),
+                };
+            }
+        });
+        ui.horizontal(|ui| {
+            let mut bot_plays = stage.bot.is_some();
+            if ui
+                .add(egui::Checkbox::new(&mut bot_plays, "Bot plays"))
+                .changed()
+            {
+                stage.bot = bot_plays.then(Bot::new);
+            }
+            if let Some(bot) = &stage.bot {
+                ui.label(format!("{} updates", bot.frames()));
+            }
+        });
+
+        egui::CollapsingHeader::new("Map generation").show(ui, |ui| {
+            render_generation_ui(ui, &self.map, &mut stage.generation_step);
+        });
 
         ui.add(egui::Checkbox::new(
             &mut stage.zoom_show_full,
             "Zoom to room",
         ));
         ui.add(egui::Checkbox::new(&mut stage.show_dark, "Show dark"));
+        ui.add(egui::Checkbox::new(
+            &mut stage.teleport_on_click,
+            "Teleport on next click (T)",
+        ));
+        ui.add(egui::Checkbox::new(
+            &mut self.show_breadcrumbs,
+            "Show breadcrumbs",
+        ));
+        ui.add(egui::Checkbox::new(
+            &mut self.camera_follows_player,
+            "Camera follows player",
+        ));
+        ui.add(egui::Checkbox::new(
+            &mut self.sleep_far_items,
+            "Sleep far items",
+        ));
+        ui.label(format!(
+            "Unloaded rooms: {}",
+            self.room_streamer.unloaded_room_count()
+        ));
+        ui.horizontal(|ui| {
+            let cheats = &mut self.player.cheats;
+            ui.add(egui::Checkbox::new(&mut cheats.god_mode, "God mode"));
+            ui.add(egui::Checkbox::new(&mut cheats.noclip, "Noclip"));
+            ui.add(egui::Checkbox::new(
+                &mut cheats.infinite_jump,
+                "Infinite jump",
+            ));
+        });
+
+        egui::CollapsingHeader::new("Spawn at click").show(ui, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for (index, (label, _)) in SPAWN_PALETTE.iter().enumerate() {
+                    let selected = stage.spawn_selection == Some(index);
+                    if ui
+                        .add(egui::Button::new(*label).selected(selected))
+                        .clicked()
+                    {
+                        stage.spawn_selection = if selected { None } else { Some(index) };
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.add(egui::Label::new("Count:"));
+                ui.add(egui::DragValue::new(&mut stage.spawn_count).range(1..=MAX_SPAWN_COUNT));
+                ui.add(egui::Checkbox::new(&mut stage.spawn_elite, "Elite"));
+            });
+        });
     }
 }
@@ -0,0 +1,186 @@
+use crate::state::map_like::GameMap;
+use crate::state::map_like::game_map::GenerationOutcome;
+use egui::{Color32, Pos2, Rect, Stroke, Ui, Vec2};
+
+const VIEW_SIZE: Vec2 = Vec2::new(320.0, 240.0);
+const ROOM_COLOR: Color32 = Color32::from_gray(140);
+const CRITICAL_PATH_COLOR: Color32 = Color32::from_rgb(230, 190, 60);
+const PLACED_COLOR: Color32 = Color32::from_rgb(80, 200, 90);
+const REJECTED_COLOR: Color32 = Color32::from_rgb(220, 70, 60);
+const GRAPH_COLOR: Color32 = Color32::from_rgb(90, 150, 230);
+
+fn outcome_text(outcome: GenerationOutcome) -> String {
+    match outcome {
+        GenerationOutcome::Placed => String::from("Placed"),
+        GenerationOutcome::NoMatchingDoor => String::from("No door of the opposite direction"),
+        GenerationOutcome::SamePosition => String::from("On top of the room it connects to"),
+        GenerationOutcome::Overlaps { x, y } => format!("Overlaps a placed room at ({} {})", x, y),
+    }
+}
+
+// Steps through the attempts of the generator to add rooms. Shows the rooms placed so far with
+// the graph of which room each was connected to, the candidate of the current step and the
+// critical path (start to the deepest room) of the finished map.
+pub fn render_generation_ui(ui: &mut Ui, map: &GameMap, step: &mut usize) {
+    let steps = map.generation_steps();
+    *step = (*step).min(steps.len());
+
+    let placed = steps
+        .iter()
+        .filter(|step| step.outcome == GenerationOutcome::Placed)
+        .count();
+    ui.label(format!(
+        "{} attempts, {} rooms placed, {} rejected",
+        steps.len(),
+        placed,
+        steps.len() - placed
+    ));
+    ui.horizontal(|ui| {
+        if ui.button("<").clicked() {
+            *step = step.saturating_sub(1);
+        }
+        ui.add(egui::Slider::new(step, 0..=steps.len()).text("Step"));
+        if ui.button(">").clicked() {
+            *step = (*step + 1).min(steps.len());
+        }
+        // Skips the rejected attempts
+        if ui.button("Next room").clicked() {
+            *step = steps
+                .iter()
+                .enumerate()
+                .skip(*step)
+                .find(|(_, step)| step.outcome == GenerationOutcome::Placed)
+                .map_or(steps.len(), |(index, _)| index + 1);
+        }
+    });
+
+    // The rooms that are there after the steps before the current one, room 0 is the first room
+    // and every placed step adds the next one
+    let done = &steps[..step.saturating_sub(1)];
+    let current = step.checked_sub(1).map(|index| &steps[index]);
+    let rooms_placed = 1 + done
+        .iter()
+        .filter(|step| step.outcome == GenerationOutcome::Placed)
+        .count();
+    match current {
+        Some(current) => ui.label(format!(
+            "Step {}: from room {}, {}",
+            *step,
+            current.existing_room,
+            outcome_text(current.outcome)
+        )),
+        None => ui.label("Only the first room"),
+    };
+
+    // Everything that is drawn has to fit in the view
+    let mut bounds: Vec<(i32, i32, u32, u32)> = map
+        .rooms
+        .iter()
+        .map(|room| {
+            let (x, y) = room.get_pos();
+            (x, y, room.w, room.h)
+        })
+        .collect();
+    bounds.extend(steps.iter().filter_map(|step| step.candidate));
+    let min_x = bounds.iter().map(|b| b.0).min().unwrap_or(0) as f32;
+    let min_y = bounds.iter().map(|b| b.1).min().unwrap_or(0) as f32;
+    let max_x = bounds.iter().map(|b| b.0 + b.2 as i32).max().unwrap_or(1) as f32;
+    let max_y = bounds.iter().map(|b| b.1 + b.3 as i32).max().unwrap_or(1) as f32;
+    let scale = (VIEW_SIZE.x / (max_x - min_x)).min(VIEW_SIZE.y / (max_y - min_y));
+
+    let (response, painter) = ui.allocate_painter(VIEW_SIZE, egui::Sense::hover());
+    painter.rect_filled(response.rect, 0.0, Color32::from_black_alpha(200));
+    let origin = response.rect.min;
+    let to_screen = |x: f32, y: f32| origin + Vec2::new((x - min_x) * scale, (y - min_y) * scale);
+    let tile_rect = |(x, y, w, h): (i32, i32, u32, u32)| {
+        Rect::from_min_max(
+            to_screen(x as f32, y as f32),
+            to_screen((x + w as i32) as f32, (y + h as i32) as f32),
+        )
+    };
+    let room_center = |room_index: usize| {
+        let room = &map.rooms[room_index];
+        let (x, y) = room.get_pos();
+        to_screen(
+            x as f32 + room.w as f32 / 2.0,
+            y as f32 + room.h as f32 / 2.0,
+        )
+    };
+
+    let critical_path = map.critical_path();
+    for room_index in 0..rooms_placed {
+        let room = &map.rooms[room_index];
+        let (x, y) = room.get_pos();
+        let color = if critical_path.contains(&room_index) {
+            CRITICAL_PATH_COLOR
+        } else {
+            ROOM_COLOR
+        };
+        painter.rect_stroke(
+            tile_rect((x, y, room.w, room.h)),
+            0.0,
+            Stroke::new(1.0, color),
+            egui::StrokeKind::Inside,
+        );
+    }
+
+    // The room graph, each placed room is connected to the room it was added to
+    let mut new_room = 1;
+    for done_step in done {
+        if done_step.outcome == GenerationOutcome::Placed {
+            painter.line_segment(
+                [room_center(done_step.existing_room), room_center(new_room)],
+                Stroke::new(1.5, GRAPH_COLOR),
+            );
+            new_room += 1;
+        }
+    }
+    for door in &map.doors[..rooms_placed - 1] {
+        painter.circle_filled(
+            to_screen(door.x as f32 + 0.5, door.y as f32 + 0.5),
+            2.0,
+            GRAPH_COLOR,
+        );
+    }
+
+    if let Some(current) = current {
+        let color = match current.outcome {
+            GenerationOutcome::Placed => PLACED_COLOR,
+            _ => REJECTED_COLOR,
+        };
+        if let Some(candidate) = current.candidate {
+            painter.rect_filled(tile_rect(candidate), 0.0, color.gamma_multiply(0.3));
+            painter.rect_stroke(
+                tile_rect(candidate),
+                0.0,
+                Stroke::new(1.5, color),
+                egui::StrokeKind::Inside,
+            );
+        }
+        if let GenerationOutcome::Overlaps { x, y } = current.outcome {
+            painter.rect_filled(tile_rect((x, y, 1, 1)), 0.0, REJECTED_COLOR);
+        }
+        let (door_x, door_y) = current.door;
+        painter.circle_stroke(
+            to_screen(door_x as f32 + 0.5, door_y as f32 + 0.5),
+            4.0,
+            Stroke::new(1.5, color),
+        );
+        painter.line_segment(
+            [
+                room_center(current.existing_room),
+                to_screen(door_x as f32 + 0.5, door_y as f32 + 0.5),
+            ],
+            Stroke::new(1.0, color),
+        );
+    }
+
+    let legend = Pos2::new(response.rect.left() + 4.0, response.rect.bottom() - 4.0);
+    painter.text(
+        legend,
+        egui::Align2::LEFT_BOTTOM,
+        "critical path",
+        egui::FontId::monospace(10.0),
+        CRITICAL_PATH_COLOR,
+    );
+}
@@ -1,5 +1,12 @@
 pub mod common;
+pub mod console;
 pub mod editor;
 pub mod game;
+pub mod generation;
+pub mod thumbnails;
 
-pub use common::{DebugMenu, EditorSelection, GameStateDebugMenu, TileSelection};
+pub use common::{
+    DebugMenu, EditorSelection, FAST_FORWARD_TIME_SCALE, GameStateDebugMenu,
+    SLOW_MOTION_TIME_SCALE, TileSelection, TileTool,
+};
+pub use console::Console;
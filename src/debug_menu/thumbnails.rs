@@ -0,0 +1,70 @@
+use crate::state::map_like::Room;
+use crate::state::{BaseTile, OverlayTile};
+use egui::{Color32, ColorImage, TextureHandle, TextureOptions};
+use std::collections::HashMap;
+
+// Thumbnails are drawn this many screen pixels per tile, big rooms are scaled down to fit
+const PIXELS_PER_TILE: f32 = 2.0;
+const MAX_SIZE: f32 = 48.0;
+
+fn tile_color(base: BaseTile, overlay: OverlayTile) -> Color32 {
+    match (base, overlay) {
+        (_, OverlayTile::Ladder | OverlayTile::LadderPlatform) => Color32::from_rgb(170, 120, 60),
+        (_, OverlayTile::Platform) => Color32::from_rgb(140, 100, 60),
+        (_, OverlayTile::StartDoor) => Color32::from_rgb(120, 200, 120),
+        (_, OverlayTile::Spikes) => Color32::from_rgb(200, 60, 60),
+        (_, OverlayTile::Torch) => Color32::from_rgb(240, 170, 60),
+        (_, OverlayTile::Grass | OverlayTile::Vine) => Color32::from_rgb(70, 150, 60),
+        (BaseTile::NotPartOfRoom, _) => Color32::TRANSPARENT,
+        (BaseTile::Empty, _) => Color32::from_rgb(30, 30, 40),
+        (BaseTile::Wood | BaseTile::LockedDoor | BaseTile::DashBarrier, _) => {
+            Color32::from_rgb(120, 85, 50)
+        }
+        // Stone and the slopes and walls made of it
+        _ => Color32::from_rgb(130, 130, 140),
+    }
+}
+
+// One pixel for every tile of the room
+fn room_image(room: &Room) -> ColorImage {
+    let mut pixels = Vec::with_capacity((room.w * room.h) as usize);
+    for y in 0..room.h {
+        for x in 0..room.w {
+            let (base, overlay) = room.get_absolute(x, y);
+            pixels.push(tile_color(base, overlay));
+        }
+    }
+    ColorImage {
+        size: [room.w as usize, room.h as usize],
+        pixels,
+    }
+}
+
+// Small pictures of the rooms for the room list of the editor, made when the list is first shown
+// and again after the rooms are reloaded
+#[derive(Default)]
+pub struct RoomThumbnails {
+    textures: HashMap<String, TextureHandle>,
+}
+
+impl RoomThumbnails {
+    pub fn clear(&mut self) {
+        self.textures.clear();
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, file_name: &str, room: &Room) {
+        let texture = self
+            .textures
+            .entry(file_name.to_string())
+            .or_insert_with(|| {
+                ui.ctx().load_texture(
+                    format!("room_thumbnail_{}", file_name),
+                    room_image(room),
+                    TextureOptions::NEAREST,
+                )
+            });
+        let scale = PIXELS_PER_TILE.min(MAX_SIZE / room.w.max(room.h) as f32);
+        let size = egui::vec2(room.w as f32 * scale, room.h as f32 * scale);
+        ui.add(egui::Image::new((texture.id(), size)));
+    }
+}
@@ -0,0 +1,186 @@
+use crate::camera::Camera;
+use crate::render::{GpuObjects, TILE_SIZE};
+use crate::state::lighting::{Light, MAX_LIGHTS};
+use miniquad::*;
+
+#[repr(C)]
+struct LightUniforms {
+    // xy size of the screen in pixels, z the ambient light, w how many of the lights are used
+    screen: [f32; 4],
+    // xy position in pixels from the bottom left of the screen, z radius in pixels
+    lights: [[f32; 4]; MAX_LIGHTS],
+}
+
+// The world drawn in to a texture of the size of the screen
+struct Target {
+    pass: RenderPass,
+    texture: TextureId,
+    size: (u32, u32),
+}
+
+// The world of a dark room is drawn in to an offscreen target first. The target is then drawn on
+// the screen darkened by the ambient light, except for around the lights.
+pub struct LightPass {
+    pipeline: Pipeline,
+    target: Option<Target>,
+}
+
+impl LightPass {
+    pub fn new(ctx: &mut Context) -> Self {
+        let shader = ctx
+            .new_shader(
+                ShaderSource::Glsl {
+                    vertex: VERTEX_SHADER,
+                    fragment: FRAGMENT_SHADER,
+                },
+                ShaderMeta {
+                    images: vec!["scene".to_string()],
+                    uniforms: UniformBlockLayout {
+                        uniforms: vec![
+                            UniformDesc::new("screen", UniformType::Float4),
+                            UniformDesc::new("lights", UniformType::Float4).array(MAX_LIGHTS),
+                        ],
+                    },
+                },
+            )
+            .expect("failed to compile lighting shader");
+        let pipeline = ctx.new_pipeline(
+            &[BufferLayout::default()],
+            &[
+                VertexAttribute::new("pos", VertexFormat::Float2),
+                VertexAttribute::new("uv", VertexFormat::Float2),
+            ],
+            shader,
+            PipelineParams {
+                cull_face: CullFace::Nothing,
+                ..Default::default()
+            },
+        );
+        LightPass {
+            pipeline,
+            target: None,
+        }
+    }
+
+    /// The offscreen pass to draw the world in to, made again when the screen size changes
+    pub fn begin(
+        &mut self,
+        ctx: &mut Context,
+        gpu_objects: &mut GpuObjects,
+        camera: &Camera,
+        action: PassAction,
+    ) {
+        let size = (
+            camera.screen_w.max(1.0) as u32,
+            camera.screen_h.max(1.0) as u32,
+        );
+        if self
+            .target
+            .as_ref()
+            .is_none_or(|target| target.size != size)
+        {
+            self.delete(ctx, gpu_objects);
+            let texture = gpu_objects.new_render_texture(
+                ctx,
+                TextureParams {
+                    width: size.0,
+                    height: size.1,
+                    min_filter: FilterMode::Nearest,
+                    mag_filter: FilterMode::Nearest,
+                    ..Default::default()
+                },
+            );
+            let pass = ctx.new_render_pass(texture, None);
+            self.target = Some(Target {
+                pass,
+                texture,
+                size,
+            });
+        }
+        ctx.begin_pass(self.target.as_ref().map(|target| target.pass), action);
+    }
+
+    /// Draws what was drawn in to the offscreen pass on the screen with the lighting, in the
+    /// default pass. The quad is the unit quad of the renderer.
+    pub fn composite(
+        &self,
+        ctx: &mut Context,
+        quad: &Bindings,
+        camera: &Camera,
+        ambient: f32,
+        lights: &[Light],
+    ) {
+        let Some(target) = &self.target else {
+            return;
+        };
+        let mut uniforms = LightUniforms {
+            screen: [
+                camera.screen_w,
+                camera.screen_h,
+                ambient,
+                lights.len().min(MAX_LIGHTS) as f32,
+            ],
+            lights: [[0.0; 4]; MAX_LIGHTS],
+        };
+        for (uniform, light) in uniforms.lights.iter_mut().zip(lights) {
+            let (x, y) = camera.tile_to_screen(light.pos.x, light.pos.y);
+            *uniform = [
+                x,
+                camera.screen_h - y,
+                light.radius * TILE_SIZE * camera.zoom,
+                0.0,
+            ];
+        }
+        let bindings = Bindings {
+            vertex_buffers: quad.vertex_buffers.clone(),
+            index_buffer: quad.index_buffer,
+            images: vec![target.texture],
+        };
+        ctx.apply_pipeline(&self.pipeline);
+        ctx.apply_bindings(&bindings);
+        ctx.apply_uniforms(UniformsSource::table(&uniforms));
+        ctx.draw(0, 6, 1);
+    }
+
+    pub fn delete(&mut self, ctx: &mut Context, gpu_objects: &mut GpuObjects) {
+        if let Some(target) = self.target.take() {
+            ctx.delete_render_pass(target.pass);
+            gpu_objects.delete_texture(ctx, target.texture);
+        }
+    }
+}
+
+const VERTEX_SHADER: &str = r#"#version 100
+attribute vec2 pos;
+attribute vec2 uv;
+varying vec2 v_uv;
+void main() {
+    v_uv = uv;
+    gl_Position = vec4(pos * 2.0 - 1.0, 0.0, 1.0);
+}
+"#;
+
+// The size of the lights array is MAX_LIGHTS
+const _: () = assert!(MAX_LIGHTS == 16);
+const FRAGMENT_SHADER: &str = r#"#version 100
+precision mediump float;
+varying vec2 v_uv;
+uniform sampler2D scene;
+uniform vec4 screen;
+uniform vec4 lights[16];
+void main() {
+    vec2 px = v_uv * screen.xy;
+    float light = screen.z;
+    for (int i = 0; i < 16; i++) {
+        if (float(i) >= screen.w) {
+            break;
+        }
+        float d = distance(px, lights[i].xy) / lights[i].z;
+        light += 1.0 - smoothstep(0.3, 1.0, d);
+    }
+    // A few steps of brightness instead of a smooth fade, to go with the pixel art
+    light = min(max(floor(light * 6.0) / 6.0, screen.z), 1.0);
+    vec4 color = texture2D(scene, v_uv);
+    gl_FragColor = vec4(color.rgb * light, 1.0);
+}
+"#;
@@ -6,16 +6,43 @@ mod state;
 use crate::state::{GameState, InputState};
 mod atlas_info;
 mod debug_menu;
+mod light_pass;
 mod minimap;
+mod net;
+mod pixel_font;
 mod render;
+mod save;
 mod sound_handler;
+mod telemetry;
+mod tuning;
 
 use crate::camera::Camera;
-use crate::debug_menu::{DebugMenu, EditorSelection, GameStateDebugMenu, TileSelection};
+use crate::debug_menu::{
+    Console, DebugMenu, EditorSelection, FAST_FORWARD_TIME_SCALE, GameStateDebugMenu,
+    SLOW_MOTION_TIME_SCALE, TileSelection, TileTool,
+};
 use crate::render::{DrawableGameState, Renderer};
-use crate::sound_handler::SoundHandler;
+use crate::sound_handler::{Music, SoundHandler};
+use crate::state::desync::DesyncChecker;
 use crate::state::game_state::{Editor, Game};
 use egui_miniquad as egui_mq;
+use std::thread::JoinHandle;
+
+// WASD for the free camera, the arrow keys move it too
+#[derive(Default)]
+struct CameraKeys {
+    left: bool,
+    right: bool,
+    up: bool,
+    down: bool,
+}
+
+// Escape pauses the game, the world is still drawn but not updated
+#[derive(PartialEq)]
+enum GameFlow {
+    Running,
+    Paused,
+}
 
 trait FullGameState: GameState + DrawableGameState + GameStateDebugMenu {}
 impl<T: GameState + DrawableGameState + GameStateDebugMenu> FullGameState for T {}
@@ -24,9 +51,14 @@ struct Stage {
     egui_mq: egui_mq::EguiMq,
 
     sound_handler: SoundHandler,
+    // The first game is generated on a thread while the sounds load, a loading screen is shown
+    // until both are done
+    loading_game: Option<JoinHandle<Game>>,
 
     input: InputState,
+    camera_keys: CameraKeys,
     state: Box<dyn FullGameState>,
+    flow: GameFlow,
     renderer: Renderer,
     camera: Camera,
 
@@ -39,8 +71,11 @@ struct Stage {
     time_spent_updating: f64,
 
     mouse_pressed: bool,
+    // Last position of the mouse in screen pixels
+    mouse_pos: (f32, f32),
 
     debug_menu: DebugMenu,
+    console: Console,
 }
 
 impl Stage {
@@ -48,8 +83,9 @@ impl Stage {
         println!("Stage new");
 
         let mut renderer = Renderer::new();
-        let state = Box::new(Game::new());
-        // let state = Box::new(Editor::new());
+        let loading_game = Some(std::thread::spawn(Game::new));
+        // Stands in for the game until it has been generated
+        let state = Box::new(Editor::new());
 
         let egui_mq = egui_mq::EguiMq::new(&mut *renderer.ctx);
         egui_extras::install_image_loaders(egui_mq.egui_ctx());
@@ -57,7 +93,9 @@ impl Stage {
         Stage {
             egui_mq,
             sound_handler: SoundHandler::new(),
+            loading_game,
             state,
+            flow: GameFlow::Running,
             renderer,
             last_time: date::now(),
             updates: 0,
@@ -67,12 +105,55 @@ impl Stage {
             time_spent_drawing: 0.0,
             time_spent_updating: 0.0,
             mouse_pressed: false,
+            mouse_pos: (0.0, 0.0),
             debug_menu: DebugMenu::new(),
+            console: Console::default(),
             input: InputState::default(),
+            camera_keys: CameraKeys::default(),
             camera: Camera::new(0.0, 0.0, 2.0, width as f32, height as f32),
         }
     }
 
+    // From 0.0 to 1.0, the sounds and the game count half each
+    fn loading_progress(&self) -> f32 {
+        let game_progress = match &self.loading_game {
+            Some(game) if !game.is_finished() => 0.0,
+            _ => 1.0,
+        };
+        (self.sound_handler.loading_progress() + game_progress) * 0.5
+    }
+
+    // Returns true while still loading. The sounds keep loading after, when they are first played
+    fn update_loading(&mut self) -> bool {
+        self.sound_handler.load_some();
+        if self.loading_progress() < 1.0 {
+            return true;
+        }
+        if let Some(game) = self.loading_game.take() {
+            self.state = Box::new(game.join().expect("Generating the game failed"));
+            self.sound_handler.play_music(Music::Theme);
+        }
+        false
+    }
+
+    fn draw_loading_screen(&mut self) {
+        self.renderer.clear();
+        let progress = self.loading_progress();
+        self.egui_mq
+            .run(&mut *self.renderer.ctx, |_mq_ctx, egui_ctx| {
+                egui::Window::new("Loading")
+                    .title_bar(false)
+                    .resizable(false)
+                    .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                    .show(egui_ctx, |ui| {
+                        ui.label("Loading");
+                        ui.add(egui::ProgressBar::new(progress).desired_width(240.0));
+                    });
+            });
+        self.egui_mq.draw(&mut *self.renderer.ctx);
+        self.renderer.ctx.commit_frame();
+    }
+
     fn handle_editor_tile_drawing(&mut self, x: f32, y: f32) {
         let coords = self.camera.screen_to_tile(x, y);
         self.state.mouse_drawing(coords, &self.debug_menu);
@@ -85,23 +166,99 @@ impl EventHandler for Stage {
         let mut frame_time = update_start - self.last_time;
         self.last_time = update_start;
 
+        telemetry::record_frame(frame_time);
         if frame_time > 1.0 / 10.0 {
             frame_time = 1.0 / 10.0;
         }
 
-        self.accumulator += frame_time;
+        if self.update_loading() {
+            return;
+        }
+
+        // Fast-forward and slow-motion change how many fixed updates fit in to a frame. Everything
+        // (animations included) is stepped by the fixed updates so it all slows down together,
+        // sounds are still played at their normal pitch.
+        self.accumulator += frame_time * self.debug_menu.time_scale * self.state.time_scale();
+
+        let dt = 1.0 / self.debug_menu.updates_per_second;
 
-        let dt = 1.0 / 60.0;
+        // Both peers of a network session restart from the same seed when it starts
+        if let Some(session) = &mut self.debug_menu.net_session {
+            session.poll();
+            if let Some(seed) = session.take_seed_to_start() {
+                self.state = Box::new(Game::new_with_seed(seed));
+                self.debug_menu.is_game = true;
+                self.debug_menu.desync_checker = None;
+            }
+        }
+
+        // The player stands still while the free camera is used
+        let idle_input = InputState::default();
+
+        if self.flow == GameFlow::Paused {
+            self.accumulator = 0.0;
+        }
 
         while self.accumulator >= dt {
-            self.state.update(&self.input, &self.sound_handler); // HERE is the actual game call
+            let bot_input = self.state.bot_input(&mut self.debug_menu);
+            let local_input = if let Some(input) = &bot_input {
+                input
+            } else if self.debug_menu.free_camera {
+                &idle_input
+            } else {
+                &self.input
+            };
+            let network_input;
+            let input = match &mut self.debug_menu.net_session {
+                Some(session) => match session.step(local_input) {
+                    Some(input) => {
+                        network_input = input;
+                        &network_input
+                    }
+                    // The game waits for the input of the peer
+                    None => {
+                        self.accumulator = 0.0;
+                        break;
+                    }
+                },
+                None => local_input,
+            };
+            self.state.update(input, &self.sound_handler); // HERE is the actual game call
             self.state
-                .update_camera(&mut self.camera, !self.debug_menu.zoom_show_full); // HERE is the actual game call
+                .check_desync(input, &self.sound_handler, &mut self.debug_menu);
+            // Game over, the next game is made while the loading screen is shown
+            if let Some(seed) = self.state.next_game_seed() {
+                self.loading_game = Some(std::thread::spawn(move || Game::new_with_seed(seed)));
+                if let Some(checker) = &mut self.debug_menu.desync_checker {
+                    *checker = DesyncChecker::new(seed);
+                }
+                self.accumulator = 0.0;
+                break;
+            }
+            self.camera.update_shake();
+            if !self.debug_menu.free_camera {
+                self.state
+                    .update_camera(&mut self.camera, !self.debug_menu.zoom_show_full); // HERE is the actual game call
+            }
             self.updates += 1;
             self.accumulator -= dt;
 
             self.input.swing_pressed = false;
             self.input.jump_pressed = false;
+            self.input.dash_pressed = false;
+            self.input.bomb_pressed = false;
+        }
+
+        self.state.record_telemetry();
+
+        if self.debug_menu.free_camera {
+            let keys = &self.camera_keys;
+            let axis =
+                |negative: bool, positive: bool| positive as i32 as f32 - negative as i32 as f32;
+            let dx = axis(keys.left || self.input.left, keys.right || self.input.right);
+            let dy = axis(keys.up || self.input.up, keys.down || self.input.down);
+            self.camera
+                .free_fly(dx, dy, self.debug_menu.free_camera_speed, frame_time as f32);
         }
 
         let elapsed = update_start - self.last_time_ups;
@@ -126,6 +283,11 @@ impl EventHandler for Stage {
     }
 
     fn draw(&mut self) {
+        if self.loading_game.is_some() {
+            self.draw_loading_screen();
+            return;
+        }
+
         // Game
         let draw_start = date::now();
 
@@ -134,14 +296,64 @@ impl EventHandler for Stage {
             &self.camera,
             self.debug_menu.show_dark,
             self.input.show_map,
+            &self.sound_handler.captions(),
         );
         self.frames += 1;
         let draw_total = date::now() - draw_start;
         self.time_spent_drawing += draw_total;
 
         // GUI
+        let (buffers, textures) = self.renderer.live_gpu_objects();
+        let render_stats = self.renderer.last_frame_stats();
+        let asset_warnings = telemetry::asset_warnings();
         self.egui_mq
             .run(&mut *self.renderer.ctx, |_mq_ctx, egui_ctx| {
+                self.console
+                    .show(egui_ctx, self.state.as_mut(), &mut self.debug_menu);
+                self.debug_menu.render_toast(egui_ctx);
+                self.debug_menu
+                    .render_sound_overlay(egui_ctx, &self.sound_handler);
+
+                if !asset_warnings.is_empty() {
+                    egui::Window::new("Missing assets")
+                        .anchor(egui::Align2::RIGHT_TOP, [-8.0, 8.0])
+                        .show(egui_ctx, |ui| {
+                            for warning in &asset_warnings {
+                                ui.colored_label(egui::Color32::YELLOW, warning);
+                            }
+                        });
+                }
+
+                if self.flow == GameFlow::Paused {
+                    egui_ctx
+                        .layer_painter(egui::LayerId::background())
+                        .rect_filled(
+                            egui_ctx.screen_rect(),
+                            0.0,
+                            egui::Color32::from_black_alpha(150),
+                        );
+                    egui::Window::new("Paused")
+                        .collapsible(false)
+                        .resizable(false)
+                        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                        .show(egui_ctx, |ui| {
+                            if ui.button("Resume").clicked() {
+                                self.flow = GameFlow::Running;
+                            }
+                            if ui.button("Restart room").clicked() {
+                                self.state.restart_room();
+                                self.flow = GameFlow::Running;
+                            }
+                            let mut captions = self.sound_handler.captions_enabled();
+                            if ui.checkbox(&mut captions, "Captions").changed() {
+                                self.sound_handler.set_captions_enabled(captions);
+                            }
+                            if ui.button("Quit").clicked() {
+                                window::request_quit();
+                            }
+                        });
+                }
+
                 egui::Window::new("Debug").show(egui_ctx, |ui| {
                     let previous_selection = self.debug_menu.is_game;
                     egui::ComboBox::from_id_salt("Select one!")
@@ -164,10 +376,26 @@ impl EventHandler for Stage {
                             self.debug_menu.current_editor_room_index = 0;
                             self.debug_menu.editor_selection = EditorSelection::Tiles {
                                 selection: TileSelection::Stone,
+                                tool: TileTool::Brush,
                             }
                         }
                     }
 
+                    self.debug_menu.render_simulation_ui(ui);
+                    ui.label(format!("GPU: {} buffers, {} textures", buffers, textures));
+                    ui.label(format!(
+                        "Last frame: {} draws, {} uploads, {} pipelines, {} texture binds, {} uniforms",
+                        render_stats.draw_calls,
+                        render_stats.buffer_uploads,
+                        render_stats.pipeline_switches,
+                        render_stats.texture_binds,
+                        render_stats.uniform_updates
+                    ));
+                    self.debug_menu.render_camera_ui(ui, &mut self.camera);
+                    self.debug_menu.render_network_ui(ui);
+                    self.debug_menu.render_tuning_ui(ui);
+                    ui.checkbox(&mut self.debug_menu.show_sound_overlay, "Sound overlay");
+
                     self.state.render_ui(ui, &mut self.debug_menu)
                 });
             });
@@ -183,11 +411,24 @@ impl EventHandler for Stage {
     }
 
     fn key_down_event(&mut self, keycode: KeyCode, keymods: KeyMods, repeat: bool) {
+        if keycode == KeyCode::GraveAccent && !repeat {
+            self.console.toggle();
+            // Keys held when the console opened would stay held
+            self.input = InputState::default();
+            self.camera_keys = CameraKeys::default();
+            return;
+        }
+        // Typing in the console or a text field of the debug menu does not play the game
+        if self.console.open || self.egui_mq.egui_ctx().wants_keyboard_input() {
+            self.egui_mq.key_down_event(keycode, keymods);
+            return;
+        }
         match keycode {
             KeyCode::Left => self.input.left = true,
             KeyCode::Right => self.input.right = true,
             KeyCode::Up => self.input.up = true,
             KeyCode::M => self.input.show_map = true,
+            KeyCode::Tab => self.input.show_quest_log = true,
             KeyCode::X => {
                 if !repeat && !self.input.swing_held {
                     self.input.swing_pressed = true
@@ -200,7 +441,28 @@ impl EventHandler for Stage {
                 }
                 self.input.jump_held = true
             }
+            KeyCode::C => self.input.grab_held = true,
+            KeyCode::V if !repeat => self.input.dash_pressed = true,
+            KeyCode::B if !repeat => self.input.bomb_pressed = true,
             KeyCode::Down => self.input.down = true,
+            KeyCode::W => self.camera_keys.up = true,
+            KeyCode::A => self.camera_keys.left = true,
+            KeyCode::S => self.camera_keys.down = true,
+            KeyCode::D => self.camera_keys.right = true,
+            KeyCode::F if !repeat => self.debug_menu.toggle_time_scale(FAST_FORWARD_TIME_SCALE),
+            KeyCode::G if !repeat => self.debug_menu.toggle_time_scale(SLOW_MOTION_TIME_SCALE),
+            KeyCode::Escape if !repeat => {
+                self.flow = match self.flow {
+                    GameFlow::Running => GameFlow::Paused,
+                    GameFlow::Paused => GameFlow::Running,
+                }
+            }
+            KeyCode::T if !repeat => {
+                let coords = self
+                    .camera
+                    .screen_to_tile(self.mouse_pos.0, self.mouse_pos.1);
+                self.state.teleport_player(coords);
+            }
             _ => {}
         }
         self.egui_mq.key_down_event(keycode, keymods);
@@ -212,9 +474,15 @@ impl EventHandler for Stage {
             KeyCode::Right => self.input.right = false,
             KeyCode::Up => self.input.up = false,
             KeyCode::M => self.input.show_map = false,
+            KeyCode::Tab => self.input.show_quest_log = false,
             KeyCode::X => self.input.swing_held = false,
             KeyCode::Z => self.input.jump_held = false,
+            KeyCode::C => self.input.grab_held = false,
             KeyCode::Down => self.input.down = false,
+            KeyCode::W => self.camera_keys.up = false,
+            KeyCode::A => self.camera_keys.left = false,
+            KeyCode::S => self.camera_keys.down = false,
+            KeyCode::D => self.camera_keys.right = false,
             _ => {}
         }
         self.egui_mq.key_up_event(keycode, keymods);
@@ -227,6 +495,7 @@ impl EventHandler for Stage {
 
     fn mouse_motion_event(&mut self, x: f32, y: f32) {
         self.egui_mq.mouse_motion_event(x, y);
+        self.mouse_pos = (x, y);
 
         if self.egui_mq.egui_ctx().wants_pointer_input() {
             return;
@@ -257,14 +526,30 @@ impl EventHandler for Stage {
         self.mouse_pressed = false;
 
         self.egui_mq.mouse_button_up_event(mb, x, y);
+
+        self.state.mouse_released(&self.debug_menu);
+    }
+
+    fn quit_requested_event(&mut self) {
+        telemetry::write_summary("quit");
+        if let Err(error) = save::save() {
+            eprintln!("Could not save the progress: {}", error);
+        }
     }
 
     fn char_event(&mut self, character: char, _keymods: KeyMods, _repeat: bool) {
+        // The console key does not end up in the console
+        if character == '`' || character == '~' {
+            return;
+        }
         self.egui_mq.char_event(character);
     }
 }
 
 fn main() {
+    telemetry::install_panic_hook();
+    tuning::load();
+    save::load();
     miniquad::start(
         conf::Conf {
             window_title: String::from("Miniquad Dual-Grid Tilemap"),
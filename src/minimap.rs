@@ -1,9 +1,11 @@
 use crate::camera::Camera;
 use crate::render::{Renderer, TextureIndexes, TextureInfo, Uniforms};
 use crate::state::map_like::{GameMap, MapLike};
+use crate::state::medals::Medal;
 use miniquad::{FilterMode, MipmapFilterMode, TextureWrap, UniformsSource};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
+#[derive(Clone)]
 struct MinimapLocation {
     // top left in pixels
     x: f32,
@@ -39,10 +41,21 @@ fn minimap_location_mix(
     }
 }
 
+/// The rooms to show on the minimap, only the visited ones are shown
+pub struct MinimapRooms<'a> {
+    pub current: usize,
+    pub visited: &'a HashSet<usize>,
+    pub completed: &'a HashSet<usize>,
+    // The best medal got for each room, they are shown in the color of the medal
+    pub medals: &'a HashMap<usize, Medal>,
+}
+
+#[derive(Clone)]
 pub struct Minimap {
     minimap_smooth_center: Option<(f32, f32)>, // Smooth the transition of the centered room
-    visited_rooms: HashSet<usize>,             // Define what rooms to show
     previous_room_index: Option<usize>, // Used for defining when to recreate the minimap texture
+    previous_completed_count: usize,    // Completing a room recreates the texture too
+    previous_medal_count: usize,        // And so does getting a medal
     location: Option<MinimapLocation>,
 }
 
@@ -51,14 +64,11 @@ const MINIMAP_CENTER_LERP: f32 = 0.13;
 
 impl Minimap {
     pub fn new() -> Minimap {
-        let visited_rooms = HashSet::new();
-        // for i in 0..100 {
-        //     visited_rooms.insert(i);
-        // }
         Minimap {
             minimap_smooth_center: None,
-            visited_rooms,
             previous_room_index: None,
+            previous_completed_count: 0,
+            previous_medal_count: 0,
             location: None,
         }
     }
@@ -66,16 +76,16 @@ impl Minimap {
     fn update_minimap_size_and_location(
         &mut self,
         map: &GameMap,
+        visited_rooms: &HashSet<usize>,
         camera: &Camera,
         draw_big: bool,
         smooth_center: (f32, f32),
-        texture_width: u32,
-        texture_height: u32,
+        (texture_width, texture_height): (u32, u32),
     ) {
         let (start_x, start_y, _map_width, _map_height) = map.get_bounds();
         let desired_location = if draw_big {
             let (view_start_x, view_start_y, view_width, view_height) =
-                map.get_bounds_for_rooms(&self.visited_rooms);
+                map.get_bounds_for_rooms(visited_rooms);
 
             // Scale the map so that the currently visible part fits the screen
             let max_dim = view_width.max(view_height);
@@ -145,20 +155,33 @@ impl Minimap {
         renderer: &mut Renderer,
         camera: &Camera,
         map: &GameMap,
-        current_room_index: usize,
+        rooms: MinimapRooms,
         draw_big: bool, // Controls if is drawn into the corner of as big one on the center of the screen
     ) {
-        self.visited_rooms.insert(current_room_index);
-
+        let MinimapRooms {
+            current: current_room_index,
+            visited: visited_rooms,
+            completed: completed_rooms,
+            medals,
+        } = rooms;
         let smooth_center = self.update_and_get_minimap_smooth_center(map, current_room_index);
 
-        // Redraw texture only when the current room changes
+        // Redraw texture only when the current room changes or a room is completed
         if self.previous_room_index.is_none()
             || self.previous_room_index.unwrap() != current_room_index
+            || self.previous_completed_count != completed_rooms.len()
+            || self.previous_medal_count != medals.len()
         {
             self.previous_room_index = Some(current_room_index);
-            let (pixels, texture_width, texture_height) =
-                self.construct_minimap_image(map, current_room_index);
+            self.previous_completed_count = completed_rooms.len();
+            self.previous_medal_count = medals.len();
+            let (pixels, texture_width, texture_height) = self.construct_minimap_image(
+                map,
+                current_room_index,
+                visited_rooms,
+                completed_rooms,
+                medals,
+            );
             self.update_minimap_texture_with_pixels(
                 renderer,
                 pixels,
@@ -172,11 +195,11 @@ impl Minimap {
 
         self.update_minimap_size_and_location(
             map,
+            visited_rooms,
             camera,
             draw_big,
             smooth_center,
-            current_size.0,
-            current_size.1,
+            current_size,
         );
 
         self.draw_minimap(renderer, camera);
@@ -209,6 +232,9 @@ impl Minimap {
         &mut self,
         map: &GameMap,
         current_room_index: usize,
+        visited_rooms: &HashSet<usize>,
+        completed_rooms: &HashSet<usize>,
+        medals: &HashMap<usize, Medal>,
     ) -> (Vec<u8>, u32, u32) {
         // Construct the minimap image
         let (start_x, start_y, map_width, map_height) = map.get_bounds();
@@ -224,6 +250,7 @@ impl Minimap {
         const MINIMAP_BORDER_COLOR: [u8; 4] = [255, 255, 255, 255]; // white (room outlines only)
         const MINIMAP_CURRENT_ROOM_COLOR: [u8; 4] = [173, 216, 230, 255]; // light blue (current room interior)
         const MINIMAP_OTHER_ROOM_COLOR: [u8; 4] = [200, 200, 205, 255]; // light gray (other room interior)
+        const MINIMAP_COMPLETED_ROOM_COLOR: [u8; 4] = [232, 205, 120, 255]; // gold (completed room interior)
         // const MINIMAP_DOOR_COLOR: [u8; 4] = [230, 230, 230, 255]; // light gray (other room interior)
         const MINIMAP_DOOR_COLOR: [u8; 4] = [200, 200, 205, 255]; // light gray (other room interior)
         const TRANSPARENT: [u8; 4] = [0, 0, 0, 0];
@@ -244,21 +271,25 @@ impl Minimap {
                 let tx = (px_pad - PAD) + start_x;
                 let ty = (py_pad - PAD) + start_y;
 
-                if map.is_room_border_for_some_room(tx, ty, &self.visited_rooms) {
+                if map.is_room_border_for_some_room(tx, ty, visited_rooms) {
                     if map.is_door_at_i(tx, ty) {
                         pixels.extend_from_slice(&MINIMAP_DOOR_COLOR);
                     } else {
                         pixels.extend_from_slice(&MINIMAP_BORDER_COLOR);
                     }
                 } else if let Some((index, _room)) = map.get_room_at_i(tx, ty) {
-                    let color = if !self.visited_rooms.contains(&index) {
-                        &TRANSPARENT
+                    let color = if !visited_rooms.contains(&index) {
+                        TRANSPARENT
                     } else if current_room_index == index {
-                        &MINIMAP_CURRENT_ROOM_COLOR
+                        MINIMAP_CURRENT_ROOM_COLOR
+                    } else if let Some(medal) = medals.get(&index) {
+                        medal.color()
+                    } else if completed_rooms.contains(&index) {
+                        MINIMAP_COMPLETED_ROOM_COLOR
                     } else {
-                        &MINIMAP_OTHER_ROOM_COLOR
+                        MINIMAP_OTHER_ROOM_COLOR
                     };
-                    pixels.extend_from_slice(color);
+                    pixels.extend_from_slice(&color);
                 } else {
                     pixels.extend_from_slice(&TRANSPARENT);
                 }
@@ -293,11 +324,12 @@ impl Minimap {
             renderer
                 .ctx
                 .texture_set_wrap(new_tex, TextureWrap::Clamp, TextureWrap::Clamp);
-            renderer.textures.insert(
+            renderer.replace_texture(
                 TextureIndexes::Minimap,
                 TextureInfo::new(texture_width as f32, texture_height as f32, new_tex),
             );
         } else {
+            renderer.stats.buffer_uploads += 1;
             renderer.ctx.texture_update(minimap_info.texture, &pixels);
         }
     }
@@ -311,6 +343,7 @@ impl Minimap {
 
         renderer.bindings.images[0] = minimap_info.texture;
         renderer.bindings.images[1] = background.texture;
+        renderer.stats.bind(&renderer.bindings);
         renderer.ctx.apply_bindings(&renderer.bindings);
 
         if let Some(location) = &self.location {
@@ -339,9 +372,11 @@ impl Minimap {
                 bg_tex_size: [background.w, background.h, 0.0, 0.0],
             };
 
+            renderer.stats.uniform_updates += 1;
             renderer
                 .ctx
                 .apply_uniforms(UniformsSource::table(&uniforms));
+            renderer.stats.draw_calls += 1;
             renderer.ctx.draw(0, 6, 1);
         }
     }
@@ -0,0 +1,285 @@
+use crate::state::InputState;
+use std::collections::BTreeMap;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+// The local input of a frame is used this many frames later, so that it has time to get to the
+// other peer before it is needed
+const INPUT_DELAY: u32 = 4;
+// Every packet repeats the last inputs, a lost packet is covered by the next one
+const RESENT_INPUTS: u32 = 16;
+const MAX_PACKET_SIZE: usize = 512;
+
+const HELLO: u8 = 0;
+const WELCOME: u8 = 1;
+const INPUTS: u8 = 2;
+
+// The inputs that are sent to the other peer, one bit each. Showing the map and the quest log
+// are left out, they do not change the game.
+fn pack(input: &InputState) -> u16 {
+    [
+        input.left,
+        input.right,
+        input.up,
+        input.down,
+        input.swing_pressed,
+        input.jump_pressed,
+        input.swing_held,
+        input.jump_held,
+        input.grab_held,
+        input.dash_pressed,
+        input.bomb_pressed,
+    ]
+    .iter()
+    .enumerate()
+    .fold(0, |bits, (i, &pressed)| bits | (pressed as u16) << i)
+}
+
+fn unpack(bits: u16) -> InputState {
+    let bit = |i: u16| bits & (1 << i) != 0;
+    InputState {
+        left: bit(0),
+        right: bit(1),
+        up: bit(2),
+        down: bit(3),
+        swing_pressed: bit(4),
+        jump_pressed: bit(5),
+        swing_held: bit(6),
+        jump_held: bit(7),
+        grab_held: bit(8),
+        dash_pressed: bit(9),
+        bomb_pressed: bit(10),
+        ..Default::default()
+    }
+}
+
+// Two peers simulating the same game in lockstep. Both start from the seed of the host and only
+// the inputs are sent over UDP, a frame is simulated once the inputs of both peers for it are
+// there. There is only one player in the game so far, both peers control it together.
+pub struct LockstepSession {
+    socket: UdpSocket,
+    peer: Option<SocketAddr>,
+    seed: u64,
+    started: bool,
+    // Set when the session starts, the game is restarted from the seed
+    seed_to_start: Option<u64>,
+    // The next frame to simulate
+    frame: u32,
+    local_inputs: BTreeMap<u32, u16>,
+    remote_inputs: BTreeMap<u32, u16>,
+}
+
+impl LockstepSession {
+    /// Waits for a peer to join on the port
+    pub fn host(port: u16) -> io::Result<Self> {
+        Self::new(UdpSocket::bind(("0.0.0.0", port))?, None, rand::random())
+    }
+
+    pub fn join(address: &str) -> io::Result<Self> {
+        let peer = address.to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "could not resolve the address")
+        })?;
+        // The seed comes from the host
+        Self::new(UdpSocket::bind(("0.0.0.0", 0))?, Some(peer), 0)
+    }
+
+    fn new(socket: UdpSocket, peer: Option<SocketAddr>, seed: u64) -> io::Result<Self> {
+        socket.set_nonblocking(true)?;
+        // Nothing is pressed during the first frames, before the first inputs are used
+        let empty_inputs: BTreeMap<u32, u16> = (0..INPUT_DELAY).map(|frame| (frame, 0)).collect();
+        Ok(LockstepSession {
+            socket,
+            peer,
+            seed,
+            started: false,
+            seed_to_start: None,
+            frame: 0,
+            local_inputs: empty_inputs.clone(),
+            remote_inputs: empty_inputs,
+        })
+    }
+
+    #[cfg(test)]
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    pub fn status(&self) -> String {
+        match (self.started, self.peer.is_some()) {
+            (false, false) => format!(
+                "Hosting on port {}, waiting for a peer",
+                self.socket.local_addr().map_or(0, |addr| addr.port())
+            ),
+            (false, true) => "Joining".to_string(),
+            (true, _) if self.remote_inputs.contains_key(&self.frame) => {
+                format!("Connected, frame {}", self.frame)
+            }
+            (true, _) => format!("Waiting for the peer, frame {}", self.frame),
+        }
+    }
+
+    /// The seed to restart the game from, once when the session starts
+    pub fn take_seed_to_start(&mut self) -> Option<u64> {
+        self.seed_to_start.take()
+    }
+
+    fn send(&self, packet: &[u8]) {
+        if let Some(peer) = self.peer {
+            // Lost packets are sent again anyway
+            let _ = self.socket.send_to(packet, peer);
+        }
+    }
+
+    // Stores the inputs of an INPUTS packet. The frame numbers come from the other peer, so the
+    // whole packet is dropped if they overflow and only frames that can still be used are kept.
+    fn receive_inputs(&mut self, packet: &[u8]) {
+        let first_frame = u32::from_le_bytes(packet[1..5].try_into().unwrap());
+        let frames = packet[5..].chunks_exact(2);
+        if first_frame.checked_add(frames.len() as u32).is_none() {
+            return;
+        }
+        let wanted = self.frame..self.frame + INPUT_DELAY + RESENT_INPUTS;
+        for (i, bits) in frames.enumerate() {
+            let frame = first_frame + i as u32;
+            if wanted.contains(&frame) {
+                self.remote_inputs
+                    .insert(frame, u16::from_le_bytes([bits[0], bits[1]]));
+            }
+        }
+    }
+
+    fn start(&mut self) {
+        if !self.started {
+            self.started = true;
+            self.seed_to_start = Some(self.seed);
+        }
+    }
+
+    /// Reads the packets that have arrived and sends the handshake or the latest inputs, call
+    /// once per frame
+    pub fn poll(&mut self) {
+        let mut buffer = [0; MAX_PACKET_SIZE];
+        while let Ok((size, from)) = self.socket.recv_from(&mut buffer) {
+            let packet = &buffer[..size];
+            match packet.first() {
+                // Only the first peer gets to join
+                Some(&HELLO) if self.peer.is_none_or(|peer| peer == from) => {
+                    self.peer = Some(from);
+                    self.start();
+                    let mut welcome = vec![WELCOME];
+                    welcome.extend(self.seed.to_le_bytes());
+                    self.send(&welcome);
+                }
+                Some(&WELCOME) if size == 9 && Some(from) == self.peer => {
+                    self.seed = u64::from_le_bytes(packet[1..9].try_into().unwrap());
+                    self.start();
+                }
+                Some(&INPUTS) if size >= 5 && Some(from) == self.peer => {
+                    self.receive_inputs(packet);
+                }
+                _ => {}
+            }
+        }
+
+        if !self.started {
+            if self.peer.is_some() {
+                self.send(&[HELLO]);
+            }
+            return;
+        }
+
+        let last_frame = self.frame + INPUT_DELAY;
+        let first_frame = last_frame.saturating_sub(RESENT_INPUTS);
+        let mut inputs = vec![INPUTS];
+        inputs.extend(first_frame.to_le_bytes());
+        inputs.extend(
+            self.local_inputs
+                .range(first_frame..last_frame)
+                .flat_map(|(_, bits)| bits.to_le_bytes()),
+        );
+        self.send(&inputs);
+    }
+
+    /// The input to simulate the next frame with, None while the input of the peer has not
+    /// arrived yet. The local input is used INPUT_DELAY frames later.
+    pub fn step(&mut self, local_input: &InputState) -> Option<InputState> {
+        if !self.started {
+            return None;
+        }
+        let remote_bits = *self.remote_inputs.get(&self.frame)?;
+        let local_bits = self.local_inputs[&self.frame];
+        self.local_inputs
+            .insert(self.frame + INPUT_DELAY, pack(local_input));
+
+        // Forget what the peer can not be missing anymore
+        let frame = self.frame;
+        self.local_inputs
+            .retain(|&old, _| old + RESENT_INPUTS >= frame);
+        self.remote_inputs.retain(|&old, _| old >= frame);
+        self.frame += 1;
+
+        let mut input = unpack(local_bits | remote_bits);
+        input.show_map = local_input.show_map;
+        input.show_quest_log = local_input.show_quest_log;
+        Some(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn peers_simulate_the_same_inputs() {
+        let mut host = LockstepSession::host(0).unwrap();
+        let port = host.local_addr().unwrap().port();
+        let mut peer = LockstepSession::join(&format!("127.0.0.1:{}", port)).unwrap();
+
+        let jumping = InputState {
+            jump_pressed: true,
+            right: true,
+            ..Default::default()
+        };
+        let mut host_inputs = vec![];
+        let mut peer_inputs = vec![];
+        for _ in 0..500 {
+            host.poll();
+            peer.poll();
+            if let Some(input) = host.step(&jumping) {
+                host_inputs.push(pack(&input));
+            }
+            if let Some(input) = peer.step(&InputState::default()) {
+                peer_inputs.push(pack(&input));
+            }
+            if host_inputs.len() > 20 && peer_inputs.len() > 20 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+
+        assert_eq!(host.take_seed_to_start(), peer.take_seed_to_start());
+        let frames = host_inputs.len().min(peer_inputs.len());
+        assert!(frames > 20);
+        assert_eq!(host_inputs[..frames], peer_inputs[..frames]);
+        assert_eq!(host_inputs[0], 0);
+        assert!(unpack(host_inputs[frames - 1]).right);
+    }
+
+    #[test]
+    fn inputs_outside_of_the_window_are_ignored() {
+        let mut session = LockstepSession::host(0).unwrap();
+        let before = session.remote_inputs.clone();
+
+        let mut overflowing = vec![INPUTS];
+        overflowing.extend((u32::MAX - 1).to_le_bytes());
+        overflowing.extend([1, 0].repeat(4));
+        session.receive_inputs(&overflowing);
+
+        let mut far_ahead = vec![INPUTS];
+        far_ahead.extend(1_000_000u32.to_le_bytes());
+        far_ahead.extend([1, 0].repeat(4));
+        session.receive_inputs(&far_ahead);
+
+        assert_eq!(session.remote_inputs, before);
+    }
+}
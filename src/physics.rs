@@ -1,10 +1,8 @@
 use crate::state::BoundingBox;
 use crate::state::Dir;
 use crate::state::Pos;
-use crate::state::map_like::MapLike;
-
-const GRAVITY: f32 = 0.0070;
-const TERMINAL_VELOCITY: f32 = 0.90;
+use crate::state::map_like::{MapLike, Material};
+use crate::tuning::tuning;
 
 pub struct KinematicResult {
     pub new_bb: BoundingBox,
@@ -12,6 +10,8 @@ pub struct KinematicResult {
     pub on_top: bool,
     pub on_left: bool,
     pub on_right: bool,
+    // What the ground is made of when on_bottom
+    pub ground: Option<Material>,
 }
 
 impl KinematicResult {
@@ -31,7 +31,18 @@ pub fn integrate_kinematic(
     let mut on_right = false;
 
     // Horizontal sweep
-    let (out_x, hit_x) = sweep_axis(world, bb.x, bb.y, bb.w, bb.h, bb.vx, Axis::X);
+    let (mut out_x, mut hit_x) = sweep_axis(world, bb.x, bb.y, bb.w, bb.h, bb.vx, Axis::X);
+
+    // Walking in to a slope lifts the body as much as it moves sideways
+    let mut start_y = bb.y;
+    if hit_x && gravity && slope_under(world, bb.x + bb.vx, bb.y, bb.w, bb.h) {
+        let lifted_y = bb.y - bb.vx.abs() - EPS;
+        if !world.overlaps_solid(bb.x + bb.vx, lifted_y, bb.w, bb.h) {
+            out_x = bb.x + bb.vx;
+            hit_x = false;
+            start_y = lifted_y;
+        }
+    }
 
     if hit_x {
         if bb.vx > 0.0 {
@@ -44,13 +55,27 @@ pub fn integrate_kinematic(
 
     // Gravity
     let mut vy = if gravity {
-        (bb.vy + GRAVITY).min(TERMINAL_VELOCITY)
+        let tuning = tuning();
+        (bb.vy + tuning.gravity).min(tuning.terminal_velocity)
     } else {
         bb.vy
     };
 
     // Vertical sweep
-    let (out_y, hit_y) = sweep_axis(world, out_x, bb.y, bb.w, bb.h, vy, Axis::Y);
+    let (mut out_y, mut hit_y) = sweep_axis(world, out_x, start_y, bb.w, bb.h, vy, Axis::Y);
+
+    // Walking down a slope keeps the body on it instead of falling off in small steps
+    let was_on_ground = world.overlaps_solid(bb.x, bb.y + EPS * 4.0, bb.w, bb.h);
+    if !hit_y && gravity && vy >= 0.0 && was_on_ground {
+        let step = bb.vx.abs() + EPS * 4.0;
+        if slope_under(world, out_x, out_y + step, bb.w, bb.h) {
+            let (snapped_y, snapped) = sweep_axis(world, out_x, out_y, bb.w, bb.h, step, Axis::Y);
+            if snapped {
+                out_y = snapped_y;
+                hit_y = true;
+            }
+        }
+    }
 
     if hit_y {
         if vy > 0.0 {
@@ -62,15 +87,21 @@ pub fn integrate_kinematic(
         vy = 0.0;
     }
 
+    let new_bb = BoundingBox {
+        x: out_x,
+        y: out_y,
+        w: bb.w,
+        h: bb.h,
+        vx: bb.vx,
+        vy,
+    };
     KinematicResult {
-        new_bb: BoundingBox {
-            x: out_x,
-            y: out_y,
-            w: bb.w,
-            h: bb.h,
-            vx: bb.vx,
-            vy,
+        ground: if on_bottom {
+            ground_material(world, &new_bb)
+        } else {
+            None
         },
+        new_bb,
         on_bottom,
         on_top,
         on_left,
@@ -78,6 +109,39 @@ pub fn integrate_kinematic(
     }
 }
 
+/// Like integrate_kinematic but lands on the platforms when falling on them from above, for
+/// everything that does not drop through platforms on its own
+pub fn integrate_kinematic_with_platforms(
+    world: &dyn MapLike,
+    bb: &BoundingBox,
+    gravity: bool,
+) -> KinematicResult {
+    let mut result = integrate_kinematic(world, bb, gravity);
+    if check_and_snap_platforms(bb, &mut result.new_bb, world) {
+        result.on_bottom = true;
+        result.ground = ground_material(world, &result.new_bb);
+    }
+    result
+}
+
+/// What the ground under the box is made of, the platforms are wood. The middle of the bottom
+/// counts first and then the corners, for standing on the edge of a ledge.
+pub fn ground_material(world: &dyn MapLike, bb: &BoundingBox) -> Option<Material> {
+    let ty = (bb.y + bb.h + EPS * 16.0).floor() as i32;
+    [bb.x + bb.w * 0.5, bb.x, bb.x + bb.w].iter().find_map(|x| {
+        let tx = x.floor() as i32;
+        let (base, _) = world.get_at(tx, ty);
+        base.material()
+            .or_else(|| world.is_platform_at(tx, ty).then_some(Material::Wood))
+    })
+}
+
+// Is there a slope under either bottom corner of the box
+fn slope_under(world: &dyn MapLike, x: f32, y: f32, w: f32, h: f32) -> bool {
+    let bottom = (y + h).floor() as i32;
+    world.is_slope_at(x.floor() as i32, bottom) || world.is_slope_at((x + w).floor() as i32, bottom)
+}
+
 #[derive(Copy, Clone)]
 enum Axis {
     X,
@@ -213,7 +277,7 @@ pub fn check_and_snap_hang(
     };
 
     // Not close to the wall
-    if dist_to_wall > EPS * 3.0 {
+    if dist_to_wall > tuning().hang_epsilon {
         return None;
     }
 
@@ -248,3 +312,145 @@ pub fn check_and_snap_hang(
         y: snapped_y,
     })
 }
+
+/// Walks the tiles the line between the points crosses, in order, and checks that none of them
+/// is solid. A line going exactly through the corner of tiles is blocked by either of the tiles
+/// beside the corner.
+pub fn has_line_of_sight(map: &dyn MapLike, from: Pos, to: Pos) -> bool {
+    let (dx, dy) = (to.x - from.x, to.y - from.y);
+    let (mut tx, mut ty) = (from.x.floor() as i32, from.y.floor() as i32);
+    let (end_tx, end_ty) = (to.x.floor() as i32, to.y.floor() as i32);
+    let step_x = if dx < 0.0 { -1 } else { 1 };
+    let step_y = if dy < 0.0 { -1 } else { 1 };
+    // How far along the line, from 0.0 to 1.0, the next tile edge is on each axis and how much
+    // further the one after it is
+    let first_edge = |start: f32, tile: i32, delta: f32| {
+        if delta > 0.0 {
+            (tile as f32 + 1.0 - start) / delta
+        } else if delta < 0.0 {
+            (tile as f32 - start) / delta
+        } else {
+            f32::INFINITY
+        }
+    };
+    let mut next_x = first_edge(from.x, tx, dx);
+    let mut next_y = first_edge(from.y, ty, dy);
+    let step_len_x = (1.0 / dx).abs();
+    let step_len_y = (1.0 / dy).abs();
+
+    // Every tile crossed moves one step on one of the axes, or on both through a corner
+    let mut steps_left = (end_tx - tx).abs() + (end_ty - ty).abs();
+    while steps_left > 0 {
+        if map.is_solid_at_tile(tx, ty) {
+            return false;
+        }
+        if next_x < next_y {
+            tx += step_x;
+            next_x += step_len_x;
+            steps_left -= 1;
+        } else if next_y < next_x {
+            ty += step_y;
+            next_y += step_len_y;
+            steps_left -= 1;
+        } else {
+            if map.is_solid_at_tile(tx + step_x, ty) || map.is_solid_at_tile(tx, ty + step_y) {
+                return false;
+            }
+            tx += step_x;
+            ty += step_y;
+            next_x += step_len_x;
+            next_y += step_len_y;
+            steps_left -= 2;
+        }
+    }
+    !map.is_solid_at_tile(tx, ty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::map_like::Room;
+    use crate::state::{BaseTile, OverlayTile};
+
+    fn room_with_stone_at(x: u32, y: u32) -> Room {
+        let mut room = Room::new_empty(0, 0, 10, 10, BaseTile::Empty, OverlayTile::None);
+        room.set_base_absolute(x, y, BaseTile::Stone);
+        room
+    }
+
+    #[test]
+    fn line_of_sight_is_blocked_by_the_corner_of_a_tile() {
+        // The line clips the bottom right corner of the stone, sampling the line a few times per
+        // tile steps over it
+        let room = room_with_stone_at(3, 3);
+        assert!(!has_line_of_sight(
+            &room,
+            Pos::new(1.5, 5.5),
+            Pos::new(7.5, 1.5)
+        ));
+        assert!(!has_line_of_sight(
+            &room,
+            Pos::new(7.5, 1.5),
+            Pos::new(1.5, 5.5)
+        ));
+        // Just past the corner
+        assert!(has_line_of_sight(
+            &room,
+            Pos::new(1.5, 5.5),
+            Pos::new(7.5, 3.5)
+        ));
+    }
+
+    #[test]
+    fn walks_up_and_down_a_slope() {
+        // The floor is at y = 7, a slope at x = 5 leads up on to stone that is a tile higher
+        let mut room = Room::new_boxed(0, 0, 12, 8);
+        room.set_base_absolute(5, 6, BaseTile::SlopeUpRight);
+        for x in 6..11 {
+            room.set_base_absolute(x, 6, BaseTile::Stone);
+        }
+        let mut bb = BoundingBox {
+            x: 2.0,
+            y: 7.0 - 0.75 - EPS,
+            w: 0.5,
+            h: 0.75,
+            vx: 0.05,
+            vy: 0.0,
+        };
+
+        for _ in 0..130 {
+            let res = integrate_kinematic(&room, &bb, true);
+            assert!(
+                !res.on_right,
+                "stopped by the slope at x = {}",
+                res.new_bb.x
+            );
+            bb = res.new_bb;
+        }
+        assert!(bb.x > 8.0);
+        assert!((bb.y + bb.h - 6.0).abs() < 0.01);
+
+        bb.vx = -0.05;
+        for _ in 0..130 {
+            bb = integrate_kinematic(&room, &bb, true).new_bb;
+        }
+        assert!(bb.x < 3.0);
+        assert!((bb.y + bb.h - 7.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn line_of_sight_through_a_corner_is_blocked_by_either_side() {
+        let room = room_with_stone_at(3, 2);
+        assert!(!has_line_of_sight(
+            &room,
+            Pos::new(1.5, 1.5),
+            Pos::new(5.5, 5.5)
+        ));
+        let room = room_with_stone_at(6, 6);
+        assert!(has_line_of_sight(
+            &room,
+            Pos::new(1.5, 1.5),
+            Pos::new(5.5, 5.5)
+        ));
+    }
+}
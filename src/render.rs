@@ -1,14 +1,22 @@
 use super::state::enemies::Enemy;
 use crate::atlas_info::AtlasInfo;
 use crate::camera::Camera;
+use crate::light_pass::LightPass;
+use crate::pixel_font;
+use crate::sound_handler::Caption;
 use crate::state::GameState;
+use crate::state::abilities::Ability;
+use crate::state::checkpoints::draw_flag;
 use crate::state::game_state::{Editor, Game};
+use crate::state::lighting::{Light, MAX_LIGHTS};
 use crate::state::map_like::{DoorDir, MapLike};
-use crate::state::{BaseTile, OverlayTile};
+use crate::state::training::draw_switch;
+use crate::state::{BaseTile, BoundingBox, OverlayTile, Pos};
+use crate::telemetry;
 
 use image::GenericImageView;
 use miniquad::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[repr(C)]
 pub struct Uniforms {
@@ -25,19 +33,186 @@ pub struct Uniforms {
 }
 
 #[repr(C)]
+#[derive(Clone, Copy)]
 struct Vertex {
     pos: [f32; 2],
     uv: [f32; 2],
 }
 
+/// Draw layers from back to front. Everything queued during a frame is sorted by the layer and
+/// draws within the same layer keep the order they were queued in.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum RenderLayer {
+    Background,
+    Tiles,
+    BehindEntities, // Overlay tiles, doors and such decor
+    Entities,
+    Player,
+    Foreground,
+    Particles,
+    Hud, // Drawn in screen pixels on its own pass after all the other layers
+}
+
+enum DrawCommand {
+    // Sprite from the texture atlas. Consecutive sprites are drawn in one batch.
+    Sprite([Vertex; 4]),
+    // Colored quad with its own model matrix
+    Rect {
+        model: [f32; 16],
+        color: [f32; 4],
+        world_base: [f32; 2],
+        world_scale: [f32; 2],
+    },
+    // Dual-grid tile mesh (positions in world pixels, precomputed UVs)
+    DualGrid {
+        tile_type_index: u8,
+        opacity: f32,
+        vertices: Vec<Vertex>,
+        indices: Vec<u16>,
+    },
+    // Dual-grid tile mesh that is already in the buffers of the tile type, see TileMesh
+    TileMesh {
+        tile_type_index: u8,
+        bg_region: u8,
+        opacity: f32,
+    },
+}
+
+// The dual grid mesh of a tile type of the map, kept in its own buffers between frames
+struct TileMesh {
+    vb: BufferId,
+    ib: BufferId,
+    vb_cap: usize,
+    ib_cap: usize,
+    index_count: i32,
+}
+
+// The buffers and textures the renderer has made and not deleted yet, the light pass target
+// included. Replaced ones are deleted right away and the rest when the renderer is dropped, the
+// debug menu shows the counts.
+#[derive(Default)]
+pub struct GpuObjects {
+    buffers: HashSet<BufferId>,
+    textures: HashSet<TextureId>,
+}
+
+impl GpuObjects {
+    fn replace_buffer(&mut self, ctx: &mut Context, buffer: &mut BufferId, new: BufferId) {
+        ctx.delete_buffer(*buffer);
+        self.buffers.remove(buffer);
+        self.buffers.insert(new);
+        *buffer = new;
+    }
+
+    fn replace_texture(&mut self, ctx: &mut Context, old: TextureId, new: TextureId) {
+        ctx.delete_texture(old);
+        self.textures.remove(&old);
+        self.textures.insert(new);
+    }
+
+    pub fn new_render_texture(&mut self, ctx: &mut Context, params: TextureParams) -> TextureId {
+        let texture = ctx.new_render_texture(params);
+        self.textures.insert(texture);
+        texture
+    }
+
+    pub fn delete_texture(&mut self, ctx: &mut Context, texture: TextureId) {
+        ctx.delete_texture(texture);
+        self.textures.remove(&texture);
+    }
+}
+
+/// What the renderer asked from the GPU during a frame, for measuring rendering changes
+#[derive(Default, Clone, Copy)]
+pub struct RenderStats {
+    pub draw_calls: u32,
+    pub buffer_uploads: u32,
+    pub pipeline_switches: u32,
+    pub texture_binds: u32,
+    pub uniform_updates: u32,
+}
+
+impl RenderStats {
+    pub fn bind(&mut self, bindings: &Bindings) {
+        self.texture_binds += bindings.images.len() as u32;
+    }
+}
+
+// The base tiles under the dual grid range. They are read from the map once when the tile
+// meshes are built and every tile type indexes in to them instead of asking the map again.
+struct TileWindow {
+    x: i32,
+    y: i32,
+    w: i32,
+    h: i32,
+    tiles: Vec<BaseTile>,
+    // Index in to BIOME_BACKGROUNDS of the room of each tile
+    biomes: Vec<usize>,
+}
+
+impl TileWindow {
+    // (start_x, start_y, end_x, end_y) of the dual grid cells, the cells use the tiles up to end
+    fn new(map: &dyn MapLike, (start_x, start_y, end_x, end_y): (i32, i32, i32, i32)) -> Self {
+        let w = end_x - start_x + 1;
+        let h = end_y - start_y + 1;
+        let mut tiles = Vec::with_capacity((w * h) as usize);
+        let mut biomes = Vec::with_capacity((w * h) as usize);
+        for y in start_y..start_y + h {
+            for x in start_x..start_x + w {
+                tiles.push(map.get_at(x, y).0);
+                let biome = map.get_room_at_i(x, y).map_or(0, |(_, room)| room.biome());
+                biomes.push(biome as usize % BIOME_BACKGROUNDS.len());
+            }
+        }
+        TileWindow {
+            x: start_x,
+            y: start_y,
+            w,
+            h,
+            tiles,
+            biomes,
+        }
+    }
+
+    fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.w && y < self.y + self.h
+    }
+
+    // Outside of the window is never drawn, it is treated as stone like outside of the map
+    fn get(&self, x: i32, y: i32) -> BaseTile {
+        if !self.contains(x, y) {
+            return BaseTile::Stone;
+        }
+        self.tiles[((x - self.x) + (y - self.y) * self.w) as usize]
+    }
+
+    fn biome(&self, x: i32, y: i32) -> usize {
+        if !self.contains(x, y) {
+            return 0;
+        }
+        self.biomes[((x - self.x) + (y - self.y) * self.w) as usize]
+    }
+}
+
+struct QueuedDraw {
+    layer: RenderLayer,
+    command: DrawCommand,
+}
+
 pub struct Renderer {
     pub ctx: Box<Context>,
     pipeline: Pipeline,
     pipeline_tiles: Pipeline,
     pipeline_hud: Pipeline,
+    light_pass: LightPass,
     pub bindings: Bindings, // pub as MiniMap is using it
     pub textures: HashMap<TextureIndexes, TextureInfo>, // pub as MiniMap is using it
     atlas_info: AtlasInfo,
+
+    // Layer that the draw_* functions queue into
+    layer: RenderLayer,
+    draw_queue: Vec<QueuedDraw>,
+
     // Batched sprite data for atlas-rendered quads (positions in world pixels, precomputed UVs)
     atlas_batch_vertices: Vec<Vertex>,
     atlas_batch_indices: Vec<u16>,
@@ -52,8 +227,16 @@ pub struct Renderer {
     dualgrid_vb_cap: usize,
     dualgrid_ib_cap: usize,
 
-    dualgrid_vertices: Vec<Vec<Vertex>>,
-    dualgrid_indices: Vec<Vec<u16>>,
+    // By (tile type, background region), the background has a mesh for each biome
+    tile_meshes: HashMap<(u8, u8), TileMesh>,
+    // The dual grid range and map revision the tile meshes were built for, they are built again
+    // only when either changes
+    tile_meshes_key: Option<((i32, i32, i32, i32), u64)>,
+
+    gpu_objects: GpuObjects,
+
+    pub stats: RenderStats, // pub as MiniMap is drawing too
+    last_frame_stats: RenderStats,
 }
 
 #[derive(Eq, PartialEq, Hash)]
@@ -77,12 +260,35 @@ impl TextureInfo {
     }
 }
 
+// Drawn in place of a texture that could not be loaded, a checkerboard that stands out
+fn checkerboard_rgba8(w: u32, h: u32) -> Vec<u8> {
+    const SQUARE: u32 = 8;
+    let mut pixels = Vec::with_capacity((w * h * 4) as usize);
+    for y in 0..h {
+        for x in 0..w {
+            let color = if (x / SQUARE + y / SQUARE).is_multiple_of(2) {
+                [200, 0, 180, 255]
+            } else {
+                [20, 20, 20, 255]
+            };
+            pixels.extend_from_slice(&color);
+        }
+    }
+    pixels
+}
+
 fn load_texture(ctx: &mut Box<dyn RenderingBackend>, path: &str) -> TextureInfo {
-    // Load background texture (tiled 64x64 area) from assets
-    let img = image::open(path).expect("failed to load assets/tile_backgrounds.png");
-    let (w, h) = img.dimensions();
-    let bg_rgba8 = img.to_rgba8();
-    let texture = ctx.new_texture_from_rgba8(w as u16, h as u16, &bg_rgba8);
+    let (w, h, rgba8) = match image::open(path) {
+        Ok(img) => {
+            let (w, h) = img.dimensions();
+            (w, h, img.to_rgba8().into_raw())
+        }
+        Err(error) => {
+            telemetry::record_asset_warning(format!("Could not load {}: {}", path, error));
+            (256, 256, checkerboard_rgba8(256, 256))
+        }
+    };
+    let texture = ctx.new_texture_from_rgba8(w as u16, h as u16, &rgba8);
     // Nearest for pixel art, Repeat so UVs can wrap every 64 px
     ctx.texture_set_filter(texture, FilterMode::Nearest, MipmapFilterMode::None);
     ctx.texture_set_wrap(texture, TextureWrap::Clamp, TextureWrap::Clamp);
@@ -95,6 +301,17 @@ fn load_texture(ctx: &mut Box<dyn RenderingBackend>, path: &str) -> TextureInfo
 }
 
 pub const TILE_SIZE: f32 = 16.0;
+// Entities this many tiles off the screen are still drawn, for swings and health bars that
+// reach outside of the bounding box
+const CULL_MARGIN: f32 = 2.0;
+const CLEAR_COLOR: (f32, f32, f32, f32) = (0.08, 0.09, 0.10, 1.0);
+// The drawn tile range moves in blocks of this many tiles, the tile meshes are built again then
+const TILE_MESH_SNAP: i32 = 8;
+// The background art of each biome, as the 64x64 region of tile_backgrounds.png it is from. Rooms
+// of biomes past the end wrap around.
+pub const BIOME_BACKGROUNDS: [u8; 2] = [4, 3];
+// A sound this many tiles to the side of the player gets an arrow in its caption
+const CAPTION_SIDE_TILES: f32 = 3.0;
 
 const DUAL_GRID_UV_TABLE: [(u32, u32); 16] = [
     (0, 0), // 0
@@ -118,11 +335,64 @@ const DUAL_GRID_UV_TABLE: [(u32, u32); 16] = [
 pub trait DrawableGameState: GameState {
     fn draw_extra_mid(&self, camera: &Camera, renderer: &mut Renderer, show_dark: bool);
     fn draw_extra_last(&self, camera: &Camera, renderer: &mut Renderer, show_dark: bool);
+
+    /// (ambient light, lights) for the lighting pass, an ambient light of 1.0 skips it
+    fn lights(&self, _camera: &Camera) -> (f32, Vec<Light>) {
+        (1.0, Vec::new())
+    }
 }
 
 impl DrawableGameState for Game {
-    fn draw_extra_mid(&self, _camera: &Camera, renderer: &mut Renderer, _show_dark: bool) {
+    // The light of the player always makes it, the rest are the ones closest to the view
+    fn lights(&self, camera: &Camera) -> (f32, Vec<Light>) {
+        if !self.lighting.is_dark() {
+            return (1.0, Vec::new());
+        }
+        let torches = self
+            .map
+            .get_overlays()
+            .iter()
+            .filter(|info| matches!(info.tile, OverlayTile::Torch))
+            .map(|info| Light {
+                pos: Pos::new(info.x as f32 + 0.5, info.y as f32 + 0.5),
+                radius: self.lighting.torch_radius,
+            });
+        let glowing_enemies = self.enemies.iter().filter_map(|enemy| {
+            Some(Light {
+                pos: enemy.bb().center(),
+                radius: enemy.light_radius()?,
+            })
+        });
+        let mut lights: Vec<Light> = torches
+            .chain(glowing_enemies)
+            .filter(|light| {
+                let bb = BoundingBox {
+                    x: light.pos.x - light.radius,
+                    y: light.pos.y - light.radius,
+                    w: light.radius * 2.0,
+                    h: light.radius * 2.0,
+                    vx: 0.0,
+                    vy: 0.0,
+                };
+                camera.sees(&bb, 0.0)
+            })
+            .collect();
+        let distance = |light: &Light| (light.pos.x - camera.x).hypot(light.pos.y - camera.y);
+        lights.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+        lights.insert(
+            0,
+            Light {
+                pos: self.player().bb.center(),
+                radius: self.lighting.player_radius,
+            },
+        );
+        lights.truncate(MAX_LIGHTS);
+        (self.lighting.ambient(), lights)
+    }
+
+    fn draw_extra_mid(&self, camera: &Camera, renderer: &mut Renderer, _show_dark: bool) {
         // Draw the doors
+        renderer.set_layer(RenderLayer::BehindEntities);
         for door in &self.map.doors {
             renderer.draw_from_texture_atlas(
                 "door",
@@ -134,23 +404,69 @@ impl DrawableGameState for Game {
                 1.0,
                 1.0,
             );
+            if door.is_locked() {
+                renderer.draw_from_texture_atlas(
+                    "door_lock",
+                    0,
+                    false,
+                    door.x as f32,
+                    door.y as f32,
+                    1.0,
+                    1.0,
+                    1.0,
+                );
+            }
+        }
+
+        self.hidden_walls.draw(renderer);
+        self.checkpoints.draw(renderer);
+        self.training.draw(renderer);
+
+        if self.show_breadcrumbs {
+            self.breadcrumbs.draw(renderer);
         }
 
         // Items
-        for item in &self.items {
+        renderer.set_layer(RenderLayer::Entities);
+        for item in self
+            .items
+            .iter()
+            .filter(|item| camera.sees(item.bb(), CULL_MARGIN))
+        {
             item.draw(renderer);
         }
 
         // Enemies
-        for enemy in &self.enemies {
+        for enemy in self
+            .enemies
+            .iter()
+            .filter(|enemy| camera.sees(enemy.bb(), CULL_MARGIN))
+        {
             enemy.draw(renderer);
         }
+
+        renderer.set_layer(RenderLayer::Particles);
+        for particle in self
+            .particles
+            .iter()
+            .filter(|particle| camera.sees(&particle.bb(), CULL_MARGIN))
+        {
+            particle.draw(renderer);
+        }
+
+        if let Some(escape) = &self.escape {
+            escape.draw(renderer);
+        }
     }
 
     fn draw_extra_last(&self, camera: &Camera, renderer: &mut Renderer, show_dark: bool) {
         for enemy in &self.enemies {
-            if enemy.get_health().ratio() < 1.0 && enemy.should_render_health_bar() {
-                renderer.draw_enemy_health_bar(camera, enemy.as_ref());
+            if camera.sees(enemy.bb(), CULL_MARGIN)
+                && enemy.get_health().ratio() < 1.0
+                && enemy.should_render_health_bar()
+                && enemy.boss_info().is_none()
+            {
+                renderer.draw_enemy_health_bar(enemy.as_ref());
             }
         }
 
@@ -210,11 +526,89 @@ impl DrawableGameState for Game {
                 );
             }
         }
+
+        renderer.set_layer(RenderLayer::Hud);
+
+        // Price tags above the wares in the current room, green when the shop has a sale
+        for item in &self.items {
+            let Some(for_sale) = item.for_sale() else {
+                continue;
+            };
+            let center = item.bb().center();
+            if self
+                .map
+                .get_room_at(center.x, center.y)
+                .map(|(index, _)| index)
+                != self.cur_room_index()
+            {
+                continue;
+            }
+            let text = format!("{}", self.shops.price(for_sale, self.rooms_visited()));
+            let pixel_size = camera.zoom.round().max(1.0);
+            let text_width = pixel_font::text_width(&text) as f32 * pixel_size;
+            let (x, y) = camera.tile_to_screen(center.x, item.bb().y);
+            let color = if self.shops.is_on_sale(for_sale) {
+                [0.45, 0.9, 0.35, 1.0]
+            } else {
+                [0.98, 0.8, 0.2, 1.0]
+            };
+            renderer.draw_text_hud(
+                &text,
+                x - text_width / 2.0,
+                y - (pixel_font::GLYPH_HEIGHT as f32 + 3.0) * pixel_size,
+                pixel_size,
+                color,
+            );
+        }
+
+        self.boss_bar.draw(renderer, camera);
+        self.fight_results.draw(renderer, camera);
+        self.training.draw_hud(renderer, camera);
+        let progress = self.quest_progress();
+        self.quest_log.draw_hud(
+            renderer,
+            camera,
+            &self.map,
+            &progress,
+            self.player.bb.center(),
+        );
+        if let Some(target) = self.compass_target() {
+            self.compass.draw(renderer, camera, target);
+        }
+        if self.show_quest_log {
+            self.quest_log.draw_log(renderer, &progress);
+        }
+        self.wallet.draw(renderer, camera);
+
+        if self.can_retry_boss() {
+            let text = "Jump to retry the fight";
+            let pixel_size = 3.0;
+            let width = pixel_font::text_width(text) as f32 * pixel_size;
+            renderer.draw_text_hud(
+                text,
+                (camera.screen_w - width) / 2.0,
+                camera.screen_h * 0.6,
+                pixel_size,
+                [0.95, 0.95, 0.95, 1.0],
+            );
+        }
+
+        // Fade to dark red on game over
+        if self.is_game_over() {
+            renderer.draw_rect_hud(
+                0.0,
+                0.0,
+                camera.screen_w,
+                camera.screen_h,
+                [0.25, 0.02, 0.02, 0.5 + 0.4 * self.game_over_progress()],
+            );
+        }
     }
 }
 
 impl DrawableGameState for Editor {
     fn draw_extra_mid(&self, _camera: &Camera, renderer: &mut Renderer, _show_dark: bool) {
+        renderer.set_layer(RenderLayer::BehindEntities);
         for door in self.room.get_doors() {
             let room_pos = self.room.get_pos();
             let x = room_pos.0 + door.x as i32;
@@ -232,10 +626,42 @@ impl DrawableGameState for Editor {
             );
         }
 
-        // draw enemy templates
+        // Hidden walls look like stone in the game, mark them clearly in the editor
+        let (room_x, room_y, room_w, room_h) = self.room.get_bounds();
+        for x in room_x..room_x + room_w as i32 {
+            for y in room_y..room_y + room_h as i32 {
+                if self.room.get_at(x, y).0 == BaseTile::HiddenWall {
+                    renderer.draw_from_texture_atlas(
+                        "wall_crack",
+                        2,
+                        false,
+                        x as f32,
+                        y as f32,
+                        1.0,
+                        1.0,
+                        1.0,
+                    );
+                }
+            }
+        }
+
+        // draw enemy and item templates
+        renderer.set_layer(RenderLayer::Entities);
+        for template in &self.room.item_templates {
+            match template.as_item() {
+                Some(item) => item.draw(renderer),
+                None if template.is_training_switch() => {
+                    draw_switch(renderer, template.pos(), false)
+                }
+                None => draw_flag(renderer, template.pos(), 1.0),
+            }
+        }
         for template in &self.room.object_templates {
             let bb = template.get_bb();
-            let texture_index = template.get_texture_index();
+            let Some(texture_index) = template.get_texture_index() else {
+                template.as_object().draw(renderer);
+                continue;
+            };
             renderer.draw_from_texture_atlas(
                 texture_index,
                 0,
@@ -249,7 +675,13 @@ impl DrawableGameState for Editor {
         }
     }
 
-    fn draw_extra_last(&self, _camera: &Camera, _renderer: &mut Renderer, _show_dark: bool) {}
+    // Ghost of the tiles the rectangle, line or fill tool paints when the mouse is released
+    fn draw_extra_last(&self, _camera: &Camera, renderer: &mut Renderer, _show_dark: bool) {
+        renderer.set_layer(RenderLayer::Foreground);
+        for &(x, y) in &self.tile_preview {
+            renderer.draw_rect(x as f32, y as f32, 1.0, 1.0, [1.0, 1.0, 1.0, 0.35]);
+        }
+    }
 }
 
 impl Renderer {
@@ -482,18 +914,32 @@ impl Renderer {
             BufferSource::empty::<u16>(dualgrid_ib_cap),
         );
 
-        // TODO: Why 5 vectors? What if we just used one for all dual-grid tiles?
-        let dualgrid_vertices = vec![Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new()];
-        let dualgrid_indices = vec![Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new()];
+        let mut gpu_objects = GpuObjects::default();
+        gpu_objects.buffers.extend([
+            vertex_buffer,
+            index_buffer,
+            atlas_vb,
+            atlas_ib,
+            dualgrid_vb,
+            dualgrid_ib,
+        ]);
+        gpu_objects
+            .textures
+            .extend(textures.values().map(|info| info.texture));
+
+        let light_pass = LightPass::new(&mut *ctx);
 
         Renderer {
             ctx,
             pipeline,
             pipeline_tiles,
             pipeline_hud,
+            light_pass,
             bindings,
             textures,
             atlas_info,
+            layer: RenderLayer::Entities,
+            draw_queue: Vec::new(),
             atlas_batch_vertices: Vec::new(),
             atlas_batch_indices: Vec::new(),
             atlas_vb,
@@ -504,11 +950,38 @@ impl Renderer {
             dualgrid_ib,
             dualgrid_ib_cap,
             dualgrid_vb_cap,
-            dualgrid_indices,
-            dualgrid_vertices,
+            tile_meshes: HashMap::new(),
+            tile_meshes_key: None,
+            gpu_objects,
+            stats: RenderStats::default(),
+            last_frame_stats: RenderStats::default(),
         }
     }
 
+    /// The stats of the last drawn frame
+    pub fn last_frame_stats(&self) -> RenderStats {
+        self.last_frame_stats
+    }
+
+    /// Puts a new texture in place of the old one and deletes the old one
+    pub fn replace_texture(&mut self, index: TextureIndexes, info: TextureInfo) {
+        let new = info.texture;
+        if let Some(old) = self.textures.insert(index, info) {
+            self.gpu_objects
+                .replace_texture(&mut *self.ctx, old.texture, new);
+        } else {
+            self.gpu_objects.textures.insert(new);
+        }
+    }
+
+    /// (buffers, textures) made by the renderer that are still alive
+    pub fn live_gpu_objects(&self) -> (usize, usize) {
+        (
+            self.gpu_objects.buffers.len(),
+            self.gpu_objects.textures.len(),
+        )
+    }
+
     pub fn resize(&mut self, _w: f32, _h: f32) {
         // Nothing to do yet
     }
@@ -519,89 +992,267 @@ impl Renderer {
         camera: &Camera,
         show_dark: bool,
         draw_big_map: bool,
+        captions: &[Caption],
     ) {
         let clear = PassAction::Clear {
-            color: Some((0.08, 0.09, 0.10, 1.0)),
+            color: Some(CLEAR_COLOR),
             depth: Some(1.0),
             stencil: Some(0),
         };
 
-        self.ctx.begin_default_pass(clear);
-        self.ctx.apply_pipeline(&self.pipeline);
-        self.ctx.apply_bindings(&self.bindings);
-
-        // Begin new sprite batch for this frame
-        self.atlas_batch_vertices.clear();
-        self.atlas_batch_indices.clear();
+        self.draw_queue.clear();
+        self.last_frame_stats = std::mem::take(&mut self.stats);
+
+        let range = Self::dual_grid_range(camera);
+        let key = (range, state.map().revision());
+        if self.tile_meshes_key != Some(key) {
+            self.tile_meshes_key = Some(key);
+            let window = TileWindow::new(state.map(), range);
+            for (biome, &region) in BIOME_BACKGROUNDS.iter().enumerate() {
+                let background =
+                    self.update_dual_grid_indices(range, |x, y| window.biome(x, y) == biome, 4);
+                self.upload_tile_mesh((4, region), background);
+            }
+            let outside = self.update_dual_grid_indices(
+                range,
+                |x, y| matches!(window.get(x, y), BaseTile::NotPartOfRoom),
+                2,
+            );
+            self.upload_tile_mesh((2, 2), outside);
+            let mut stone = self.update_dual_grid_indices(
+                range,
+                |x, y| {
+                    matches!(
+                        window.get(x, y),
+                        BaseTile::Stone
+                            | BaseTile::HiddenWall
+                            | BaseTile::Cracked
+                            | BaseTile::BombWall
+                    )
+                },
+                0,
+            );
+            self.add_slope_triangles(range, |x, y| window.get(x, y), 0, &mut stone);
+            self.upload_tile_mesh((0, 0), stone);
+            let wood = self.update_dual_grid_indices(
+                range,
+                |x, y| {
+                    matches!(
+                        window.get(x, y),
+                        BaseTile::Wood | BaseTile::LockedDoor | BaseTile::DashBarrier
+                    )
+                },
+                1,
+            );
+            self.upload_tile_mesh((1, 1), wood);
+        }
 
         // Draw base grid using dual-grid textured tiles
-        self.draw_base_dual_grid(|_x, _y| true, camera, 4, 1.0);
-        self.draw_base_dual_grid(
-            |x, y| matches!(state.map().get_at(x, y).0, BaseTile::NotPartOfRoom),
-            camera,
-            2,
-            1.0,
-        );
-        self.draw_base_dual_grid(
-            |x, y| matches!(state.map().get_at(x, y).0, BaseTile::Stone),
-            camera,
-            0,
-            1.0,
-        );
-        self.draw_base_dual_grid(
-            |x, y| matches!(state.map().get_at(x, y).0, BaseTile::Wood),
-            camera,
-            1,
-            1.0,
-        );
+        self.set_layer(RenderLayer::Background);
+        for bg_region in BIOME_BACKGROUNDS {
+            self.queue(DrawCommand::TileMesh {
+                tile_type_index: 4,
+                bg_region,
+                opacity: 1.0,
+            });
+        }
+        self.set_layer(RenderLayer::Tiles);
+        for tile_type_index in [2, 0, 1] {
+            self.queue(DrawCommand::TileMesh {
+                tile_type_index,
+                bg_region: tile_type_index,
+                opacity: 1.0,
+            });
+        }
 
         // Draw overlay tiles
-        self.draw_overlay(state.map());
+        self.set_layer(RenderLayer::BehindEntities);
+        self.draw_overlay(state.map(), range);
+        self.draw_tile_marks(state.map(), range);
 
         // draw (coins and enemies) OR (doors)
+        self.set_layer(RenderLayer::Entities);
         state.draw_extra_mid(camera, self, show_dark);
 
-        // draw player on top
-        state.player().draw(self, camera);
+        self.set_layer(RenderLayer::Player);
+        state.player().draw(self);
 
-        // Flush all queued atlas sprites in one draw call
-        self.flush_atlas_batch(camera);
+        // Health bars and "the dark"
+        self.set_layer(RenderLayer::Foreground);
+        state.draw_extra_last(camera, self, show_dark);
 
+        self.set_layer(RenderLayer::Hud);
+        self.draw_hud(state, camera);
+        self.draw_captions(captions, state.player().bb.center(), camera);
+
+        // Dark rooms are drawn offscreen first and then on the screen with the lights
+        let (ambient, lights) = state.lights(camera);
+        if ambient < 1.0 {
+            self.light_pass
+                .begin(&mut *self.ctx, &mut self.gpu_objects, camera, clear);
+        } else {
+            self.ctx.begin_default_pass(clear);
+        }
+        self.stats.pipeline_switches += 1;
+        self.ctx.apply_pipeline(&self.pipeline);
+        self.stats.bind(&self.bindings);
+        self.ctx.apply_bindings(&self.bindings);
+        self.flush_draw_queue(camera, false);
         self.ctx.end_render_pass();
+        if ambient < 1.0 {
+            // The composite covers all of the screen
+            self.ctx.begin_default_pass(PassAction::Nothing);
+            self.stats.pipeline_switches += 1;
+            self.stats.draw_calls += 1;
+            self.light_pass
+                .composite(&mut *self.ctx, &self.bindings, camera, ambient, &lights);
+            self.ctx.end_render_pass();
+        }
 
         // Draw hud new HUD pipeline
         let no_clear = PassAction::Nothing;
         self.ctx.begin_default_pass(no_clear);
+        self.stats.pipeline_switches += 1;
         self.ctx.apply_pipeline(&self.pipeline_hud);
+        self.stats.bind(&self.bindings);
         self.ctx.apply_bindings(&self.bindings);
 
-        state.draw_extra_last(camera, self, show_dark);
+        self.flush_draw_queue(camera, true);
+        state.update_and_draw_minimap(self, camera, draw_big_map);
 
-        self.draw_hud(state, camera, draw_big_map);
+        self.ctx.end_render_pass();
+    }
 
+    /// Clears the screen without drawing anything, for when there is no game to draw yet
+    pub fn clear(&mut self) {
+        self.ctx.begin_default_pass(PassAction::clear_color(
+            CLEAR_COLOR.0,
+            CLEAR_COLOR.1,
+            CLEAR_COLOR.2,
+            CLEAR_COLOR.3,
+        ));
         self.ctx.end_render_pass();
     }
 
-    pub fn draw_hud(&mut self, state: &mut dyn GameState, camera: &Camera, draw_big_map: bool) {
-        self.draw_player_health_bar(state, camera);
-        state.update_and_draw_minimap(self, camera, draw_big_map);
+    pub fn set_layer(&mut self, layer: RenderLayer) {
+        self.layer = layer;
+    }
+
+    fn queue(&mut self, command: DrawCommand) {
+        self.draw_queue.push(QueuedDraw {
+            layer: self.layer,
+            command,
+        });
+    }
+
+    pub fn draw_hud(&mut self, state: &dyn GameState, camera: &Camera) {
+        self.draw_player_hearts(state, camera);
+        self.draw_player_lives(state, camera);
+        self.draw_player_abilities(state, camera);
+    }
+
+    // The closed captions at the bottom of the screen, newest lowest. Sounds from off to the
+    // side of the player get an arrow pointing to them.
+    fn draw_captions(&mut self, captions: &[Caption], listener: Pos, camera: &Camera) {
+        let pixel_size = 2.0;
+        let padding = 6.0;
+        let line_height = pixel_font::GLYPH_HEIGHT as f32 * pixel_size + padding * 2.0;
+        let now = date::now();
+
+        for (i, caption) in captions.iter().rev().enumerate() {
+            let text = match caption.pos {
+                Some(pos) if pos.x < listener.x - CAPTION_SIDE_TILES => {
+                    format!("< [{}]", caption.text)
+                }
+                Some(pos) if pos.x > listener.x + CAPTION_SIDE_TILES => {
+                    format!("[{}] >", caption.text)
+                }
+                _ => format!("[{}]", caption.text),
+            };
+            let opacity = caption.opacity(now);
+            let width = pixel_font::text_width(&text) as f32 * pixel_size + padding * 2.0;
+            let x = (camera.screen_w - width) / 2.0;
+            let y = camera.screen_h - 60.0 - (i + 1) as f32 * (line_height + 4.0);
+            self.draw_rect_hud(x, y, width, line_height, [0.0, 0.0, 0.0, 0.7 * opacity]);
+            self.draw_text_hud(
+                &text,
+                x + padding,
+                y + padding,
+                pixel_size,
+                [1.0, 1.0, 1.0, opacity],
+            );
+        }
     }
 
-    fn draw_player_health_bar(&mut self, state: &dyn GameState, camera: &Camera) {
-        let max_width = 200.0;
-        let height = 20.0;
+    // A square of the color of each ability the player has, below the coin counter
+    fn draw_player_abilities(&mut self, state: &dyn GameState, camera: &Camera) {
+        let size = 12.0;
+        let spacing = 6.0;
         let padding = 10.0;
 
-        let x = camera.screen_w - max_width - padding;
-        let y = padding;
+        let x = camera.screen_w - 200.0 - padding;
+        let y = 80.0;
+
+        for (i, ability) in state.player().abilities.iter().enumerate() {
+            let ability_x = x + i as f32 * (size + spacing);
+            self.draw_rect_hud(ability_x, y, size, size, [0.1, 0.1, 0.1, 1.0]);
+            self.draw_rect_hud(
+                ability_x + 2.0,
+                y + 2.0,
+                size - 4.0,
+                size - 4.0,
+                ability.color(),
+            );
+        }
+    }
+
+    fn draw_player_lives(&mut self, state: &dyn GameState, camera: &Camera) {
+        let size = 12.0;
+        let spacing = 6.0;
+        let padding = 10.0;
+
+        // Below the hearts, aligned to their left edge
+        let x = camera.screen_w - 200.0 - padding;
+        let y = padding + 20.0 + spacing;
+
+        for i in 0..state.player().lives {
+            let life_x = x + i as f32 * (size + spacing);
+            self.draw_rect_hud(life_x, y, size, size, [0.1, 0.1, 0.1, 1.0]);
+            self.draw_rect_hud(
+                life_x + 2.0,
+                y + 2.0,
+                size - 4.0,
+                size - 4.0,
+                [0.42, 0.75, 0.19, 1.0],
+            );
+        }
+    }
+
+    // A heart for every point of health, the lost ones are faded
+    fn draw_player_hearts(&mut self, state: &dyn GameState, camera: &Camera) {
+        let size = 21.0;
+        let spacing = 4.0;
+        let padding = 10.0;
 
-        let filled_width = max_width * state.player().health.ratio();
+        let x = camera.screen_w - 200.0 - padding;
+        let y = padding;
 
-        self.draw_rect_hud(camera, x, y, max_width, height, [0.1, 0.1, 0.1, 1.0]);
-        self.draw_rect_hud(camera, x, y, filled_width, height, [0.65, 0.11, 0.11, 1.0]);
+        let health = state.player().health;
+        for i in 0..health.max {
+            let alpha = if i < health.current { 1.0 } else { 0.25 };
+            self.draw_from_texture_atlas_hud(
+                "heart",
+                0,
+                x + i as f32 * (size + spacing),
+                y,
+                size,
+                size,
+                alpha,
+            );
+        }
     }
 
-    fn draw_enemy_health_bar(&mut self, camera: &Camera, enemy: &dyn Enemy) {
+    fn draw_enemy_health_bar(&mut self, enemy: &dyn Enemy) {
         let padding = 0.3;
         let height = 0.1;
         let max_width = enemy.bb().w + padding + padding;
@@ -610,8 +1261,8 @@ impl Renderer {
 
         let filled_width = max_width * enemy.get_health().ratio();
 
-        self.draw_rect(camera, x, y, max_width, height, [0.1, 0.1, 0.1, 1.0]);
-        self.draw_rect(camera, x, y, filled_width, height, [0.65, 0.11, 0.11, 1.0]);
+        self.draw_rect(x, y, max_width, height, [0.1, 0.1, 0.1, 1.0]);
+        self.draw_rect(x, y, filled_width, height, [0.65, 0.11, 0.11, 1.0]);
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -631,9 +1282,7 @@ impl Renderer {
             return;
         }
 
-        // Lookup atlas location and texture size for UVs
-        let texture = self.textures.get(&TextureIndexes::Atlas).unwrap();
-        let atlas_rect = self.atlas_info.get_rect(texture_index, atlas_index as i32);
+        let (u0, u1, v0, v1) = self.atlas_uvs(texture_index, atlas_index, flip, false);
 
         // World pixel quad (destination)
         let pxw = px * TILE_SIZE;
@@ -641,6 +1290,42 @@ impl Renderer {
         let ww = w * TILE_SIZE;
         let hh = h * TILE_SIZE;
 
+        self.queue(DrawCommand::Sprite([
+            // top-left
+            Vertex {
+                pos: [pxw, pyw],
+                uv: [u0, v0],
+            },
+            // top-right
+            Vertex {
+                pos: [pxw + ww, pyw],
+                uv: [u1, v0],
+            },
+            // bottom-right
+            Vertex {
+                pos: [pxw + ww, pyw + hh],
+                uv: [u1, v1],
+            },
+            // bottom-left
+            Vertex {
+                pos: [pxw, pyw + hh],
+                uv: [u0, v1],
+            },
+        ]));
+    }
+
+    // UVs (u0, u1, v0, v1) of an atlas sprite. Flipping just swaps the UVs.
+    fn atlas_uvs(
+        &self,
+        texture_index: &str,
+        atlas_index: u32,
+        flip_x: bool,
+        flip_y: bool,
+    ) -> (f32, f32, f32, f32) {
+        // Lookup atlas location and texture size for UVs
+        let texture = self.textures.get(&TextureIndexes::Atlas).unwrap();
+        let atlas_rect = self.atlas_info.get_rect(texture_index, atlas_index as i32);
+
         // UVs from SOURCE rect, not destination size
         let base_u = atlas_rect.x as f32 / texture.w;
         let base_v = atlas_rect.y as f32 / texture.h;
@@ -652,191 +1337,312 @@ impl Renderer {
         let v_min = base_v;
         let v_max = base_v + dv;
 
-        let (u0, u1) = if flip { (u_max, u_min) } else { (u_min, u_max) };
-        let (v0, v1) = (v_min, v_max);
-
-        let base_index = self.atlas_batch_vertices.len() as u16;
-
-        // top-left
-        self.atlas_batch_vertices.push(Vertex {
-            pos: [pxw, pyw],
-            uv: [u0, v0],
-        });
-        // top-right
-        self.atlas_batch_vertices.push(Vertex {
-            pos: [pxw + ww, pyw],
-            uv: [u1, v0],
-        });
-        // bottom-right
-        self.atlas_batch_vertices.push(Vertex {
-            pos: [pxw + ww, pyw + hh],
-            uv: [u1, v1],
-        });
-        // bottom-left
-        self.atlas_batch_vertices.push(Vertex {
-            pos: [pxw, pyw + hh],
-            uv: [u0, v1],
-        });
-
-        self.atlas_batch_indices.extend_from_slice(&[
-            base_index,
-            base_index + 1,
-            base_index + 2,
-            base_index,
-            base_index + 2,
-            base_index + 3,
-        ]);
+        let (u0, u1) = if flip_x {
+            (u_max, u_min)
+        } else {
+            (u_min, u_max)
+        };
+        let (v0, v1) = if flip_y {
+            (v_max, v_min)
+        } else {
+            (v_min, v_max)
+        };
+        (u0, u1, v0, v1)
     }
 
-    pub fn draw_rect(
+    /// Draws the sprite rotated by angle_rad around the pivot point (in tiles)
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_from_texture_atlas_rotated(
         &mut self,
-        camera: &Camera,
+        texture_index: &str,
+        atlas_index: u32,
+        flip_x: bool,
+        flip_y: bool,
         px: f32,
         py: f32,
         w: f32,
         h: f32,
-        color: [f32; 4],
+        pivot: (f32, f32),
+        angle_rad: f32,
     ) {
-        // bind white texture and use full-quad UVs
-        let background = self.textures.get(&TextureIndexes::TileBackground).unwrap();
-        let white = self.textures.get(&TextureIndexes::White1x1).unwrap();
+        let (u0, u1, v0, v1) = self.atlas_uvs(texture_index, atlas_index, flip_x, flip_y);
+
+        let (sin, cos) = angle_rad.sin_cos();
+        let rotate = |x: f32, y: f32| {
+            let dx = x - pivot.0;
+            let dy = y - pivot.1;
+            [
+                (pivot.0 + dx * cos - dy * sin) * TILE_SIZE,
+                (pivot.1 + dx * sin + dy * cos) * TILE_SIZE,
+            ]
+        };
 
-        self.bindings.images[0] = white.texture;
-        self.bindings.images[1] = background.texture;
+        self.queue(DrawCommand::Sprite([
+            Vertex {
+                pos: rotate(px, py),
+                uv: [u0, v0],
+            },
+            Vertex {
+                pos: rotate(px + w, py),
+                uv: [u1, v0],
+            },
+            Vertex {
+                pos: rotate(px + w, py + h),
+                uv: [u1, v1],
+            },
+            Vertex {
+                pos: rotate(px, py + h),
+                uv: [u0, v1],
+            },
+        ]));
+    }
 
-        self.ctx.apply_bindings(&self.bindings);
+    /// Draws the sprite scaled around its bottom center, for squash and stretch effects
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_from_texture_atlas_scaled(
+        &mut self,
+        texture_index: &str,
+        atlas_index: u32,
+        flip: bool,
+        px: f32,
+        py: f32,
+        w: f32,
+        h: f32,
+        alpha: f32,
+        scale: (f32, f32),
+    ) {
+        let scaled_w = w * scale.0;
+        let scaled_h = h * scale.1;
+        self.draw_from_texture_atlas(
+            texture_index,
+            atlas_index,
+            flip,
+            px + (w - scaled_w) * 0.5,
+            py + h - scaled_h,
+            scaled_w,
+            scaled_h,
+            alpha,
+        );
+    }
 
-        let view = Self::camera_view(camera);
-        let proj = Self::ortho_mvp(camera);
+    pub fn draw_rect(&mut self, px: f32, py: f32, w: f32, h: f32, color: [f32; 4]) {
         let model = Self::mat4_mul(
             Self::mat4_translation(px * TILE_SIZE, py * TILE_SIZE),
             Self::mat4_scale(w * TILE_SIZE, h * TILE_SIZE),
         );
-        let vp = Self::mat4_mul(proj, view);
-        let mvp = Self::mat4_mul(vp, model);
-
-        let uniforms = Uniforms {
-            mvp,
+        self.queue(DrawCommand::Rect {
+            model,
             color,
-            uv_base: [0.0, 0.0, 0.0, 0.0],
-            uv_scale: [1.0, 1.0, 0.0, 0.0],
-            world_base: [px * TILE_SIZE, py * TILE_SIZE, 0.0, 0.0],
-            world_scale: [w * TILE_SIZE, h * TILE_SIZE, 0.0, 0.0],
-            color_key: [1.0, 0.0, 1.0, 0.01],
-            bg_tile_size: [64.0, 64.0, 0.0, 0.0],
-            bg_region_origin: [0.0, 0.0, 0.0, 0.0],
-            bg_tex_size: [background.w, background.h, 0.0, 0.0],
-        };
-        self.ctx.apply_uniforms(UniformsSource::table(&uniforms));
-        self.ctx.draw(0, 6, 1);
+            world_base: [px * TILE_SIZE, py * TILE_SIZE],
+            world_scale: [w * TILE_SIZE, h * TILE_SIZE],
+        });
     }
 
-    fn draw_rect_hud(&mut self, camera: &Camera, x: f32, y: f32, w: f32, h: f32, color: [f32; 4]) {
-        let background = self.textures.get(&TextureIndexes::TileBackground).unwrap();
-        let white = self.textures.get(&TextureIndexes::White1x1).unwrap();
-
-        self.bindings.images[0] = white.texture;
-        self.bindings.images[1] = background.texture;
-        self.ctx.apply_bindings(&self.bindings);
-
-        let proj = Self::ortho_mvp(camera);
-        let model = Self::mat4_mul(Self::mat4_translation(x, y), Self::mat4_scale(w, h));
-        let mvp = Self::mat4_mul(proj, model);
-
-        let uniforms = Uniforms {
-            mvp,
-            color,
-            uv_base: [0.0, 0.0, 0.0, 0.0],
-            uv_scale: [1.0, 1.0, 0.0, 0.0],
-            world_base: [x, y, 0.0, 0.0],
-            world_scale: [w, h, 0.0, 0.0],
-            color_key: [1.0, 0.0, 1.0, 0.01],
-            bg_tile_size: [64.0, 64.0, 0.0, 0.0],
-            bg_region_origin: [0.0, 0.0, 0.0, 0.0],
-            bg_tex_size: [background.w, background.h, 0.0, 0.0],
-        };
-
-        self.ctx.apply_uniforms(UniformsSource::table(&uniforms));
-        self.ctx.draw(0, 6, 1);
+    // Draws text with the pixel font, pixel_size is the size of one font pixel in screen pixels
+    pub fn draw_text_hud(&mut self, text: &str, x: f32, y: f32, pixel_size: f32, color: [f32; 4]) {
+        for (i, c) in text.chars().enumerate() {
+            let glyph_x = x + (i as u32 * (pixel_font::GLYPH_WIDTH + 1)) as f32 * pixel_size;
+            for (row_i, row) in pixel_font::glyph(c).iter().enumerate() {
+                for col_i in 0..pixel_font::GLYPH_WIDTH {
+                    if row & (1 << (pixel_font::GLYPH_WIDTH - 1 - col_i)) != 0 {
+                        self.draw_rect_hud(
+                            glyph_x + col_i as f32 * pixel_size,
+                            y + row_i as f32 * pixel_size,
+                            pixel_size,
+                            pixel_size,
+                            color,
+                        );
+                    }
+                }
+            }
+        }
     }
 
     #[allow(clippy::too_many_arguments)]
-    pub fn draw_rect_rotated(
+    pub fn draw_from_texture_atlas_hud(
         &mut self,
-        camera: &Camera,
-        px: f32,
-        py: f32,
+        texture_index: &str,
+        atlas_index: u32,
+        x: f32,
+        y: f32,
         w: f32,
         h: f32,
-        pivot_x: f32,
-        pivot_y: f32,
-        angle_rad: f32,
-        color: [f32; 4],
+        alpha: f32,
     ) {
-        let background = self.textures.get(&TextureIndexes::TileBackground).unwrap();
-        let white = self.textures.get(&TextureIndexes::White1x1).unwrap();
-        // bind white texture and use full-quad UVs
-        self.bindings.images[0] = white.texture;
-        self.bindings.images[1] = background.texture;
-        self.ctx.apply_bindings(&self.bindings);
-
-        let view = Self::camera_view(camera);
-        let proj = Self::ortho_mvp(camera);
+        self.draw_from_texture_atlas(
+            texture_index,
+            atlas_index,
+            false,
+            x / TILE_SIZE,
+            y / TILE_SIZE,
+            w / TILE_SIZE,
+            h / TILE_SIZE,
+            alpha,
+        );
+    }
 
-        // --- build model matrix with pivot rotation ---
-        let pxw = px * TILE_SIZE;
-        let pyw = py * TILE_SIZE;
-        let ww = w * TILE_SIZE;
-        let hw = h * TILE_SIZE;
+    pub fn draw_rect_hud(&mut self, x: f32, y: f32, w: f32, h: f32, color: [f32; 4]) {
+        let model = Self::mat4_mul(Self::mat4_translation(x, y), Self::mat4_scale(w, h));
+        self.queue(DrawCommand::Rect {
+            model,
+            color,
+            world_base: [x, y],
+            world_scale: [w, h],
+        });
+    }
 
-        let pivot_wx = pivot_x * TILE_SIZE;
-        let pivot_wy = pivot_y * TILE_SIZE;
+    // The cracks of the breakable tiles and the locks of the locked doors, the tiles themselves
+    // are drawn as stone and wood. The ability locks get a mark of the color of the ability.
+    fn draw_tile_marks(
+        &mut self,
+        map: &dyn MapLike,
+        (start_x, start_y, end_x, end_y): (i32, i32, i32, i32),
+    ) {
+        for y in start_y..=end_y {
+            for x in start_x..=end_x {
+                let (sprite, frame) = match map.get_at(x, y).0 {
+                    BaseTile::Cracked => ("wall_crack", 2),
+                    BaseTile::LockedDoor => ("door_lock", 0),
+                    BaseTile::DashBarrier => {
+                        self.draw_lock_mark(x, y, Ability::Dash);
+                        continue;
+                    }
+                    BaseTile::BombWall => {
+                        self.draw_from_texture_atlas(
+                            "wall_crack",
+                            1,
+                            false,
+                            x as f32,
+                            y as f32,
+                            1.0,
+                            1.0,
+                            1.0,
+                        );
+                        self.draw_lock_mark(x, y, Ability::Bomb);
+                        continue;
+                    }
+                    _ => continue,
+                };
+                self.draw_from_texture_atlas(
+                    sprite, frame, false, x as f32, y as f32, 1.0, 1.0, 1.0,
+                );
+            }
+        }
+    }
 
-        // Order (column-vector convention): M = T(pivot) * R * T(-pivot) * T(pos) * S
-        // But because your rect is positioned by translating its origin (px,py),
-        // a clean way is: T(pivot) * R * T(pos - pivot) * S
-        let t_pivot = Self::mat4_translation(pivot_wx, pivot_wy);
-        let r = Self::mat4_rotation_z(angle_rad);
-        let t_from_pivot = Self::mat4_translation(pxw - pivot_wx, pyw - pivot_wy);
-        let s = Self::mat4_scale(ww, hw);
+    fn draw_lock_mark(&mut self, x: i32, y: i32, ability: Ability) {
+        let pixel = 1.0 / TILE_SIZE;
+        self.draw_rect(
+            x as f32 + pixel * 5.0,
+            y as f32 + pixel * 5.0,
+            pixel * 6.0,
+            pixel * 6.0,
+            [0.1, 0.1, 0.1, 1.0],
+        );
+        self.draw_rect(
+            x as f32 + pixel * 6.0,
+            y as f32 + pixel * 6.0,
+            pixel * 4.0,
+            pixel * 4.0,
+            ability.color(),
+        );
+    }
 
-        let model = Self::mat4_mul(Self::mat4_mul(Self::mat4_mul(t_pivot, r), t_from_pivot), s);
+    // A ladder that does not lead down to anything is drawn faded and chipped
+    fn draw_ladder(&mut self, map: &dyn MapLike, x: i32, y: i32) {
+        if map.is_ladder_supported(x, y) {
+            self.draw_from_texture_atlas("tiles", 0, false, x as f32, y as f32, 1.0, 1.0, 1.0);
+        } else {
+            self.draw_from_texture_atlas("tiles", 0, false, x as f32, y as f32, 1.0, 1.0, 0.5);
+            self.draw_from_texture_atlas("wall_crack", 0, false, x as f32, y as f32, 1.0, 1.0, 1.0);
+        }
+    }
 
-        let vp = Self::mat4_mul(proj, view);
-        let mvp = Self::mat4_mul(vp, model);
+    // The decorations do not have sprites in the atlas yet, they are drawn out of rectangles
+    fn draw_torch(&mut self, x: i32, y: i32) {
+        let pixel = 1.0 / TILE_SIZE;
+        let (x, y) = (x as f32, y as f32);
+        // Flickers a little differently for every torch
+        let flicker = ((date::now() * 12.0 + (x * 7.0 + y * 3.0) as f64).sin() * 0.5 + 0.5) as f32;
+        self.draw_rect(
+            x + pixel * 3.0,
+            y + pixel * 1.0,
+            pixel * 10.0,
+            pixel * 10.0,
+            [1.0, 0.6, 0.2, 0.10 + 0.05 * flicker],
+        );
+        self.draw_rect(
+            x + pixel * 7.0,
+            y + pixel * 8.0,
+            pixel * 2.0,
+            pixel * 6.0,
+            [0.4, 0.25, 0.12, 1.0],
+        );
+        let flame_h = pixel * (3.0 + flicker * 2.0);
+        self.draw_rect(
+            x + pixel * 6.0,
+            y + pixel * 8.0 - flame_h,
+            pixel * 4.0,
+            flame_h,
+            [1.0, 0.55, 0.1, 1.0],
+        );
+        self.draw_rect(
+            x + pixel * 7.0,
+            y + pixel * 8.0 - flame_h * 0.6,
+            pixel * 2.0,
+            flame_h * 0.6,
+            [1.0, 0.9, 0.4, 1.0],
+        );
+    }
 
-        let uniforms = Uniforms {
-            mvp,
-            color,
-            uv_base: [0.0, 0.0, 0.0, 0.0],
-            uv_scale: [1.0, 1.0, 0.0, 0.0],
-            world_base: [pxw, pyw, 0.0, 0.0],
-            world_scale: [ww, hw, 0.0, 0.0],
-            color_key: [1.0, 0.0, 1.0, 0.01],
-            bg_tile_size: [64.0, 64.0, 0.0, 0.0],
-            bg_region_origin: [0.0, 0.0, 0.0, 0.0],
-            bg_tex_size: [background.w, background.h, 0.0, 0.0],
-        };
+    fn draw_grass(&mut self, x: i32, y: i32) {
+        let pixel = 1.0 / TILE_SIZE;
+        for blade in 0..5 {
+            // Blades of different heights, but the same ones every frame
+            let height = 2 + (x * 5 + y * 3 + blade * 7).rem_euclid(4);
+            self.draw_rect(
+                x as f32 + pixel * (1 + blade * 3) as f32,
+                (y + 1) as f32 - pixel * height as f32,
+                pixel,
+                pixel * height as f32,
+                [0.3, 0.6 + 0.05 * (blade % 2) as f32, 0.2, 1.0],
+            );
+        }
+    }
 
-        self.ctx.apply_uniforms(UniformsSource::table(&uniforms));
-        self.ctx.draw(0, 6, 1);
+    fn draw_vine(&mut self, x: i32, y: i32) {
+        let pixel = 1.0 / TILE_SIZE;
+        let (x, y) = (x as f32, y as f32);
+        self.draw_rect(
+            x + pixel * 7.0,
+            y,
+            pixel * 2.0,
+            1.0,
+            [0.25, 0.45, 0.15, 1.0],
+        );
+        for (leaf_x, leaf_y) in [(4.0, 2.0), (9.0, 7.0), (4.0, 12.0)] {
+            self.draw_rect(
+                x + pixel * leaf_x,
+                y + pixel * leaf_y,
+                pixel * 3.0,
+                pixel * 2.0,
+                [0.35, 0.65, 0.25, 1.0],
+            );
+        }
     }
 
-    fn draw_overlay(&mut self, map: &dyn MapLike) {
-        for item in map.get_overlays() {
+    fn draw_overlay(
+        &mut self,
+        map: &dyn MapLike,
+        (start_x, start_y, end_x, end_y): (i32, i32, i32, i32),
+    ) {
+        for item in map.get_overlays().iter().filter(|item| {
+            (start_x..=end_x).contains(&item.x) && (start_y..=end_y).contains(&item.y)
+        }) {
             match item.tile {
                 OverlayTile::None => {}
                 OverlayTile::Ladder => {
-                    self.draw_from_texture_atlas(
-                        "tiles",
-                        0,
-                        false,
-                        item.x as f32,
-                        item.y as f32,
-                        1.0,
-                        1.0,
-                        1.0,
-                    );
+                    self.draw_ladder(map, item.x, item.y);
                 }
                 OverlayTile::Platform => {
                     self.draw_from_texture_atlas(
@@ -851,9 +1657,10 @@ impl Renderer {
                     );
                 }
                 OverlayTile::LadderPlatform => {
+                    self.draw_ladder(map, item.x, item.y);
                     self.draw_from_texture_atlas(
                         "tiles",
-                        0,
+                        2,
                         false,
                         item.x as f32,
                         item.y as f32,
@@ -861,9 +1668,11 @@ impl Renderer {
                         1.0,
                         1.0,
                     );
+                }
+                OverlayTile::Spikes => {
                     self.draw_from_texture_atlas(
-                        "tiles",
-                        2,
+                        "spikes",
+                        0,
                         false,
                         item.x as f32,
                         item.y as f32,
@@ -884,20 +1693,15 @@ impl Renderer {
                         1.0,
                     );
                 }
+                OverlayTile::Torch => self.draw_torch(item.x, item.y),
+                OverlayTile::Grass => self.draw_grass(item.x, item.y),
+                OverlayTile::Vine => self.draw_vine(item.x, item.y),
             };
         }
     }
 
-    fn update_dual_grid_indices(
-        &mut self,
-        camera: &Camera,
-        checker_fn: impl Fn(i32, i32) -> bool,
-        tile_type_index: u8,
-    ) {
-        let tilemap = self.textures.get(&TextureIndexes::Tile).unwrap();
-        let tex_w = tilemap.w;
-        let tex_h = tilemap.h;
-
+    // (start_x, start_y, end_x, end_y) of the dual grid cells that are drawn
+    fn dual_grid_range(camera: &Camera) -> (i32, i32, i32, i32) {
         // Apply half-tile offset: 0.5 left (negative X), 0.5 down (positive Y)
         let offset_x = 0.5 * TILE_SIZE;
         let offset_y = 0.5 * TILE_SIZE;
@@ -911,11 +1715,40 @@ impl Renderer {
         let world_max_x = camera.x * TILE_SIZE + half_w_world + TILE_SIZE;
         let world_max_y = camera.y * TILE_SIZE + half_h_world + TILE_SIZE;
 
-        // Convert world bounds to dual-grid tile indices
-        let start_x = ((world_min_x - offset_x) / TILE_SIZE).floor() as i32 - 10;
-        let end_x = ((world_max_x - offset_x) / TILE_SIZE).ceil() as i32 + 10;
-        let start_y = ((world_min_y - offset_y) / TILE_SIZE).floor() as i32 - 10;
-        let end_y = ((world_max_y - offset_y) / TILE_SIZE).ceil() as i32 + 10;
+        // Convert world bounds to dual-grid tile indices, snapped outwards to whole blocks so
+        // that small camera moves keep the same range (and the same tile meshes)
+        let snap_down = |tile: i32| tile.div_euclid(TILE_MESH_SNAP) * TILE_MESH_SNAP;
+        let snap_up = |tile: i32| snap_down(tile) + TILE_MESH_SNAP - 1;
+        let start_x = snap_down(((world_min_x - offset_x) / TILE_SIZE).floor() as i32 - 10);
+        let end_x = snap_up(((world_max_x - offset_x) / TILE_SIZE).ceil() as i32 + 10);
+        let start_y = snap_down(((world_min_y - offset_y) / TILE_SIZE).floor() as i32 - 10);
+        let end_y = snap_up(((world_max_y - offset_y) / TILE_SIZE).ceil() as i32 + 10);
+        (start_x, start_y, end_x, end_y)
+    }
+
+    fn update_dual_grid_indices(
+        &mut self,
+        (start_x, start_y, end_x, end_y): (i32, i32, i32, i32),
+        checker_fn: impl Fn(i32, i32) -> bool,
+        tile_type_index: u8,
+    ) -> (Vec<Vertex>, Vec<u16>) {
+        let tilemap = self.textures.get(&TextureIndexes::Tile).unwrap();
+        let tex_w = tilemap.w;
+        let tex_h = tilemap.h;
+
+        let offset_x = 0.5 * TILE_SIZE;
+        let offset_y = 0.5 * TILE_SIZE;
+
+        // Every tile is checked once, a cell looks at the four tiles around its corners
+        let checked_w = end_x - start_x + 1;
+        let mut checked = Vec::with_capacity((checked_w * (end_y - start_y + 1)) as usize);
+        for y in start_y..=end_y {
+            for x in start_x..=end_x {
+                checked.push(checker_fn(x, y));
+            }
+        }
+        let is_set = |x: i32, y: i32| checked[((x - start_x) + (y - start_y) * checked_w) as usize];
+
         let mut vertices: Vec<Vertex> = Vec::new();
         let mut indices: Vec<u16> = Vec::new();
         let mut base_index: u16 = 0;
@@ -925,10 +1758,10 @@ impl Renderer {
 
         for y in start_y..end_y {
             for x in start_x..end_x {
-                let tl = checker_fn(x, y);
-                let tr = checker_fn(x + 1, y);
-                let bl = checker_fn(x, y + 1);
-                let br = checker_fn(x + 1, y + 1);
+                let tl = is_set(x, y);
+                let tr = is_set(x + 1, y);
+                let bl = is_set(x, y + 1);
+                let br = is_set(x + 1, y + 1);
 
                 let mut mask: u32 = 0;
                 if tl {
@@ -992,8 +1825,47 @@ impl Renderer {
                 base_index = base_index.wrapping_add(4);
             }
         }
-        self.dualgrid_vertices[tile_type_index as usize] = vertices;
-        self.dualgrid_indices[tile_type_index as usize] = indices;
+        (vertices, indices)
+    }
+
+    // The slopes are not part of the dual grid, they are triangles filled like the inside of the
+    // tile type
+    fn add_slope_triangles(
+        &self,
+        (start_x, start_y, end_x, end_y): (i32, i32, i32, i32),
+        tile_at: impl Fn(i32, i32) -> BaseTile,
+        tile_type_index: u8,
+        (vertices, indices): &mut (Vec<Vertex>, Vec<u16>),
+    ) {
+        let tilemap = self.textures.get(&TextureIndexes::Tile).unwrap();
+        let (u, v) = DUAL_GRID_UV_TABLE[15];
+        let base_u =
+            (u as f32 * TILE_SIZE + 0.5 + tile_type_index as f32 * 4.0 * TILE_SIZE) / tilemap.w;
+        let base_v = (v as f32 * TILE_SIZE + 0.5) / tilemap.h;
+        let du = (TILE_SIZE - 1.0) / tilemap.w;
+        let dv = (TILE_SIZE - 1.0) / tilemap.h;
+
+        for y in start_y..=end_y {
+            for x in start_x..=end_x {
+                // Corners of the triangle inside the tile
+                let corners = match tile_at(x, y) {
+                    BaseTile::SlopeUpRight => [(0.0, 1.0), (1.0, 1.0), (1.0, 0.0)],
+                    BaseTile::SlopeUpLeft => [(0.0, 0.0), (0.0, 1.0), (1.0, 1.0)],
+                    _ => continue,
+                };
+                let base_index = vertices.len() as u16;
+                for (corner_x, corner_y) in corners {
+                    vertices.push(Vertex {
+                        pos: [
+                            (x as f32 + corner_x) * TILE_SIZE,
+                            (y as f32 + corner_y) * TILE_SIZE,
+                        ],
+                        uv: [base_u + corner_x * du, base_v + corner_y * dv],
+                    });
+                }
+                indices.extend_from_slice(&[base_index, base_index + 1, base_index + 2]);
+            }
+        }
     }
 
     fn draw_base_dual_grid(
@@ -1003,49 +1875,249 @@ impl Renderer {
         tile_type_index: u8,
         opacity: f32,
     ) {
-        self.update_dual_grid_indices(camera, checker_fn, tile_type_index);
-        let vertices = &self.dualgrid_vertices[tile_type_index as usize];
-        let indices = &self.dualgrid_indices[tile_type_index as usize];
+        let (vertices, indices) = self.update_dual_grid_indices(
+            Self::dual_grid_range(camera),
+            checker_fn,
+            tile_type_index,
+        );
 
         if vertices.is_empty() {
             return;
         }
 
+        self.queue(DrawCommand::DualGrid {
+            tile_type_index,
+            opacity,
+            vertices,
+            indices,
+        });
+    }
+
+    // Uploads the mesh to the buffers of the (tile type, background region)
+    fn upload_tile_mesh(&mut self, key: (u8, u8), (vertices, indices): (Vec<Vertex>, Vec<u16>)) {
+        let ctx = &mut *self.ctx;
+        let gpu_objects = &mut self.gpu_objects;
+        let mesh = self.tile_meshes.entry(key).or_insert_with(|| {
+            let vb = ctx.new_buffer(
+                BufferType::VertexBuffer,
+                BufferUsage::Dynamic,
+                BufferSource::empty::<Vertex>(1),
+            );
+            let ib = ctx.new_buffer(
+                BufferType::IndexBuffer,
+                BufferUsage::Dynamic,
+                BufferSource::empty::<u16>(1),
+            );
+            gpu_objects.buffers.extend([vb, ib]);
+            TileMesh {
+                vb,
+                ib,
+                vb_cap: 1,
+                ib_cap: 1,
+                index_count: 0,
+            }
+        });
+
+        mesh.index_count = indices.len() as i32;
+        if indices.is_empty() {
+            return;
+        }
+        if vertices.len() > mesh.vb_cap {
+            mesh.vb_cap = vertices.len().next_power_of_two();
+            let buffer = ctx.new_buffer(
+                BufferType::VertexBuffer,
+                BufferUsage::Dynamic,
+                BufferSource::empty::<Vertex>(mesh.vb_cap),
+            );
+            gpu_objects.replace_buffer(ctx, &mut mesh.vb, buffer);
+        }
+        if indices.len() > mesh.ib_cap {
+            mesh.ib_cap = indices.len().next_power_of_two();
+            let buffer = ctx.new_buffer(
+                BufferType::IndexBuffer,
+                BufferUsage::Dynamic,
+                BufferSource::empty::<u16>(mesh.ib_cap),
+            );
+            gpu_objects.replace_buffer(ctx, &mut mesh.ib, buffer);
+        }
+
+        self.stats.buffer_uploads += 2;
+        ctx.buffer_update(mesh.vb, BufferSource::slice(&vertices));
+        ctx.buffer_update(mesh.ib, BufferSource::slice(&indices));
+    }
+
+    // Draws the queued commands of either the world layers or the HUD layer in layer order
+    fn flush_draw_queue(&mut self, camera: &Camera, hud: bool) {
+        let (mut commands, rest): (Vec<QueuedDraw>, Vec<QueuedDraw>) =
+            std::mem::take(&mut self.draw_queue)
+                .into_iter()
+                .partition(|queued| (queued.layer == RenderLayer::Hud) == hud);
+        self.draw_queue = rest;
+
+        // Stable sort so that the draws within a layer keep their order
+        commands.sort_by_key(|queued| queued.layer);
+
+        self.atlas_batch_vertices.clear();
+        self.atlas_batch_indices.clear();
+
+        for queued in commands {
+            if !matches!(queued.command, DrawCommand::Sprite(_)) {
+                self.flush_atlas_batch(camera, hud);
+            }
+            match queued.command {
+                DrawCommand::Sprite(quad) => {
+                    let base_index = self.atlas_batch_vertices.len() as u16;
+                    self.atlas_batch_vertices.extend_from_slice(&quad);
+                    self.atlas_batch_indices.extend_from_slice(&[
+                        base_index,
+                        base_index + 1,
+                        base_index + 2,
+                        base_index,
+                        base_index + 2,
+                        base_index + 3,
+                    ]);
+                }
+                DrawCommand::Rect {
+                    model,
+                    color,
+                    world_base,
+                    world_scale,
+                } => {
+                    self.draw_rect_now(camera, hud, model, color, world_base, world_scale);
+                }
+                DrawCommand::DualGrid {
+                    tile_type_index,
+                    opacity,
+                    vertices,
+                    indices,
+                } => {
+                    self.draw_dual_grid_now(camera, tile_type_index, opacity, &vertices, &indices);
+                }
+                DrawCommand::TileMesh {
+                    tile_type_index,
+                    bg_region,
+                    opacity,
+                } => {
+                    if let Some(mesh) = self.tile_meshes.get(&(tile_type_index, bg_region))
+                        && mesh.index_count > 0
+                    {
+                        let buffers = (mesh.vb, mesh.ib, mesh.index_count);
+                        self.draw_dual_grid_buffers(camera, bg_region, opacity, buffers);
+                    }
+                }
+            }
+        }
+        self.flush_atlas_batch(camera, hud);
+    }
+
+    fn draw_rect_now(
+        &mut self,
+        camera: &Camera,
+        hud: bool,
+        model: [f32; 16],
+        color: [f32; 4],
+        world_base: [f32; 2],
+        world_scale: [f32; 2],
+    ) {
+        // bind white texture and use full-quad UVs
+        let background = self.textures.get(&TextureIndexes::TileBackground).unwrap();
+        let white = self.textures.get(&TextureIndexes::White1x1).unwrap();
+
+        self.bindings.images[0] = white.texture;
+        self.bindings.images[1] = background.texture;
+
+        self.stats.bind(&self.bindings);
+        self.ctx.apply_bindings(&self.bindings);
+
+        let proj = Self::ortho_mvp(camera);
+        let vp = if hud {
+            proj
+        } else {
+            Self::mat4_mul(proj, Self::camera_view(camera))
+        };
+        let mvp = Self::mat4_mul(vp, model);
+
+        let uniforms = Uniforms {
+            mvp,
+            color,
+            uv_base: [0.0, 0.0, 0.0, 0.0],
+            uv_scale: [1.0, 1.0, 0.0, 0.0],
+            world_base: [world_base[0], world_base[1], 0.0, 0.0],
+            world_scale: [world_scale[0], world_scale[1], 0.0, 0.0],
+            color_key: [1.0, 0.0, 1.0, 0.01],
+            bg_tile_size: [64.0, 64.0, 0.0, 0.0],
+            bg_region_origin: [0.0, 0.0, 0.0, 0.0],
+            bg_tex_size: [background.w, background.h, 0.0, 0.0],
+        };
+        self.stats.uniform_updates += 1;
+        self.ctx.apply_uniforms(UniformsSource::table(&uniforms));
+        self.stats.draw_calls += 1;
+        self.ctx.draw(0, 6, 1);
+    }
+
+    fn draw_dual_grid_now(
+        &mut self,
+        camera: &Camera,
+        tile_type_index: u8,
+        opacity: f32,
+        vertices: &[Vertex],
+        indices: &[u16],
+    ) {
         if vertices.len() > self.dualgrid_vb_cap {
             self.dualgrid_vb_cap = vertices.len().next_power_of_two();
-            self.dualgrid_vb = self.ctx.new_buffer(
+            let buffer = self.ctx.new_buffer(
                 BufferType::VertexBuffer,
                 BufferUsage::Stream,
                 BufferSource::empty::<Vertex>(self.dualgrid_vb_cap),
             );
+            self.gpu_objects
+                .replace_buffer(&mut *self.ctx, &mut self.dualgrid_vb, buffer);
         }
 
         if indices.len() > self.dualgrid_ib_cap {
             self.dualgrid_ib_cap = indices.len().next_power_of_two();
-            self.dualgrid_ib = self.ctx.new_buffer(
+            let buffer = self.ctx.new_buffer(
                 BufferType::IndexBuffer,
                 BufferUsage::Stream,
                 BufferSource::empty::<u16>(self.dualgrid_ib_cap),
             );
+            self.gpu_objects
+                .replace_buffer(&mut *self.ctx, &mut self.dualgrid_ib, buffer);
         }
 
+        self.stats.buffer_uploads += 2;
         self.ctx
             .buffer_update(self.dualgrid_vb, BufferSource::slice(vertices));
         self.ctx
             .buffer_update(self.dualgrid_ib, BufferSource::slice(indices));
 
+        let buffers = (self.dualgrid_vb, self.dualgrid_ib, indices.len() as i32);
+        self.draw_dual_grid_buffers(camera, tile_type_index, opacity, buffers);
+    }
+
+    // Draws (vertex buffer, index buffer, index count) with the tile pipeline, filled with the
+    // 64x64 region of the background texture
+    fn draw_dual_grid_buffers(
+        &mut self,
+        camera: &Camera,
+        bg_region: u8,
+        opacity: f32,
+        (vb, ib, index_count): (BufferId, BufferId, i32),
+    ) {
         // Bind textures
         let background = self.textures.get(&TextureIndexes::TileBackground).unwrap();
         let tile = self.textures.get(&TextureIndexes::Tile).unwrap();
 
         let batched_bindings = Bindings {
-            vertex_buffers: vec![self.dualgrid_vb],
-            index_buffer: self.dualgrid_ib,
+            vertex_buffers: vec![vb],
+            index_buffer: ib,
             images: vec![tile.texture, background.texture],
         };
 
         // Switch to batched pipeline
+        self.stats.pipeline_switches += 1;
         self.ctx.apply_pipeline(&self.pipeline_tiles);
+        self.stats.bind(&batched_bindings);
         self.ctx.apply_bindings(&batched_bindings);
 
         // Build VP (no per-tile model matrix since positions are in world pixels)
@@ -1062,28 +2134,35 @@ impl Renderer {
             world_scale: [TILE_SIZE, TILE_SIZE, 0.0, 0.0],
             color_key: [1.0, 0.0, 1.0, 0.01],
             bg_tile_size: [64.0, 64.0, 0.0, 0.0],
-            bg_region_origin: [64.0 * tile_type_index as f32, 0.0, 0.0, 0.0],
+            bg_region_origin: [64.0 * bg_region as f32, 0.0, 0.0, 0.0],
             bg_tex_size: [background.w, background.h, 0.0, 0.0],
         };
+        self.stats.uniform_updates += 1;
         self.ctx.apply_uniforms(UniformsSource::table(&uniforms));
-        self.ctx.draw(0, indices.len() as i32, 1);
+        self.stats.draw_calls += 1;
+        self.ctx.draw(0, index_count, 1);
 
         // Restore default pipeline and bindings for subsequent draws
+        self.stats.pipeline_switches += 1;
         self.ctx.apply_pipeline(&self.pipeline);
+        self.stats.bind(&self.bindings);
         self.ctx.apply_bindings(&self.bindings);
     }
 
-    fn flush_atlas_batch(&mut self, camera: &Camera) {
+    fn flush_atlas_batch(&mut self, camera: &Camera, hud: bool) {
         if self.atlas_batch_vertices.is_empty() {
             return;
         }
         let background = self.textures.get(&TextureIndexes::TileBackground).unwrap();
         let atlas = self.textures.get(&TextureIndexes::Atlas).unwrap();
 
-        // // VP matrix (no per-sprite model)
-        let view = Self::camera_view(camera);
+        // // VP matrix (no per-sprite model), the HUD is drawn in screen pixels
         let proj = Self::ortho_mvp(camera);
-        let vp = Self::mat4_mul(proj, view);
+        let vp = if hud {
+            proj
+        } else {
+            Self::mat4_mul(proj, Self::camera_view(camera))
+        };
 
         // // Uniforms that are shared across the whole batch
         let uniforms = Uniforms {
@@ -1098,30 +2177,37 @@ impl Renderer {
             bg_region_origin: [0.0, 0.0, 0.0, 0.0],
             bg_tex_size: [background.w, background.h, 0.0, 0.0],
         };
+        self.stats.uniform_updates += 1;
         self.ctx.apply_uniforms(UniformsSource::table(&uniforms));
 
         if self.atlas_batch_vertices.len() > self.atlas_vb_cap {
             self.atlas_vb_cap = self.atlas_batch_vertices.len().next_power_of_two();
-            self.atlas_vb = self.ctx.new_buffer(
+            let buffer = self.ctx.new_buffer(
                 BufferType::VertexBuffer,
                 BufferUsage::Stream,
                 BufferSource::empty::<Vertex>(self.atlas_vb_cap),
             );
+            self.gpu_objects
+                .replace_buffer(&mut *self.ctx, &mut self.atlas_vb, buffer);
         }
 
         if self.atlas_batch_indices.len() > self.atlas_ib_cap {
             self.atlas_ib_cap = self.atlas_batch_indices.len().next_power_of_two();
-            self.atlas_ib = self.ctx.new_buffer(
+            let buffer = self.ctx.new_buffer(
                 BufferType::IndexBuffer,
                 BufferUsage::Stream,
                 BufferSource::empty::<u16>(self.atlas_ib_cap),
             );
+            self.gpu_objects
+                .replace_buffer(&mut *self.ctx, &mut self.atlas_ib, buffer);
         }
 
+        self.stats.buffer_uploads += 1;
         self.ctx.buffer_update(
             self.atlas_vb,
             BufferSource::slice(&self.atlas_batch_vertices),
         );
+        self.stats.buffer_uploads += 1;
         self.ctx.buffer_update(
             self.atlas_ib,
             BufferSource::slice(&self.atlas_batch_indices),
@@ -1133,8 +2219,13 @@ impl Renderer {
             images: vec![atlas.texture, background.texture],
         };
 
+        self.stats.bind(&batched_bindings);
         self.ctx.apply_bindings(&batched_bindings);
+        self.stats.draw_calls += 1;
         self.ctx.draw(0, self.atlas_batch_indices.len() as i32, 1);
+
+        self.atlas_batch_vertices.clear();
+        self.atlas_batch_indices.clear();
     }
 
     pub fn ortho_mvp(camera: &Camera) -> [f32; 16] {
@@ -1156,8 +2247,9 @@ impl Renderer {
     }
 
     fn camera_view(camera: &Camera) -> [f32; 16] {
-        let cx = camera.x * TILE_SIZE;
-        let cy = camera.y * TILE_SIZE;
+        let (shake_x, shake_y) = camera.shake_offset();
+        let cx = (camera.x + shake_x) * TILE_SIZE;
+        let cy = (camera.y + shake_y) * TILE_SIZE;
         let zoom = camera.zoom;
 
         // Pixel-snap the camera to avoid subpixel seams at various zoom levels
@@ -1175,16 +2267,6 @@ impl Renderer {
         Self::mat4_mul(translate_to_screen_center, ts)
     }
 
-    fn mat4_rotation_z(angle_rad: f32) -> [f32; 16] {
-        let c = angle_rad.cos();
-        let s = angle_rad.sin();
-
-        // Column-major 4x4 rotation around Z
-        [
-            c, s, 0.0, 0.0, -s, c, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
-        ]
-    }
-
     pub fn mat4_mul(a: [f32; 16], b: [f32; 16]) -> [f32; 16] {
         let mut out = [0.0f32; 16];
         for row in 0..4 {
@@ -1212,6 +2294,19 @@ impl Renderer {
     }
 }
 
+impl Drop for Renderer {
+    fn drop(&mut self) {
+        self.light_pass
+            .delete(&mut *self.ctx, &mut self.gpu_objects);
+        for buffer in self.gpu_objects.buffers.drain() {
+            self.ctx.delete_buffer(buffer);
+        }
+        for texture in self.gpu_objects.textures.drain() {
+            self.ctx.delete_texture(texture);
+        }
+    }
+}
+
 const VERTEX_SHADER: &str = r#"#version 100
 attribute vec2 pos;
 attribute vec2 uv;
@@ -0,0 +1,138 @@
+use crate::state::medals::Medal;
+use crate::telemetry::log;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::{RwLock, RwLockWriteGuard};
+use std::{fs, io};
+
+// Next to the executable like the tuning file
+const SAVE_FILE: &str = "save.json";
+// Bump when a change to the save can not be handled by the serde defaults (a field renamed or its
+// meaning changed) and add the migration from the previous version to MIGRATIONS. New fields
+// only need a default.
+const SAVE_VERSION: u32 = 1;
+// MIGRATIONS[n] turns a save of version n + 1 in to version n + 2, they are run in order
+const MIGRATIONS: &[fn(&mut Value)] = &[];
+const _: () = assert!(MIGRATIONS.len() as u32 == SAVE_VERSION - 1);
+
+// What the player has done over all the games. Every field has a default so that a save from
+// before the field was added still loads.
+#[derive(Clone, Default, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Progress {
+    pub most_rooms_visited: u32,
+    pub most_coins: u32,
+    // The best medal of each room with a par time, by the file name of the room
+    pub medals: BTreeMap<String, Medal>,
+}
+
+#[derive(Serialize)]
+struct SaveFile<'a> {
+    version: u32,
+    progress: &'a Progress,
+}
+
+static PROGRESS: RwLock<Progress> = RwLock::new(Progress {
+    most_rooms_visited: 0,
+    most_coins: 0,
+    medals: BTreeMap::new(),
+});
+
+pub fn progress() -> Progress {
+    PROGRESS
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+pub fn progress_mut() -> RwLockWriteGuard<'static, Progress> {
+    PROGRESS
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// Reads the progress out of a save of any version up to the current one, returns the version the
+// save was written with
+fn parse(json: &str) -> Result<(Progress, u32), String> {
+    let mut save: Value = serde_json::from_str(json).map_err(|error| error.to_string())?;
+    let version = save
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or("no version")? as u32;
+    if version == 0 || version > SAVE_VERSION {
+        return Err(format!("unknown version {}", version));
+    }
+    for migration in &MIGRATIONS[version as usize - 1..] {
+        migration(&mut save);
+    }
+    let progress = match save.get_mut("progress") {
+        Some(progress) => {
+            serde_json::from_value(progress.take()).map_err(|error| error.to_string())?
+        }
+        None => Progress::default(),
+    };
+    Ok((progress, version))
+}
+
+// The old file is kept next to the new one before anything overwrites it
+fn back_up(suffix: &str) {
+    let backup = format!("{}.{}.bak", SAVE_FILE, suffix);
+    match fs::copy(SAVE_FILE, &backup) {
+        Ok(_) => log!("Old save kept as {}", backup),
+        Err(error) => log!("Could not back up {}: {}", SAVE_FILE, error),
+    }
+}
+
+/// Reads the save file if there is one, call before the game starts. A save of an older version
+/// is migrated and one that can not be read is replaced, the old file is backed up in both cases.
+pub fn load() {
+    let Ok(json) = fs::read_to_string(SAVE_FILE) else {
+        return;
+    };
+    match parse(&json) {
+        Ok((progress, version)) => {
+            if version < SAVE_VERSION {
+                log!("Save migrated from version {} to {}", version, SAVE_VERSION);
+                back_up(&format!("v{}", version));
+            }
+            *progress_mut() = progress;
+            log!("Progress read from {}", SAVE_FILE);
+        }
+        Err(error) => {
+            log!("Could not read {}: {}", SAVE_FILE, error);
+            back_up("unreadable");
+        }
+    }
+}
+
+pub fn save() -> io::Result<()> {
+    let progress = progress();
+    let save = SaveFile {
+        version: SAVE_VERSION,
+        progress: &progress,
+    };
+    let json = serde_json::to_string_pretty(&save).map_err(io::Error::other)?;
+    fs::write(SAVE_FILE, json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saves_with_missing_and_unknown_fields_load() {
+        let json = r#"{"version": 1, "progress": {"most_coins": 12, "from_a_newer_game": 3}}"#;
+        let (progress, version) = parse(json).unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(
+            progress,
+            Progress {
+                most_coins: 12,
+                ..Progress::default()
+            }
+        );
+        assert!(parse(r#"{"progress": {}}"#).is_err());
+        assert!(parse(r#"{"version": 99}"#).is_err());
+    }
+}
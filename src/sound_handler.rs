@@ -1,10 +1,19 @@
+use crate::state::Pos;
+use crate::state::map_like::Material;
+use crate::telemetry::{self, log};
 use egui::ahash::HashMap;
+use miniquad::date;
 use quad_snd::{AudioContext, PlaySoundParams, Sound as SndSound};
-use rand::seq::IndexedRandom;
+use rand::Rng;
+use std::collections::{HashSet, VecDeque};
 use std::fs;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, Sender};
 
+// A sound can list the sounds to play instead of it on the ground of a material, see Sound::on
 macro_rules! define_sounds {
-    ($($variant:ident => $file:literal),+ $(,)?) => {
+    ($($variant:ident => $file:literal $({ $($material:ident => $on_material:ident),+ $(,)? })?),+ $(,)?) => {
         #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
         pub enum Sound {
             $($variant),+
@@ -20,78 +29,728 @@ macro_rules! define_sounds {
                     $(Sound::$variant => $file),+
                 }
             }
+
+            /// The variant of the sound made on the ground of the material, the sound itself when
+            /// there is none for it or the ground is not known
+            pub fn on(self, material: Option<Material>) -> Sound {
+                match (self, material) {
+                    $($($((Sound::$variant, Some(Material::$material)) => Sound::$on_material,)+)?)+
+                    _ => self,
+                }
+            }
         }
     };
 }
 
 define_sounds! {
     Jump        => "jump",
-    Walk        => "walk",
+    Walk        => "walk" { Wood => WalkWood },
     CollectCoin => "collect_coin",
     Swing       => "swipe__swipe_woosh_07",
     Throw       => "throw",
     Clink       => "clink",
     EnemyHit    => "enemy_hit__bong_clunk_hit_short_01",
     PlayerHit   => "player_hit__bong_clunk_hit_short_05",
+    Land        => "land_thud" { Wood => LandWood },
+    EncounterClear => "encounter_clear",
+    OneUp       => "one_up",
+    Clang       => "klang",
+    RoomComplete => "one_up",
+    WalkWood    => "walk_wood",
+    LandWood    => "land_wood",
+}
+
+// Groups of sounds that can be muted on their own from the sound overlay of the debug menu
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum SoundCategory {
+    Player,
+    Combat,
+    Pickups,
+    Events,
+}
+
+impl SoundCategory {
+    pub const ALL: [SoundCategory; 4] = [
+        SoundCategory::Player,
+        SoundCategory::Combat,
+        SoundCategory::Pickups,
+        SoundCategory::Events,
+    ];
+}
+
+impl Sound {
+    pub fn category(self) -> SoundCategory {
+        match self {
+            Sound::Jump
+            | Sound::Walk
+            | Sound::WalkWood
+            | Sound::Swing
+            | Sound::Throw
+            | Sound::Land
+            | Sound::LandWood => SoundCategory::Player,
+            Sound::Clink | Sound::EnemyHit | Sound::PlayerHit | Sound::Clang => {
+                SoundCategory::Combat
+            }
+            Sound::CollectCoin | Sound::OneUp => SoundCategory::Pickups,
+            Sound::EncounterClear | Sound::RoomComplete => SoundCategory::Events,
+        }
+    }
+
+    /// Closed caption shown on the HUD when the sound plays, None for the sounds that are too
+    /// common to be worth one
+    pub fn caption(self) -> Option<&'static str> {
+        match self {
+            Sound::Jump
+            | Sound::Walk
+            | Sound::WalkWood
+            | Sound::Swing
+            | Sound::Throw
+            | Sound::Land
+            | Sound::LandWood => None,
+            Sound::CollectCoin => Some("Coin jingles"),
+            Sound::Clink => Some("Clink"),
+            Sound::EnemyHit => Some("Enemy thuds"),
+            Sound::PlayerHit => Some("You get hurt"),
+            Sound::Clang => Some("Metal clangs"),
+            Sound::EncounterClear => Some("Doors unlock"),
+            Sound::OneUp => Some("Chime"),
+            Sound::RoomComplete => Some("Fanfare plays"),
+        }
+    }
 }
 
-fn load_sound(path: &str) -> std::io::Result<Vec<u8>> {
-    fs::read(path)
+// Captions are shown this long after their sound, fading out over the last part of it
+const CAPTION_SECONDS: f64 = 2.5;
+const CAPTION_FADE_SECONDS: f64 = 0.5;
+// The oldest captions make room for new ones past this many
+const MAX_CAPTIONS: usize = 4;
+
+// A caption of a sound that played, see Sound::caption
+#[derive(Clone)]
+pub struct Caption {
+    pub text: &'static str,
+    // Where the sound came from, when the one playing it said
+    pub pos: Option<Pos>,
+    time: f64,
+}
+
+impl Caption {
+    /// From 1.0 to 0.0 as the caption fades out
+    pub fn opacity(&self, now: f64) -> f32 {
+        ((self.time + CAPTION_SECONDS - now) / CAPTION_FADE_SECONDS).clamp(0.0, 1.0) as f32
+    }
+}
+
+// How many of the latest sounds the sound overlay lists
+const RECENT_SOUNDS: usize = 40;
+// Sounds played closer together than this are listed as one with a count
+const SAME_MOMENT_SECONDS: f64 = 0.001;
+
+// A sound that was played, or would have been, for the sound overlay
+#[derive(Clone)]
+pub struct PlayedSound {
+    pub sound: Sound,
+    // Index of the variant file played and how many there are, None when there are no files
+    pub variant: Option<(usize, usize)>,
+    pub volume: f32,
+    // Where in the world the sound came from, when the one playing it said
+    pub pos: Option<Pos>,
+    pub time: f64,
+    // How many times it was played at the same moment, like coins collected on the same frame
+    pub count: u32,
+}
+
+// Long tracks that loop in the background, one at a time
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Music {
+    Theme,
+}
+
+impl Music {
+    pub const fn file_name(self) -> &'static str {
+        match self {
+            Music::Theme => "theme",
+        }
+    }
+}
+
+// Short pieces of music for big moments, the music is quieter while they play
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Stinger {
+    SecretFound,
+    KeyObtained,
+    BossPhase,
+}
+
+impl Stinger {
+    pub const fn file_name(self) -> &'static str {
+        match self {
+            Stinger::SecretFound => "stinger_secret_found",
+            Stinger::KeyObtained => "stinger_key_obtained",
+            Stinger::BossPhase => "stinger_boss_phase",
+        }
+    }
+
+    // About as long as the stinger, the music is ducked this long
+    const fn seconds(self) -> f64 {
+        match self {
+            Stinger::SecretFound => 2.5,
+            Stinger::KeyObtained => 1.5,
+            Stinger::BossPhase => 2.0,
+        }
+    }
+
+    fn caption(self) -> &'static str {
+        match self {
+            Stinger::SecretFound => "Mysterious tune",
+            Stinger::KeyObtained => "Triumphant jingle",
+            Stinger::BossPhase => "Music swells",
+        }
+    }
+}
+
+// Loaded while the game starts, the rest of the sounds are loaded the first time they are played
+const PRECACHED_SOUNDS: &[Sound] = &[
+    Sound::Jump,
+    Sound::Walk,
+    Sound::WalkWood,
+    Sound::Land,
+    Sound::LandWood,
+    Sound::Swing,
+    Sound::Throw,
+    Sound::EnemyHit,
+    Sound::PlayerHit,
+    Sound::CollectCoin,
+    Sound::Clink,
+];
+// A sound played before it was loaded is played once it is, unless it is too late by then
+const LATE_PLAY_SECONDS: f64 = 0.25;
+const MUSIC_VOLUME: f32 = 0.5;
+// The music keeps playing quietly under a stinger, so it goes on in sync after it
+const DUCKED_MUSIC_VOLUME: f32 = 0.1;
+const MUSIC_RESTORE_PER_FRAME: f32 = 0.02;
+const STINGER_VOLUME: f32 = 0.8;
+// Files made in to sounds per frame, the files are read on a thread
+const SOUNDS_LOADED_PER_FRAME: usize = 8;
+
+// What the loader thread is asked to read
+enum LoadRequest {
+    Sound(Sound),
+    Music(Music),
+    Stinger(Stinger),
+}
+
+// Files the loader thread has read, the ones that do not exist are left out
+enum LoadedFiles {
+    Sound {
+        sound: Sound,
+        plain: Option<Vec<u8>>,
+        variants: Vec<Vec<u8>>,
+    },
+    Music {
+        music: Music,
+        bytes: Option<Vec<u8>>,
+    },
+    Stinger {
+        stinger: Stinger,
+        bytes: Option<Vec<u8>>,
+    },
+}
+
+fn read_music(file_name: &str) -> Option<Vec<u8>> {
+    fs::read(format!("assets/music/{}.wav", file_name)).ok()
+}
+
+fn read_files(request: LoadRequest) -> LoadedFiles {
+    match request {
+        // The non variant sound and the variant sounds such as "assets/sounds/dest/{}__v01.wav"
+        LoadRequest::Sound(sound) => {
+            let file_name = sound.file_name();
+            LoadedFiles::Sound {
+                sound,
+                plain: fs::read(format!("assets/sounds/{}", file_name)).ok(),
+                variants: (1..9)
+                    .filter_map(|variant_i| {
+                        let path =
+                            format!("assets/sounds/dest/{}__v{:02}.wav", file_name, variant_i);
+                        fs::read(path).ok()
+                    })
+                    .collect(),
+            }
+        }
+        // Only the track that is about to play is read, not every track at startup
+        LoadRequest::Music(music) => LoadedFiles::Music {
+            music,
+            bytes: read_music(music.file_name()),
+        },
+        LoadRequest::Stinger(stinger) => LoadedFiles::Stinger {
+            stinger,
+            bytes: read_music(stinger.file_name()),
+        },
+    }
 }
 
 pub struct SoundHandler {
     #[allow(dead_code)]
     sounds: HashMap<Sound, SndSound>,
+    // The sounds that are loaded, the ones without files have no variants
     sound_variants: HashMap<Sound, Vec<SndSound>>,
-    audio_context: AudioContext,
+    // None for the silent sound handler, nothing is ever loaded to play then
+    audio_context: Option<AudioContext>,
+    // Set while a game is simulated on the side, see muted
+    muted: AtomicBool,
+    // Files are read on a thread when asked for, load_some makes sounds of them
+    requests: Sender<LoadRequest>,
+    files: Receiver<LoadedFiles>,
+    requested: Mutex<HashSet<Sound>>,
+    precached: usize,
+    // Sounds played before they were loaded, with their volume and when they were played
+    waiting: Mutex<Vec<(Sound, f32, f64)>>,
+    // The music playing and the music that should be, it changes in load_some
+    music: Option<(Music, SndSound)>,
+    wanted_music: Mutex<Option<Music>>,
+    music_volume: f32,
+    // Stingers are loaded when first played like the sounds, None for the ones without a file
+    stingers: HashMap<Stinger, Option<SndSound>>,
+    requested_stingers: HashSet<Stinger>,
+    // Stingers to play on the next load_some, with when they were played
+    queued_stingers: Mutex<Vec<(Stinger, f64)>>,
+    // The music is ducked until this time
+    ducked_until: f64,
+    // For the sound overlay, the sounds are played from &self so these are behind locks
+    recent: Mutex<VecDeque<PlayedSound>>,
+    muted_categories: Mutex<HashSet<SoundCategory>>,
+    // Closed captions, off by default and turned on from the pause menu
+    captions_enabled: AtomicBool,
+    captions: Mutex<VecDeque<Caption>>,
 }
 
 impl SoundHandler {
+    /// The precached sounds are read on a thread, call load_some every frame until they are
+    /// loaded and after that for the sounds and music loaded when they are first played
     pub fn new() -> Self {
-        let audio_context = AudioContext::new();
-
-        let mut sounds: HashMap<Sound, SndSound> = HashMap::default();
-        let mut sound_variants: HashMap<Sound, Vec<SndSound>> = HashMap::default();
-        for sound in Sound::ALL {
-            let file_name = sound.file_name();
+        Self::with_audio_context(Some(AudioContext::new()))
+    }
 
-            // Non variant sound
-            let full_path = format!("assets/sounds/{}", file_name);
-            if let Ok(bytes) = load_sound(&full_path) {
-                let click_sound = SndSound::load(&audio_context, &bytes);
-                sounds.insert(*sound, click_sound);
-            }
+    /// Plays nothing and needs no audio device, for running games in the tests
+    #[cfg(test)]
+    pub fn silent() -> Self {
+        Self::with_audio_context(None)
+    }
 
-            // Variant sounds
-            // 1. List file names such as "assets/sounds/dest/{}__v01.wav"
-            let mut variant_sounds: Vec<SndSound> = Vec::new();
-            for variant_i in 1..9 {
-                let full_path = format!("assets/sounds/dest/{}__v{:02}.wav", file_name, variant_i);
-                if let Ok(bytes) = load_sound(&full_path) {
-                    let click_sound = SndSound::load(&audio_context, &bytes);
-                    variant_sounds.push(click_sound);
+    fn with_audio_context(audio_context: Option<AudioContext>) -> Self {
+        let (request_sender, request_receiver) = std::sync::mpsc::channel();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            for request in request_receiver {
+                if sender.send(read_files(request)).is_err() {
+                    return;
                 }
             }
-            sound_variants.insert(*sound, variant_sounds);
+        });
+        if audio_context.is_some() {
+            for sound in PRECACHED_SOUNDS {
+                request_sender.send(LoadRequest::Sound(*sound)).unwrap();
+            }
         }
 
         SoundHandler {
-            sounds,
-            sound_variants,
+            sounds: HashMap::default(),
+            sound_variants: HashMap::default(),
             audio_context,
+            muted: AtomicBool::new(false),
+            requests: request_sender,
+            files: receiver,
+            requested: Mutex::new(PRECACHED_SOUNDS.iter().copied().collect()),
+            precached: 0,
+            waiting: Mutex::new(Vec::new()),
+            music: None,
+            wanted_music: Mutex::new(None),
+            music_volume: MUSIC_VOLUME,
+            stingers: HashMap::default(),
+            requested_stingers: HashSet::new(),
+            queued_stingers: Mutex::new(Vec::new()),
+            ducked_until: 0.0,
+            recent: Mutex::new(VecDeque::new()),
+            muted_categories: Mutex::new(HashSet::new()),
+            captions_enabled: AtomicBool::new(false),
+            captions: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Makes sounds of some of the files that have been read so far, changes the music and plays
+    /// the stingers
+    pub fn load_some(&mut self) {
+        if self.audio_context.is_none() {
+            return;
+        }
+        let wanted_music = *self
+            .wanted_music
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some((music, track)) = &self.music
+            && Some(*music) != wanted_music
+        {
+            track.stop(self.audio_context());
+            track.delete(self.audio_context());
+            self.music = None;
+        }
+
+        for _ in 0..SOUNDS_LOADED_PER_FRAME {
+            let Ok(files) = self.files.try_recv() else {
+                break;
+            };
+            match files {
+                LoadedFiles::Sound {
+                    sound,
+                    plain,
+                    variants,
+                } => self.add_sound(sound, plain, variants),
+                LoadedFiles::Music { music, bytes } => {
+                    if wanted_music != Some(music) || self.music.is_some() {
+                        continue;
+                    }
+                    let Some(bytes) = bytes else {
+                        log!("No music file for {:?}", music);
+                        continue;
+                    };
+                    let track = SndSound::load(self.audio_context(), &bytes);
+                    track.play(
+                        self.audio_context(),
+                        PlaySoundParams {
+                            looped: true,
+                            volume: self.music_volume,
+                        },
+                    );
+                    self.music = Some((music, track));
+                }
+                LoadedFiles::Stinger { stinger, bytes } => {
+                    if bytes.is_none() {
+                        telemetry::record_asset_warning(format!("No music file for {:?}", stinger));
+                    }
+                    let stinger_sound =
+                        bytes.map(|bytes| SndSound::load(self.audio_context(), &bytes));
+                    self.stingers.insert(stinger, stinger_sound);
+                }
+            }
+        }
+
+        self.play_stingers();
+    }
+
+    // Plays the queued stingers that are loaded, and ducks the music under them
+    fn play_stingers(&mut self) {
+        let now = date::now();
+        let queued = std::mem::take(
+            &mut *self
+                .queued_stingers
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        );
+        let mut still_loading = Vec::new();
+        for (stinger, time) in queued {
+            if now - time > LATE_PLAY_SECONDS {
+                continue;
+            }
+            match self.stingers.get(&stinger) {
+                Some(Some(stinger_sound)) => {
+                    stinger_sound.play(
+                        self.audio_context(),
+                        PlaySoundParams {
+                            looped: false,
+                            volume: STINGER_VOLUME,
+                        },
+                    );
+                    self.ducked_until = self.ducked_until.max(now + stinger.seconds());
+                    // Only the stingers that are heard get a caption
+                    if self.captions_enabled() {
+                        self.caption(Caption {
+                            text: stinger.caption(),
+                            pos: None,
+                            time: now,
+                        });
+                    }
+                }
+                Some(None) => {}
+                None => {
+                    if self.requested_stingers.insert(stinger) {
+                        let _ = self.requests.send(LoadRequest::Stinger(stinger));
+                    }
+                    still_loading.push((stinger, time));
+                }
+            }
+        }
+        self.queued_stingers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .extend(still_loading);
+
+        // Quiet at once under the stinger and back up gradually after it
+        let volume = if now < self.ducked_until {
+            DUCKED_MUSIC_VOLUME
+        } else {
+            (self.music_volume + MUSIC_RESTORE_PER_FRAME).min(MUSIC_VOLUME)
+        };
+        if volume != self.music_volume {
+            self.music_volume = volume;
+            if let Some((_, track)) = &self.music {
+                track.set_volume(self.audio_context(), volume);
+            }
         }
     }
 
+    fn audio_context(&self) -> &AudioContext {
+        self.audio_context
+            .as_ref()
+            .expect("Sounds are only loaded with an audio context")
+    }
+
+    fn add_sound(&mut self, sound: Sound, plain: Option<Vec<u8>>, variants: Vec<Vec<u8>>) {
+        if PRECACHED_SOUNDS.contains(&sound) {
+            self.precached += 1;
+        }
+        // Sounds without any files stay silent
+        if plain.is_none() && variants.is_empty() {
+            telemetry::record_asset_warning(format!("No sound files for {:?}", sound));
+        }
+        if let Some(bytes) = plain {
+            self.sounds
+                .insert(sound, SndSound::load(self.audio_context(), &bytes));
+        }
+        let variants = variants
+            .iter()
+            .map(|bytes| SndSound::load(self.audio_context(), bytes))
+            .collect();
+        self.sound_variants.insert(sound, variants);
+
+        // Played while it was loading
+        let now = date::now();
+        let mut waiting = self
+            .waiting
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut late = Vec::new();
+        waiting.retain(|&(waiting_sound, volume, time)| {
+            if waiting_sound != sound {
+                return true;
+            }
+            if now - time < LATE_PLAY_SECONDS {
+                late.push(volume);
+            }
+            false
+        });
+        drop(waiting);
+        for volume in late {
+            self.play_loaded(sound, volume);
+        }
+    }
+
+    /// From 0.0 to 1.0 when the precached sounds are loaded, the rest load when first played
+    pub fn loading_progress(&self) -> f32 {
+        self.precached as f32 / PRECACHED_SOUNDS.len() as f32
+    }
+
+    /// Starts looping the music, replacing what was playing. The file is read on the loader
+    /// thread so the music starts a moment later.
+    pub fn play_music(&self, music: Music) {
+        let mut wanted_music = self
+            .wanted_music
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if *wanted_music == Some(music) {
+            return;
+        }
+        *wanted_music = Some(music);
+        let _ = self.requests.send(LoadRequest::Music(music));
+    }
+
+    /// Plays the stinger over the music, which is quieter until the stinger is over
+    pub fn play_stinger(&self, stinger: Stinger) {
+        if self.muted.load(Ordering::Relaxed) || self.audio_context.is_none() {
+            return;
+        }
+        self.queued_stingers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push((stinger, date::now()));
+    }
+
+    pub fn stop_music(&self) {
+        *self
+            .wanted_music
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+    }
+
+    /// Runs f without playing any of the sounds
+    pub fn muted<T>(&self, f: impl FnOnce() -> T) -> T {
+        self.muted.store(true, Ordering::Relaxed);
+        let result = f();
+        self.muted.store(false, Ordering::Relaxed);
+        result
+    }
+
     pub fn play(&self, sound: Sound) {
-        let sound_variants = self.sound_variants.get(&sound).unwrap();
+        self.play_from(sound, None);
+    }
 
+    /// Like play, the spot is shown in the sound overlay
+    pub fn play_at(&self, sound: Sound, pos: Pos) {
+        self.play_from(sound, Some(pos));
+    }
+
+    fn play_from(&self, sound: Sound, pos: Option<Pos>) {
+        if self.muted.load(Ordering::Relaxed) || self.audio_context.is_none() {
+            return;
+        }
+        let volume = if self.is_category_muted(sound.category()) {
+            0.0
+        } else {
+            PlaySoundParams::default().volume
+        };
+        let time = date::now();
+        let variant = match self.sound_variants.get(&sound) {
+            Some(sound_variants) => {
+                let variant = self.play_loaded(sound, volume);
+                variant.map(|index| (index, sound_variants.len()))
+            }
+            // Heard once loaded, unless that takes too long
+            None => {
+                self.request(sound);
+                self.waiting
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .push((sound, volume, time));
+                None
+            }
+        };
+        self.record(PlayedSound {
+            sound,
+            variant,
+            volume,
+            pos,
+            time,
+            count: 1,
+        });
+        if let Some(text) = sound.caption()
+            && self.captions_enabled()
+        {
+            self.caption(Caption { text, pos, time });
+        }
+    }
+
+    // Plays a random variant of a loaded sound, returns the index of it
+    fn play_loaded(&self, sound: Sound, volume: f32) -> Option<usize> {
+        let sound_variants = self.sound_variants.get(&sound)?;
         if sound_variants.is_empty() {
+            return None;
+        }
+        let index = rand::rng().random_range(0..sound_variants.len());
+        if volume > 0.0 {
+            sound_variants[index].play(
+                self.audio_context(),
+                PlaySoundParams {
+                    looped: false,
+                    volume,
+                },
+            );
+        }
+        Some(index)
+    }
+
+    // Asks the loader thread for the files of the sound, once
+    fn request(&self, sound: Sound) {
+        let mut requested = self
+            .requested
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if requested.insert(sound) {
+            let _ = self.requests.send(LoadRequest::Sound(sound));
+        }
+    }
+
+    fn record(&self, played: PlayedSound) {
+        let mut recent = self
+            .recent
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(last) = recent.back_mut()
+            && last.sound == played.sound
+            && played.time - last.time < SAME_MOMENT_SECONDS
+        {
+            last.count += 1;
             return;
         }
+        recent.push_back(played);
+        if recent.len() > RECENT_SOUNDS {
+            recent.pop_front();
+        }
+    }
 
-        let mut rng = rand::rng();
-        let sns_sound = sound_variants.choose(&mut rng).unwrap();
+    // A caption that is already shown is shown longer instead of twice
+    fn caption(&self, caption: Caption) {
+        let mut captions = self
+            .captions
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        captions.retain(|shown| shown.text != caption.text);
+        captions.push_back(caption);
+        if captions.len() > MAX_CAPTIONS {
+            captions.pop_front();
+        }
+    }
+
+    /// The captions to show, newest last
+    pub fn captions(&self) -> Vec<Caption> {
+        let mut captions = self
+            .captions
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let now = date::now();
+        captions.retain(|caption| caption.time + CAPTION_SECONDS > now);
+        captions.iter().cloned().collect()
+    }
+
+    pub fn captions_enabled(&self) -> bool {
+        self.captions_enabled.load(Ordering::Relaxed)
+    }
 
-        sns_sound.play(&self.audio_context, PlaySoundParams::default());
+    pub fn set_captions_enabled(&self, enabled: bool) {
+        self.captions_enabled.store(enabled, Ordering::Relaxed);
+        if !enabled {
+            self.captions
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .clear();
+        }
+    }
+
+    /// The latest sounds, newest last
+    pub fn recent_sounds(&self) -> Vec<PlayedSound> {
+        let recent = self
+            .recent
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        recent.iter().cloned().collect()
+    }
+
+    pub fn is_category_muted(&self, category: SoundCategory) -> bool {
+        let muted = self
+            .muted_categories
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        muted.contains(&category)
+    }
+
+    pub fn set_category_muted(&self, category: SoundCategory, muted: bool) {
+        let mut muted_categories = self
+            .muted_categories
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if muted {
+            muted_categories.insert(category);
+        } else {
+            muted_categories.remove(&category);
+        }
     }
 }
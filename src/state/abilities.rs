@@ -0,0 +1,74 @@
+use crate::state::map_like::BaseTile;
+
+// Movement abilities the player finds in the world. Some of the ways forward are locked until
+// the player has the ability for them.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Ability {
+    // A second jump in the air, for the ledges that are too high for one jump
+    DoubleJump,
+    // A quick burst forwards that breaks the dash barriers
+    Dash,
+    // Dropped bombs break the bomb walls and the cracked tiles around them
+    Bomb,
+}
+
+impl Ability {
+    pub const ALL: [Ability; 3] = [Ability::DoubleJump, Ability::Dash, Ability::Bomb];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Ability::DoubleJump => "Double jump",
+            Ability::Dash => "Dash",
+            Ability::Bomb => "Bomb",
+        }
+    }
+
+    /// The pickups and the locks of the ability are drawn in this color
+    pub fn color(self) -> [f32; 4] {
+        match self {
+            Ability::DoubleJump => [0.45, 0.8, 0.95, 1.0],
+            Ability::Dash => [0.95, 0.55, 0.2, 1.0],
+            Ability::Bomb => [0.85, 0.25, 0.3, 1.0],
+        }
+    }
+
+    /// The tile that only opens for the ability. Double jump locks are ledges too high to get up
+    /// on without it, there is no tile for them.
+    pub fn lock_tile(self) -> Option<BaseTile> {
+        match self {
+            Ability::DoubleJump => None,
+            Ability::Dash => Some(BaseTile::DashBarrier),
+            Ability::Bomb => Some(BaseTile::BombWall),
+        }
+    }
+}
+
+/// The abilities the player has
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Abilities {
+    bits: u8,
+}
+
+impl Abilities {
+    pub fn has(&self, ability: Ability) -> bool {
+        self.bits & (1 << ability as u8) != 0
+    }
+
+    /// Returns false if the ability was there already
+    pub fn insert(&mut self, ability: Ability) -> bool {
+        let had = self.has(ability);
+        self.bits |= 1 << ability as u8;
+        !had
+    }
+
+    pub fn with(mut self, ability: Ability) -> Self {
+        self.insert(ability);
+        self
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Ability> + '_ {
+        Ability::ALL
+            .into_iter()
+            .filter(|&ability| self.has(ability))
+    }
+}
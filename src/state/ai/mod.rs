@@ -0,0 +1,3 @@
+pub mod pathfinding;
+
+pub use pathfinding::{JumpReach, PathEdge, Pathfinder, reachable_tiles};
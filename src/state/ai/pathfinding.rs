@@ -0,0 +1,394 @@
+use crate::physics::EPS;
+use crate::state::common::BoundingBox;
+use crate::state::map_like::{BaseTile, MapLike};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+// How high (in tiles) and how far a walking enemy can jump
+const JUMP_HEIGHT: i32 = 2;
+const JUMP_DISTANCE: i32 = 3;
+// Longest drop a walking enemy is willing to take
+const MAX_DROP: i32 = 8;
+// A single search gives up after this many nodes, the goal is treated as unreachable
+const MAX_SEARCH_NODES: u32 = 1500;
+// All the searches of one frame share this many nodes, the rest are retried on the next frame
+const NODE_BUDGET_PER_FRAME: u32 = 3000;
+// Found paths (and unreachable goals) are reused for this many frames
+const CACHE_FRAMES: u32 = 30;
+
+/// How high (in tiles) and how far the walker can jump
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct JumpReach {
+    pub height: i32,
+    pub distance: i32,
+}
+
+impl JumpReach {
+    pub const WALKER: JumpReach = JumpReach {
+        height: JUMP_HEIGHT,
+        distance: JUMP_DISTANCE,
+    };
+}
+
+/// How a walker gets to a tile from the previous tile of the path
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PathEdge {
+    Walk,
+    Jump,
+    Drop,
+    Climb,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PathStep {
+    pub x: i32,
+    pub y: i32,
+    pub edge: PathEdge,
+}
+
+// (start tile, goal tile)
+type PathKey = ((i32, i32), (i32, i32));
+
+#[derive(Clone)]
+struct CachedPath {
+    path: Option<Vec<PathStep>>,
+    frame: u32,
+}
+
+// A* over the tile grid for things that walk, jump and fall like the player does. A tile in
+// the path is the tile the walker occupies (its feet), so it is always empty with something to
+// stand on below it or a ladder in it.
+#[derive(Clone)]
+pub struct Pathfinder {
+    cache: HashMap<PathKey, CachedPath>,
+    frame: u32,
+    nodes_left: u32,
+}
+
+impl Default for Pathfinder {
+    fn default() -> Self {
+        Pathfinder {
+            cache: HashMap::new(),
+            frame: 0,
+            nodes_left: NODE_BUDGET_PER_FRAME,
+        }
+    }
+}
+
+impl Pathfinder {
+    /// Refills the node budget and forgets the stale paths, call once per game update
+    pub fn begin_frame(&mut self) {
+        self.frame += 1;
+        self.nodes_left = NODE_BUDGET_PER_FRAME;
+        let frame = self.frame;
+        self.cache
+            .retain(|_, cached| frame - cached.frame < CACHE_FRAMES);
+    }
+
+    /// The tile that the feet of the bounding box are in
+    pub fn tile_of(bb: &BoundingBox) -> (i32, i32) {
+        (
+            bb.center().x.floor() as i32,
+            (bb.y + bb.h - EPS).floor() as i32,
+        )
+    }
+
+    /// Steps from start to goal, not including the start. None if the goal cannot be reached or
+    /// if the frame is out of budget (the search will be retried on a later frame).
+    pub fn find_path(
+        &mut self,
+        map: &dyn MapLike,
+        start: (i32, i32),
+        goal: (i32, i32),
+    ) -> Option<&[PathStep]> {
+        let key = (start, goal);
+        if !self.cache.contains_key(&key) {
+            if self.nodes_left == 0 {
+                return None;
+            }
+            let path = self.search(map, start, goal).ok()?;
+            self.cache.insert(
+                key,
+                CachedPath {
+                    path,
+                    frame: self.frame,
+                },
+            );
+        }
+        self.cache[&key].path.as_deref()
+    }
+
+    // Err if the frame budget ran out before the search was done
+    fn search(
+        &mut self,
+        map: &dyn MapLike,
+        start: (i32, i32),
+        goal: (i32, i32),
+    ) -> Result<Option<Vec<PathStep>>, ()> {
+        // Things in the air path from where they are going to land
+        let (Some(start), Some(goal)) = (settle(map, start), settle(map, goal)) else {
+            return Ok(None);
+        };
+
+        let heuristic =
+            |(x, y): (i32, i32)| (x - goal.0).unsigned_abs() + (y - goal.1).unsigned_abs();
+
+        let mut open = BinaryHeap::new();
+        let mut came_from: HashMap<(i32, i32), ((i32, i32), PathEdge)> = HashMap::new();
+        let mut best_cost: HashMap<(i32, i32), u32> = HashMap::new();
+        best_cost.insert(start, 0);
+        open.push(Reverse((heuristic(start), 0, start)));
+
+        let mut searched_nodes = 0;
+        while let Some(Reverse((_, cost, node))) = open.pop() {
+            if node == goal {
+                let mut path = vec![];
+                let mut current = goal;
+                while let Some(&(previous, edge)) = came_from.get(&current) {
+                    path.push(PathStep {
+                        x: current.0,
+                        y: current.1,
+                        edge,
+                    });
+                    current = previous;
+                }
+                path.reverse();
+                return Ok(Some(path));
+            }
+            if cost > best_cost[&node] {
+                continue;
+            }
+
+            if self.nodes_left == 0 {
+                return Err(());
+            }
+            self.nodes_left -= 1;
+            searched_nodes += 1;
+            if searched_nodes > MAX_SEARCH_NODES {
+                return Ok(None);
+            }
+
+            for (next, edge, edge_cost) in neighbours(map, node, JumpReach::WALKER) {
+                let next_cost = cost + edge_cost;
+                if best_cost.get(&next).is_none_or(|&best| next_cost < best) {
+                    best_cost.insert(next, next_cost);
+                    came_from.insert(next, (node, edge));
+                    open.push(Reverse((next_cost + heuristic(next), next_cost, next)));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Every tile a walker can get to from the start tile, the start included if it can stand there
+pub fn reachable_tiles(
+    map: &dyn MapLike,
+    start: (i32, i32),
+    reach: JumpReach,
+) -> HashSet<(i32, i32)> {
+    let mut reached = HashSet::new();
+    let Some(start) = settle(map, start) else {
+        return reached;
+    };
+    reached.insert(start);
+    let mut open = vec![start];
+    while let Some(node) = open.pop() {
+        for (next, _, _) in neighbours(map, node, reach) {
+            if reached.insert(next) {
+                open.push(next);
+            }
+        }
+    }
+    reached
+}
+
+fn passable(map: &dyn MapLike, x: i32, y: i32) -> bool {
+    !map.is_solid_at_tile(x, y)
+}
+
+// Walkers do not want to stand in spikes
+fn standable(map: &dyn MapLike, x: i32, y: i32) -> bool {
+    passable(map, x, y)
+        && !map.is_spikes_at(x, y)
+        && (map.is_ground_at(x, y + 1) || map.is_climbable_at(x, y))
+}
+
+// Is the tile a slope that goes up when walking in the direction
+fn slope_rises_towards(map: &dyn MapLike, x: i32, y: i32, dir: i32) -> bool {
+    match map.get_at(x, y).0 {
+        BaseTile::SlopeUpRight => dir == 1,
+        BaseTile::SlopeUpLeft => dir == -1,
+        _ => false,
+    }
+}
+
+// Falls straight down from the tile until there is something to stand on
+fn settle(map: &dyn MapLike, (x, y): (i32, i32)) -> Option<(i32, i32)> {
+    (0..=MAX_DROP)
+        .map(|dy| (x, y + dy))
+        .take_while(|&(x, y)| passable(map, x, y))
+        .find(|&(x, y)| standable(map, x, y))
+}
+
+// Tiles reachable from a standable tile with a single move, with the cost of the move. Every
+// cost is at least the manhattan distance of the move so that the heuristic stays admissible.
+fn neighbours(
+    map: &dyn MapLike,
+    (x, y): (i32, i32),
+    reach: JumpReach,
+) -> Vec<((i32, i32), PathEdge, u32)> {
+    let mut result = vec![];
+
+    for dir in [-1, 1] {
+        // Walking off to the side, or off a ledge
+        if passable(map, x + dir, y) {
+            if standable(map, x + dir, y) {
+                result.push(((x + dir, y), PathEdge::Walk, 1));
+            } else if slope_rises_towards(map, x, y + 1, -dir) && standable(map, x + dir, y + 1) {
+                // Walking down off the slope under the feet
+                result.push(((x + dir, y + 1), PathEdge::Walk, 2));
+            } else if let Some((_, landing_y)) = settle(map, (x + dir, y)) {
+                result.push((
+                    (x + dir, landing_y),
+                    PathEdge::Drop,
+                    1 + (landing_y - y) as u32,
+                ));
+            }
+        }
+
+        // Walking up from the top of a slope on to the tile above the next one
+        if slope_rises_towards(map, x, y, dir)
+            && !passable(map, x + dir, y)
+            && standable(map, x + dir, y - 1)
+        {
+            result.push(((x + dir, y - 1), PathEdge::Walk, 2));
+        }
+
+        // Jumping straight up, then sideways and falling down on something
+        for height in 1..=reach.height {
+            if !passable(map, x, y - height) {
+                break;
+            }
+            for distance in 1..=reach.distance {
+                let apex_x = x + dir * distance;
+                if !passable(map, apex_x, y - height) {
+                    break;
+                }
+                let Some(landing) = settle(map, (apex_x, y - height)) else {
+                    continue;
+                };
+                // Already covered by just walking there
+                if landing == (x + dir, y) {
+                    continue;
+                }
+                let fall = (landing.1 - (y - height)) as u32;
+                let cost = distance as u32 + height as u32 + fall + 1;
+                result.push((landing, PathEdge::Jump, cost));
+            }
+        }
+    }
+
+    // Dropping through a platform
+    if map.is_platform_at(x, y + 1)
+        && !map.is_solid_at_tile(x, y + 1)
+        && let Some(landing) = settle(map, (x, y + 1))
+    {
+        result.push((landing, PathEdge::Drop, (landing.1 - y) as u32));
+    }
+
+    // Ladders and vines
+    if map.is_climbable_at(x, y) && standable(map, x, y - 1) {
+        result.push(((x, y - 1), PathEdge::Climb, 1));
+    }
+    if map.is_climbable_at(x, y + 1) && passable(map, x, y + 1) {
+        result.push(((x, y + 1), PathEdge::Climb, 1));
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::map_like::Room;
+
+    // 12x8 room with walls around it, the floor is at y = 6
+    fn room() -> Room {
+        Room::new_boxed(0, 0, 12, 8)
+    }
+
+    #[test]
+    fn walks_along_the_floor() {
+        let room = room();
+        let mut pathfinder = Pathfinder::default();
+        let path = pathfinder.find_path(&room, (1, 6), (4, 6)).unwrap();
+        assert_eq!(
+            path.iter().map(|step| (step.x, step.y)).collect::<Vec<_>>(),
+            vec![(2, 6), (3, 6), (4, 6)]
+        );
+        assert!(path.iter().all(|step| step.edge == PathEdge::Walk));
+    }
+
+    #[test]
+    fn jumps_up_on_ledges_and_drops_down() {
+        let mut room = room();
+        // A two tall pillar in the middle of the room
+        room.set_base_absolute(6, 6, BaseTile::Stone);
+        room.set_base_absolute(6, 5, BaseTile::Stone);
+
+        let mut pathfinder = Pathfinder::default();
+        let path = pathfinder.find_path(&room, (4, 6), (6, 4)).unwrap();
+        assert_eq!(path.last().unwrap().edge, PathEdge::Jump);
+
+        let path = pathfinder
+            .find_path(&room, (6, 4), (8, 6))
+            .unwrap()
+            .to_vec();
+        assert_eq!(
+            path.last(),
+            Some(&PathStep {
+                x: 8,
+                y: 6,
+                edge: PathEdge::Walk
+            })
+        );
+        assert!(path.iter().any(|step| step.edge != PathEdge::Walk));
+
+        // Too high to jump up on
+        room.set_base_absolute(6, 4, BaseTile::Stone);
+        room.set_base_absolute(6, 3, BaseTile::Stone);
+        room.set_base_absolute(6, 2, BaseTile::Stone);
+        room.set_base_absolute(6, 1, BaseTile::Stone);
+        let mut pathfinder = Pathfinder::default();
+        assert!(pathfinder.find_path(&room, (4, 6), (8, 6)).is_none());
+    }
+
+    #[test]
+    fn walks_up_and_down_slopes() {
+        let mut room = room();
+        room.set_base_absolute(5, 6, BaseTile::SlopeUpRight);
+        for x in 6..9 {
+            room.set_base_absolute(x, 6, BaseTile::Stone);
+        }
+        room.set_base_absolute(9, 6, BaseTile::SlopeUpLeft);
+
+        let mut pathfinder = Pathfinder::default();
+        let path = pathfinder.find_path(&room, (2, 6), (7, 5)).unwrap();
+        assert!(path.iter().all(|step| step.edge == PathEdge::Walk));
+        let path = pathfinder.find_path(&room, (7, 5), (10, 6)).unwrap();
+        assert!(path.iter().all(|step| step.edge == PathEdge::Walk));
+    }
+
+    #[test]
+    fn out_of_budget_searches_are_retried() {
+        let room = room();
+        let mut pathfinder = Pathfinder {
+            nodes_left: 1,
+            ..Default::default()
+        };
+        assert!(pathfinder.find_path(&room, (1, 6), (10, 6)).is_none());
+        pathfinder.begin_frame();
+        assert!(pathfinder.find_path(&room, (1, 6), (10, 6)).is_some());
+    }
+}
@@ -1,8 +1,18 @@
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AnimationMode {
+    // start..=end, start..=end, ...
+    Loop,
+    // start..=end once and then stays at the last frame
+    OnceThenHold,
+    // start..=end..=start, ... without repeating the turning frames
+    PingPong,
+}
+
 pub struct AnimationConfigResult {
     start: u32,
     end: u32,
     dur: u32,
-    loops: bool,
+    mode: AnimationMode,
     reverse: bool,
 }
 
@@ -12,7 +22,7 @@ impl AnimationConfigResult {
             start,
             end,
             dur,
-            loops: true,
+            mode: AnimationMode::Loop,
             reverse: false,
         }
     }
@@ -22,7 +32,7 @@ impl AnimationConfigResult {
             start,
             end,
             dur,
-            loops: false,
+            mode: AnimationMode::OnceThenHold,
             reverse: false,
         }
     }
@@ -32,16 +42,31 @@ impl AnimationConfigResult {
             start,
             end,
             dur,
-            loops: false,
+            mode: AnimationMode::OnceThenHold,
             reverse: true,
         }
     }
+
+    pub fn new_ping_pong(start: u32, end: u32, dur: u32) -> Self {
+        Self {
+            start,
+            end,
+            dur,
+            mode: AnimationMode::PingPong,
+            reverse: false,
+        }
+    }
+
+    fn total_frames(&self) -> u32 {
+        self.end - self.start + 1
+    }
 }
 
 pub trait AnimationConfig {
     fn get_config(&self) -> AnimationConfigResult;
 }
 
+#[derive(Clone)]
 pub struct AnimationHandler<T> {
     state: T,
     current_frame: u32,
@@ -70,20 +95,145 @@ impl<T: AnimationConfig + PartialEq> AnimationHandler<T> {
         self.current_frame += 1;
     }
 
+    /// True once a OnceThenHold animation has shown all of its frames. Looping animations
+    /// never finish.
+    pub fn finished(&self) -> bool {
+        let config = self.state.get_config();
+        match config.mode {
+            AnimationMode::OnceThenHold => self.current_frame >= config.total_frames() * config.dur,
+            AnimationMode::Loop | AnimationMode::PingPong => false,
+        }
+    }
+
+    /// True only on the first game frame after a OnceThenHold animation has shown all of its
+    /// frames
+    pub fn just_finished(&self) -> bool {
+        let config = self.state.get_config();
+        config.mode == AnimationMode::OnceThenHold
+            && self.current_frame == config.total_frames() * config.dur
+    }
+
+    /// True on the first game frame that the given atlas index is shown. Holding the last frame
+    /// of a finished animation does not count as entering it again.
+    pub fn entered_frame(&self, atlas_index: u32) -> bool {
+        let config = self.state.get_config();
+        self.current_frame.is_multiple_of(config.dur)
+            && !self.finished()
+            && self.get_atlas_index() == atlas_index
+    }
+
     pub fn get_atlas_index(&self) -> u32 {
         let config = self.state.get_config();
         let frame_index = self.current_frame / config.dur;
-        let total_frames = config.end - config.start + 1;
-        let non_reverse_frame = if !config.loops && frame_index >= total_frames {
-            config.start + total_frames - 1
-        } else {
-            config.start + frame_index % total_frames
+        let total_frames = config.total_frames();
+        let offset = match config.mode {
+            AnimationMode::Loop => frame_index % total_frames,
+            AnimationMode::OnceThenHold => frame_index.min(total_frames - 1),
+            AnimationMode::PingPong => {
+                if total_frames == 1 {
+                    0
+                } else {
+                    let period = 2 * (total_frames - 1);
+                    let i = frame_index % period;
+                    if i < total_frames { i } else { period - i }
+                }
+            }
         };
 
         if config.reverse {
-            total_frames - 1 - (non_reverse_frame - config.start) + config.start
+            config.end - offset
         } else {
-            non_reverse_frame
+            config.start + offset
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(PartialEq)]
+    enum TestState {
+        Looping,
+        Once,
+        ReverseOnce,
+        PingPong,
+    }
+
+    impl AnimationConfig for TestState {
+        fn get_config(&self) -> AnimationConfigResult {
+            match self {
+                TestState::Looping => AnimationConfigResult::new(2, 4, 2),
+                TestState::Once => AnimationConfigResult::new_no_loop(2, 4, 2),
+                TestState::ReverseOnce => AnimationConfigResult::new_reverse_no_loop(2, 4, 2),
+                TestState::PingPong => AnimationConfigResult::new_ping_pong(2, 4, 2),
+            }
+        }
+    }
+
+    fn indexes(state: TestState, frames: u32) -> Vec<u32> {
+        let mut handler = AnimationHandler::new(state);
+        let mut result = vec![];
+        for _ in 0..frames {
+            result.push(handler.get_atlas_index());
+            handler.increment_frame();
+        }
+        result
+    }
+
+    #[test]
+    fn loop_repeats_from_start() {
+        assert_eq!(indexes(TestState::Looping, 8), vec![2, 2, 3, 3, 4, 4, 2, 2]);
+    }
+
+    #[test]
+    fn once_holds_last_frame_and_finishes() {
+        assert_eq!(indexes(TestState::Once, 8), vec![2, 2, 3, 3, 4, 4, 4, 4]);
+        assert_eq!(
+            indexes(TestState::ReverseOnce, 8),
+            vec![4, 4, 3, 3, 2, 2, 2, 2]
+        );
+
+        let mut handler = AnimationHandler::new(TestState::Once);
+        for _ in 0..5 {
+            handler.increment_frame();
+        }
+        assert!(!handler.finished());
+        handler.increment_frame();
+        assert!(handler.finished());
+        assert!(handler.just_finished());
+        handler.increment_frame();
+        assert!(handler.finished());
+        assert!(!handler.just_finished());
+    }
+
+    #[test]
+    fn entered_frame_only_on_first_game_frame() {
+        let mut handler = AnimationHandler::new(TestState::Once);
+        let mut entered = vec![];
+        for _ in 0..10 {
+            entered.push(handler.entered_frame(4));
+            handler.increment_frame();
+        }
+        assert_eq!(
+            entered,
+            vec![
+                false, false, false, false, true, false, false, false, false, false
+            ]
+        );
+    }
+
+    #[test]
+    fn ping_pong_turns_around_without_repeating_ends() {
+        assert_eq!(
+            indexes(TestState::PingPong, 10),
+            vec![2, 2, 3, 3, 4, 4, 3, 3, 2, 2]
+        );
+
+        let mut handler = AnimationHandler::new(TestState::PingPong);
+        for _ in 0..100 {
+            handler.increment_frame();
         }
+        assert!(!handler.finished());
     }
 }
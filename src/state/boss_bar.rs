@@ -0,0 +1,114 @@
+use crate::camera::Camera;
+use crate::pixel_font;
+use crate::render::Renderer;
+use crate::state::enemies::common::BossInfo;
+
+const INTRO_FRAMES: u32 = 60;
+// How long the lag bar waits after damage before draining and how fast it drains
+const LAG_DELAY_FRAMES: u32 = 30;
+const LAG_DRAIN_PER_FRAME: f32 = 0.005;
+
+// Boss health bar at the bottom of the screen. Slides in when a boss is first shown and the
+// damage taken is shown as a lighter part that drains after a moment.
+#[derive(Default, Clone)]
+pub struct BossBar {
+    name: &'static str,
+    phases: u32,
+    // The segment of the bar the health is in, counting from the full end
+    phase: u32,
+    active: bool,
+    ratio: f32,
+    lag_ratio: f32,
+    lag_delay: u32,
+    intro_frames: u32,
+}
+
+// The segments of the bar are the phases of the boss, a new one starts every time the health
+// drops past one of the lines between them
+fn phase_at(ratio: f32, phases: u32) -> u32 {
+    (((1.0 - ratio) * phases as f32 + 0.001) as u32).min(phases - 1)
+}
+
+impl BossBar {
+    /// Returns true when the boss moved on to its next phase on this update
+    pub fn update(&mut self, boss: Option<(BossInfo, f32)>) -> bool {
+        let Some((info, ratio)) = boss else {
+            self.active = false;
+            return false;
+        };
+
+        if !self.active || self.name != info.name {
+            let phases = info.phases.max(1);
+            *self = BossBar {
+                name: info.name,
+                phases,
+                phase: phase_at(ratio, phases),
+                active: true,
+                ratio,
+                lag_ratio: ratio,
+                lag_delay: 0,
+                intro_frames: 0,
+            };
+        }
+
+        if ratio < self.ratio {
+            self.lag_delay = LAG_DELAY_FRAMES;
+        }
+        self.ratio = ratio;
+        self.intro_frames = (self.intro_frames + 1).min(INTRO_FRAMES);
+        let phase = phase_at(ratio, self.phases);
+        let next_phase = phase > self.phase;
+        self.phase = phase;
+
+        if self.lag_delay > 0 {
+            self.lag_delay -= 1;
+        } else {
+            self.lag_ratio = (self.lag_ratio - LAG_DRAIN_PER_FRAME).max(self.ratio);
+        }
+        next_phase
+    }
+
+    pub fn draw(&self, renderer: &mut Renderer, camera: &Camera) {
+        if !self.active {
+            return;
+        }
+
+        // Ease out the bar growing from the center
+        let t = self.intro_frames as f32 / INTRO_FRAMES as f32;
+        let intro = 1.0 - (1.0 - t) * (1.0 - t);
+
+        let full_width = (camera.screen_w * 0.6).min(600.0);
+        let width = full_width * intro;
+        let height = 14.0;
+        let x = (camera.screen_w - width) / 2.0;
+        let y = camera.screen_h - height - 24.0;
+        let border = 2.0;
+
+        renderer.draw_rect_hud(
+            x - border,
+            y - border,
+            width + border * 2.0,
+            height + border * 2.0,
+            [0.1, 0.1, 0.1, 1.0],
+        );
+        renderer.draw_rect_hud(x, y, width * self.lag_ratio, height, [0.85, 0.8, 0.7, 1.0]);
+        renderer.draw_rect_hud(x, y, width * self.ratio, height, [0.65, 0.11, 0.11, 1.0]);
+        for phase in 1..self.phases {
+            let phase_x = x + width * phase as f32 / self.phases as f32;
+            renderer.draw_rect_hud(phase_x - 1.0, y, 2.0, height, [0.1, 0.1, 0.1, 1.0]);
+        }
+
+        // The name appears once the bar is fully open
+        if self.intro_frames == INTRO_FRAMES {
+            let pixel_size = 3.0;
+            let text_width = pixel_font::text_width(self.name) as f32 * pixel_size;
+            renderer.draw_text_hud(
+                self.name,
+                (camera.screen_w - text_width) / 2.0,
+                y - border - 6.0 - pixel_font::GLYPH_HEIGHT as f32 * pixel_size,
+                pixel_size,
+                [0.95, 0.92, 0.85, 1.0],
+            );
+        }
+    }
+}
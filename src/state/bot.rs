@@ -0,0 +1,196 @@
+use crate::state::ai::{PathEdge, Pathfinder};
+use crate::state::common::{BoundingBox, Pos};
+use crate::state::game_rng::GameRng;
+use crate::state::game_state::{Game, InputState};
+use crate::state::map_like::MapLike;
+use crate::telemetry::log;
+use rand::seq::IndexedRandom;
+
+// Coins and enemies further away than this are not gone after
+const SIGHT: f32 = 10.0;
+// Enemies this close (x, y) are swung at
+const SWING_REACH: (f32, f32) = (1.6, 1.2);
+const SWING_COOLDOWN_FRAMES: u32 = 20;
+// The jump key is held this long for a full height jump
+const JUMP_HOLD_FRAMES: u32 = 12;
+// A door to wander to is given up after this long
+const WANDER_FRAMES: u32 = 600;
+// Standing on the same tile this long means the bot is stuck, it jumps and picks a new door
+const STUCK_FRAMES: u32 = 90;
+// How often the bot prints how much there is in the game, a count that keeps growing is a leak
+const REPORT_FRAMES: u64 = 60 * 60;
+
+// The tile of the nearest one in sight
+fn nearest_tile<'a>(center: Pos, bbs: impl Iterator<Item = &'a BoundingBox>) -> Option<(i32, i32)> {
+    bbs.map(|bb| {
+        let other = bb.center();
+        ((other.x - center.x).hypot(other.y - center.y), bb)
+    })
+    .filter(|(distance, _)| *distance < SIGHT)
+    .min_by(|a, b| a.0.total_cmp(&b.0))
+    .map(|(_, bb)| Pathfinder::tile_of(bb))
+}
+
+// Plays the game for soak tests by giving the input instead of the keyboard. It collects the
+// coins and fights the enemies it sees, otherwise it wanders from door to door.
+pub struct Bot {
+    pathfinder: Pathfinder,
+    rng: GameRng,
+    wander_target: Option<(i32, i32)>,
+    wander_frames: u32,
+    jump_frames: u32,
+    swing_cooldown: u32,
+    last_tile: (i32, i32),
+    stuck_frames: u32,
+    frames: u64,
+}
+
+impl Bot {
+    pub fn new() -> Self {
+        Bot {
+            pathfinder: Pathfinder::default(),
+            rng: GameRng::new(rand::random()),
+            wander_target: None,
+            wander_frames: 0,
+            jump_frames: 0,
+            swing_cooldown: 0,
+            last_tile: (0, 0),
+            stuck_frames: 0,
+            frames: 0,
+        }
+    }
+
+    /// How many updates the bot has played
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    fn start_jump(&mut self) {
+        if self.jump_frames == 0 {
+            self.jump_frames = JUMP_HOLD_FRAMES;
+        }
+    }
+
+    // The nearest coin, then the nearest enemy, then a random door
+    fn pick_goal(&mut self, game: &Game) -> (i32, i32) {
+        let center = game.player.bb.center();
+        let coins = game.items.iter().filter(|item| item.is_coin());
+        if let Some(goal) = nearest_tile(center, coins.map(|item| item.bb()))
+            .or_else(|| nearest_tile(center, game.enemies.iter().map(|enemy| enemy.bb())))
+        {
+            return goal;
+        }
+
+        self.wander_frames += 1;
+        if self.wander_target.is_none()
+            || self.wander_target == Some(Pathfinder::tile_of(&game.player.bb))
+            || self.wander_frames > WANDER_FRAMES
+        {
+            self.wander_target = game
+                .map
+                .doors
+                .choose(&mut self.rng)
+                .map(|door| (door.x, door.y));
+            self.wander_frames = 0;
+        }
+        self.wander_target
+            .unwrap_or(Pathfinder::tile_of(&game.player.bb))
+    }
+
+    /// The input for the next update of the game
+    pub fn input(&mut self, game: &Game) -> InputState {
+        self.frames += 1;
+        self.pathfinder.begin_frame();
+        if self.frames.is_multiple_of(REPORT_FRAMES) {
+            log!(
+                "Bot has played {} updates: {} enemies, {} items, {} particles",
+                self.frames,
+                game.enemies.len(),
+                game.items.len(),
+                game.particles.len()
+            );
+        }
+
+        let mut input = InputState::default();
+        // Game over waits for a jump
+        if game.player.is_dead() {
+            input.jump_pressed = true;
+            return input;
+        }
+
+        let center = game.player.bb.center();
+        let tile = Pathfinder::tile_of(&game.player.bb);
+        if tile == self.last_tile {
+            self.stuck_frames += 1;
+        } else {
+            self.last_tile = tile;
+            self.stuck_frames = 0;
+        }
+        if self.stuck_frames > STUCK_FRAMES {
+            self.stuck_frames = 0;
+            self.wander_target = None;
+            self.start_jump();
+        }
+
+        self.swing_cooldown = self.swing_cooldown.saturating_sub(1);
+        let enemy_in_reach = game.enemies.iter().map(|e| e.bb().center()).find(|enemy| {
+            (enemy.x - center.x).abs() < SWING_REACH.0 && (enemy.y - center.y).abs() < SWING_REACH.1
+        });
+
+        if let Some(enemy) = enemy_in_reach {
+            // Turns to face the enemy
+            input.left = enemy.x < center.x;
+            input.right = !input.left;
+            if self.swing_cooldown == 0 {
+                input.swing_pressed = true;
+                self.swing_cooldown = SWING_COOLDOWN_FRAMES;
+            }
+            input.swing_held = true;
+        } else {
+            let goal = self.pick_goal(game);
+            let step = self
+                .pathfinder
+                .find_path(&game.map, tile, goal)
+                .and_then(|path| path.first().copied());
+            match step {
+                Some(step) => {
+                    input.left = step.x < tile.0;
+                    input.right = step.x > tile.0;
+                    match step.edge {
+                        PathEdge::Walk => {}
+                        PathEdge::Jump => self.start_jump(),
+                        PathEdge::Climb => {
+                            input.up = step.y < tile.1;
+                            input.down = step.y > tile.1;
+                        }
+                        // Straight down is through a platform
+                        PathEdge::Drop if step.x == tile.0 => {
+                            input.down = true;
+                            input.jump_pressed = true;
+                        }
+                        PathEdge::Drop => {}
+                    }
+                }
+                // Straight at the goal, jumping over walls and gaps on the way
+                None => {
+                    let dir = (goal.0 - tile.0).signum();
+                    input.left = dir < 0;
+                    input.right = dir > 0;
+                    if dir != 0
+                        && (game.map.is_solid_at_tile(tile.0 + dir, tile.1)
+                            || !game.map.is_ground_at(tile.0 + dir, tile.1 + 1))
+                    {
+                        self.start_jump();
+                    }
+                }
+            }
+        }
+
+        if self.jump_frames > 0 && !input.down {
+            input.jump_pressed = self.jump_frames == JUMP_HOLD_FRAMES;
+            input.jump_held = true;
+            self.jump_frames -= 1;
+        }
+        input
+    }
+}
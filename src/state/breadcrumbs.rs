@@ -0,0 +1,49 @@
+use crate::render::Renderer;
+use crate::state::common::Pos;
+use std::collections::VecDeque;
+
+// About 16 seconds of movement is remembered
+const CAPACITY: usize = 240;
+const RECORD_EVERY_FRAMES: u32 = 4;
+// Standing still does not push the older points out
+const MIN_DISTANCE: f32 = 0.2;
+const DOT_SIZE: f32 = 2.0 / 16.0;
+
+// Recent positions of the player in a ring buffer, drawn as dots that fade out with age
+#[derive(Default, Clone)]
+pub struct Breadcrumbs {
+    points: VecDeque<Pos>,
+    frame: u32,
+}
+
+impl Breadcrumbs {
+    pub fn record(&mut self, pos: Pos) {
+        self.frame += 1;
+        if !self.frame.is_multiple_of(RECORD_EVERY_FRAMES) {
+            return;
+        }
+        if let Some(last) = self.points.back()
+            && (last.x - pos.x).abs() < MIN_DISTANCE
+            && (last.y - pos.y).abs() < MIN_DISTANCE
+        {
+            return;
+        }
+        if self.points.len() == CAPACITY {
+            self.points.pop_front();
+        }
+        self.points.push_back(pos);
+    }
+
+    pub fn draw(&self, renderer: &mut Renderer) {
+        for (i, pos) in self.points.iter().enumerate() {
+            let alpha = (i + 1) as f32 / self.points.len() as f32;
+            renderer.draw_rect(
+                pos.x - DOT_SIZE / 2.0,
+                pos.y - DOT_SIZE / 2.0,
+                DOT_SIZE,
+                DOT_SIZE,
+                [1.0, 1.0, 1.0, 0.7 * alpha],
+            );
+        }
+    }
+}
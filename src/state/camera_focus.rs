@@ -0,0 +1,50 @@
+use crate::state::common::Pos;
+use std::collections::VecDeque;
+
+// Something other than the player for the camera to frame for a while, like a boss that arrived
+// or a door that opened
+#[derive(Clone, Copy)]
+pub struct FocusHint {
+    pub pos: Pos,
+    // How many tiles wide the view is while framing it
+    pub view_tiles: u32,
+    // How many game updates it is framed for
+    pub frames: u32,
+}
+
+// The hints are framed one after another. The camera eases over to each of them and back to the
+// usual view after the last one.
+#[derive(Clone, Default)]
+pub struct CameraFocus {
+    queue: VecDeque<FocusHint>,
+}
+
+impl CameraFocus {
+    /// Framed after the hints that are already queued
+    pub fn push(&mut self, hint: FocusHint) {
+        self.queue.push_back(hint);
+    }
+
+    /// Framed right away, the current hint continues after it
+    pub fn interrupt(&mut self, hint: FocusHint) {
+        self.queue.push_front(hint);
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
+
+    /// Call once per game update
+    pub fn update(&mut self) {
+        if let Some(hint) = self.queue.front_mut() {
+            hint.frames = hint.frames.saturating_sub(1);
+            if hint.frames == 0 {
+                self.queue.pop_front();
+            }
+        }
+    }
+
+    pub fn current(&self) -> Option<&FocusHint> {
+        self.queue.front()
+    }
+}
@@ -0,0 +1,126 @@
+use crate::render::Renderer;
+use crate::state::common::{BoundingBox, Pos};
+
+const FLAG_W: f32 = 0.5;
+const FLAG_H: f32 = 1.0;
+const POLE_W: f32 = 1.0 / 16.0;
+const CLOTH_W: f32 = 6.0 / 16.0;
+const CLOTH_H: f32 = 4.0 / 16.0;
+// How long the cloth takes to go up the pole once touched
+const RAISE_FRAMES: u32 = 20;
+// The column of light on the flag when the player respawns at it
+const RESPAWN_FRAMES: u32 = 40;
+
+const POLE_COLOR: [f32; 4] = [0.55, 0.5, 0.45, 1.0];
+const LOWERED_COLOR: [f32; 4] = [0.5, 0.3, 0.3, 1.0];
+const RAISED_COLOR: [f32; 4] = [0.95, 0.3, 0.25, 1.0];
+const RESPAWN_COLOR: [f32; 3] = [1.0, 0.95, 0.7];
+
+/// The flag placed at the spot, the spot is the middle of the flag
+pub fn flag_bb(pos: Pos) -> BoundingBox {
+    BoundingBox {
+        x: pos.x - FLAG_W / 2.0,
+        y: pos.y - FLAG_H / 2.0,
+        w: FLAG_W,
+        h: FLAG_H,
+        vx: 0.0,
+        vy: 0.0,
+    }
+}
+
+/// raised is from 0.0 for a flag at the foot of the pole to 1.0 for one at the top
+pub fn draw_flag(renderer: &mut Renderer, pos: Pos, raised: f32) {
+    let bb = flag_bb(pos);
+    renderer.draw_rect(bb.x, bb.y, POLE_W, bb.h, POLE_COLOR);
+    let cloth_y = bb.y + (bb.h - CLOTH_H) * (1.0 - raised);
+    let color = if raised > 0.0 {
+        RAISED_COLOR
+    } else {
+        LOWERED_COLOR
+    };
+    renderer.draw_rect(bb.x + POLE_W, cloth_y, CLOTH_W, CLOTH_H, color);
+}
+
+#[derive(Clone)]
+struct Flag {
+    pos: Pos,
+    // Counts up to RAISE_FRAMES after the flag is touched
+    raise_frames: u32,
+}
+
+// Checkpoint flags placed in the rooms. Touching a flag makes it the spot the player comes back
+// to after dying, until another flag is touched.
+#[derive(Clone, Default)]
+pub struct Checkpoints {
+    flags: Vec<Flag>,
+    active: Option<usize>,
+    respawn_frames: u32,
+}
+
+impl Checkpoints {
+    pub fn new(positions: &[Pos]) -> Self {
+        Checkpoints {
+            flags: positions
+                .iter()
+                .map(|&pos| Flag {
+                    pos,
+                    raise_frames: 0,
+                })
+                .collect(),
+            active: None,
+            respawn_frames: 0,
+        }
+    }
+
+    /// Returns true when the player touched a flag that was not the active one
+    pub fn update(&mut self, player_bb: &BoundingBox) -> bool {
+        self.respawn_frames = self.respawn_frames.saturating_sub(1);
+        for (index, flag) in self.flags.iter_mut().enumerate() {
+            if flag.raise_frames > 0 {
+                flag.raise_frames = (flag.raise_frames + 1).min(RAISE_FRAMES);
+            }
+            if self.active != Some(index) && player_bb.overlaps(&flag_bb(flag.pos)) {
+                self.active = Some(index);
+                flag.raise_frames = flag.raise_frames.max(1);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Where the feet of the player go when respawning, the foot of the active flag
+    pub fn respawn_pos(&self) -> Option<Pos> {
+        let flag = &self.flags[self.active?];
+        let bb = flag_bb(flag.pos);
+        Some(Pos::new(flag.pos.x, bb.y + bb.h))
+    }
+
+    pub fn start_respawn_animation(&mut self) {
+        self.respawn_frames = RESPAWN_FRAMES;
+    }
+
+    pub fn draw(&self, renderer: &mut Renderer) {
+        for flag in &self.flags {
+            draw_flag(
+                renderer,
+                flag.pos,
+                flag.raise_frames as f32 / RAISE_FRAMES as f32,
+            );
+        }
+        if self.respawn_frames > 0
+            && let Some(flag) = self.active.map(|index| &self.flags[index])
+        {
+            let fade = self.respawn_frames as f32 / RESPAWN_FRAMES as f32;
+            let width = FLAG_W * (2.0 - fade);
+            let [r, g, b] = RESPAWN_COLOR;
+            let bb = flag_bb(flag.pos);
+            renderer.draw_rect(
+                flag.pos.x - width / 2.0,
+                bb.y - FLAG_H,
+                width,
+                FLAG_H * 2.0,
+                [r, g, b, fade * 0.6],
+            );
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use super::common::BoundingBox;
 use super::game_map::MapLike;
-use crate::physics::integrate_kinematic;
+use crate::physics::integrate_kinematic_with_platforms;
 
 pub struct Coin {
     pub bb: BoundingBox,
@@ -22,7 +22,7 @@ impl Coin {
     }
 
     pub fn update(&mut self, map: &dyn MapLike) {
-        let res = integrate_kinematic(map, &self.bb, true);
+        let res = integrate_kinematic_with_platforms(map, &self.bb, true);
         self.bb = res.new_bb;
     }
 
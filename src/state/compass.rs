@@ -0,0 +1,109 @@
+use crate::camera::Camera;
+use crate::render::Renderer;
+use crate::state::common::Pos;
+
+// Pings are forgotten after this long or once the player gets close to them
+const PING_FRAMES: u32 = 60 * 20;
+const PING_REACHED_DISTANCE: f32 = 1.5;
+// How far from the screen edges the arrow is, in screen pixels
+const EDGE_MARGIN: f32 = 28.0;
+
+const PING_COLOR: [f32; 4] = [0.35, 0.85, 0.95, 1.0];
+const OBJECTIVE_COLOR: [f32; 4] = [0.98, 0.8, 0.2, 1.0];
+const UNEXPLORED_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 0.8];
+
+#[derive(Clone, Copy)]
+pub enum CompassTarget {
+    Ping(Pos),
+    Objective(Pos),
+    UnexploredDoor(Pos),
+}
+
+impl CompassTarget {
+    fn pos(&self) -> Pos {
+        match self {
+            CompassTarget::Ping(pos)
+            | CompassTarget::Objective(pos)
+            | CompassTarget::UnexploredDoor(pos) => *pos,
+        }
+    }
+
+    fn color(&self) -> [f32; 4] {
+        match self {
+            CompassTarget::Ping(_) => PING_COLOR,
+            CompassTarget::Objective(_) => OBJECTIVE_COLOR,
+            CompassTarget::UnexploredDoor(_) => UNEXPLORED_COLOR,
+        }
+    }
+}
+
+/// A chevron out of squares with the tip at the given screen position
+pub fn draw_arrow(renderer: &mut Renderer, tip: (f32, f32), dir: (f32, f32), color: [f32; 4]) {
+    let size = 4.0;
+    for i in 0..4 {
+        let back = i as f32 * size;
+        for side in [-1.0, 1.0] {
+            let x = tip.0 - dir.0 * back + side * -dir.1 * back;
+            let y = tip.1 - dir.1 * back + side * dir.0 * back;
+            renderer.draw_rect_hud(x - size / 2.0, y - size / 2.0, size, size, color);
+        }
+    }
+}
+
+// Arrow on the edge of the screen towards a target that is off the screen. The game decides
+// the target, a ping goes before the objective and the objective before exploring.
+#[derive(Default, Clone)]
+pub struct Compass {
+    ping: Option<Pos>,
+    ping_frames: u32,
+}
+
+impl Compass {
+    pub fn ping(&mut self, pos: Pos) {
+        self.ping = Some(pos);
+        self.ping_frames = PING_FRAMES;
+    }
+
+    pub fn update(&mut self, player_center: Pos) {
+        self.ping_frames = self.ping_frames.saturating_sub(1);
+        if let Some(ping) = self.ping {
+            let distance =
+                ((ping.x - player_center.x).powi(2) + (ping.y - player_center.y).powi(2)).sqrt();
+            if self.ping_frames == 0 || distance < PING_REACHED_DISTANCE {
+                self.ping = None;
+            }
+        }
+    }
+
+    pub fn get_ping(&self) -> Option<Pos> {
+        self.ping
+    }
+
+    pub fn draw(&self, renderer: &mut Renderer, camera: &Camera, target: CompassTarget) {
+        let pos = target.pos();
+        let (x, y) = camera.tile_to_screen(pos.x, pos.y);
+
+        // Pings are marked where they are when they are on the screen
+        let half_w = camera.screen_w / 2.0 - EDGE_MARGIN;
+        let half_h = camera.screen_h / 2.0 - EDGE_MARGIN;
+        let dx = x - camera.screen_w / 2.0;
+        let dy = y - camera.screen_h / 2.0;
+        if dx.abs() <= half_w && dy.abs() <= half_h {
+            if let CompassTarget::Ping(_) = target {
+                let size = 4.0 * camera.zoom.max(1.0);
+                renderer.draw_rect_hud(x - size / 2.0, y - size / 2.0, size, size, PING_COLOR);
+            }
+            return;
+        }
+
+        // Pushed in from the target along the line to the center of the screen until it is on
+        // the screen
+        let scale = (half_w / dx.abs()).min(half_h / dy.abs());
+        let length = (dx * dx + dy * dy).sqrt();
+        let tip = (
+            camera.screen_w / 2.0 + dx * scale,
+            camera.screen_h / 2.0 + dy * scale,
+        );
+        draw_arrow(renderer, tip, (dx / length, dy / length), target.color());
+    }
+}
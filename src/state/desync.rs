@@ -0,0 +1,89 @@
+use crate::sound_handler::SoundHandler;
+use crate::state::game_state::{Game, GameState, InputState};
+use crate::telemetry::log;
+
+// Runs a second game next to the real one, started from the same seed and given the same
+// inputs. The states are compared after every update, the first update where they differ means
+// that something in the game is not deterministic (thread rng, hash map order and such).
+pub struct DesyncChecker {
+    shadow: Box<Game>,
+    frame: u32,
+    desync_frame: Option<u32>,
+}
+
+impl DesyncChecker {
+    /// The real game has to be started from the same seed, see Game::new_with_seed
+    pub fn new(seed: u64) -> Self {
+        DesyncChecker {
+            shadow: Box::new(Game::new_with_seed(seed)),
+            frame: 0,
+            desync_frame: None,
+        }
+    }
+
+    /// Call after every update of the real game, with the input it was updated with
+    pub fn update(&mut self, game: &Game, input: &InputState, sound_handler: &SoundHandler) {
+        if self.desync_frame.is_some() {
+            return;
+        }
+        sound_handler.muted(|| self.shadow.update(input, sound_handler));
+        self.frame += 1;
+        if game.state_hash() != self.shadow.state_hash() {
+            log!("Desync at update {}", self.frame);
+            self.desync_frame = Some(self.frame);
+        }
+    }
+
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// The first update where the games were different
+    pub fn desync_frame(&self) -> Option<u32> {
+        self.desync_frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_makes_the_same_game() {
+        assert_eq!(
+            Game::new_with_seed(7).state_hash(),
+            Game::new_with_seed(7).state_hash()
+        );
+        assert_ne!(
+            Game::new_with_seed(7).state_hash(),
+            Game::new_with_seed(8).state_hash()
+        );
+    }
+
+    #[test]
+    fn game_over_starts_the_same_next_game() {
+        let sound_handler = SoundHandler::silent();
+        let mut games = [Game::new_with_seed(7), Game::new_with_seed(7)];
+        for game in &mut games {
+            game.player.lives = 0;
+            let max_health = game.player.health.max;
+            game.player.got_hit(max_health);
+        }
+        let input = InputState::default();
+        for _ in 0..1000 {
+            for game in &mut games {
+                game.update(&input, &sound_handler);
+            }
+            assert_eq!(games[0].state_hash(), games[1].state_hash());
+            if games[0].next_game_seed().is_some() {
+                break;
+            }
+        }
+        let seed = games[0].next_game_seed().expect("the game should be over");
+        assert_eq!(games[1].next_game_seed(), Some(seed));
+        assert_eq!(
+            Game::new_with_seed(seed).state_hash(),
+            Game::new_with_seed(seed).state_hash()
+        );
+    }
+}
@@ -0,0 +1,328 @@
+use crate::physics::{has_line_of_sight, integrate_kinematic_with_platforms};
+use crate::render::Renderer;
+use crate::state::animation_handler::{AnimationConfig, AnimationConfigResult, AnimationHandler};
+use crate::state::common::{BoundingBox, Dir, Health, Pos};
+use crate::state::enemies::Enemy;
+use crate::state::enemies::common::{
+    Alert, EnemyHit, EnemyHitResult, EnemyUpdateContext, EnemyUpdateResult, MAX_SPOT_RADIUS,
+};
+use crate::state::item::{Item, ItemType};
+use crate::state::map_like::{GameMap, MapLike};
+use crate::tuning::tuning;
+
+const WALK_SPEED: f32 = 0.01;
+const IMMUNITY_FRAMES: u32 = 30;
+// Horizontal speed of the arrows, the vertical speed is whatever gets them to the target
+const ARROW_SPEED: f32 = 0.2;
+// Time between letting an arrow go and starting to draw the next one
+const RELOAD_FRAMES: u32 = 45;
+
+const BODY_COLOR: [f32; 4] = [0.3, 0.5, 0.3, 1.0];
+const HEAD_COLOR: [f32; 4] = [0.85, 0.7, 0.55, 1.0];
+const BOW_COLOR: [f32; 4] = [0.55, 0.35, 0.2, 1.0];
+const STRING_COLOR: [f32; 4] = [0.9, 0.9, 0.85, 1.0];
+
+#[derive(Clone, PartialEq)]
+enum ArcherAnimationState {
+    Walking,
+    // Draws the bow, the arrow goes when the last frame is done
+    Aiming,
+    Shooting,
+}
+
+impl AnimationConfig for ArcherAnimationState {
+    fn get_config(&self) -> AnimationConfigResult {
+        match self {
+            ArcherAnimationState::Walking => AnimationConfigResult::new(0, 1, 12),
+            ArcherAnimationState::Aiming => AnimationConfigResult::new_no_loop(2, 4, 12),
+            ArcherAnimationState::Shooting => AnimationConfigResult::new_no_loop(5, 5, 20),
+        }
+    }
+}
+
+// Patrols its ledge without ever walking off it. Stops when it sees the player, draws the bow
+// and shoots arrows that fly with gravity.
+#[derive(Clone)]
+pub struct Archer {
+    bb: BoundingBox,
+    health: Health,
+    dir: Dir,
+    animation_handler: AnimationHandler<ArcherAnimationState>,
+    immunity_frames: u32,
+    reload_frames: u32,
+    alert: Alert,
+}
+
+impl Archer {
+    pub fn new(x: f32, y: f32) -> Self {
+        Archer {
+            bb: BoundingBox {
+                x,
+                y,
+                w: 8.0 / 16.0,
+                h: 12.0 / 16.0,
+                vx: 0.0,
+                vy: 0.0,
+            },
+            health: Health::new(2),
+            dir: Dir::Left,
+            animation_handler: AnimationHandler::new(ArcherAnimationState::Walking),
+            immunity_frames: 0,
+            reload_frames: 0,
+            alert: Alert::default(),
+        }
+    }
+
+    fn bow_pos(&self) -> Pos {
+        let center = self.bb.center();
+        Pos::new(center.x, self.bb.y + self.bb.h * 0.4)
+    }
+
+    // The player is seen when the archer was alerted recently and nothing is in between
+    fn visible_target(&self, map: &GameMap) -> Option<Pos> {
+        let target = self.alert.target()?;
+        let bow = self.bow_pos();
+        let in_range = (target.x - bow.x).hypot(target.y - bow.y) <= MAX_SPOT_RADIUS;
+        (in_range && has_line_of_sight(map, bow, target)).then_some(target)
+    }
+
+    // Aimed so that the arrow falls on the target, items fall with the same gravity
+    fn shoot_at(&self, target: Pos) -> Item {
+        let bow = self.bow_pos();
+        let dx = target.x - bow.x;
+        let frames = (dx.abs() / ARROW_SPEED).ceil().max(1.0);
+        let vx = dx / frames;
+        let vy = (target.y - bow.y) / frames - tuning().gravity * (frames + 1.0) / 2.0;
+        Item::new_with_velocity(bow.x, bow.y, vx, vy, ItemType::Arrow)
+    }
+
+    fn face_towards(&mut self, x: f32) {
+        self.dir = if x < self.bb.center().x {
+            Dir::Left
+        } else {
+            Dir::Right
+        };
+    }
+}
+
+impl Enemy for Archer {
+    fn bb(&self) -> &BoundingBox {
+        &self.bb
+    }
+
+    fn update(&mut self, ctx: &mut EnemyUpdateContext) -> Vec<EnemyUpdateResult> {
+        let map = ctx.map;
+        let mut update_results = Vec::new();
+        self.immunity_frames = self.immunity_frames.saturating_sub(1);
+        self.reload_frames = self.reload_frames.saturating_sub(1);
+        self.alert.update();
+
+        let target = self.visible_target(map);
+        if let Some(target) = target {
+            self.face_towards(target.x);
+        }
+
+        match self.animation_handler.current_state() {
+            ArcherAnimationState::Walking => {
+                if target.is_some() && self.reload_frames == 0 {
+                    self.animation_handler
+                        .set_state(ArcherAnimationState::Aiming);
+                }
+            }
+            ArcherAnimationState::Aiming => match target {
+                // Lets go of the arrow once the bow is fully drawn
+                Some(target) if self.animation_handler.finished() => {
+                    update_results.push(EnemyUpdateResult::ShootItem {
+                        item: self.shoot_at(target),
+                    });
+                    self.reload_frames = RELOAD_FRAMES;
+                    self.animation_handler
+                        .set_state(ArcherAnimationState::Shooting);
+                }
+                Some(_) => {}
+                None => self
+                    .animation_handler
+                    .set_state(ArcherAnimationState::Walking),
+            },
+            ArcherAnimationState::Shooting => {
+                if self.animation_handler.finished() {
+                    self.animation_handler
+                        .set_state(ArcherAnimationState::Walking);
+                }
+            }
+        }
+
+        // Stands still while the player is in sight, patrols otherwise
+        let speed = match (self.animation_handler.current_state(), target) {
+            (ArcherAnimationState::Walking, None) => WALK_SPEED,
+            _ => 0.0,
+        };
+        self.bb.vx = match self.dir {
+            Dir::Left => -speed,
+            Dir::Right => speed,
+        };
+
+        let res = integrate_kinematic_with_platforms(map, &self.bb, true);
+        self.bb = res.new_bb;
+
+        // Turns around at walls and ledges, the ledge is where it shoots from
+        let front_x = match self.dir {
+            Dir::Left => self.bb.x - 0.1,
+            Dir::Right => self.bb.x + self.bb.w + 0.1,
+        };
+        let below_y = (self.bb.y + self.bb.h + 0.1).floor() as i32;
+        let ledge_ahead = res.on_bottom && !map.is_ground_at(front_x.floor() as i32, below_y);
+        if speed > 0.0 {
+            if res.on_left {
+                self.dir = Dir::Right;
+            } else if res.on_right {
+                self.dir = Dir::Left;
+            } else if ledge_ahead {
+                self.dir = match self.dir {
+                    Dir::Left => Dir::Right,
+                    Dir::Right => Dir::Left,
+                };
+            }
+        }
+
+        self.animation_handler.increment_frame();
+
+        update_results
+    }
+
+    fn should_remove(&self) -> bool {
+        self.health.current == 0
+    }
+
+    fn get_health(&self) -> Health {
+        self.health
+    }
+
+    fn maybe_got_hit(&mut self, hit: EnemyHit) -> EnemyHitResult {
+        if self.immunity_frames > 0 {
+            return EnemyHitResult::DidNotHit;
+        }
+
+        self.health.decrease();
+        self.immunity_frames = IMMUNITY_FRAMES;
+        // Getting hit spoils the shot
+        self.animation_handler
+            .set_state(ArcherAnimationState::Walking);
+        self.face_towards(hit.from.x);
+        EnemyHitResult::GotHit
+    }
+
+    fn apply_impulse(&mut self, vx: f32, vy: f32) {
+        self.bb.vx += vx;
+        self.bb.vy += vy;
+    }
+
+    fn fragment_color(&self) -> [f32; 4] {
+        BODY_COLOR
+    }
+
+    fn maybe_damage_player(&self) -> Option<u32> {
+        Some(1)
+    }
+
+    fn pack(&self) -> Option<&'static str> {
+        Some("archer")
+    }
+
+    fn is_alerted(&self) -> bool {
+        self.alert.target().is_some()
+    }
+
+    fn alert(&mut self, target: Pos) {
+        self.alert.alert(target);
+    }
+
+    fn spot_radius(&self) -> f32 {
+        MAX_SPOT_RADIUS
+    }
+
+    // There is no sprite for the archer, it is drawn from rectangles. The atlas index of the
+    // animation tells which pose to draw.
+    fn draw(&self, renderer: &mut Renderer) {
+        let pixel = 1.0 / 16.0;
+        let bb = self.bb;
+        let alpha = if self.immunity_frames % 8 >= 4 {
+            0.5
+        } else {
+            1.0
+        };
+        let with_alpha = |[r, g, b, _]: [f32; 4]| [r, g, b, alpha];
+        let frame = self.animation_handler.get_atlas_index();
+
+        // Legs take turns stepping forward while walking
+        let (left_leg, right_leg) = match frame {
+            0 => (0.0, pixel * 2.0),
+            1 => (pixel * 2.0, 0.0),
+            _ => (pixel, pixel),
+        };
+        let legs_y = bb.y + bb.h - pixel * 3.0;
+        renderer.draw_rect(
+            bb.x + pixel + left_leg,
+            legs_y,
+            pixel * 2.0,
+            pixel * 3.0,
+            with_alpha(BODY_COLOR),
+        );
+        renderer.draw_rect(
+            bb.x + bb.w - pixel * 3.0 - right_leg,
+            legs_y,
+            pixel * 2.0,
+            pixel * 3.0,
+            with_alpha(BODY_COLOR),
+        );
+        renderer.draw_rect(
+            bb.x + pixel,
+            bb.y + pixel * 4.0,
+            bb.w - pixel * 2.0,
+            bb.h - pixel * 7.0,
+            with_alpha(BODY_COLOR),
+        );
+        renderer.draw_rect(
+            bb.x + pixel * 2.0,
+            bb.y,
+            pixel * 4.0,
+            pixel * 4.0,
+            with_alpha(HEAD_COLOR),
+        );
+
+        // The bow is held in front, the string is pulled further back on every aiming frame
+        let bow_y = bb.y + pixel * 3.0;
+        let bow_h = pixel * 7.0;
+        let pull = match frame {
+            2..=4 => (frame - 1) as f32 * pixel,
+            _ => 0.0,
+        };
+        let (bow_x, string_x) = match self.dir {
+            Dir::Left => (bb.x - pixel, bb.x + pull),
+            Dir::Right => (bb.x + bb.w, bb.x + bb.w - pixel - pull),
+        };
+        renderer.draw_rect(bow_x, bow_y, pixel, bow_h, with_alpha(BOW_COLOR));
+        renderer.draw_rect(
+            string_x,
+            bow_y + pull,
+            pixel,
+            bow_h - pull * 2.0,
+            with_alpha(STRING_COLOR),
+        );
+        // The arrow is on the string until it is let go
+        if let ArcherAnimationState::Aiming = self.animation_handler.current_state() {
+            let arrow_w = pull + pixel * 4.0;
+            let arrow_x = match self.dir {
+                Dir::Left => string_x - arrow_w + pixel,
+                Dir::Right => string_x,
+            };
+            renderer.draw_rect(
+                arrow_x,
+                bow_y + bow_h / 2.0,
+                arrow_w,
+                pixel,
+                with_alpha(BOW_COLOR),
+            );
+        }
+    }
+}
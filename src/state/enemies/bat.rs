@@ -1,14 +1,20 @@
-use crate::physics::integrate_kinematic;
+use crate::physics::{integrate_kinematic, integrate_kinematic_with_platforms};
 use crate::render::TILE_SIZE;
 use crate::state::animation_handler::{AnimationConfig, AnimationConfigResult, AnimationHandler};
-use crate::state::common::{BoundingBox, Health};
+use crate::state::common::{BoundingBox, Health, Pos};
 use crate::state::enemies::Enemy;
-use crate::state::enemies::common::{EnemyHitResult, EnemyHitType, EnemyUpdateResult};
-use crate::state::map_like::GameMap;
+use crate::state::enemies::common::{
+    Alert, DamageType, EnemyHit, EnemyHitResult, EnemyUpdateContext, EnemyUpdateResult,
+    HEAVY_HIT_STUN_FRAMES, STUNNED_STOMP_DAMAGE, Stun,
+};
+use crate::state::game_rng::GameRng;
 use rand::Rng;
 
+const SPEED: f32 = 0.01;
+const ALERTED_SPEED: f32 = 0.02;
+
 // Bat flies around
-#[derive(PartialEq)]
+#[derive(Clone, PartialEq)]
 enum BatAnimationState {
     Flying,
     Standing,
@@ -25,22 +31,27 @@ impl AnimationConfig for BatAnimationState {
     }
 }
 
+#[derive(Clone)]
 enum BatState {
     Flying { dir_rad: f32 },
     Standing,
     Falling { frames_remaining: i32 },
 }
 
+#[derive(Clone)]
 pub struct Bat {
     bb: BoundingBox,
     health: Health,
     state: BatState,
     animation_handler: AnimationHandler<BatAnimationState>,
+    stun: Stun,
+    alert: Alert,
+    rng: GameRng,
 }
 
 impl Bat {
     pub fn new(x: f32, y: f32) -> Self {
-        let mut rng = rand::rng();
+        let mut rng = GameRng::for_spawn(x, y);
 
         Bat {
             bb: BoundingBox {
@@ -56,6 +67,9 @@ impl Bat {
                 dir_rad: rng.random_range(0.0..std::f32::consts::PI * 2.0),
             },
             animation_handler: AnimationHandler::new(BatAnimationState::Standing),
+            stun: Stun::default(),
+            alert: Alert::default(),
+            rng,
         }
     }
 }
@@ -65,13 +79,24 @@ impl Enemy for Bat {
         &self.bb
     }
 
-    fn update(&mut self, map: &GameMap) -> Vec<EnemyUpdateResult> {
+    fn update(&mut self, ctx: &mut EnemyUpdateContext) -> Vec<EnemyUpdateResult> {
+        let map = ctx.map;
         let mut new_state: Option<BatState> = None;
+        self.stun.update();
+        self.alert.update();
+        let bat_center = self.bb.get_center();
 
         match &mut self.state {
             BatState::Flying { dir_rad } => {
-                self.bb.vx = dir_rad.cos() * 0.01;
-                self.bb.vy = dir_rad.sin() * 0.01;
+                // Alerted bats fly straight at where the player was seen
+                let speed = if let Some(target) = self.alert.target() {
+                    *dir_rad = (target.y - bat_center.y).atan2(target.x - bat_center.x);
+                    ALERTED_SPEED
+                } else {
+                    SPEED
+                };
+                self.bb.vx = dir_rad.cos() * speed;
+                self.bb.vy = dir_rad.sin() * speed;
 
                 let res = integrate_kinematic(map, &self.bb, false);
 
@@ -99,11 +124,13 @@ impl Enemy for Bat {
                 self.animation_handler.set_state(BatAnimationState::Flying);
             }
             BatState::Standing => {
-                let mut rng = rand::rng();
-
-                if rng.random_range(0..300) == 0 {
-                    let dir_rad =
-                        rng.random_range(std::f32::consts::PI * 1.25..std::f32::consts::PI * 1.75);
+                // Stunned bats stay on the ground
+                if !self.stun.is_stunned()
+                    && (self.alert.target().is_some() || self.rng.random_range(0..300) == 0)
+                {
+                    let dir_rad = self
+                        .rng
+                        .random_range(std::f32::consts::PI * 1.25..std::f32::consts::PI * 1.75);
                     self.state = BatState::Flying { dir_rad }
                 }
 
@@ -113,11 +140,12 @@ impl Enemy for Bat {
             BatState::Falling { frames_remaining } => {
                 self.animation_handler.set_state(BatAnimationState::Falling);
                 let orig_vy = self.bb.vy;
-                let res = integrate_kinematic(map, &self.bb, true);
+                let res = integrate_kinematic_with_platforms(map, &self.bb, true);
                 self.bb = res.new_bb;
 
                 if res.on_bottom {
                     self.bb.vy = -orig_vy * 0.8;
+                    self.bb.vx *= 0.8;
 
                     if *frames_remaining <= 0 {
                         new_state = Some(BatState::Standing);
@@ -145,7 +173,13 @@ impl Enemy for Bat {
         self.health
     }
 
-    fn maybe_got_hit(&mut self, _hit_type: EnemyHitType) -> EnemyHitResult {
+    fn maybe_got_hit(&mut self, hit: EnemyHit) -> EnemyHitResult {
+        if self.stun.is_stunned() && hit.damage_type == DamageType::Stomp {
+            self.health.decrease_by(STUNNED_STOMP_DAMAGE);
+            self.stun.clear();
+            return EnemyHitResult::GotHit;
+        }
+
         if matches!(self.state, BatState::Falling { .. }) {
             EnemyHitResult::DidNotHit
         } else {
@@ -153,13 +187,41 @@ impl Enemy for Bat {
                 frames_remaining: 120,
             };
             self.health.decrease();
+            if hit.is_heavy() {
+                self.stun.stun(HEAVY_HIT_STUN_FRAMES);
+            }
 
             EnemyHitResult::GotHit
         }
     }
 
+    fn pack(&self) -> Option<&'static str> {
+        Some("bat")
+    }
+
+    fn is_alerted(&self) -> bool {
+        self.alert.target().is_some()
+    }
+
+    fn alert(&mut self, target: Pos) {
+        self.alert.alert(target);
+    }
+
+    fn apply_impulse(&mut self, vx: f32, vy: f32) {
+        self.bb.vx += vx;
+        self.bb.vy += vy;
+    }
+
+    fn fragment_color(&self) -> [f32; 4] {
+        [0.46, 0.26, 0.54, 1.0]
+    }
+
+    fn gib_sprite(&self) -> Option<&'static str> {
+        Some("bat_gibs")
+    }
+
     fn maybe_damage_player(&self) -> Option<u32> {
-        if matches!(self.state, BatState::Falling { .. }) {
+        if self.stun.is_stunned() || matches!(self.state, BatState::Falling { .. }) {
             None
         } else {
             Some(1)
@@ -178,5 +240,6 @@ impl Enemy for Bat {
             bb.h + 2.0 / TILE_SIZE,
             1.0,
         );
+        self.stun.draw(renderer, bb);
     }
 }
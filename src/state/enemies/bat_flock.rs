@@ -0,0 +1,330 @@
+use crate::state::common::{BoundingBox, Health, Pos};
+use crate::state::enemies::Enemy;
+use crate::state::enemies::common::{
+    Alert, EnemyHit, EnemyHitResult, EnemyUpdateContext, EnemyUpdateResult,
+};
+use crate::state::game_rng::GameRng;
+use crate::state::map_like::MapLike;
+use crate::state::spatial_grid::SpatialGrid;
+use rand::Rng;
+
+const BATLING_COUNT: usize = 8;
+const BATLING_W: f32 = 7.0 / 16.0;
+const BATLING_H: f32 = 4.0 / 16.0;
+
+// Boids steering, each batling only looks at the batlings within the neighbour radius
+const NEIGHBOUR_RADIUS: f32 = 1.5;
+const SEPARATION_RADIUS: f32 = 0.5;
+const SEPARATION_WEIGHT: f32 = 0.02;
+const COHESION_WEIGHT: f32 = 0.001;
+const ALIGNMENT_WEIGHT: f32 = 0.05;
+// Pull towards the player when alerted, otherwise back towards where the flock started
+const ATTRACTION_WEIGHT: f32 = 0.002;
+const HOME_WEIGHT: f32 = 0.0005;
+const JITTER: f32 = 0.002;
+const MAX_SPEED: f32 = 0.04;
+const ALERTED_MAX_SPEED: f32 = 0.06;
+
+const FRAGMENT_COLOR: [f32; 4] = [0.46, 0.26, 0.54, 1.0];
+
+#[derive(Clone)]
+struct Batling {
+    bb: BoundingBox,
+    alive: bool,
+    // So that the batlings do not flap their wings in sync
+    frame_offset: u32,
+}
+
+// A flock of tiny bats that moves as one enemy. Every batling dies to a single hit, the flock is
+// gone once all of them are.
+#[derive(Clone)]
+pub struct BatFlock {
+    bb: BoundingBox,
+    home: Pos,
+    batlings: Vec<Batling>,
+    health: Health,
+    alert: Alert,
+    // Batlings killed since the last update, they burst into fragments on the next update
+    killed: Vec<(Pos, f32, f32)>,
+    frame: u32,
+    rng: GameRng,
+}
+
+impl BatFlock {
+    pub fn new(x: f32, y: f32) -> Self {
+        // Start in a tight cluster, the same every time so that the editor shows the flock in
+        // a stable place
+        let batlings = (0..BATLING_COUNT)
+            .map(|i| Batling {
+                bb: BoundingBox {
+                    x: x + (i % 4) as f32 * 0.4,
+                    y: y + (i / 4) as f32 * 0.4 + (i % 2) as f32 * 0.1,
+                    w: BATLING_W,
+                    h: BATLING_H,
+                    vx: 0.0,
+                    vy: 0.0,
+                },
+                alive: true,
+                frame_offset: i as u32 * 5,
+            })
+            .collect();
+
+        let mut flock = BatFlock {
+            bb: BoundingBox {
+                x,
+                y,
+                w: 0.0,
+                h: 0.0,
+                vx: 0.0,
+                vy: 0.0,
+            },
+            home: Pos::new(x, y),
+            batlings,
+            health: Health::new(BATLING_COUNT as u32),
+            alert: Alert::default(),
+            killed: Vec::new(),
+            frame: 0,
+            rng: GameRng::for_spawn(x, y),
+        };
+        flock.update_bb();
+        flock.home = flock.bb.center();
+        flock
+    }
+
+    fn alive(&self) -> impl Iterator<Item = &Batling> {
+        self.batlings.iter().filter(|batling| batling.alive)
+    }
+
+    // The flock bounding box covers all the batlings that are still alive
+    fn update_bb(&mut self) {
+        let mut min = Pos::new(f32::MAX, f32::MAX);
+        let mut max = Pos::new(f32::MIN, f32::MIN);
+        for batling in self.alive() {
+            min.x = min.x.min(batling.bb.x);
+            min.y = min.y.min(batling.bb.y);
+            max.x = max.x.max(batling.bb.x + batling.bb.w);
+            max.y = max.y.max(batling.bb.y + batling.bb.h);
+        }
+        if min.x > max.x {
+            return;
+        }
+        self.bb.x = min.x;
+        self.bb.y = min.y;
+        self.bb.w = max.x - min.x;
+        self.bb.h = max.y - min.y;
+    }
+
+    // Boids: keep a distance to the close neighbours, move towards the center of the
+    // neighbours and fly the same way as they do. The jitter is added on top in update.
+    fn steering(&self, grid: &SpatialGrid, index: usize, target: Pos) -> (f32, f32) {
+        let batling = &self.batlings[index];
+        let center = batling.bb.center();
+
+        let mut separation = (0.0, 0.0);
+        let mut neighbour_center = (0.0, 0.0);
+        let mut neighbour_v = (0.0, 0.0);
+        let mut neighbours = 0;
+        for other_index in grid.query_radius(center, NEIGHBOUR_RADIUS) {
+            if other_index == index {
+                continue;
+            }
+            let other = &self.batlings[other_index].bb;
+            let other_center = other.center();
+            let dx = center.x - other_center.x;
+            let dy = center.y - other_center.y;
+            let dist = (dx * dx + dy * dy).sqrt().max(0.01);
+            if dist < SEPARATION_RADIUS {
+                separation.0 += dx / dist * (SEPARATION_RADIUS - dist);
+                separation.1 += dy / dist * (SEPARATION_RADIUS - dist);
+            }
+            neighbour_center.0 += other_center.x;
+            neighbour_center.1 += other_center.y;
+            neighbour_v.0 += other.vx;
+            neighbour_v.1 += other.vy;
+            neighbours += 1;
+        }
+
+        let mut ax = separation.0 * SEPARATION_WEIGHT;
+        let mut ay = separation.1 * SEPARATION_WEIGHT;
+        if neighbours > 0 {
+            let n = neighbours as f32;
+            ax += (neighbour_center.0 / n - center.x) * COHESION_WEIGHT;
+            ay += (neighbour_center.1 / n - center.y) * COHESION_WEIGHT;
+            ax += (neighbour_v.0 / n - batling.bb.vx) * ALIGNMENT_WEIGHT;
+            ay += (neighbour_v.1 / n - batling.bb.vy) * ALIGNMENT_WEIGHT;
+        }
+
+        let dx = target.x - center.x;
+        let dy = target.y - center.y;
+        let dist = (dx * dx + dy * dy).sqrt().max(0.01);
+        let weight = if self.alert.target().is_some() {
+            ATTRACTION_WEIGHT
+        } else {
+            HOME_WEIGHT
+        };
+        ax += dx / dist * weight;
+        ay += dy / dist * weight;
+
+        (ax, ay)
+    }
+}
+
+impl Enemy for BatFlock {
+    fn bb(&self) -> &BoundingBox {
+        &self.bb
+    }
+
+    fn update(&mut self, ctx: &mut EnemyUpdateContext) -> Vec<EnemyUpdateResult> {
+        let map = ctx.map;
+        self.alert.update();
+        self.frame += 1;
+
+        let results = self
+            .killed
+            .drain(..)
+            .map(|(pos, vx, vy)| EnemyUpdateResult::SpawnFragments {
+                pos,
+                vx,
+                vy,
+                color: FRAGMENT_COLOR,
+            })
+            .collect();
+
+        let mut grid = SpatialGrid::new(NEIGHBOUR_RADIUS);
+        for (index, batling) in self.batlings.iter().enumerate() {
+            if batling.alive {
+                grid.insert(index, batling.bb.center());
+            }
+        }
+
+        let target = self.alert.target().unwrap_or(self.home);
+        let accelerations: Vec<(f32, f32)> = (0..self.batlings.len())
+            .map(|index| self.steering(&grid, index, target))
+            .collect();
+
+        let max_speed = if self.alert.target().is_some() {
+            ALERTED_MAX_SPEED
+        } else {
+            MAX_SPEED
+        };
+        for (batling, (ax, ay)) in self.batlings.iter_mut().zip(accelerations) {
+            if !batling.alive {
+                continue;
+            }
+            let bb = &mut batling.bb;
+            bb.vx += ax + self.rng.random_range(-JITTER..JITTER);
+            bb.vy += ay + self.rng.random_range(-JITTER..JITTER);
+            let speed = (bb.vx * bb.vx + bb.vy * bb.vy).sqrt();
+            if speed > max_speed {
+                bb.vx *= max_speed / speed;
+                bb.vy *= max_speed / speed;
+            }
+
+            // Batlings are tiny, checking the center against the tiles is enough. They turn
+            // back from the walls.
+            let center = bb.center();
+            if map.is_solid_at_tile((center.x + bb.vx).floor() as i32, center.y.floor() as i32) {
+                bb.vx = -bb.vx;
+            } else {
+                bb.x += bb.vx;
+            }
+            if map.is_solid_at_tile(center.x.floor() as i32, (center.y + bb.vy).floor() as i32) {
+                bb.vy = -bb.vy;
+            } else {
+                bb.y += bb.vy;
+            }
+        }
+
+        self.update_bb();
+
+        results
+    }
+
+    fn maybe_got_hit(&mut self, hit: EnemyHit) -> EnemyHitResult {
+        // The batling closest to the hit dies
+        let closest = self
+            .batlings
+            .iter_mut()
+            .filter(|batling| batling.alive)
+            .min_by(|a, b| {
+                let dist = |batling: &Batling| {
+                    let center = batling.bb.center();
+                    (center.x - hit.pos.x).powi(2) + (center.y - hit.pos.y).powi(2)
+                };
+                dist(a).total_cmp(&dist(b))
+            });
+        let Some(batling) = closest else {
+            return EnemyHitResult::DidNotHit;
+        };
+
+        batling.alive = false;
+        self.killed
+            .push((batling.bb.center(), batling.bb.vx, batling.bb.vy));
+        self.health.decrease();
+        EnemyHitResult::GotHit
+    }
+
+    fn maybe_damage_player(&self) -> Option<u32> {
+        Some(1)
+    }
+
+    fn draw(&self, renderer: &mut crate::render::Renderer) {
+        for batling in self.alive() {
+            let bb = &batling.bb;
+            let atlas_index = ((self.frame + batling.frame_offset) / 6) % 2;
+            renderer.draw_from_texture_atlas(
+                "batling",
+                atlas_index,
+                false,
+                bb.x,
+                bb.y,
+                bb.w,
+                bb.h,
+                1.0,
+            );
+        }
+    }
+
+    fn should_remove(&self) -> bool {
+        self.health.current == 0
+    }
+
+    fn get_health(&self) -> Health {
+        self.health
+    }
+
+    fn should_render_health_bar(&self) -> bool {
+        false
+    }
+
+    fn overlaps(&self, bb: &BoundingBox) -> bool {
+        self.alive().any(|batling| batling.bb.overlaps(bb))
+    }
+
+    fn overlaps_line(&self, a: &Pos, b: &Pos) -> bool {
+        self.alive().any(|batling| batling.bb.overlaps_line(a, b))
+    }
+
+    fn apply_impulse(&mut self, vx: f32, vy: f32) {
+        for batling in self.batlings.iter_mut() {
+            batling.bb.vx += vx;
+            batling.bb.vy += vy;
+        }
+    }
+
+    fn fragment_color(&self) -> [f32; 4] {
+        FRAGMENT_COLOR
+    }
+
+    fn pack(&self) -> Option<&'static str> {
+        Some("bat")
+    }
+
+    fn is_alerted(&self) -> bool {
+        self.alert.target().is_some()
+    }
+
+    fn alert(&mut self, target: Pos) {
+        self.alert.alert(target);
+    }
+}
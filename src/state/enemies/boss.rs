@@ -0,0 +1,388 @@
+use crate::physics::integrate_kinematic_with_platforms;
+use crate::render::Renderer;
+use crate::state::animation_handler::{AnimationConfig, AnimationConfigResult, AnimationHandler};
+use crate::state::common::{BoundingBox, Dir, Health, Pos};
+use crate::state::enemies::common::{
+    Alert, BossInfo, EnemyHit, EnemyHitResult, EnemyUpdateContext, EnemyUpdateResult,
+    MAX_SPOT_RADIUS,
+};
+use crate::state::enemies::{Enemy, Slime};
+use crate::state::item::{Item, ItemType};
+
+const HEALTH: u32 = 12;
+const PHASES: u32 = 3;
+const IMMUNITY_FRAMES: u32 = 30;
+// Rest between the attacks, shorter in the later phases
+const IDLE_FRAMES: [u32; PHASES as usize] = [90, 70, 50];
+// Squats before every attack so that the player sees it coming
+const WINDUP_FRAMES: u32 = 30;
+const CHARGE_SPEED: f32 = 0.09;
+const CHARGE_FRAMES: u32 = 80;
+// Projectiles of a volley, spread evenly around the direction of the player
+const VOLLEY_PROJECTILES: u32 = 5;
+const VOLLEY_SPREAD: f32 = 0.05;
+const VOLLEY_VY: f32 = -0.18;
+const SUMMONED_SLIMES: u32 = 2;
+// Shakes apart for this long after the last hit before it is gone and the doors open
+const DYING_FRAMES: u32 = 90;
+
+const SLIME_COLOR: [f32; 4] = [0.42, 0.75, 0.19, 1.0];
+const CROWN_COLOR: [f32; 4] = [0.98, 0.82, 0.2, 1.0];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Attack {
+    Charge,
+    Volley,
+    Summon,
+}
+
+// The attacks taken in turns in each phase, a new one is added every phase
+const PHASE_ATTACKS: [&[Attack]; PHASES as usize] = [
+    &[Attack::Charge],
+    &[Attack::Charge, Attack::Volley],
+    &[Attack::Charge, Attack::Volley, Attack::Summon],
+];
+
+#[derive(Clone)]
+enum BossState {
+    // Waits for the player before anything else, then rests between the attacks
+    Idle { frames_left: u32 },
+    Windup { attack: Attack, frames_left: u32 },
+    Charging { frames_left: u32 },
+    Dying { frames_left: u32 },
+}
+
+// The boss uses the slime sprite at a larger size
+#[derive(Clone, PartialEq)]
+enum BossAnimationState {
+    Idle,
+    Windup,
+    Charging,
+}
+
+impl AnimationConfig for BossAnimationState {
+    fn get_config(&self) -> AnimationConfigResult {
+        match self {
+            BossAnimationState::Idle => AnimationConfigResult::new(0, 1, 30),
+            BossAnimationState::Windup => AnimationConfigResult::new_no_loop(2, 4, 10),
+            BossAnimationState::Charging => AnimationConfigResult::new(5, 5, 1),
+        }
+    }
+}
+
+// A big crowned slime that seals the room it is in once it sees the player. Charges across the
+// room, and in the later phases also throws volleys of slime and summons slimes to help. The
+// doors open again when it dies.
+#[derive(Clone)]
+pub struct Boss {
+    bb: BoundingBox,
+    health: Health,
+    dir: Dir,
+    state: BossState,
+    animation_handler: AnimationHandler<BossAnimationState>,
+    immunity_frames: u32,
+    attacks_done: usize,
+    alert: Alert,
+    dead: bool,
+}
+
+impl Boss {
+    pub fn new(x: f32, y: f32) -> Self {
+        Boss {
+            bb: BoundingBox {
+                x,
+                y,
+                w: 24.0 / 16.0,
+                h: 20.0 / 16.0,
+                vx: 0.0,
+                vy: 0.0,
+            },
+            health: Health::new(HEALTH),
+            dir: Dir::Left,
+            state: BossState::Idle { frames_left: 0 },
+            animation_handler: AnimationHandler::new(BossAnimationState::Idle),
+            immunity_frames: 0,
+            attacks_done: 0,
+            alert: Alert::default(),
+            dead: false,
+        }
+    }
+
+    // The phase goes up every time a third of the health is lost
+    fn phase(&self) -> usize {
+        let lost = self.health.max - self.health.current;
+        ((lost * PHASES / self.health.max) as usize).min(PHASES as usize - 1)
+    }
+
+    fn next_attack(&self) -> Attack {
+        let attacks = PHASE_ATTACKS[self.phase()];
+        attacks[self.attacks_done % attacks.len()]
+    }
+
+    fn face_towards(&mut self, x: f32) {
+        self.dir = if x < self.bb.center().x {
+            Dir::Left
+        } else {
+            Dir::Right
+        };
+    }
+
+    fn dir_sign(&self) -> f32 {
+        match self.dir {
+            Dir::Left => -1.0,
+            Dir::Right => 1.0,
+        }
+    }
+
+    // Starts the attack, the charge goes on in its own state
+    fn attack(&mut self, attack: Attack, update_results: &mut Vec<EnemyUpdateResult>) {
+        self.attacks_done += 1;
+        let center = self.bb.center();
+        match attack {
+            Attack::Charge => {
+                self.state = BossState::Charging {
+                    frames_left: CHARGE_FRAMES,
+                };
+                return;
+            }
+            Attack::Volley => {
+                for i in 0..VOLLEY_PROJECTILES {
+                    let spread = i as f32 / (VOLLEY_PROJECTILES - 1) as f32 - 0.5;
+                    let vx = self.dir_sign() * 0.05 + spread * VOLLEY_SPREAD * 2.0;
+                    update_results.push(EnemyUpdateResult::ShootItem {
+                        item: Item::new_with_velocity(
+                            center.x,
+                            self.bb.y,
+                            vx,
+                            VOLLEY_VY,
+                            ItemType::GreenProjectile,
+                        ),
+                    });
+                }
+            }
+            Attack::Summon => {
+                for i in 0..SUMMONED_SLIMES {
+                    let side = if i % 2 == 0 { -1.0 } else { 1.0 };
+                    let mut slime = Slime::new(center.x + side * self.bb.w * 0.5, self.bb.y);
+                    slime.apply_impulse(side * 0.04, -0.12);
+                    if let Some(target) = self.alert.target() {
+                        slime.alert(target);
+                    }
+                    update_results.push(EnemyUpdateResult::SpawnEnemy {
+                        enemy: Box::new(slime),
+                    });
+                }
+            }
+        }
+        self.state = BossState::Idle {
+            frames_left: IDLE_FRAMES[self.phase()],
+        };
+    }
+}
+
+impl Enemy for Boss {
+    fn bb(&self) -> &BoundingBox {
+        &self.bb
+    }
+
+    fn update(&mut self, ctx: &mut EnemyUpdateContext) -> Vec<EnemyUpdateResult> {
+        let map = ctx.map;
+        let mut update_results = Vec::new();
+        self.immunity_frames = self.immunity_frames.saturating_sub(1);
+        self.alert.update();
+
+        // Keeps the room sealed for as long as it is fighting, the game only seals the room when
+        // the player is in it
+        let fighting = self.alert.target().is_some();
+        if fighting && !matches!(self.state, BossState::Dying { .. }) {
+            update_results.push(EnemyUpdateResult::LockDoors);
+        }
+
+        match self.state.clone() {
+            BossState::Idle { frames_left } => {
+                self.bb.vx *= 0.8;
+                self.animation_handler.set_state(BossAnimationState::Idle);
+                if let Some(target) = self.alert.target() {
+                    self.face_towards(target.x);
+                    self.state = match frames_left {
+                        0 => BossState::Windup {
+                            attack: self.next_attack(),
+                            frames_left: WINDUP_FRAMES,
+                        },
+                        _ => BossState::Idle {
+                            frames_left: frames_left - 1,
+                        },
+                    };
+                }
+            }
+            BossState::Windup {
+                attack,
+                frames_left,
+            } => {
+                self.bb.vx = 0.0;
+                self.animation_handler.set_state(BossAnimationState::Windup);
+                if frames_left == 0 {
+                    self.attack(attack, &mut update_results);
+                } else {
+                    self.state = BossState::Windup {
+                        attack,
+                        frames_left: frames_left - 1,
+                    };
+                }
+            }
+            BossState::Charging { frames_left } => {
+                self.animation_handler
+                    .set_state(BossAnimationState::Charging);
+                self.bb.vx = self.dir_sign() * CHARGE_SPEED;
+                self.state = BossState::Charging {
+                    frames_left: frames_left.saturating_sub(1),
+                };
+            }
+            BossState::Dying { frames_left } => {
+                self.bb.vx = 0.0;
+                if frames_left % 15 == 0 {
+                    let center = self.bb.center();
+                    update_results.push(EnemyUpdateResult::SpawnFragments {
+                        pos: center,
+                        vx: 0.0,
+                        vy: -0.05,
+                        color: SLIME_COLOR,
+                    });
+                }
+                if frames_left == 0 {
+                    self.dead = true;
+                    update_results.push(EnemyUpdateResult::UnlockDoors);
+                } else {
+                    self.state = BossState::Dying {
+                        frames_left: frames_left - 1,
+                    };
+                }
+            }
+        }
+
+        let res = integrate_kinematic_with_platforms(map, &self.bb, true);
+        self.bb = res.new_bb;
+
+        // The charge ends at a wall or when it has gone on long enough
+        if let BossState::Charging { frames_left } = self.state
+            && (frames_left == 0 || res.on_left || res.on_right)
+        {
+            self.bb.vx = 0.0;
+            self.state = BossState::Idle {
+                frames_left: IDLE_FRAMES[self.phase()],
+            };
+        }
+
+        self.animation_handler.increment_frame();
+
+        update_results
+    }
+
+    fn should_remove(&self) -> bool {
+        self.dead
+    }
+
+    fn get_health(&self) -> Health {
+        self.health
+    }
+
+    fn maybe_got_hit(&mut self, hit: EnemyHit) -> EnemyHitResult {
+        if self.immunity_frames > 0 || matches!(self.state, BossState::Dying { .. }) {
+            return EnemyHitResult::DidNotHit;
+        }
+
+        self.health.decrease();
+        self.immunity_frames = IMMUNITY_FRAMES;
+        // Getting hit wakes it up even if it did not see the player yet
+        self.alert.alert(hit.from);
+        if self.health.is_empty() {
+            self.state = BossState::Dying {
+                frames_left: DYING_FRAMES,
+            };
+        }
+        EnemyHitResult::GotHit
+    }
+
+    fn fragment_color(&self) -> [f32; 4] {
+        SLIME_COLOR
+    }
+
+    fn gib_sprite(&self) -> Option<&'static str> {
+        Some("slime_gibs")
+    }
+
+    fn boss_info(&self) -> Option<BossInfo> {
+        Some(BossInfo {
+            name: "Slime King",
+            phases: PHASES,
+        })
+    }
+
+    fn maybe_damage_player(&self) -> Option<u32> {
+        match self.state {
+            BossState::Dying { .. } => None,
+            BossState::Charging { .. } => Some(2),
+            _ => Some(1),
+        }
+    }
+
+    fn pack(&self) -> Option<&'static str> {
+        Some("slime")
+    }
+
+    fn is_alerted(&self) -> bool {
+        self.alert.target().is_some()
+    }
+
+    fn alert(&mut self, target: Pos) {
+        self.alert.alert(target);
+    }
+
+    fn spot_radius(&self) -> f32 {
+        MAX_SPOT_RADIUS
+    }
+
+    fn draw(&self, renderer: &mut Renderer) {
+        let pixel = 1.0 / 16.0;
+        let bb = self.bb;
+        let (alpha, shake) = match self.state {
+            BossState::Dying { frames_left } => (
+                frames_left as f32 / DYING_FRAMES as f32,
+                if frames_left % 4 < 2 { pixel } else { -pixel },
+            ),
+            _ if self.immunity_frames % 8 >= 4 => (0.5, 0.0),
+            _ => (1.0, 0.0),
+        };
+        renderer.draw_from_texture_atlas(
+            "slime",
+            self.animation_handler.get_atlas_index(),
+            self.dir.goes_right(),
+            bb.x - pixel * 2.0 + shake,
+            bb.y - pixel * 2.0,
+            bb.w + pixel * 4.0,
+            bb.h + pixel * 4.0,
+            alpha,
+        );
+
+        // Crown with three points, it sinks in to the head when squatting
+        let squat = match self.animation_handler.current_state() {
+            BossAnimationState::Windup => pixel * 2.0,
+            _ => 0.0,
+        };
+        let [r, g, b, _] = CROWN_COLOR;
+        let crown_color = [r, g, b, alpha];
+        let crown_w = pixel * 10.0;
+        let crown_x = bb.center().x - crown_w / 2.0 + shake;
+        let crown_y = bb.y - pixel * 2.0 + squat;
+        renderer.draw_rect(crown_x, crown_y, crown_w, pixel * 3.0, crown_color);
+        for i in 0..3 {
+            renderer.draw_rect(
+                crown_x + i as f32 * pixel * 4.0,
+                crown_y - pixel * 2.0,
+                pixel * 2.0,
+                pixel * 2.0,
+                crown_color,
+            );
+        }
+    }
+}
@@ -3,12 +3,15 @@ use crate::render::TILE_SIZE;
 use crate::state::animation_handler::{AnimationConfig, AnimationConfigResult, AnimationHandler};
 use crate::state::common::{BoundingBox, Health};
 use crate::state::enemies::Enemy;
-use crate::state::enemies::common::{EnemyHitResult, EnemyHitType, EnemyUpdateResult};
+use crate::state::enemies::common::{
+    EnemyHit, EnemyHitResult, EnemyUpdateContext, EnemyUpdateResult,
+};
+use crate::state::game_rng::GameRng;
 use crate::state::item::{Item, ItemType};
 use crate::state::map_like::{GameMap, MapLike};
 use rand::Rng;
 
-#[derive(PartialEq)]
+#[derive(Clone, PartialEq)]
 enum BurrowerAnimationState {
     Wiggling,
     Burbing,
@@ -33,16 +36,16 @@ impl AnimationConfig for BurrowerAnimationState {
     }
 }
 
+#[derive(Clone)]
 pub struct Burrower {
     bb: BoundingBox,
     animation_handler: AnimationHandler<BurrowerAnimationState>,
     frames_remaining: u32,
     health: Health,
     immunity_frames: u32,
+    rng: GameRng,
 }
 
-const BURROWING_DOWN_FRAMES: u32 = 30;
-
 impl Burrower {
     pub fn new(x: f32, y: f32) -> Self {
         Burrower {
@@ -58,6 +61,7 @@ impl Burrower {
             animation_handler: AnimationHandler::new(BurrowerAnimationState::Digging),
             health: Health::new(2),
             immunity_frames: 0,
+            rng: GameRng::for_spawn(x, y),
         }
     }
 }
@@ -65,6 +69,7 @@ impl Burrower {
 fn find_random_close_floor_pos_at_room_here(
     current_pos: &BoundingBox,
     map: &GameMap,
+    rng: &mut GameRng,
 ) -> Option<BoundingBox> {
     let center = current_pos.get_center();
 
@@ -74,7 +79,6 @@ fn find_random_close_floor_pos_at_room_here(
     let current_y = center.y.floor() as i32;
 
     for _ in 0..50 {
-        let mut rng = rand::rng();
         let x_diff = rng.random_range(-10..10);
         let y_diff = rng.random_range(-10..10);
 
@@ -112,33 +116,45 @@ impl Enemy for Burrower {
         &self.bb
     }
 
-    fn update(&mut self, map: &GameMap) -> Vec<EnemyUpdateResult> {
+    fn update(&mut self, ctx: &mut EnemyUpdateContext) -> Vec<EnemyUpdateResult> {
+        let map = ctx.map;
         let mut update_results = Vec::new();
         self.immunity_frames = self.immunity_frames.saturating_sub(1);
 
-        if self.frames_remaining == 0 {
+        // Spit the projectile when the mouth is fully open
+        if self.animation_handler.current_state() == &BurrowerAnimationState::Burbing
+            && self.animation_handler.entered_frame(6)
+        {
+            let projectile = Item::new(
+                self.bb.x + self.bb.w / 2.0,
+                self.bb.y + self.bb.w / 2.0, // Use the w (not h) here to center at "head circle"
+                ItemType::GreenProjectile,
+            );
+
+            update_results
+                .push(EnemyUpdateResult::SpawnItemThrowTowardsPlayer { item: projectile });
+        }
+
+        // Burbing and burrowing up and down last as long as their animations, the rest are timed
+        let state_done = match self.animation_handler.current_state() {
+            BurrowerAnimationState::Burbing
+            | BurrowerAnimationState::BurrowingUp
+            | BurrowerAnimationState::BurrowingDown => self.animation_handler.just_finished(),
+            _ => self.frames_remaining == 0,
+        };
+
+        if state_done {
             match self.animation_handler.current_state() {
                 BurrowerAnimationState::BurrowingUp => {
-                    self.frames_remaining = 30;
                     self.animation_handler
                         .set_state(BurrowerAnimationState::Burbing);
                 }
                 BurrowerAnimationState::Burbing => {
-                    let projectile = Item::new(
-                        self.bb.x + self.bb.w / 2.0,
-                        self.bb.y + self.bb.w / 2.0, // Use the w (not h) here to center at "head circle"
-                        ItemType::GreenProjectile,
-                    );
-
-                    update_results
-                        .push(EnemyUpdateResult::SpawnItemThrowTowardsPlayer { item: projectile });
-
                     self.frames_remaining = 180;
                     self.animation_handler
                         .set_state(BurrowerAnimationState::Wiggling);
                 }
                 BurrowerAnimationState::Wiggling => {
-                    self.frames_remaining = BURROWING_DOWN_FRAMES;
                     self.animation_handler
                         .set_state(BurrowerAnimationState::BurrowingDown);
                 }
@@ -149,7 +165,9 @@ impl Enemy for Burrower {
                 }
                 BurrowerAnimationState::Hidden => {
                     // Randomize the position of the burrower
-                    if let Some(new_bb) = find_random_close_floor_pos_at_room_here(&self.bb, map) {
+                    if let Some(new_bb) =
+                        find_random_close_floor_pos_at_room_here(&self.bb, map, &mut self.rng)
+                    {
                         self.bb = new_bb;
                     }
 
@@ -158,14 +176,13 @@ impl Enemy for Burrower {
                         .set_state(BurrowerAnimationState::Digging);
                 }
                 BurrowerAnimationState::Digging => {
-                    self.frames_remaining = 30;
                     self.animation_handler
                         .set_state(BurrowerAnimationState::BurrowingUp);
                 }
             }
         }
 
-        self.frames_remaining -= 1;
+        self.frames_remaining = self.frames_remaining.saturating_sub(1);
 
         self.animation_handler.increment_frame();
 
@@ -180,7 +197,7 @@ impl Enemy for Burrower {
         self.health
     }
 
-    fn maybe_got_hit(&mut self, _hit_type: EnemyHitType) -> EnemyHitResult {
+    fn maybe_got_hit(&mut self, _hit: EnemyHit) -> EnemyHitResult {
         if self.immunity_frames > 0 {
             return EnemyHitResult::DidNotHit;
         }
@@ -189,7 +206,6 @@ impl Enemy for Burrower {
                 EnemyHitResult::DidNotHit
             }
             _ => {
-                self.frames_remaining = BURROWING_DOWN_FRAMES;
                 self.animation_handler
                     .set_state(BurrowerAnimationState::BurrowingDown);
                 self.health.decrease();
@@ -199,6 +215,14 @@ impl Enemy for Burrower {
         }
     }
 
+    fn fragment_color(&self) -> [f32; 4] {
+        [0.56, 0.34, 0.23, 1.0]
+    }
+
+    fn gib_sprite(&self) -> Option<&'static str> {
+        Some("burrower_gibs")
+    }
+
     fn maybe_damage_player(&self) -> Option<u32> {
         match self.animation_handler.current_state() {
             BurrowerAnimationState::Hidden
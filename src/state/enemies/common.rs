@@ -1,29 +1,220 @@
+use crate::render::Renderer;
 use crate::sound_handler::{Sound, SoundHandler};
-use crate::state::common::{BoundingBox, Health};
+use crate::state::common::{BoundingBox, Dir, Health, Pos};
+use crate::state::game_rng::GameRng;
 use crate::state::item::Item;
 use crate::state::map_like::GameMap;
 
-pub enum EnemyHitType {
-    Swing,
+#[derive(Clone, Copy, PartialEq)]
+pub enum HitStrength {
+    Normal,
+    // Heavy hits stun the enemies that can be stunned
+    Heavy,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DamageType {
+    Melee,
     Stomp,
     Projectile,
+    Explosion,
+    Hazard,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Resistance {
+    Normal,
+    // Only heavy hits get through
+    Resistant,
+    Immune,
+}
+
+// How long an alerted enemy heads towards where the player was
+pub const ALERT_FRAMES: u32 = 300;
+// Enemies notice the player this close, the ones that see further use up to the max
+pub const SPOT_RADIUS: f32 = 4.0;
+pub const MAX_SPOT_RADIUS: f32 = 8.0;
+
+// Enemies that can be alerted by their pack keep one of these
+#[derive(Default, Clone)]
+pub struct Alert {
+    target: Option<Pos>,
+    frames_left: u32,
+}
+
+impl Alert {
+    pub fn alert(&mut self, target: Pos) {
+        self.target = Some(target);
+        self.frames_left = ALERT_FRAMES;
+    }
+
+    pub fn update(&mut self) {
+        self.frames_left = self.frames_left.saturating_sub(1);
+        if self.frames_left == 0 {
+            self.target = None;
+        }
+    }
+
+    pub fn target(&self) -> Option<Pos> {
+        self.target
+    }
+}
+
+pub struct BossInfo {
+    pub name: &'static str,
+    // The health bar is split in to this many segments
+    pub phases: u32,
+}
+
+#[derive(Clone, Copy)]
+pub struct EnemyHit {
+    pub damage_type: DamageType,
+    pub strength: HitStrength,
+    // Where the hit landed, enemies made of many parts use this to tell which part got hit
+    pub pos: Pos,
+    // Where the hit came from (the attacker or where the projectile was flying from), used for
+    // blocking hits from the front
+    pub from: Pos,
+}
+
+impl EnemyHit {
+    pub fn new(damage_type: DamageType, strength: HitStrength, pos: Pos) -> Self {
+        EnemyHit {
+            damage_type,
+            strength,
+            pos,
+            from: pos,
+        }
+    }
+
+    pub fn from(mut self, from: Pos) -> Self {
+        self.from = from;
+        self
+    }
+
+    pub fn is_heavy(&self) -> bool {
+        self.strength == HitStrength::Heavy
+    }
+}
+
+// How long heavy hits stun for and how much stomping a stunned enemy hurts
+pub const HEAVY_HIT_STUN_FRAMES: u32 = 150;
+pub const STUNNED_STOMP_DAMAGE: u32 = 2;
+
+// Enemies that can be stunned keep one of these, while stunned they stay in place with stars
+// circling above them and take bonus damage from stomps
+#[derive(Default, Clone)]
+pub struct Stun {
+    frames_left: u32,
+}
+
+impl Stun {
+    pub fn stun(&mut self, frames: u32) {
+        self.frames_left = self.frames_left.max(frames);
+    }
+
+    pub fn clear(&mut self) {
+        self.frames_left = 0;
+    }
+
+    pub fn is_stunned(&self) -> bool {
+        self.frames_left > 0
+    }
+
+    pub fn update(&mut self) {
+        self.frames_left = self.frames_left.saturating_sub(1);
+    }
+
+    pub fn draw(&self, renderer: &mut Renderer, bb: &BoundingBox) {
+        if !self.is_stunned() {
+            return;
+        }
+
+        let size = 2.0 / 16.0;
+        let center_x = bb.x + bb.w / 2.0;
+        let center_y = bb.y - 3.0 / 16.0;
+        for i in 0..3 {
+            let angle = self.frames_left as f32 * 0.1 + i as f32 * std::f32::consts::TAU / 3.0;
+            renderer.draw_rect(
+                center_x + angle.cos() * bb.w * 0.5 - size / 2.0,
+                center_y + angle.sin() * 2.0 / 16.0 - size / 2.0,
+                size,
+                size,
+                [0.98, 0.95, 0.21, 1.0],
+            );
+        }
+    }
 }
 
 pub enum EnemyHitResult {
     GotHit,
     DidNotHit,
+    // The hit bounced off, for example from a shield
+    Blocked,
+}
+
+// What the enemies know about the world when they are updated. Made for every enemy separately,
+// so that each gets a generator of its own.
+pub struct EnemyUpdateContext<'a> {
+    pub map: &'a GameMap,
+    pub player_bb: BoundingBox,
+    pub player_dir: Dir,
+    pub items: &'a [Item],
+    // Game updates since the game started
+    pub frame: u64,
+    pub rng: GameRng,
 }
 
 pub enum EnemyUpdateResult {
+    // Item flying off with the velocity the enemy gave it
+    ShootItem {
+        item: Item,
+    },
     // Spawn an item that will be thrown towards the player (with gravity and such)
-    SpawnItemThrowTowardsPlayer { item: Item },
+    SpawnItemThrowTowardsPlayer {
+        item: Item,
+    },
+    // Burst of fragments, for when a part of the enemy is destroyed
+    SpawnFragments {
+        pos: Pos,
+        vx: f32,
+        vy: f32,
+        color: [f32; 4],
+    },
+    // A new enemy, for example one summoned by a boss
+    SpawnEnemy {
+        enemy: Box<dyn Enemy>,
+    },
+    // Seal the doors of the room the enemy is in, only done while the player is in the room
+    LockDoors,
+    // Open the doors sealed by LockDoors
+    UnlockDoors,
 }
 
-pub trait Enemy {
+// So that boxed enemies can be cloned, implemented for every enemy that is Clone
+pub trait CloneEnemy {
+    fn clone_box(&self) -> Box<dyn Enemy>;
+}
+
+impl<T: Enemy + Clone + 'static> CloneEnemy for T {
+    fn clone_box(&self) -> Box<dyn Enemy> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Enemy> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+// Enemies are updated in parallel (see Game::update), so they need to be Send
+pub trait Enemy: Send + CloneEnemy {
     fn bb(&self) -> &BoundingBox;
-    fn update(&mut self, map: &GameMap) -> Vec<EnemyUpdateResult>;
+    fn update(&mut self, ctx: &mut EnemyUpdateContext) -> Vec<EnemyUpdateResult>;
 
-    fn maybe_got_hit(&mut self, hit_type: EnemyHitType) -> EnemyHitResult;
+    // Only called for hits that get through the resistances, see maybe_got_hit_with_sound
+    fn maybe_got_hit(&mut self, hit: EnemyHit) -> EnemyHitResult;
     fn maybe_damage_player(&self) -> Option<u32>;
     fn draw(&self, renderer: &mut crate::render::Renderer);
 
@@ -33,15 +224,91 @@ pub trait Enemy {
     fn should_render_health_bar(&self) -> bool {
         true
     }
+
+    /// Touching the player, items and swings. Enemies made of many parts check the parts instead
+    /// of their whole bounding box.
+    fn overlaps(&self, bb: &BoundingBox) -> bool {
+        self.bb().overlaps(bb)
+    }
+
+    fn overlaps_line(&self, a: &Pos, b: &Pos) -> bool {
+        self.bb().overlaps_line(a, b)
+    }
+
+    /// Pushes the enemy, for example knockback from being stomped. Enemies that are anchored in
+    /// place ignore this.
+    fn apply_impulse(&mut self, _vx: f32, _vy: f32) {}
+
+    /// Color of the fragments the enemy bursts into when killed
+    fn fragment_color(&self) -> [f32; 4] {
+        [0.6, 0.6, 0.6, 1.0]
+    }
+
+    /// Sprite with the chunks that are left behind when the enemy is killed, see Item::new_gib
+    fn gib_sprite(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Bosses get the big health bar at the bottom of the screen instead of the small one
+    fn boss_info(&self) -> Option<BossInfo> {
+        None
+    }
+
+    /// Enemies of the same pack alert each other when one of them notices the player
+    fn pack(&self) -> Option<&'static str> {
+        None
+    }
+
+    fn is_alerted(&self) -> bool {
+        false
+    }
+
+    /// Head towards the target (where the player was seen) for a while
+    fn alert(&mut self, _target: Pos) {}
+
+    /// How close the player has to be for the enemy to notice, at most MAX_SPOT_RADIUS
+    fn spot_radius(&self) -> f32 {
+        SPOT_RADIUS
+    }
+
+    /// Alerted walkers are given targets along a path to the player instead of the player
+    /// position itself
+    fn follows_paths(&self) -> bool {
+        false
+    }
+
+    /// Glowing enemies light up dark rooms this many tiles around them
+    fn light_radius(&self) -> Option<f32> {
+        None
+    }
+
+    /// How the enemy takes each type of damage
+    fn resistance(&self, _damage_type: DamageType) -> Resistance {
+        Resistance::Normal
+    }
+
     fn maybe_got_hit_with_sound(
         &mut self,
-        hit_type: EnemyHitType,
+        hit: EnemyHit,
         sound_handler: &SoundHandler,
     ) -> EnemyHitResult {
-        match self.maybe_got_hit(hit_type) {
+        let blocked = match self.resistance(hit.damage_type) {
+            Resistance::Normal => false,
+            Resistance::Resistant => !hit.is_heavy(),
+            Resistance::Immune => true,
+        };
+        if blocked {
+            return EnemyHitResult::DidNotHit;
+        }
+
+        match self.maybe_got_hit(hit) {
             EnemyHitResult::DidNotHit => EnemyHitResult::DidNotHit,
+            EnemyHitResult::Blocked => {
+                sound_handler.play(Sound::Clang);
+                EnemyHitResult::Blocked
+            }
             EnemyHitResult::GotHit => {
-                sound_handler.play(Sound::EnemyHit);
+                sound_handler.play_at(Sound::EnemyHit, hit.pos);
                 EnemyHitResult::GotHit
             }
         }
@@ -0,0 +1,131 @@
+use crate::state::common::{BoundingBox, Health, Pos};
+use crate::state::enemies::Enemy;
+use crate::state::enemies::common::{
+    BossInfo, DamageType, EnemyHit, EnemyHitResult, EnemyUpdateContext, EnemyUpdateResult,
+    Resistance,
+};
+
+const ARMOR_IMMUNITY_FRAMES: u32 = 30;
+const OUTLINE: f32 = 1.0 / 16.0;
+const OUTLINE_COLOR: [f32; 4] = [0.98, 0.8, 0.2, 0.8];
+
+// A tougher version of any enemy. It has armor worth its whole health on top of the health,
+// the armor takes the first hits wherever they come from. Hits to the player do double damage.
+#[derive(Clone)]
+pub struct Elite {
+    enemy: Box<dyn Enemy>,
+    armor: Health,
+    immunity_frames: u32,
+}
+
+impl Elite {
+    pub fn new(enemy: Box<dyn Enemy>) -> Self {
+        let armor = Health::new(enemy.get_health().max);
+        Elite {
+            enemy,
+            armor,
+            immunity_frames: 0,
+        }
+    }
+}
+
+impl Enemy for Elite {
+    fn bb(&self) -> &BoundingBox {
+        self.enemy.bb()
+    }
+
+    fn update(&mut self, ctx: &mut EnemyUpdateContext) -> Vec<EnemyUpdateResult> {
+        self.immunity_frames = self.immunity_frames.saturating_sub(1);
+        self.enemy.update(ctx)
+    }
+
+    fn maybe_got_hit(&mut self, hit: EnemyHit) -> EnemyHitResult {
+        if self.armor.is_empty() {
+            return self.enemy.maybe_got_hit(hit);
+        }
+        if self.immunity_frames > 0 {
+            return EnemyHitResult::DidNotHit;
+        }
+        self.armor.decrease();
+        self.immunity_frames = ARMOR_IMMUNITY_FRAMES;
+        EnemyHitResult::GotHit
+    }
+
+    fn maybe_damage_player(&self) -> Option<u32> {
+        self.enemy.maybe_damage_player().map(|damage| damage * 2)
+    }
+
+    fn draw(&self, renderer: &mut crate::render::Renderer) {
+        if !self.armor.is_empty() {
+            let bb = self.enemy.bb();
+            renderer.draw_rect(
+                bb.x - OUTLINE,
+                bb.y - OUTLINE,
+                bb.w + OUTLINE * 2.0,
+                bb.h + OUTLINE * 2.0,
+                OUTLINE_COLOR,
+            );
+        }
+        self.enemy.draw(renderer);
+    }
+
+    fn should_remove(&self) -> bool {
+        self.enemy.should_remove()
+    }
+
+    fn get_health(&self) -> Health {
+        let health = self.enemy.get_health();
+        Health {
+            current: health.current + self.armor.current,
+            max: health.max + self.armor.max,
+        }
+    }
+
+    fn should_render_health_bar(&self) -> bool {
+        self.enemy.should_render_health_bar()
+    }
+
+    fn overlaps(&self, bb: &BoundingBox) -> bool {
+        self.enemy.overlaps(bb)
+    }
+
+    fn overlaps_line(&self, a: &Pos, b: &Pos) -> bool {
+        self.enemy.overlaps_line(a, b)
+    }
+
+    fn apply_impulse(&mut self, vx: f32, vy: f32) {
+        self.enemy.apply_impulse(vx, vy);
+    }
+
+    fn fragment_color(&self) -> [f32; 4] {
+        self.enemy.fragment_color()
+    }
+
+    fn gib_sprite(&self) -> Option<&'static str> {
+        self.enemy.gib_sprite()
+    }
+
+    fn boss_info(&self) -> Option<BossInfo> {
+        self.enemy.boss_info()
+    }
+
+    fn pack(&self) -> Option<&'static str> {
+        self.enemy.pack()
+    }
+
+    fn is_alerted(&self) -> bool {
+        self.enemy.is_alerted()
+    }
+
+    fn alert(&mut self, target: Pos) {
+        self.enemy.alert(target);
+    }
+
+    fn follows_paths(&self) -> bool {
+        self.enemy.follows_paths()
+    }
+
+    fn resistance(&self, damage_type: DamageType) -> Resistance {
+        self.enemy.resistance(damage_type)
+    }
+}
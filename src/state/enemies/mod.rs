@@ -1,12 +1,24 @@
+pub mod archer;
 pub mod bat;
+pub mod bat_flock;
+pub mod boss;
 pub mod burrower;
 pub mod common;
+pub mod elite;
+pub mod shield_bearer;
 pub mod slime;
+pub mod wisp;
 pub mod worm;
 
 // Re-export commonly used items at the module root
+pub use archer::Archer;
 pub use bat::Bat;
+pub use bat_flock::BatFlock;
+pub use boss::Boss;
 pub use burrower::Burrower;
 pub use common::Enemy;
+pub use elite::Elite;
+pub use shield_bearer::ShieldBearer;
 pub use slime::Slime;
+pub use wisp::Wisp;
 pub use worm::Worm;
@@ -0,0 +1,229 @@
+use crate::physics::integrate_kinematic_with_platforms;
+use crate::render::TILE_SIZE;
+use crate::state::animation_handler::{AnimationConfig, AnimationConfigResult, AnimationHandler};
+use crate::state::common::{BoundingBox, Dir, Health, Pos};
+use crate::state::enemies::Enemy;
+use crate::state::enemies::common::{
+    Alert, DamageType, EnemyHit, EnemyHitResult, EnemyUpdateContext, EnemyUpdateResult,
+};
+use crate::state::map_like::MapLike;
+
+const WALK_SPEED: f32 = 0.012;
+const IMMUNITY_FRAMES: u32 = 30;
+// How long the shield flashes after blocking, the same swing can not clang again meanwhile
+const BLOCK_FRAMES: u32 = 20;
+// Thrown items flying at it from this close are turned towards
+const INCOMING_DISTANCE: f32 = 3.0;
+
+#[derive(Clone, PartialEq)]
+enum ShieldBearerAnimationState {
+    Walking,
+    Blocking,
+}
+
+impl AnimationConfig for ShieldBearerAnimationState {
+    fn get_config(&self) -> AnimationConfigResult {
+        match self {
+            ShieldBearerAnimationState::Walking => AnimationConfigResult::new(0, 1, 16),
+            ShieldBearerAnimationState::Blocking => AnimationConfigResult::new(2, 2, 1),
+        }
+    }
+}
+
+// Walks back and forth holding a shield in front. Swings and projectiles from the front bounce
+// off the shield, it has to be hit from behind or stomped on.
+#[derive(Clone)]
+pub struct ShieldBearer {
+    bb: BoundingBox,
+    health: Health,
+    dir: Dir,
+    animation_handler: AnimationHandler<ShieldBearerAnimationState>,
+    immunity_frames: u32,
+    block_frames: u32,
+    alert: Alert,
+}
+
+impl ShieldBearer {
+    pub fn new(x: f32, y: f32) -> Self {
+        ShieldBearer {
+            bb: BoundingBox {
+                x,
+                y,
+                w: 10.0 / 16.0,
+                h: 12.0 / 16.0,
+                vx: 0.0,
+                vy: 0.0,
+            },
+            health: Health::new(3),
+            dir: Dir::Left,
+            animation_handler: AnimationHandler::new(ShieldBearerAnimationState::Walking),
+            immunity_frames: 0,
+            block_frames: 0,
+            alert: Alert::default(),
+        }
+    }
+
+    // Hits coming from above are never blocked, the shield only covers the front
+    fn is_in_front(&self, from: Pos) -> bool {
+        let center = self.bb.center();
+        let above = from.y < self.bb.y;
+        let in_front = match self.dir {
+            Dir::Left => from.x < center.x,
+            Dir::Right => from.x > center.x,
+        };
+        in_front && !above
+    }
+
+    fn face_towards(&mut self, x: f32) {
+        self.dir = if x < self.bb.center().x {
+            Dir::Left
+        } else {
+            Dir::Right
+        };
+    }
+}
+
+impl Enemy for ShieldBearer {
+    fn bb(&self) -> &BoundingBox {
+        &self.bb
+    }
+
+    fn update(&mut self, ctx: &mut EnemyUpdateContext) -> Vec<EnemyUpdateResult> {
+        let map = ctx.map;
+        self.immunity_frames = self.immunity_frames.saturating_sub(1);
+        self.block_frames = self.block_frames.saturating_sub(1);
+        self.alert.update();
+
+        // Alerted shield bearers keep the shield towards the player, and all of them turn it
+        // towards things thrown at them
+        if let Some(target) = self.alert.target() {
+            self.face_towards(target.x);
+        }
+        let center = self.bb.center();
+        if let Some(incoming) = ctx.items.iter().find(|item| {
+            let item_center = item.bb().center();
+            let dx = center.x - item_center.x;
+            item.can_hit_enemy()
+                && dx.abs() <= INCOMING_DISTANCE
+                && (item_center.y - center.y).abs() <= self.bb.h
+                && dx * item.bb().vx > 0.0
+        }) {
+            self.face_towards(incoming.bb().center().x);
+        }
+
+        let speed = if self.block_frames > 0 {
+            0.0
+        } else {
+            WALK_SPEED
+        };
+        self.bb.vx = match self.dir {
+            Dir::Left => -speed,
+            Dir::Right => speed,
+        };
+
+        let res = integrate_kinematic_with_platforms(map, &self.bb, true);
+        self.bb = res.new_bb;
+
+        // Turns around at walls and ledges
+        let front_x = match self.dir {
+            Dir::Left => self.bb.x - 0.1,
+            Dir::Right => self.bb.x + self.bb.w + 0.1,
+        };
+        let below_y = (self.bb.y + self.bb.h + 0.1).floor() as i32;
+        let ledge_ahead = res.on_bottom && !map.is_ground_at(front_x.floor() as i32, below_y);
+        if res.on_left {
+            self.dir = Dir::Right;
+        } else if res.on_right {
+            self.dir = Dir::Left;
+        } else if ledge_ahead && self.alert.target().is_none() {
+            self.dir = match self.dir {
+                Dir::Left => Dir::Right,
+                Dir::Right => Dir::Left,
+            };
+        }
+
+        if self.block_frames > 0 {
+            self.animation_handler
+                .set_state(ShieldBearerAnimationState::Blocking);
+        } else {
+            self.animation_handler
+                .set_state(ShieldBearerAnimationState::Walking);
+        }
+        self.animation_handler.increment_frame();
+
+        vec![]
+    }
+
+    fn should_remove(&self) -> bool {
+        self.health.current == 0
+    }
+
+    fn get_health(&self) -> Health {
+        self.health
+    }
+
+    fn maybe_got_hit(&mut self, hit: EnemyHit) -> EnemyHitResult {
+        if self.immunity_frames > 0 {
+            return EnemyHitResult::DidNotHit;
+        }
+
+        if matches!(hit.damage_type, DamageType::Melee | DamageType::Projectile)
+            && self.is_in_front(hit.from)
+        {
+            if self.block_frames > 0 {
+                return EnemyHitResult::DidNotHit;
+            }
+            self.block_frames = BLOCK_FRAMES;
+            return EnemyHitResult::Blocked;
+        }
+
+        self.health.decrease();
+        self.immunity_frames = IMMUNITY_FRAMES;
+        // Turns around to face whoever hit it from behind
+        self.face_towards(hit.from.x);
+        EnemyHitResult::GotHit
+    }
+
+    fn apply_impulse(&mut self, vx: f32, vy: f32) {
+        self.bb.vx += vx;
+        self.bb.vy += vy;
+    }
+
+    fn fragment_color(&self) -> [f32; 4] {
+        [0.36, 0.43, 0.88, 1.0]
+    }
+
+    fn gib_sprite(&self) -> Option<&'static str> {
+        Some("shield_bearer_gibs")
+    }
+
+    fn maybe_damage_player(&self) -> Option<u32> {
+        Some(1)
+    }
+
+    fn pack(&self) -> Option<&'static str> {
+        Some("shield")
+    }
+
+    fn is_alerted(&self) -> bool {
+        self.alert.target().is_some()
+    }
+
+    fn alert(&mut self, target: Pos) {
+        self.alert.alert(target);
+    }
+
+    fn draw(&self, renderer: &mut crate::render::Renderer) {
+        let bb = self.bb();
+        renderer.draw_from_texture_atlas(
+            "shield_bearer",
+            self.animation_handler.get_atlas_index(),
+            self.dir.goes_right(),
+            bb.x - 1.0 / TILE_SIZE,
+            bb.y - 1.0 / TILE_SIZE,
+            bb.w + 2.0 / TILE_SIZE,
+            bb.h + 2.0 / TILE_SIZE,
+            1.0,
+        );
+    }
+}
@@ -1,14 +1,17 @@
-use crate::physics::integrate_kinematic;
+use crate::physics::integrate_kinematic_with_platforms;
 use crate::render::TILE_SIZE;
 use crate::state::animation_handler::{AnimationConfig, AnimationConfigResult, AnimationHandler};
-use crate::state::common::{BoundingBox, Dir, Health};
+use crate::state::common::{BoundingBox, Dir, Health, Pos};
 use crate::state::enemies::Enemy;
-use crate::state::enemies::common::{EnemyHitResult, EnemyHitType, EnemyUpdateResult};
-use crate::state::map_like::GameMap;
+use crate::state::enemies::common::{
+    Alert, DamageType, EnemyHit, EnemyHitResult, EnemyUpdateContext, EnemyUpdateResult,
+    HEAVY_HIT_STUN_FRAMES, STUNNED_STOMP_DAMAGE, Stun,
+};
+use crate::tuning::tuning;
 use rand::prelude::IndexedRandom;
 
 // Slime bounces around
-#[derive(PartialEq)]
+#[derive(Clone, PartialEq)]
 enum SlimeAnimationState {
     Idle,
     Jumping,
@@ -23,11 +26,14 @@ impl AnimationConfig for SlimeAnimationState {
     }
 }
 
+#[derive(Clone)]
 pub enum SlimeState {
     Idle { frames_remaining: u32 },
-    Jumping { frames_remaining: u32 },
+    // Squats first and jumps when the animation gets to the stretched frame
+    Jumping { in_air: bool },
 }
 
+#[derive(Clone)]
 pub struct Slime {
     pub bb: BoundingBox,
     health: Health,
@@ -35,6 +41,8 @@ pub struct Slime {
     dir: Dir,
     animation_handler: AnimationHandler<SlimeAnimationState>,
     state: SlimeState,
+    stun: Stun,
+    alert: Alert,
 }
 
 impl Slime {
@@ -55,6 +63,8 @@ impl Slime {
             state: SlimeState::Idle {
                 frames_remaining: 100,
             },
+            stun: Stun::default(),
+            alert: Alert::default(),
         }
     }
 }
@@ -64,54 +74,69 @@ impl Enemy for Slime {
         &self.bb
     }
 
-    fn update(&mut self, map: &GameMap) -> Vec<EnemyUpdateResult> {
-        let result = integrate_kinematic(map, &self.bb, true);
+    fn update(&mut self, ctx: &mut EnemyUpdateContext) -> Vec<EnemyUpdateResult> {
+        let map = ctx.map;
+        let tuning = tuning();
+        let result = integrate_kinematic_with_platforms(map, &self.bb, true);
         self.bb = result.new_bb;
         self.immunity_frames = self.immunity_frames.saturating_sub(1);
+        self.alert.update();
 
-        let jump_total_frames = 8 * 40;
-        let jump_before_jump = 4 * 30;
-        let idling_frames = 60 * 5;
+        // Stunned slime just sits there, the animation is paused as well
+        if self.stun.is_stunned() {
+            self.stun.update();
+            self.animation_handler.set_state(SlimeAnimationState::Idle);
+            if result.on_bottom {
+                self.bb.vx = 0.0;
+            }
+            return vec![];
+        }
+
+        // Alerted slimes jump around restlessly
+        let idling_frames = if self.is_alerted() {
+            tuning.slime_alerted_idle_frames
+        } else {
+            tuning.slime_idle_frames
+        };
 
         match self.state {
             SlimeState::Idle { frames_remaining } => {
                 self.animation_handler.set_state(SlimeAnimationState::Idle);
-                self.bb.vx = 0.0;
+                // Keep the velocity from knockbacks until landing
+                if result.on_bottom {
+                    self.bb.vx = 0.0;
+                }
                 if frames_remaining == 0 {
-                    self.state = SlimeState::Jumping {
-                        frames_remaining: jump_total_frames,
+                    self.state = SlimeState::Jumping { in_air: false };
+                    self.dir = match self.alert.target() {
+                        Some(target) if target.x < self.bb.get_center().x => Dir::Left,
+                        Some(_) => Dir::Right,
+                        None => *[Dir::Left, Dir::Right].choose(&mut ctx.rng).unwrap(),
                     };
-                    self.dir = *[Dir::Left, Dir::Right].choose(&mut rand::rng()).unwrap();
                 } else {
                     self.state = SlimeState::Idle {
                         frames_remaining: frames_remaining - 1,
                     }
                 }
             }
-            SlimeState::Jumping { frames_remaining } => {
+            SlimeState::Jumping { in_air } => {
                 self.animation_handler
                     .set_state(SlimeAnimationState::Jumping);
-                if frames_remaining == jump_total_frames - jump_before_jump {
-                    self.bb.vy = -0.2;
+                if self.animation_handler.entered_frame(5) {
+                    self.bb.vy = -tuning.slime_jump_velocity;
+                    self.state = SlimeState::Jumping { in_air: true };
                 }
-                if frames_remaining <= jump_total_frames - jump_before_jump {
-                    self.bb.vx = 0.06
+                if in_air {
+                    self.bb.vx = tuning.slime_jump_speed
                         * match self.dir {
                             Dir::Right => 1.0,
                             Dir::Left => -1.0,
                         };
-                }
 
-                if frames_remaining == 0
-                    || (result.on_bottom
-                        && frames_remaining < jump_total_frames - jump_before_jump - 1)
-                {
-                    self.state = SlimeState::Idle {
-                        frames_remaining: idling_frames,
-                    }
-                } else {
-                    self.state = SlimeState::Jumping {
-                        frames_remaining: frames_remaining - 1,
+                    if result.on_bottom {
+                        self.state = SlimeState::Idle {
+                            frames_remaining: idling_frames,
+                        }
                     }
                 }
             }
@@ -130,7 +155,14 @@ impl Enemy for Slime {
         self.health
     }
 
-    fn maybe_got_hit(&mut self, _hit_type: EnemyHitType) -> EnemyHitResult {
+    fn maybe_got_hit(&mut self, hit: EnemyHit) -> EnemyHitResult {
+        if self.stun.is_stunned() && hit.damage_type == DamageType::Stomp {
+            self.health.decrease_by(STUNNED_STOMP_DAMAGE);
+            self.stun.clear();
+            self.immunity_frames = 30;
+            return EnemyHitResult::GotHit;
+        }
+
         if self.immunity_frames == 0 {
             self.health.decrease();
 
@@ -138,6 +170,9 @@ impl Enemy for Slime {
             self.state = SlimeState::Idle {
                 frames_remaining: 50,
             };
+            if hit.is_heavy() {
+                self.stun.stun(HEAVY_HIT_STUN_FRAMES);
+            }
 
             EnemyHitResult::GotHit
         } else {
@@ -145,8 +180,47 @@ impl Enemy for Slime {
         }
     }
 
+    fn apply_impulse(&mut self, vx: f32, vy: f32) {
+        self.bb.vx += vx;
+        self.bb.vy += vy;
+    }
+
+    fn fragment_color(&self) -> [f32; 4] {
+        [0.42, 0.75, 0.19, 1.0]
+    }
+
+    fn gib_sprite(&self) -> Option<&'static str> {
+        Some("slime_gibs")
+    }
+
+    fn pack(&self) -> Option<&'static str> {
+        Some("slime")
+    }
+
+    fn is_alerted(&self) -> bool {
+        self.alert.target().is_some()
+    }
+
+    fn follows_paths(&self) -> bool {
+        true
+    }
+
+    fn alert(&mut self, target: Pos) {
+        let tuning = tuning();
+        self.alert.alert(target);
+        if let SlimeState::Idle { frames_remaining } = self.state
+            && frames_remaining > tuning.slime_alerted_idle_frames
+        {
+            self.state = SlimeState::Idle {
+                frames_remaining: tuning.slime_alerted_idle_frames,
+            };
+        }
+    }
+
     fn maybe_damage_player(&self) -> Option<u32> {
-        if matches!(self.state, SlimeState::Jumping { .. }) {
+        if self.stun.is_stunned() {
+            None
+        } else if matches!(self.state, SlimeState::Jumping { .. }) {
             Some(2)
         } else {
             Some(1)
@@ -165,5 +239,6 @@ impl Enemy for Slime {
             bb.h + 2.0 / TILE_SIZE,
             1.0,
         );
+        self.stun.draw(renderer, bb);
     }
 }
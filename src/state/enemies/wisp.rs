@@ -0,0 +1,220 @@
+use crate::render::Renderer;
+use crate::state::common::{BoundingBox, Dir, Health, Pos};
+use crate::state::enemies::Enemy;
+use crate::state::enemies::common::{
+    DamageType, EnemyHit, EnemyHitResult, EnemyUpdateContext, EnemyUpdateResult, MAX_SPOT_RADIUS,
+    Resistance,
+};
+
+const IMMUNITY_FRAMES: u32 = 30;
+// The wisp speeds up slowly, so the player can outrun it for a while
+const MAX_ACCELERATION: f32 = 0.0012;
+const MAX_SPEED: f32 = 0.05;
+const RETREAT_SPEED: f32 = 0.02;
+// Drifts to a stop when the player is out of reach
+const DRAG: f32 = 0.96;
+// Knocked back this hard when hit
+const HIT_KNOCKBACK: f32 = 0.08;
+
+const GLOW_COLOR: [f32; 4] = [0.55, 0.85, 1.0, 0.35];
+// Tiles it lights up around it in dark rooms, less while it is fading
+const LIGHT_RADIUS: f32 = 3.0;
+const SHY_LIGHT_RADIUS: f32 = 1.5;
+const CORE_COLOR: [f32; 4] = [0.85, 0.95, 1.0, 1.0];
+
+// A ghostly light that floats through walls towards the player. It is shy, while the player
+// looks at it it backs off and fades.
+#[derive(Clone)]
+pub struct Wisp {
+    bb: BoundingBox,
+    health: Health,
+    immunity_frames: u32,
+    shy: bool,
+    // The game update it was last updated on, drives the flicker of the glow
+    frame: u64,
+}
+
+impl Wisp {
+    pub fn new(x: f32, y: f32) -> Self {
+        Wisp {
+            bb: BoundingBox {
+                x,
+                y,
+                w: 8.0 / 16.0,
+                h: 8.0 / 16.0,
+                vx: 0.0,
+                vy: 0.0,
+            },
+            health: Health::new(2),
+            immunity_frames: 0,
+            shy: false,
+            frame: 0,
+        }
+    }
+
+    // Changes the velocity towards the wanted one by at most the max acceleration
+    fn steer(&mut self, wanted_vx: f32, wanted_vy: f32) {
+        let (dvx, dvy) = (wanted_vx - self.bb.vx, wanted_vy - self.bb.vy);
+        let length = dvx.hypot(dvy);
+        let scale = if length > MAX_ACCELERATION {
+            MAX_ACCELERATION / length
+        } else {
+            1.0
+        };
+        self.bb.vx += dvx * scale;
+        self.bb.vy += dvy * scale;
+    }
+}
+
+impl Enemy for Wisp {
+    fn bb(&self) -> &BoundingBox {
+        &self.bb
+    }
+
+    fn update(&mut self, ctx: &mut EnemyUpdateContext) -> Vec<EnemyUpdateResult> {
+        self.immunity_frames = self.immunity_frames.saturating_sub(1);
+        self.frame = ctx.frame;
+
+        let center = self.bb.center();
+        let target = ctx.player_bb.center();
+        let (dx, dy) = (target.x - center.x, target.y - center.y);
+        let distance = dx.hypot(dy);
+        let player_faces_wisp = match ctx.player_dir {
+            Dir::Left => dx > 0.0,
+            Dir::Right => dx < 0.0,
+        };
+        self.shy = player_faces_wisp && distance <= MAX_SPOT_RADIUS;
+
+        if distance > MAX_SPOT_RADIUS || distance == 0.0 {
+            self.bb.vx *= DRAG;
+            self.bb.vy *= DRAG;
+        } else if self.shy {
+            self.steer(
+                -dx / distance * RETREAT_SPEED,
+                -dy / distance * RETREAT_SPEED,
+            );
+        } else {
+            self.steer(dx / distance * MAX_SPEED, dy / distance * MAX_SPEED);
+        }
+
+        // Goes right through the walls
+        self.bb.x += self.bb.vx;
+        self.bb.y += self.bb.vy;
+
+        vec![]
+    }
+
+    fn should_remove(&self) -> bool {
+        self.health.current == 0
+    }
+
+    fn get_health(&self) -> Health {
+        self.health
+    }
+
+    fn maybe_got_hit(&mut self, hit: EnemyHit) -> EnemyHitResult {
+        if self.immunity_frames > 0 {
+            return EnemyHitResult::DidNotHit;
+        }
+
+        self.health.decrease();
+        self.immunity_frames = IMMUNITY_FRAMES;
+        let center = self.bb.center();
+        let (dx, dy) = (center.x - hit.from.x, center.y - hit.from.y);
+        let length = dx.hypot(dy).max(0.01);
+        self.bb.vx = dx / length * HIT_KNOCKBACK;
+        self.bb.vy = dy / length * HIT_KNOCKBACK;
+        EnemyHitResult::GotHit
+    }
+
+    fn resistance(&self, damage_type: DamageType) -> Resistance {
+        match damage_type {
+            // Thrown things fly right through it
+            DamageType::Projectile => Resistance::Immune,
+            _ => Resistance::Normal,
+        }
+    }
+
+    fn apply_impulse(&mut self, vx: f32, vy: f32) {
+        self.bb.vx += vx;
+        self.bb.vy += vy;
+    }
+
+    fn fragment_color(&self) -> [f32; 4] {
+        CORE_COLOR
+    }
+
+    fn light_radius(&self) -> Option<f32> {
+        Some(if self.shy {
+            SHY_LIGHT_RADIUS
+        } else {
+            LIGHT_RADIUS
+        })
+    }
+
+    fn maybe_damage_player(&self) -> Option<u32> {
+        Some(1)
+    }
+
+    // There is no sprite for the wisp, it is a glowing square with a flickering halo
+    fn draw(&self, renderer: &mut Renderer) {
+        let pixel = 1.0 / 16.0;
+        let bb = self.bb;
+        let mut alpha = if self.shy { 0.4 } else { 1.0 };
+        if self.immunity_frames % 8 >= 4 {
+            alpha *= 0.5;
+        }
+        let with_alpha = |[r, g, b, a]: [f32; 4]| [r, g, b, a * alpha];
+        let center: Pos = bb.center();
+
+        let glow = bb.w + pixel * (2.0 + (self.frame / 6 % 3) as f32 * 2.0);
+        renderer.draw_rect(
+            center.x - glow / 2.0,
+            center.y - glow / 2.0,
+            glow,
+            glow,
+            with_alpha(GLOW_COLOR),
+        );
+        renderer.draw_rect(
+            bb.x + pixel,
+            bb.y + pixel,
+            bb.w - pixel * 2.0,
+            bb.h - pixel * 2.0,
+            with_alpha(CORE_COLOR),
+        );
+        // Eyes look towards where it is going
+        let look = bb.vx.signum() * pixel;
+        for eye_x in [bb.x + pixel * 2.0, bb.x + bb.w - pixel * 3.0] {
+            renderer.draw_rect(
+                eye_x + look,
+                bb.y + pixel * 3.0,
+                pixel,
+                pixel * 2.0,
+                with_alpha([0.1, 0.15, 0.3, 1.0]),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sound_handler::SoundHandler;
+    use crate::state::enemies::common::HitStrength;
+
+    #[test]
+    fn projectiles_go_through_the_wisp() {
+        let sound_handler = SoundHandler::silent();
+        let mut wisp = Wisp::new(0.0, 0.0);
+        let center = wisp.bb().center();
+
+        let thrown = EnemyHit::new(DamageType::Projectile, HitStrength::Heavy, center);
+        let result = wisp.maybe_got_hit_with_sound(thrown, &sound_handler);
+        assert!(matches!(result, EnemyHitResult::DidNotHit));
+        assert_eq!(wisp.get_health().current, 2);
+
+        let swing = EnemyHit::new(DamageType::Melee, HitStrength::Normal, center);
+        let result = wisp.maybe_got_hit_with_sound(swing, &sound_handler);
+        assert!(matches!(result, EnemyHitResult::GotHit));
+    }
+}
@@ -1,13 +1,14 @@
-use crate::physics::integrate_kinematic;
+use crate::physics::integrate_kinematic_with_platforms;
 use crate::render::TILE_SIZE;
 use crate::state::animation_handler::{AnimationConfig, AnimationConfigResult, AnimationHandler};
 use crate::state::common::{BoundingBox, Dir, Health};
 use crate::state::enemies::Enemy;
-use crate::state::enemies::common::{EnemyHitResult, EnemyHitType, EnemyUpdateResult};
-use crate::state::map_like::GameMap;
+use crate::state::enemies::common::{
+    EnemyHit, EnemyHitResult, EnemyUpdateContext, EnemyUpdateResult,
+};
 
 // Worm moves back and fort
-#[derive(PartialEq)]
+#[derive(Clone, PartialEq)]
 enum WormAnimationState {
     Moving,
 }
@@ -20,6 +21,7 @@ impl AnimationConfig for WormAnimationState {
     }
 }
 
+#[derive(Clone)]
 pub struct Worm {
     bb: BoundingBox,
     animation_handler: AnimationHandler<WormAnimationState>,
@@ -50,7 +52,8 @@ impl Enemy for Worm {
         &self.bb
     }
 
-    fn update(&mut self, map: &GameMap) -> Vec<EnemyUpdateResult> {
+    fn update(&mut self, ctx: &mut EnemyUpdateContext) -> Vec<EnemyUpdateResult> {
+        let map = ctx.map;
         match self.dir {
             Dir::Left => {
                 self.bb.vx = -0.01;
@@ -60,7 +63,7 @@ impl Enemy for Worm {
             }
         }
 
-        let res = integrate_kinematic(map, &self.bb, true);
+        let res = integrate_kinematic_with_platforms(map, &self.bb, true);
         self.bb = res.new_bb;
 
         if res.on_right {
@@ -85,11 +88,24 @@ impl Enemy for Worm {
         Health::new(1)
     }
 
-    fn maybe_got_hit(&mut self, _hit_type: EnemyHitType) -> EnemyHitResult {
+    fn maybe_got_hit(&mut self, _hit: EnemyHit) -> EnemyHitResult {
         self.is_dead = true;
         EnemyHitResult::GotHit
     }
 
+    fn apply_impulse(&mut self, vx: f32, vy: f32) {
+        self.bb.vx += vx;
+        self.bb.vy += vy;
+    }
+
+    fn fragment_color(&self) -> [f32; 4] {
+        [0.84, 0.48, 0.73, 1.0]
+    }
+
+    fn gib_sprite(&self) -> Option<&'static str> {
+        Some("worm_gibs")
+    }
+
     fn maybe_damage_player(&self) -> Option<u32> {
         Some(1)
     }
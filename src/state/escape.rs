@@ -0,0 +1,94 @@
+use crate::render::Renderer;
+use crate::state::common::BoundingBox;
+use crate::state::map_like::Room;
+
+// How many tiles high the view is while escaping
+pub const VIEW_TILES: u32 = 12;
+// Tiles per game update the view and the lava move up
+const SCROLL_SPEED: f32 = 0.02;
+const LAVA_COLOR: [f32; 4] = [0.95, 0.35, 0.08, 0.95];
+const LAVA_TOP_COLOR: [f32; 4] = [1.0, 0.75, 0.2, 1.0];
+
+// An escape from a room marked with Room::is_escape. The view scrolls up through the room by
+// itself and lava rises from the bottom, the player dies in the lava or when the view leaves
+// them behind. Both stop once the view reaches the top of the room.
+#[derive(Clone)]
+pub struct Escape {
+    pub room_index: usize,
+    // The y of the center of the view
+    view_y: f32,
+    // The y of the surface of the lava
+    lava_y: f32,
+    // Where the view stops, at the top of the room
+    top_view_y: f32,
+    room_x: f32,
+    room_w: f32,
+    room_bottom: f32,
+    frames: u32,
+}
+
+impl Escape {
+    /// Starts with the view on the player, or as low as it goes in the room
+    pub fn start(room_index: usize, room: &Room, player_y: f32) -> Self {
+        let (x, y) = room.get_pos();
+        let half_view = VIEW_TILES as f32 / 2.0;
+        let room_bottom = (y + room.h as i32) as f32;
+        let top_view_y = y as f32 + half_view;
+        Escape {
+            room_index,
+            view_y: player_y.min(room_bottom - half_view).max(top_view_y),
+            lava_y: room_bottom,
+            top_view_y,
+            room_x: x as f32,
+            room_w: room.w as f32,
+            room_bottom,
+            frames: 0,
+        }
+    }
+
+    pub fn update(&mut self) {
+        self.frames += 1;
+        self.view_y = (self.view_y - SCROLL_SPEED).max(self.top_view_y);
+        self.lava_y = (self.lava_y - SCROLL_SPEED).max(self.top_view_y + VIEW_TILES as f32 / 2.0);
+    }
+
+    /// The center of the view, the camera follows it instead of the player
+    pub fn view_center(&self) -> (f32, f32) {
+        (self.room_x + self.room_w / 2.0, self.view_y)
+    }
+
+    /// The player is in the lava or fell off the bottom of the view
+    pub fn kills(&self, bb: &BoundingBox) -> bool {
+        bb.y + bb.h > self.lava_y || bb.y > self.view_y + VIEW_TILES as f32 / 2.0
+    }
+
+    pub fn draw(&self, renderer: &mut Renderer) {
+        if self.lava_y >= self.room_bottom {
+            return;
+        }
+        renderer.draw_rect(
+            self.room_x,
+            self.lava_y,
+            self.room_w,
+            self.room_bottom - self.lava_y,
+            LAVA_COLOR,
+        );
+        // The surface bubbles, a row of blobs moving along it
+        let pixel = 1.0 / 16.0;
+        let mut x = self.room_x - (self.frames / 4 % 8) as f32 * pixel;
+        while x < self.room_x + self.room_w {
+            let left = x.max(self.room_x);
+            let right = (x + pixel * 4.0).min(self.room_x + self.room_w);
+            if right > left {
+                renderer.draw_rect(
+                    left,
+                    self.lava_y - pixel,
+                    right - left,
+                    pixel * 2.0,
+                    LAVA_TOP_COLOR,
+                );
+            }
+            x += pixel * 8.0;
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use crate::camera::Camera;
+use crate::pixel_font;
+use crate::render::Renderer;
+
+// How long the results card is up, the doors of the room open when it is gone
+const CARD_FRAMES: u32 = 180;
+// The card slides down from the top of the screen and back up
+const SLIDE_FRAMES: u32 = 15;
+const TITLE_COLOR: [f32; 4] = [0.98, 0.8, 0.2, 1.0];
+const TEXT_COLOR: [f32; 4] = [0.95, 0.95, 0.95, 1.0];
+
+// What happened in a fight, from when the doors of the room closed until it was won
+#[derive(Clone, Default)]
+pub struct FightStats {
+    pub frames: u32,
+    pub damage_taken: u32,
+    pub coins_earned: u32,
+    // Hits landed in a row without taking damage
+    combo: u32,
+    pub best_combo: u32,
+}
+
+#[derive(Clone)]
+struct Card {
+    room_index: usize,
+    stats: FightStats,
+    frames_left: u32,
+}
+
+// Keeps the stats of the fight in the sealed room (an encounter or a boss) and shows them on a
+// card once the fight is won. The room stays sealed while the card is up.
+#[derive(Clone, Default)]
+pub struct FightResults {
+    fight: Option<(usize, FightStats)>,
+    card: Option<Card>,
+}
+
+impl FightResults {
+    pub fn start(&mut self, room_index: usize) {
+        if self.fight.is_none() {
+            self.fight = Some((room_index, FightStats::default()));
+        }
+    }
+
+    /// The fight was lost, for example the player died
+    pub fn abandon(&mut self) {
+        self.fight = None;
+    }
+
+    /// The fight in the room was won, shows the card
+    pub fn finish(&mut self, room_index: usize) {
+        if let Some((fight_room_index, stats)) =
+            self.fight.take_if(|(index, _)| *index == room_index)
+        {
+            self.card = Some(Card {
+                room_index: fight_room_index,
+                stats,
+                frames_left: CARD_FRAMES,
+            });
+        }
+    }
+
+    pub fn landed_hit(&mut self) {
+        if let Some((_, stats)) = &mut self.fight {
+            stats.combo += 1;
+            stats.best_combo = stats.best_combo.max(stats.combo);
+        }
+    }
+
+    pub fn took_damage(&mut self, damage: u32) {
+        if let Some((_, stats)) = &mut self.fight {
+            stats.damage_taken += damage;
+            stats.combo = 0;
+        }
+    }
+
+    pub fn earned_coins(&mut self, coins: u32) {
+        if let Some((_, stats)) = &mut self.fight {
+            stats.coins_earned += coins;
+        }
+    }
+
+    pub fn update(&mut self) {
+        if let Some((_, stats)) = &mut self.fight {
+            stats.frames += 1;
+        }
+        if let Some(card) = &mut self.card {
+            card.frames_left -= 1;
+            if card.frames_left == 0 {
+                self.card = None;
+            }
+        }
+    }
+
+    /// The room is kept sealed until the card is gone
+    pub fn sealed_room(&self) -> Option<usize> {
+        self.card.as_ref().map(|card| card.room_index)
+    }
+
+    pub fn draw(&self, renderer: &mut Renderer, camera: &Camera) {
+        let Some(card) = &self.card else {
+            return;
+        };
+        let stats = &card.stats;
+        let lines = [
+            format!("Time {:.1}s", stats.frames as f32 / 60.0),
+            format!("Damage taken {}", stats.damage_taken),
+            format!("Coins earned {}", stats.coins_earned),
+            format!("Best combo {}", stats.best_combo),
+        ];
+        let title = "Room cleared";
+
+        let pixel_size = 3.0;
+        let padding = 12.0;
+        let line_height = (pixel_font::GLYPH_HEIGHT as f32 + 3.0) * pixel_size;
+        let text_width = lines
+            .iter()
+            .map(|line| pixel_font::text_width(line))
+            .chain([pixel_font::text_width(title)])
+            .max()
+            .unwrap_or(0) as f32
+            * pixel_size;
+        let width = text_width + padding * 2.0;
+        let height = line_height * (lines.len() + 2) as f32 + padding * 2.0 - 3.0 * pixel_size;
+
+        let shown = (CARD_FRAMES - card.frames_left)
+            .min(card.frames_left)
+            .min(SLIDE_FRAMES) as f32
+            / SLIDE_FRAMES as f32;
+        let x = (camera.screen_w - width) / 2.0;
+        let y = camera.screen_h * 0.15 * shown - height * (1.0 - shown);
+
+        renderer.draw_rect_hud(x, y, width, height, [0.05, 0.05, 0.08, 0.85]);
+        let title_width = pixel_font::text_width(title) as f32 * pixel_size;
+        renderer.draw_text_hud(
+            title,
+            x + (width - title_width) / 2.0,
+            y + padding,
+            pixel_size,
+            TITLE_COLOR,
+        );
+        for (i, line) in lines.iter().enumerate() {
+            renderer.draw_text_hud(
+                line,
+                x + padding,
+                y + padding + line_height * (i + 2) as f32,
+                pixel_size,
+                TEXT_COLOR,
+            );
+        }
+    }
+}
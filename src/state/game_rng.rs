@@ -0,0 +1,43 @@
+use rand::rngs::SmallRng;
+use rand::{RngCore, SeedableRng};
+
+// The randomness of everything that affects the game comes from a seeded generator kept in the
+// game state, so that a snapshot of the state plays out the same way every time. Particles are
+// only for looks and keep using the thread rng.
+#[derive(Clone)]
+pub struct GameRng(SmallRng);
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng(SmallRng::seed_from_u64(seed))
+    }
+
+    /// A generator of its own for something spawned at the spot. Enemies are updated in
+    /// parallel, so they can not share the one of the game.
+    pub fn for_spawn(x: f32, y: f32) -> Self {
+        Self::new(((x.to_bits() as u64) << 32) | y.to_bits() as u64)
+    }
+
+    /// A generator for one enemy for one update, the same game seed and update give the same
+    /// numbers no matter in which order the enemies are updated
+    pub fn for_enemy_update(seed: u64, frame: u64, enemy_index: usize) -> Self {
+        Self::new(
+            seed ^ frame.wrapping_mul(0x9e37_79b9_7f4a_7c15)
+                ^ (enemy_index as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f),
+        )
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        self.0.fill_bytes(dst);
+    }
+}
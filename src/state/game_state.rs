@@ -1,14 +1,44 @@
+use super::game_rng::GameRng;
 use super::map_like::{GameMap, MapLike, Room};
 use super::player::{Player, PlayerUpdateResult};
 use crate::camera::Camera;
-use crate::minimap::Minimap;
+use crate::minimap::{Minimap, MinimapRooms};
 use crate::render::Renderer;
-use crate::sound_handler::{Sound, SoundHandler};
-use crate::state::BoundingBox;
+use crate::save;
+use crate::sound_handler::{Sound, SoundHandler, Stinger};
+use crate::state::abilities::Ability;
+use crate::state::ai::{PathEdge, Pathfinder};
+use crate::state::boss_bar::BossBar;
+use crate::state::breadcrumbs::Breadcrumbs;
+use crate::state::camera_focus::{CameraFocus, FocusHint};
+use crate::state::checkpoints::Checkpoints;
+use crate::state::compass::{Compass, CompassTarget};
 use crate::state::enemies::Enemy;
-use crate::state::enemies::common::{EnemyHitResult, EnemyHitType, EnemyUpdateResult};
-use crate::state::item::{Item, ItemInteractionResult};
+use crate::state::enemies::common::{
+    DamageType, EnemyHit, EnemyHitResult, EnemyUpdateContext, EnemyUpdateResult, HitStrength,
+    MAX_SPOT_RADIUS,
+};
+use crate::state::escape::{self, Escape};
+use crate::state::fight_results::FightResults;
+use crate::state::hidden_walls::{HiddenWallHit, HiddenWalls};
+use crate::state::item::{Item, ItemInteractionResult, ItemType};
+use crate::state::lighting::Lighting;
+use crate::state::medals::Medal;
+use crate::state::particle::Particle;
+use crate::state::quest::{QuestLog, QuestProgress};
+use crate::state::room_completion::CompletionTracker;
+use crate::state::room_streaming::RoomStreamer;
+use crate::state::scripting::{RoomScripts, ScriptCommand};
+use crate::state::shop::{ShopData, Shops};
+use crate::state::spatial_grid::SpatialGrid;
+use crate::state::training::Training;
+use crate::state::wallet::Wallet;
+use crate::state::{BaseTile, BoundingBox, Pos};
+use crate::telemetry::{self, log};
 use rand::Rng;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
 
 #[derive(Default, Debug)]
 pub struct InputState {
@@ -18,11 +48,16 @@ pub struct InputState {
     pub down: bool,
 
     pub show_map: bool,
+    pub show_quest_log: bool,
 
     pub swing_pressed: bool,
     pub jump_pressed: bool,
     pub swing_held: bool,
     pub jump_held: bool,
+    pub dash_pressed: bool,
+    pub bomb_pressed: bool,
+    // Ledges are only grabbed while this is held when Tuning::hang_needs_grab_key is set
+    pub grab_held: bool,
 }
 
 pub trait GameState {
@@ -34,11 +69,25 @@ pub trait GameState {
     fn map(&self) -> &dyn MapLike;
     /// Returns (center_x, center_y, view_width, view_height) in tile coords for minimap centering.
     fn update_and_draw_minimap(&mut self, renderer: &mut Renderer, camera: &Camera, draw_big: bool);
+    /// How fast the simulation should currently run compared to real time, used for slow-motion
+    /// effects. The Stage runs fewer fixed updates per frame when this is below 1.0.
+    fn time_scale(&self) -> f64;
+    /// Brings the player back to where the current room was entered and puts its enemies back
+    fn restart_room(&mut self);
+    /// Keeps the high-water marks of the session summary up to date, call once per frame
+    fn record_telemetry(&self);
+    /// Set once the game is over, the next game is made from this seed while the loading screen
+    /// is shown. The game is not updated any more after it is set.
+    fn next_game_seed(&self) -> Option<u64>;
 }
 
 pub struct Editor {
     player: Player,
     pub room: Room,
+    // Tile the rectangle, line or fill tool was pressed down on, the tiles it would paint are
+    // shown as a ghost until the mouse is released
+    pub tile_drag_start: Option<(i32, i32)>,
+    pub tile_preview: Vec<(i32, i32)>,
 }
 
 impl Editor {
@@ -46,7 +95,12 @@ impl Editor {
         let room = Room::new_boxed(0, 0, 5, 5);
         let player = Player::new(2.0, 2.0);
 
-        Editor { player, room }
+        Editor {
+            player,
+            room,
+            tile_drag_start: None,
+            tile_preview: Vec::new(),
+        }
     }
 }
 
@@ -90,57 +144,301 @@ impl GameState for Editor {
         _draw_big: bool,
     ) {
     }
+
+    fn time_scale(&self) -> f64 {
+        1.0
+    }
+
+    fn restart_room(&mut self) {
+        if let Some((x, y)) = self.room.get_start_pos() {
+            self.player.respawn(x as f32, y as f32);
+        }
+    }
+
+    fn record_telemetry(&self) {}
+
+    fn next_game_seed(&self) -> Option<u64> {
+        None
+    }
 }
 
+#[derive(Clone)]
 pub struct Game {
     pub player: Player,
     pub map: GameMap,
     pub items: Vec<Item>,
     pub enemies: Vec<Box<dyn Enemy>>,
+    pub particles: Vec<Particle>,
 
     cur_room_index: Option<usize>,
     prev_room_index: Option<usize>,
     prev_room_show_frames: i32,
     room_change_position: (i32, i32),
 
+    encounter_room_index: Option<usize>,
+    cleared_encounter_rooms: HashSet<usize>,
+    // The escape going on in the current room and the rooms already escaped from
+    pub escape: Option<Escape>,
+    pub training: Training,
+    escaped_rooms: HashSet<usize>,
+    pub lighting: Lighting,
+
+    pub wallet: Wallet,
+    pub shops: Shops,
+    // Shop prices go up the further the player has got
+    visited_rooms: HashSet<usize>,
+    // The game update the current room was entered on, clearing a room is timed from it
+    room_entered_frame: u64,
+    // The best medal ever got for each room of the map, see award_medal
+    room_medals: HashMap<usize, Medal>,
+    pub quest_log: QuestLog,
+    pub show_quest_log: bool,
+    pub compass: Compass,
+    pub completion: CompletionTracker,
+    pub hidden_walls: HiddenWalls,
+    pub breadcrumbs: Breadcrumbs,
+    pub show_breadcrumbs: bool,
+    // Items far from the player are not updated until the player comes closer
+    pub sleep_far_items: bool,
+    pub room_streamer: RoomStreamer,
+    room_scripts: RoomScripts,
+    // Rooms whose doors a script has locked
+    script_locked_rooms: HashSet<usize>,
+    // Rooms sealed by the boss in them until it dies
+    boss_locked_rooms: HashSet<usize>,
+    pub fight_results: FightResults,
+    // Instead of showing the whole room the camera follows the player
+    pub camera_follows_player: bool,
+    pub boss_bar: BossBar,
+    // Where the player respawns after dying, the spot they entered the current room from or the
+    // last checkpoint flag they touched
+    checkpoint: (f32, f32),
+    pub checkpoints: Checkpoints,
+    dead_frames: u32,
+
+    // Slow-motion requested by gameplay, lasts for the given amount of game updates
+    time_scale: f64,
+    time_scale_frames: u32,
+    // Screen shake (amount, frames) requested by gameplay, passed on to the camera
+    shake: Option<(f32, u32)>,
+    // Set when the player goes through a door to another room, the camera slides over
+    room_slide: bool,
+    // Things other than the player for the camera to frame, like the final blow of a fight
+    camera_focus: CameraFocus,
+    // The game as it was when the boss fight in the current room started, restored when the
+    // player retries the fight after dying in it. Taken on the update after the room is sealed.
+    boss_retry: Option<Box<Game>>,
+    boss_fight_started: bool,
+
+    pathfinder: Pathfinder,
+
     minimap: Minimap,
+
+    rng: GameRng,
+    seed: u64,
+    // Game updates since the game started
+    frame: u64,
+    // Drawn from rng on game over, see GameState::next_game_seed
+    next_game_seed: Option<u64>,
+    // Health the player took from the enemies on this update, for the training room readout
+    damage_dealt: u32,
 }
 
 const ROOM_TRANSITION_FRAMES: i32 = 30;
+// How long the camera takes to slide to the next room
+const ROOM_SLIDE_FRAMES: u32 = 24;
+// Below this many active enemies the thread pool overhead is not worth it
+const PARALLEL_ENEMY_UPDATE_THRESHOLD: usize = 16;
+const ENCOUNTER_REWARD_COINS: u32 = 5;
+const ENCOUNTER_CLEAR_SLOW_MOTION: (f64, u32) = (0.3, 20);
+const PLAYER_DEATH_SLOW_MOTION: (f64, u32) = (0.3, 40);
+const KILL_CAM_SLOW_MOTION: (f64, u32) = (0.25, 45);
+// How many tiles wide the view is while the kill cam is on
+const KILL_CAM_VIEW_TILES: u32 = 12;
+// The camera shows a boss that sealed its room for a moment before the fight
+const BOSS_FOCUS: (u32, u32) = (14, 60);
+// Where a room script asks the camera to look, (view tiles, frames)
+const SCRIPT_FOCUS: (u32, u32) = (16, 90);
+const COINS_PER_EXTRA_LIFE: u32 = 50;
+// Enemies that notice the player alert their pack within this radius
+const ALERT_RADIUS: f32 = 7.0;
+const HIDDEN_WALL_FRAGMENT_COLOR: [f32; 4] = [0.42, 0.45, 0.55, 1.0];
+const LOCKED_DOOR_FRAGMENT_COLOR: [f32; 4] = [0.55, 0.38, 0.2, 1.0];
+const CRACKED_TILE_FRAGMENT_COLOR: [f32; 4] = [0.5, 0.48, 0.45, 1.0];
+// Flood filling a locked door stops here so that a badly placed door can not open the level
+const MAX_LOCKED_DOOR_SIZE: usize = 32;
+// Chance that a broken cracked tile drops a coin
+const CRACKED_TILE_COIN_CHANCE: f64 = 0.25;
+// How many gibs a killed enemy leaves behind
+const GIBS_PER_ENEMY: std::ops::RangeInclusive<u32> = 2..=4;
+// How many tiles wide the view is when the camera follows the player
+const FOLLOW_VIEW_TILES: u32 = 24;
+// Tiles kept around the targets when the camera frames more than the player, see camera_targets
+const TARGETS_MARGIN_TILES: f32 = 4.0;
+// Items further than this from the player in either direction sleep, see sleep_far_items
+const ITEM_WAKE_DISTANCE: f32 = 24.0;
+// (vx, vy) given to a stomped enemy, away from the player
+const STOMP_KNOCKBACK: (f32, f32) = (0.06, -0.08);
+// (vx, vy) given to an enemy hit by a thrown item, in the direction the item was flying
+const PROJECTILE_KNOCKBACK: (f32, f32) = (0.05, -0.06);
+// Tiles and enemies this close to a bomb that goes off are hit
+const EXPLOSION_RADIUS: f32 = 1.6;
+const EXPLOSION_KNOCKBACK: (f32, f32) = (0.08, -0.1);
+// Screen shakes (amount in tiles, frames)
+const STOMP_SHAKE: (f32, u32) = (0.08, 8);
+const PLAYER_HIT_SHAKE: (f32, u32) = (0.2, 16);
+const BOX_BREAK_SHAKE: (f32, u32) = (0.06, 6);
+const EXPLOSION_SHAKE: (f32, u32) = (0.15, 12);
+// How long the player stays dead before respawning or the game over is shown
+const RESPAWN_DELAY_FRAMES: u32 = 90;
+// Game over restarts with a new map after this or when jump is pressed
+const GAME_OVER_FRAMES: u32 = 300;
+// How long the player stays dead when a retry of the boss fight is offered
+const RETRY_OFFER_FRAMES: u32 = 240;
+
+// Hits the enemy with a hit of the player, the health it lost is added to damage_dealt
+fn hit_enemy(
+    enemy: &mut Box<dyn Enemy>,
+    hit: EnemyHit,
+    sound_handler: &SoundHandler,
+    damage_dealt: &mut u32,
+) -> EnemyHitResult {
+    let health = enemy.get_health().current;
+    let result = enemy.maybe_got_hit_with_sound(hit, sound_handler);
+    *damage_dealt += health.saturating_sub(enemy.get_health().current);
+    result
+}
 
 impl Game {
     pub fn new() -> Game {
-        let map = GameMap::new_random();
+        Game::new_with_seed(rand::random())
+    }
+
+    /// The same seed makes the same map, and the same inputs then play out the same way
+    pub fn new_with_seed(seed: u64) -> Game {
+        let mut rng = GameRng::new(seed);
+        let map = GameMap::new_random(&mut rng);
         let pos = map.player_start_pos();
         let player = Player::new(pos.0, pos.1);
         let enemies = map.get_enemies_from_templates();
+        let start_room_index = map
+            .get_room_at(pos.0 + 0.5, pos.1 + 0.5)
+            .map(|(index, _)| index);
+        let quest_log = QuestLog::new(&map, start_room_index);
 
-        // Add some random items to the map
-        let mut items = vec![];
+        // Add the containers placed in the rooms, the shop wares and some random items to the map
+        let mut items = map.get_items_from_templates();
+        let (shops, wares) =
+            Shops::open(ShopData::load_from_file(), &map.get_shop_slots(), &mut rng);
+        items.extend(wares);
+        let template_item_count = items.len();
         for _ in 0..10000 {
             let (min_x, min_y, width, height) = map.get_bounds();
-            let mut rng = rand::rng();
             let x = rng.random_range(min_x..min_x + width);
             let y = rng.random_range(min_y..min_y + height);
 
             if !map.is_solid_at_tile(x, y) {
-                items.push(Item::new_random(x as f32 + 0.5, y as f32 + 0.5));
+                items.push(Item::new_random(x as f32 + 0.5, y as f32 + 0.5, &mut rng));
             }
-            if items.len() > 50 {
+            if items.len() - template_item_count > 50 {
                 break;
             }
         }
 
+        // What there is to do in each room for the completion tracking
+        let mut coins_per_room = vec![0; map.rooms.len()];
+        let mut enemies_per_room = vec![0; map.rooms.len()];
+        for item in items.iter().filter(|item| item.is_coin()) {
+            let center = item.bb().center();
+            if let Some((index, _)) = map.get_room_at(center.x, center.y) {
+                coins_per_room[index] += 1;
+            }
+        }
+        for enemy in &enemies {
+            let center = enemy.bb().center();
+            if let Some((index, _)) = map.get_room_at(center.x, center.y) {
+                enemies_per_room[index] += 1;
+            }
+        }
+        let hidden_walls = HiddenWalls::new(&map);
+        let mut secrets_per_room = vec![0; map.rooms.len()];
+        for wall in hidden_walls.walls() {
+            let (x, y) = wall[0];
+            if let Some((index, _)) = map.get_room_at(x as f32 + 0.5, y as f32 + 0.5) {
+                secrets_per_room[index] += 1;
+            }
+        }
+        let completion =
+            CompletionTracker::new(&coins_per_room, &enemies_per_room, &secrets_per_room);
+
+        let progress = save::progress();
+        let room_medals = map
+            .rooms
+            .iter()
+            .enumerate()
+            .filter_map(|(index, room)| {
+                let medal = progress.medals.get(room.file_name.as_ref()?)?;
+                Some((index, *medal))
+            })
+            .collect();
+
+        let room_scripts = RoomScripts::new(&map.rooms);
+        let training = Training::new(&map);
+        let checkpoints = Checkpoints::new(&map.get_checkpoints());
+
         Game {
             player,
             map,
             items,
             enemies,
+            particles: Vec::new(),
             cur_room_index: None,
             prev_room_index: None,
             prev_room_show_frames: 0,
             room_change_position: (0, 0),
+            encounter_room_index: None,
+            cleared_encounter_rooms: HashSet::new(),
+            escape: None,
+            training,
+            lighting: Lighting::default(),
+            escaped_rooms: HashSet::new(),
+            wallet: Wallet::default(),
+            shops,
+            visited_rooms: HashSet::new(),
+            room_entered_frame: 0,
+            room_medals,
+            quest_log,
+            show_quest_log: false,
+            compass: Compass::default(),
+            completion,
+            hidden_walls,
+            breadcrumbs: Breadcrumbs::default(),
+            show_breadcrumbs: false,
+            sleep_far_items: true,
+            room_streamer: RoomStreamer::default(),
+            room_scripts,
+            script_locked_rooms: HashSet::new(),
+            boss_locked_rooms: HashSet::new(),
+            fight_results: FightResults::default(),
+            camera_follows_player: false,
+            boss_bar: BossBar::default(),
+            pathfinder: Pathfinder::default(),
+            checkpoint: pos,
+            checkpoints,
+            dead_frames: 0,
+            time_scale: 1.0,
+            time_scale_frames: 0,
+            shake: None,
+            room_slide: false,
+            camera_focus: CameraFocus::default(),
+            boss_retry: None,
+            boss_fight_started: false,
             minimap: Minimap::new(),
+            rng,
+            seed,
+            frame: 0,
+            next_game_seed: None,
+            damage_dealt: 0,
         }
     }
 
@@ -157,10 +455,460 @@ impl Game {
             self.room_change_position,
         )
     }
+
+    /// Shakes the screen by the amount (in tiles) for the given amount of updates. Overlapping
+    /// shakes keep the strongest amount and the longest duration.
+    pub fn request_shake(&mut self, (amount, frames): (f32, u32)) {
+        self.shake = match self.shake {
+            Some((old_amount, old_frames)) => {
+                Some((amount.max(old_amount), frames.max(old_frames)))
+            }
+            None => Some((amount, frames)),
+        };
+    }
+
+    /// Slows the game down for the given amount of updates. Overlapping requests keep the slowest
+    /// time scale and the longest duration.
+    pub fn request_slow_motion(&mut self, time_scale: f64, frames: u32) {
+        if self.time_scale_frames == 0 {
+            self.time_scale = time_scale;
+        } else {
+            self.time_scale = self.time_scale.min(time_scale);
+        }
+        self.time_scale_frames = self.time_scale_frames.max(frames);
+    }
+
+    // Slows the time down and zooms in on the final blow of a fight
+    fn start_kill_cam(&mut self, pos: Pos) {
+        self.camera_focus.interrupt(FocusHint {
+            pos,
+            view_tiles: KILL_CAM_VIEW_TILES,
+            frames: KILL_CAM_SLOW_MOTION.1,
+        });
+        self.request_slow_motion(KILL_CAM_SLOW_MOTION.0, KILL_CAM_SLOW_MOTION.1);
+    }
+
+    pub fn quest_progress(&self) -> QuestProgress<'_> {
+        QuestProgress {
+            cur_room_index: self.cur_room_index,
+            cleared_encounter_rooms: &self.cleared_encounter_rooms,
+            coins: self.wallet.balance(),
+            rooms_completed: self.completion.completed_rooms().len(),
+            room_count: self.completion.room_count(),
+            cur_room_progress: self
+                .cur_room_index
+                .and_then(|index| self.completion.progress(index)),
+            secrets: self.completion.secrets(),
+        }
+    }
+
+    /// What the compass points at: a ping, the room of the objective or the closest door to a
+    /// room not visited yet
+    pub fn compass_target(&self) -> Option<CompassTarget> {
+        if let Some(ping) = self.compass.get_ping() {
+            return Some(CompassTarget::Ping(ping));
+        }
+
+        let cur_room_index = self.cur_room_index?;
+        if let Some(target_room) = self
+            .quest_log
+            .target_room(&self.map, &self.quest_progress())
+            && target_room != cur_room_index
+        {
+            let (x, y) = self.map.rooms[target_room].get_center();
+            return Some(CompassTarget::Objective(Pos::new(x, y)));
+        }
+
+        let player_center = self.player.bb.center();
+        let neighbours = self.map.room_neighbours();
+        self.visited_rooms
+            .iter()
+            .flat_map(|&room| &neighbours[room])
+            .filter(|(next, _)| !self.visited_rooms.contains(next))
+            .map(|&(_, door_index)| {
+                let door = &self.map.doors[door_index];
+                Pos::new(door.x as f32 + 0.5, door.y as f32 + 0.5)
+            })
+            .min_by(|a, b| {
+                let dist = |pos: &Pos| {
+                    (pos.x - player_center.x).powi(2) + (pos.y - player_center.y).powi(2)
+                };
+                dist(a).total_cmp(&dist(b))
+            })
+            .map(CompassTarget::UnexploredDoor)
+    }
+
+    pub fn cur_room_index(&self) -> Option<usize> {
+        self.cur_room_index
+    }
+
+    pub fn rooms_visited(&self) -> u32 {
+        self.visited_rooms.len() as u32
+    }
+
+    /// Hash of what decides how the game plays out from here on, two games with the same hash
+    /// are (most likely) in the same state. Particles and the HUD are left out.
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        let mut hash_bb = |bb: &BoundingBox| {
+            for value in [bb.x, bb.y, bb.w, bb.h, bb.vx, bb.vy] {
+                value.to_bits().hash(&mut hasher);
+            }
+        };
+        hash_bb(&self.player.bb);
+        for enemy in &self.enemies {
+            hash_bb(enemy.bb());
+        }
+        for item in &self.items {
+            hash_bb(item.bb());
+        }
+        self.player.health.current.hash(&mut hasher);
+        self.player.lives.hash(&mut hasher);
+        for enemy in &self.enemies {
+            enemy.get_health().current.hash(&mut hasher);
+        }
+        self.wallet.balance().hash(&mut hasher);
+        self.cur_room_index.hash(&mut hasher);
+        self.next_game_seed.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// The player died in a boss fight and can start it over, see boss_retry
+    pub fn can_retry_boss(&self) -> bool {
+        self.boss_retry.is_some() && self.player.death_animation_finished()
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.boss_retry.is_none()
+            && self.player.lives == 0
+            && self.player.death_animation_finished()
+            && self.dead_frames >= RESPAWN_DELAY_FRAMES
+    }
+
+    // 0.0 when the game over starts, 1.0 when the game is about to restart
+    pub fn game_over_progress(&self) -> f32 {
+        if !self.is_game_over() {
+            return 0.0;
+        }
+        (self.dead_frames - RESPAWN_DELAY_FRAMES) as f32
+            / (GAME_OVER_FRAMES - RESPAWN_DELAY_FRAMES) as f32
+    }
+
+    fn room_has_enemies(&self, room_index: usize) -> bool {
+        self.enemies.iter().any(|enemy| {
+            let center = enemy.bb().center();
+            matches!(self.map.get_room_at(center.x, center.y), Some((index, _)) if index == room_index)
+        })
+    }
+
+    // Completing a room with a par time soon enough after entering it gets a medal, the best one of
+    // each room is kept in the save
+    fn award_medal(&mut self, room_index: usize) {
+        let room = &self.map.rooms[room_index];
+        let Some(file_name) = &room.file_name else {
+            return;
+        };
+        if room.par_seconds == 0 {
+            return;
+        }
+        let frames = self.frame - self.room_entered_frame;
+        let Some(medal) = Medal::for_clear_time(frames, room.par_seconds) else {
+            log!("Room {} cleared in {} frames, no medal", file_name, frames);
+            return;
+        };
+        log!(
+            "Room {} cleared in {} frames, {:?}",
+            file_name,
+            frames,
+            medal
+        );
+        let best = *save::progress_mut()
+            .medals
+            .entry(file_name.clone())
+            .and_modify(|best| *best = medal.max(*best))
+            .or_insert(medal);
+        self.room_medals.insert(room_index, best);
+    }
+
+    // What the following camera keeps in view, the player and the living bosses in their room
+    fn camera_targets(&self) -> Vec<Pos> {
+        let mut targets = vec![self.player.bb.center()];
+        if let Some(cur_room_index) = self.cur_room_index {
+            targets.extend(
+                self.enemies
+                    .iter()
+                    .filter(|enemy| {
+                        enemy.boss_info().is_some() && !enemy.get_health().is_empty()
+                    })
+                    .map(|enemy| enemy.bb().center())
+                    .filter(|center| {
+                        matches!(self.map.get_room_at(center.x, center.y), Some((index, _)) if index == cur_room_index)
+                    }),
+            );
+        }
+        targets
+    }
+
+    // (coins, enemies) left in the room
+    fn count_left_in_room(&self, room_index: usize) -> (u32, u32) {
+        let in_room = |bb: &BoundingBox| {
+            let center = bb.center();
+            matches!(self.map.get_room_at(center.x, center.y), Some((index, _)) if index == room_index)
+        };
+        let coins = self
+            .items
+            .iter()
+            .filter(|item| item.is_coin() && in_room(item.bb()))
+            .count();
+        let enemies = self
+            .enemies
+            .iter()
+            .filter(|enemy| in_room(enemy.bb()))
+            .count();
+        (coins as u32, enemies as u32)
+    }
+
+    // Calls the hook of the room script and does what the script asked for
+    fn run_room_script(&mut self, room_index: usize, hook: &str, args: impl rhai::FuncArgs) {
+        let (room_x, room_y) = self.map.rooms[room_index].get_pos();
+        for command in self.room_scripts.call(room_index, hook, args) {
+            match command {
+                ScriptCommand::SpawnItem { item_type, x, y } => {
+                    self.items
+                        .push(Item::new(room_x as f32 + x, room_y as f32 + y, item_type))
+                }
+                ScriptCommand::OpenDoors => {
+                    self.script_locked_rooms.remove(&room_index);
+                }
+                ScriptCommand::LockDoors => {
+                    self.script_locked_rooms.insert(room_index);
+                }
+                ScriptCommand::FocusCamera { x, y } => self.camera_focus.push(FocusHint {
+                    pos: Pos::new(room_x as f32 + x, room_y as f32 + y),
+                    view_tiles: SCRIPT_FOCUS.0,
+                    frames: SCRIPT_FOCUS.1,
+                }),
+            }
+        }
+    }
+
+    // A tile of the type next to the player
+    fn tile_touching_player(&self, tile: BaseTile) -> Option<(i32, i32)> {
+        let bb = &self.player.bb;
+        let margin = 0.05;
+        let (min_x, max_x) = (
+            (bb.x - margin).floor() as i32,
+            (bb.x + bb.w + margin).floor() as i32,
+        );
+        let (min_y, max_y) = (
+            (bb.y - margin).floor() as i32,
+            (bb.y + bb.h + margin).floor() as i32,
+        );
+        (min_y..=max_y)
+            .flat_map(|y| (min_x..=max_x).map(move |x| (x, y)))
+            .find(|&(x, y)| self.map.get_at(x, y).0 == tile)
+    }
+
+    // Opens the whole lock the tile is part of, a locked door or an ability lock
+    fn open_lock(&mut self, start: (i32, i32), sound_handler: &SoundHandler) {
+        let tile = self.map.get_at(start.0, start.1).0;
+        let mut lock = vec![start];
+        let mut i = 0;
+        while i < lock.len() && lock.len() < MAX_LOCKED_DOOR_SIZE {
+            let (x, y) = lock[i];
+            for next in [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)] {
+                if self.map.get_at(next.0, next.1).0 == tile && !lock.contains(&next) {
+                    lock.push(next);
+                }
+            }
+            i += 1;
+        }
+        log!("{:?} opened at {:?}", tile, start);
+        sound_handler.play(Sound::Clang);
+        let color = Ability::ALL
+            .into_iter()
+            .find(|ability| ability.lock_tile() == Some(tile))
+            .map_or(LOCKED_DOOR_FRAGMENT_COLOR, Ability::color);
+        for &(x, y) in &lock {
+            self.map.set_base(x, y, BaseTile::Empty);
+            self.particles.extend(Particle::fragments(
+                x as f32 + 0.5,
+                y as f32 + 0.5,
+                0.0,
+                0.0,
+                color,
+            ));
+        }
+    }
+
+    // Breaks the cracked tiles and the bomb walls and hurts the enemies around the bomb
+    fn explode(
+        &mut self,
+        pos: Pos,
+        attacked_enemies: &mut Vec<usize>,
+        sound_handler: &SoundHandler,
+    ) {
+        sound_handler.play(Sound::Land);
+        self.request_shake(EXPLOSION_SHAKE);
+        self.particles.extend(Particle::dust_puff(pos.x, pos.y));
+        self.particles.extend(Particle::fragments(
+            pos.x,
+            pos.y,
+            0.0,
+            0.0,
+            Ability::Bomb.color(),
+        ));
+        let reach = EXPLOSION_RADIUS.ceil() as i32;
+        let (center_x, center_y) = (pos.x.floor() as i32, pos.y.floor() as i32);
+        for y in center_y - reach..=center_y + reach {
+            for x in center_x - reach..=center_x + reach {
+                let distance = (x as f32 + 0.5 - pos.x).hypot(y as f32 + 0.5 - pos.y);
+                if distance > EXPLOSION_RADIUS {
+                    continue;
+                }
+                match self.map.get_at(x, y).0 {
+                    BaseTile::Cracked => self.break_cracked_tile((x, y), sound_handler),
+                    BaseTile::BombWall => self.open_lock((x, y), sound_handler),
+                    _ => {}
+                }
+            }
+        }
+        for (enemy_index, enemy) in self.enemies.iter_mut().enumerate() {
+            let center = enemy.bb().center();
+            if (center.x - pos.x).hypot(center.y - pos.y) > EXPLOSION_RADIUS {
+                continue;
+            }
+            let hit = EnemyHit::new(DamageType::Explosion, HitStrength::Heavy, center).from(pos);
+            if let EnemyHitResult::GotHit =
+                hit_enemy(enemy, hit, sound_handler, &mut self.damage_dealt)
+            {
+                attacked_enemies.push(enemy_index);
+                self.fight_results.landed_hit();
+                let dir = if center.x < pos.x { -1.0 } else { 1.0 };
+                enemy.apply_impulse(dir * EXPLOSION_KNOCKBACK.0, EXPLOSION_KNOCKBACK.1);
+            }
+        }
+    }
+
+    fn break_cracked_tile(&mut self, (x, y): (i32, i32), sound_handler: &SoundHandler) {
+        self.map.set_base(x, y, BaseTile::Empty);
+        sound_handler.play(Sound::Land);
+        let (center_x, center_y) = (x as f32 + 0.5, y as f32 + 0.5);
+        self.particles.extend(Particle::fragments(
+            center_x,
+            center_y,
+            0.0,
+            0.0,
+            CRACKED_TILE_FRAGMENT_COLOR,
+        ));
+        self.particles
+            .extend(Particle::dust_puff(center_x, center_y));
+        if self.rng.random_bool(CRACKED_TILE_COIN_CHANCE) {
+            self.items.push(Item::new_with_velocity(
+                center_x,
+                center_y,
+                0.0,
+                -0.1,
+                ItemType::Coin,
+            ));
+        }
+    }
+
+    fn spawn_encounter_reward(&mut self) {
+        let center = self.player.bb.get_center();
+        for i in 0..ENCOUNTER_REWARD_COINS {
+            let spread = i as f32 / (ENCOUNTER_REWARD_COINS - 1) as f32 - 0.5;
+            self.items.push(Item::new_with_velocity(
+                center.x,
+                center.y - 0.5,
+                spread * 0.1,
+                -0.2,
+                ItemType::Coin,
+            ));
+        }
+    }
 }
 
 impl GameState for Game {
     fn update(&mut self, input: &InputState, sound_handler: &SoundHandler) {
+        if self.next_game_seed.is_some() {
+            return;
+        }
+        // The state the boss fight started in is taken before anything else happens in the update,
+        // so that a retry starts the update from the same spot
+        if std::mem::take(&mut self.boss_fight_started) {
+            self.boss_retry = None;
+            self.boss_retry = Some(Box::new(self.clone()));
+        }
+        let player_was_alive = self.player.health.current > 0;
+        let player_health = self.player.health.current;
+        self.frame += 1;
+        // Bosses that get their last hit this frame get the kill cam
+        let living_bosses: Vec<usize> = self
+            .enemies
+            .iter()
+            .enumerate()
+            .filter(|(_, enemy)| enemy.boss_info().is_some() && !enemy.get_health().is_empty())
+            .map(|(index, _)| index)
+            .collect();
+        self.camera_focus.update();
+        self.time_scale_frames = self.time_scale_frames.saturating_sub(1);
+        self.pathfinder.begin_frame();
+
+        // Dying costs a life and the player is brought back to the checkpoint, without lives
+        // left it is game over
+        if self.player.is_dead() {
+            // Dying in a boss fight offers a retry from the start of the fight, with what the
+            // player had then
+            if input.jump_pressed
+                && self.can_retry_boss()
+                && let Some(snapshot) = self.boss_retry.take()
+            {
+                log!("Retrying the boss fight");
+                *self = (*snapshot).clone();
+                self.boss_retry = Some(snapshot);
+                return;
+            }
+            self.dead_frames += 1;
+            let respawn_frames = if self.boss_retry.is_some() {
+                RETRY_OFFER_FRAMES
+            } else {
+                RESPAWN_DELAY_FRAMES
+            };
+            if self.player.death_animation_finished() && self.dead_frames >= respawn_frames {
+                // The retry was not taken, the game goes on like after any death
+                if self.boss_retry.take().is_some() {
+                    self.dead_frames = RESPAWN_DELAY_FRAMES;
+                }
+                if self.player.lives > 0 {
+                    self.player.lives -= 1;
+                    self.player.respawn(self.checkpoint.0, self.checkpoint.1);
+                    self.dead_frames = 0;
+                    if self.checkpoints.respawn_pos().is_some() {
+                        self.checkpoints.start_respawn_animation();
+                        let feet = self.player.bb.center();
+                        self.particles
+                            .extend(Particle::dust_puff(feet.x, feet.y + self.player.bb.h / 2.0));
+                    }
+                    // The encounter and the boss fight are restarted when the player comes
+                    // back to the room
+                    self.encounter_room_index = None;
+                    self.boss_locked_rooms.clear();
+                    self.fight_results.abandon();
+                    self.camera_focus.clear();
+                    self.escape = None;
+                } else if self.dead_frames >= GAME_OVER_FRAMES || input.jump_pressed {
+                    // The seed comes from the game itself and not the thread rng. Every peer of a
+                    // network session and the shadow game of the desync checker have to take the
+                    // same path here to agree on the next game.
+                    let seed = self.rng.random();
+                    log!("Game over, starting a new game from seed {}", seed);
+                    self.next_game_seed = Some(seed);
+                    return;
+                }
+            }
+        }
+
         let door_bbs: Vec<BoundingBox> = self
             .map
             .doors
@@ -172,7 +920,7 @@ impl GameState for Game {
         // If player is inside one of the doors, we should move the player frame by frame towards
         // being out of the door and towards the current room
         if door_bbs.iter().any(|bb| self.player.bb.overlaps(bb)) {
-            println!("Player overlaps a door");
+            log!("Player overlaps a door");
             let mut push_dir: Option<(i32, i32)> = None;
             'outer: for step in 0..20 {
                 for dir in [(0, 1), (0, -1), (1, 0), (-1, 0)] {
@@ -211,7 +959,7 @@ impl GameState for Game {
             if let Some(push_dir) = push_dir {
                 if push_dir.1 != 0 {
                     self.player.bb.y += self.player.bb.vy;
-                    println!("Pushing player down form a door")
+                    log!("Pushing player down form a door")
                 } else {
                     self.player.bb.x += push_dir.0 as f32 * 0.05;
                     self.player.bb.y += push_dir.1 as f32 * 0.05;
@@ -219,7 +967,7 @@ impl GameState for Game {
                 // self.player.bb.vx = 0.0;
                 // self.player.bb.vy = 0.0;
             } else {
-                println!("No push dir found");
+                log!("No push dir found");
             }
         } else {
             let update_results = self.player.update(input, &self.map, sound_handler);
@@ -229,21 +977,58 @@ impl GameState for Game {
                         self.items.push(item);
                     }
                     PlayerUpdateResult::PickUpItem => {
-                        if let Some(item_match_index) = self
-                            .items
-                            .iter()
-                            .position(|item| item.overlaps(&self.player.bb))
-                        {
+                        // Wares are bought instead of picked up, they can be collected after
+                        if let Some(item) = self.items.iter_mut().find(|item| {
+                            item.for_sale().is_some() && item.overlaps(&self.player.bb)
+                        }) {
+                            let for_sale = item.for_sale().unwrap();
+                            let price = self.shops.price(for_sale, self.visited_rooms.len() as u32);
+                            if self.wallet.spend(price) {
+                                sound_handler.play(Sound::CollectCoin);
+                                self.shops.mark_bought(for_sale.ware);
+                                item.mark_bought();
+                            }
+                        } else if let Some(item_match_index) = self.items.iter().position(|item| {
+                            item.can_be_picked_up() && item.overlaps(&self.player.bb)
+                        }) {
                             let item_match = self.items.remove(item_match_index);
+                            if item_match.is_key() {
+                                sound_handler.play_stinger(Stinger::KeyObtained);
+                            }
                             self.player.set_item(item_match)
                         }
                     }
+                    PlayerUpdateResult::HardLanding { x, y } => {
+                        self.particles.extend(Particle::dust_puff(x, y));
+                    }
                 }
             }
         }
 
+        for particle in &mut self.particles {
+            particle.update();
+        }
+        self.particles.retain(|p| !p.should_remove());
+
         let mut new_items = Vec::new();
+        let mut coins_collected: Vec<Pos> = Vec::new();
+        let mut lives_gained = 0;
+        let mut hearts_gained = 0;
+        let mut abilities_gained = Vec::new();
+        let mut explosions = Vec::new();
+        // Enemies hit by the player this frame, they call their pack for help
+        let mut attacked_enemies: Vec<usize> = Vec::new();
+        let mut broke_something = false;
+        let wake_center = self.player.bb.center();
+        let sleep_far_items = self.sleep_far_items;
         self.items.retain_mut(|item| {
+            let item_center = item.bb().center();
+            if sleep_far_items
+                && ((item_center.x - wake_center.x).abs() > ITEM_WAKE_DISTANCE
+                    || (item_center.y - wake_center.y).abs() > ITEM_WAKE_DISTANCE)
+            {
+                return true;
+            }
             let mut keep_item = true;
             let mut player_damage = 0;
 
@@ -253,7 +1038,9 @@ impl GameState for Game {
                         ItemInteractionResult::RemoveItem => {
                             keep_item = false;
                         }
-                        ItemInteractionResult::IncreaseScore => {}
+                        ItemInteractionResult::IncreaseScore => {
+                            coins_collected.push(item_center);
+                        }
                         ItemInteractionResult::SpawnItem { item } => {
                             new_items.push(item);
                         }
@@ -263,6 +1050,18 @@ impl GameState for Game {
                                 player_damage += 1;
                             }
                         }
+                        ItemInteractionResult::GainLife => {
+                            lives_gained += 1;
+                        }
+                        ItemInteractionResult::Heal => {
+                            hearts_gained += 1;
+                        }
+                        ItemInteractionResult::GainAbility(ability) => {
+                            abilities_gained.push(ability);
+                        }
+                        ItemInteractionResult::Explode { pos } => {
+                            explosions.push(pos);
+                        }
                     }
                 }
             };
@@ -280,23 +1079,51 @@ impl GameState for Game {
             if let Some(swing_info) = self.player.get_swing_info()
                 && item.overlaps_line(&swing_info.pivot, &swing_info.end)
             {
-                let results = item.handle_being_swung(sound_handler);
+                let results = item.handle_being_swung(sound_handler, &mut self.rng);
+                broke_something |= !results.is_empty();
                 handle_item_results(results);
             }
 
             // Item hitting enemies
             if item.can_hit_enemy() {
-                for enemy in &mut self.enemies {
-                    if enemy.bb().overlaps(item.bb()) {
-                        match enemy
-                            .maybe_got_hit_with_sound(EnemyHitType::Projectile, sound_handler)
-                        {
+                for (enemy_index, enemy) in self.enemies.iter_mut().enumerate() {
+                    if enemy.overlaps(item.bb()) {
+                        match hit_enemy(
+                            enemy,
+                            EnemyHit::new(
+                                DamageType::Projectile,
+                                item.hit_strength(),
+                                item.bb().center(),
+                            )
+                            .from(Pos::new(
+                                item.bb().center().x - item.bb().vx,
+                                item.bb().center().y - item.bb().vy,
+                            )),
+                            sound_handler,
+                            &mut self.damage_dealt,
+                        ) {
                             EnemyHitResult::GotHit => {
-                                item.slow_down(0.5);
-                                // Projectile slows down here?
+                                attacked_enemies.push(enemy_index);
+                                self.fight_results.landed_hit();
+                                let (vx, vy) = (item.bb().vx, item.bb().vy);
+                                let dir = if vx < 0.0 { -1.0 } else { 1.0 };
+                                let strength = match item.hit_strength() {
+                                    HitStrength::Heavy => 1.5,
+                                    HitStrength::Normal => 1.0,
+                                };
+                                enemy.apply_impulse(
+                                    dir * PROJECTILE_KNOCKBACK.0 * strength,
+                                    PROJECTILE_KNOCKBACK.1 * strength,
+                                );
+                                // Bounces off so that it does not hit the same enemy again on
+                                // the next frames
+                                item.set_v(-vx * 0.3, vy.min(0.0) - 0.05);
                             }
-                            EnemyHitResult::DidNotHit => {
-                                // Projectile does not slow down here?
+                            EnemyHitResult::DidNotHit => {}
+                            EnemyHitResult::Blocked => {
+                                // Bounces back off the shield
+                                let (vx, vy) = (item.bb().vx, item.bb().vy);
+                                item.set_v(-vx * 0.5, vy.min(0.0) - 0.05);
                             }
                         }
                     }
@@ -311,45 +1138,196 @@ impl GameState for Game {
             keep_item
         });
         self.items.extend(new_items);
+        for pos in explosions {
+            self.explode(pos, &mut attacked_enemies, sound_handler);
+        }
+        for ability in abilities_gained {
+            if self.player.abilities.insert(ability) {
+                log!("Player got {}", ability.name());
+            }
+        }
 
-        for enemy in &mut self.enemies {
-            // Skip enemy if not in the current room
-            let enemy_center = enemy.bb().center();
-            if let Some((enemy_room_index, _)) =
-                self.map.get_room_at(enemy_center.x, enemy_center.y)
-                && let Some(cur_room_index) = self.cur_room_index
-            {
-                if enemy_room_index != cur_room_index {
-                    continue;
+        self.fight_results
+            .earned_coins(coins_collected.len() as u32);
+        for pos in coins_collected {
+            self.wallet.collect(pos);
+        }
+        // Coins count once they have flown to the counter
+        let balance_before = self.wallet.balance();
+        let landed = self.wallet.update();
+        lives_gained += (balance_before + landed) / COINS_PER_EXTRA_LIFE
+            - balance_before / COINS_PER_EXTRA_LIFE;
+        for _ in 0..hearts_gained {
+            if self.player.heal() {
+                sound_handler.play(Sound::OneUp);
+            }
+        }
+        for _ in 0..lives_gained {
+            if self.player.add_life() {
+                sound_handler.play(Sound::OneUp);
+            }
+        }
+
+        // Only the enemies in the current room are updated
+        let cur_room_index = self.cur_room_index;
+        let map = &self.map;
+        let enemy_is_active: Vec<bool> = self
+            .enemies
+            .iter()
+            .map(|enemy| {
+                let enemy_center = enemy.bb().center();
+                match (
+                    map.get_room_at(enemy_center.x, enemy_center.y),
+                    cur_room_index,
+                ) {
+                    (Some((enemy_room_index, _)), Some(cur_room_index)) => {
+                        enemy_room_index == cur_room_index
+                    }
+                    _ => false,
+                }
+            })
+            .collect();
+
+        // Enemy updates only read the map, the player and the items, so they can be done in
+        // parallel. The interactions with the player and the world are applied afterwards from
+        // the returned results.
+        let (player_bb, player_dir) = (self.player.bb, self.player.dir);
+        let (items, frame, seed) = (&self.items, self.frame, self.seed);
+        let update_enemy =
+            |(enemy_index, (enemy, is_active)): (usize, (&mut Box<dyn Enemy>, &bool))| {
+                if *is_active {
+                    enemy.update(&mut EnemyUpdateContext {
+                        map,
+                        player_bb,
+                        player_dir,
+                        items,
+                        frame,
+                        rng: GameRng::for_enemy_update(seed, frame, enemy_index),
+                    })
+                } else {
+                    Vec::new()
                 }
+            };
+        let active_enemy_count = enemy_is_active.iter().filter(|b| **b).count();
+        let enemy_update_results: Vec<Vec<EnemyUpdateResult>> =
+            if active_enemy_count >= PARALLEL_ENEMY_UPDATE_THRESHOLD {
+                self.enemies
+                    .par_iter_mut()
+                    .zip(enemy_is_active.par_iter())
+                    .enumerate()
+                    .map(update_enemy)
+                    .collect()
             } else {
+                self.enemies
+                    .iter_mut()
+                    .zip(enemy_is_active.iter())
+                    .enumerate()
+                    .map(update_enemy)
+                    .collect()
+            };
+
+        let mut stomped = false;
+        // Added after the dead enemies are removed, the indexes of the enemies are used until then
+        let mut new_enemies = Vec::new();
+        let player_in_door = self
+            .map
+            .doors
+            .iter()
+            .any(|door| self.player.bb.overlaps(&door.bb()));
+        for (enemy_index, ((enemy, update_results), is_active)) in self
+            .enemies
+            .iter_mut()
+            .zip(enemy_update_results)
+            .zip(enemy_is_active.iter().copied())
+            .enumerate()
+        {
+            if !is_active {
                 continue;
             }
 
-            for result in enemy.update(&self.map) {
+            for result in update_results {
                 match result {
+                    EnemyUpdateResult::SpawnFragments { pos, vx, vy, color } => {
+                        self.particles
+                            .extend(Particle::fragments(pos.x, pos.y, vx, vy, color));
+                    }
+                    EnemyUpdateResult::ShootItem { item } => self.items.push(item),
                     EnemyUpdateResult::SpawnItemThrowTowardsPlayer { mut item } => {
                         let x_diff = (self.player.bb.x - item.bb().x).clamp(-4.0, 4.0);
 
                         item.set_v(x_diff * 0.025, -0.15);
 
-                        item.randomize_direction();
-                        item.randomize_speed();
+                        item.randomize_direction(&mut self.rng);
+                        item.randomize_speed(&mut self.rng);
 
                         self.items.push(item);
                     }
+                    EnemyUpdateResult::SpawnEnemy { enemy } => new_enemies.push(enemy),
+                    // Like the encounters the room is sealed only once the player is clear of
+                    // the doors, so that they do not close on the player
+                    EnemyUpdateResult::LockDoors => {
+                        let center = enemy.bb().center();
+                        if let Some((room_index, _)) = self.map.get_room_at(center.x, center.y)
+                            && cur_room_index == Some(room_index)
+                            && !player_in_door
+                            && self.boss_locked_rooms.insert(room_index)
+                        {
+                            log!("Room {} sealed", room_index);
+                            self.boss_fight_started = true;
+                            self.fight_results.start(room_index);
+                            self.camera_focus.push(FocusHint {
+                                pos: center,
+                                view_tiles: BOSS_FOCUS.0,
+                                frames: BOSS_FOCUS.1,
+                            });
+                        }
+                    }
+                    EnemyUpdateResult::UnlockDoors => {
+                        let center = enemy.bb().center();
+                        if let Some((room_index, _)) = self.map.get_room_at(center.x, center.y)
+                            && self.boss_locked_rooms.remove(&room_index)
+                        {
+                            log!("Room {} unsealed", room_index);
+                            self.boss_retry = None;
+                            sound_handler.play(Sound::EncounterClear);
+                            self.fight_results.finish(room_index);
+                        }
+                    }
                 }
             }
 
-            if enemy.bb().overlaps(&self.player.bb) {
+            if enemy.overlaps(&self.player.bb) {
                 let mut should_hit_player = false;
                 if self.player.check_if_could_stomp(enemy.bb()) {
-                    match enemy.maybe_got_hit_with_sound(EnemyHitType::Stomp, sound_handler) {
-                        EnemyHitResult::DidNotHit => {
+                    match hit_enemy(
+                        enemy,
+                        EnemyHit::new(
+                            DamageType::Stomp,
+                            HitStrength::Normal,
+                            Pos::new(
+                                self.player.bb.get_center().x,
+                                self.player.bb.y + self.player.bb.h,
+                            ),
+                        )
+                        .from(self.player.bb.get_center()),
+                        sound_handler,
+                        &mut self.damage_dealt,
+                    ) {
+                        EnemyHitResult::DidNotHit | EnemyHitResult::Blocked => {
                             should_hit_player = true;
                         }
                         EnemyHitResult::GotHit => {
+                            attacked_enemies.push(enemy_index);
+                            self.fight_results.landed_hit();
                             self.player.apply_stomping(enemy.bb().y);
+                            stomped = true;
+                            // Knock the enemy away from the player
+                            let dir = if enemy.bb().center().x < self.player.bb.get_center().x {
+                                -1.0
+                            } else {
+                                1.0
+                            };
+                            enemy.apply_impulse(dir * STOMP_KNOCKBACK.0, STOMP_KNOCKBACK.1);
                             sound_handler.play(Sound::Clink);
                         }
                     }
@@ -362,40 +1340,232 @@ impl GameState for Game {
                 {
                     sound_handler.play(Sound::PlayerHit);
                     self.player.got_hit(contact_damage);
+                    self.player.knock_back(enemy.bb().center().x);
                 }
             }
 
             if let Some(swing_info) = self.player.get_swing_info()
                 // && enemy.can_be_hit()
-                && enemy.bb().overlaps_line(&swing_info.pivot, &swing_info.end)
+                && enemy.overlaps_line(&swing_info.pivot, &swing_info.end)
             {
-                match enemy.maybe_got_hit_with_sound(EnemyHitType::Swing, sound_handler) {
+                match hit_enemy(
+                    enemy,
+                    EnemyHit::new(DamageType::Melee, HitStrength::Normal, swing_info.end)
+                        .from(self.player.bb.get_center()),
+                    sound_handler,
+                    &mut self.damage_dealt,
+                ) {
                     EnemyHitResult::DidNotHit => {}
+                    // The clang is played by the enemy, but it still noticed the attack
+                    EnemyHitResult::Blocked => attacked_enemies.push(enemy_index),
                     EnemyHitResult::GotHit => {
+                        attacked_enemies.push(enemy_index);
+                        self.fight_results.landed_hit();
                         sound_handler.play(Sound::Clink);
                         // TODO: Maybe play a different sound here than what the stomp plays?
                     }
                 }
             }
         }
-        // Filter the enemies that are dead by enemy.is_dead() value
+
+        // Swinging at a hidden wall cracks it and in the end breaks it open, cracked tiles break
+        // with one swing
+        self.hidden_walls.update();
+        if let Some(swing_info) = self.player.get_swing_info() {
+            let tile = (
+                swing_info.end.x.floor() as i32,
+                swing_info.end.y.floor() as i32,
+            );
+            if self.map.get_at(tile.0, tile.1).0 == BaseTile::Cracked {
+                self.break_cracked_tile(tile, sound_handler);
+            }
+            match self.hidden_walls.hit(tile) {
+                HiddenWallHit::NotHit => {}
+                HiddenWallHit::Cracked => {
+                    sound_handler.play(Sound::Clink);
+                    self.particles
+                        .extend(Particle::dust_puff(swing_info.end.x, swing_info.end.y));
+                }
+                HiddenWallHit::Broke(wall) => {
+                    sound_handler.play(Sound::Land);
+                    for &(x, y) in &wall {
+                        self.map.set_base(x, y, BaseTile::Empty);
+                        self.particles.extend(Particle::fragments(
+                            x as f32 + 0.5,
+                            y as f32 + 0.5,
+                            0.0,
+                            0.0,
+                            HIDDEN_WALL_FRAGMENT_COLOR,
+                        ));
+                    }
+                    let (x, y) = wall[0];
+                    if let Some((room_index, _)) =
+                        self.map.get_room_at(x as f32 + 0.5, y as f32 + 0.5)
+                    {
+                        self.completion.secret_found(room_index);
+                        sound_handler.play(Sound::RoomComplete);
+                        sound_handler.play_stinger(Stinger::SecretFound);
+                    }
+                }
+            }
+        }
+
+        // Touching a locked door with a key in hand opens it, the key is used up
+        if self.player.holds_key()
+            && let Some(tile) = self.tile_touching_player(BaseTile::LockedDoor)
+            && self.player.use_key()
+        {
+            self.open_lock(tile, sound_handler);
+        }
+        if self.player.is_dashing()
+            && let Some(tile) = self.tile_touching_player(BaseTile::DashBarrier)
+        {
+            self.open_lock(tile, sound_handler);
+        }
+
+        // Hazards hurt everyone, the player can lure enemies into them
+        if let Some(damage) = self.map.hazard_damage(&self.player.bb)
+            && self.player.can_be_hit()
+        {
+            sound_handler.play(Sound::PlayerHit);
+            self.player.got_hit(damage);
+        }
+        for (enemy, is_active) in self.enemies.iter_mut().zip(enemy_is_active.iter()) {
+            if *is_active && self.map.hazard_damage(enemy.bb()).is_some() {
+                let center = enemy.bb().center();
+                enemy.maybe_got_hit_with_sound(
+                    EnemyHit::new(DamageType::Hazard, HitStrength::Normal, center)
+                        .from(Pos::new(center.x, center.y + 1.0)),
+                    sound_handler,
+                );
+            }
+        }
+
+        // An enemy that gets hit or spots the player alerts the nearby enemies of its pack
+        let player_center = self.player.bb.get_center();
+        let mut grid = SpatialGrid::new(ALERT_RADIUS);
+        for (enemy_index, enemy) in self.enemies.iter().enumerate() {
+            if enemy_is_active[enemy_index] && enemy.pack().is_some() {
+                grid.insert(enemy_index, enemy.bb().center());
+            }
+        }
+        let spotting_enemies: Vec<usize> = grid
+            .query_radius(player_center, MAX_SPOT_RADIUS)
+            .into_iter()
+            .filter(|&enemy_index| {
+                let center = self.enemies[enemy_index].bb().center();
+                (center.x - player_center.x).hypot(center.y - player_center.y)
+                    <= self.enemies[enemy_index].spot_radius()
+            })
+            .collect();
+        let player_tile = Pathfinder::tile_of(&self.player.bb);
+        for enemy_index in attacked_enemies.into_iter().chain(spotting_enemies) {
+            let enemy = &mut self.enemies[enemy_index];
+            let Some(pack) = enemy.pack() else {
+                continue;
+            };
+            // Already alerted enemies only keep track of the player, they have called for help
+            if enemy.is_alerted() {
+                enemy.alert(player_center);
+                continue;
+            }
+            for other_index in grid.query_radius(enemy.bb().center(), ALERT_RADIUS) {
+                let other = &mut self.enemies[other_index];
+                if other.pack() != Some(pack) {
+                    continue;
+                }
+                // Walkers head to the next jump or drop on the way to the player, or straight at
+                // the player if there is no path
+                let mut target = player_center;
+                if other.follows_paths()
+                    && let Some(path) = self.pathfinder.find_path(
+                        &self.map,
+                        Pathfinder::tile_of(other.bb()),
+                        player_tile,
+                    )
+                    && let Some(step) = path
+                        .iter()
+                        .find(|step| step.edge != PathEdge::Walk)
+                        .or(path.last())
+                {
+                    target = Pos::new(step.x as f32 + 0.5, step.y as f32 + 0.5);
+                }
+                other.alert(target);
+            }
+        }
+
+        // Filter the enemies that are dead by enemy.is_dead() value, they burst into fragments
+        // and leave some gibs behind
+        if let Some(boss) = living_bosses
+            .into_iter()
+            .find(|&index| self.enemies[index].get_health().is_empty())
+        {
+            self.start_kill_cam(self.enemies[boss].bb().center());
+        }
+        let mut last_killed = None;
+        let rng = &mut self.rng;
+        let mut killed = Vec::new();
+        for enemy in self.enemies.iter().filter(|e| e.should_remove()) {
+            let center = enemy.bb().center();
+            last_killed = Some(center);
+            if let Some((room_index, room)) = self.map.get_room_at(center.x, center.y) {
+                let (room_x, room_y) = room.get_pos();
+                killed.push((
+                    room_index,
+                    center.x - room_x as f32,
+                    center.y - room_y as f32,
+                ));
+            }
+            self.particles.extend(Particle::fragments(
+                center.x,
+                center.y,
+                enemy.bb().vx,
+                enemy.bb().vy,
+                enemy.fragment_color(),
+            ));
+            if let Some(sprite) = enemy.gib_sprite() {
+                for _ in 0..rng.random_range(GIBS_PER_ENEMY) {
+                    self.items.push(Item::new_gib(
+                        center.x,
+                        center.y,
+                        enemy.bb().vx + rng.random_range(-0.06..0.06),
+                        enemy.bb().vy - rng.random_range(0.05..0.15),
+                        sprite,
+                        rng,
+                    ));
+                }
+            }
+        }
         self.enemies.retain(|e| !e.should_remove());
+        self.enemies.extend(new_enemies);
+        for (room_index, x, y) in killed {
+            self.run_room_script(room_index, "on_enemy_killed", (x as f64, y as f64));
+        }
 
         // Store the current and previous room as well as how many frames the previous has
         // been the previous. This is used for centering the camera and displaying the "black"
         // around the current room (/ rooms).
 
-        let player_center = self.player.bb.get_center();
         if let Some((room_index, _room)) = self.map.get_room_at(player_center.x, player_center.y)
             && self.cur_room_index != Some(room_index)
         {
+            self.room_slide = self.cur_room_index.is_some();
             self.prev_room_index = self.cur_room_index;
             self.cur_room_index = Some(room_index);
+            self.visited_rooms.insert(room_index);
+            self.room_entered_frame = self.frame;
+            self.room_streamer
+                .update(&self.map, room_index, &mut self.enemies, &mut self.items);
+            self.run_room_script(room_index, "on_room_enter", ());
             self.prev_room_show_frames = ROOM_TRANSITION_FRAMES;
             self.room_change_position = (
                 player_center.x.floor() as i32,
                 player_center.y.floor() as i32,
             );
+            // A checkpoint flag stays the respawn spot until the next one is touched
+            if !self.player.is_dead() && self.checkpoints.respawn_pos().is_none() {
+                self.checkpoint = self.player.last_safe_pos();
+            }
 
             // Set the door closed here if the player is moving up and the door
             // type is up down. This helps in going to a room above
@@ -410,38 +1580,170 @@ impl GameState for Game {
         }
         self.prev_room_show_frames = 0.max(self.prev_room_show_frames - 1);
 
-        // Handle doors
-        if let Some(cur_room_index) = self.cur_room_index {
-            let mut list_of_bools: Vec<bool> = self
-                .enemies
+        self.fight_results.update();
+
+        // Handle encounters, entering an encounter room locks its doors until the enemies
+        // in it are defeated. The encounter starts only once the player is clear of the doors
+        // so that they do not close on the player.
+        if let Some(cur_room_index) = self.cur_room_index
+            && self.encounter_room_index.is_none()
+            && self.map.rooms[cur_room_index].is_encounter
+            && !self.cleared_encounter_rooms.contains(&cur_room_index)
+            && self.room_has_enemies(cur_room_index)
+            && !self
+                .map
+                .doors
                 .iter()
-                .map(|enemy| {
-                    let center = enemy.bb().center();
-                    let enemy_room = self.map.get_room_at(center.x, center.y);
-                    if let Some((index, _)) = enemy_room
-                        && index == cur_room_index
-                    {
-                        return true;
-                    };
-                    false
-                })
-                .collect();
-            list_of_bools.retain(|b| *b);
-            let _room_has_enemies = !list_of_bools.is_empty();
+                .any(|door| self.player.bb.overlaps(&door.bb()))
+        {
+            log!("Encounter started in room {}", cur_room_index);
+            self.encounter_room_index = Some(cur_room_index);
+            self.fight_results.start(cur_room_index);
+        }
+        if let Some(encounter_room_index) = self.encounter_room_index
+            && !self.room_has_enemies(encounter_room_index)
+        {
+            log!("Encounter cleared in room {}", encounter_room_index);
+            self.encounter_room_index = None;
+            self.cleared_encounter_rooms.insert(encounter_room_index);
+            self.fight_results.finish(encounter_room_index);
+            if let Some(pos) = last_killed {
+                self.start_kill_cam(pos);
+            }
+            sound_handler.play(Sound::EncounterClear);
+            self.spawn_encounter_reward();
+            self.request_slow_motion(ENCOUNTER_CLEAR_SLOW_MOTION.0, ENCOUNTER_CLEAR_SLOW_MOTION.1);
+        }
 
-            for door in &mut self.map.doors {
-                // door.update(!room_has_enemies);
-                // TODO: TEMP DOORS ALWAYS OPEN
-                door.update(true);
+        // Handle escapes, once the player is clear of the doors of an escape room the view starts
+        // to scroll up and the lava to rise. Leaving the room alive is escaping it.
+        if let Some(cur_room_index) = self.cur_room_index
+            && self.escape.is_none()
+            && self.map.rooms[cur_room_index].is_escape
+            && !self.escaped_rooms.contains(&cur_room_index)
+            && !self.player.is_dead()
+            && !self
+                .map
+                .doors
+                .iter()
+                .any(|door| self.player.bb.overlaps(&door.bb()))
+        {
+            log!("Escape started in room {}", cur_room_index);
+            self.escape = Some(Escape::start(
+                cur_room_index,
+                &self.map.rooms[cur_room_index],
+                self.player.bb.center().y,
+            ));
+        }
+        if let Some(escape) = &mut self.escape {
+            if self.cur_room_index != Some(escape.room_index) {
+                log!("Escaped from room {}", escape.room_index);
+                self.escaped_rooms.insert(escape.room_index);
+                self.escape = None;
+            } else if !self.player.is_dead() {
+                escape.update();
+                if escape.kills(&self.player.bb) {
+                    self.player.got_hit(self.player.health.current);
+                }
             }
-        } else {
-            for door in &mut self.map.doors {
-                door.update(true)
+        }
+
+        if broke_something {
+            self.request_shake(BOX_BREAK_SHAKE);
+        }
+        if stomped {
+            self.request_shake(STOMP_SHAKE);
+        }
+        if self.player.health.current < player_health {
+            self.request_shake(PLAYER_HIT_SHAKE);
+            self.fight_results
+                .took_damage(player_health - self.player.health.current);
+        }
+        let spawned = self.training.update(
+            &self.player.bb,
+            self.cur_room_index,
+            std::mem::take(&mut self.damage_dealt),
+            player_health.saturating_sub(self.player.health.current),
+        );
+        self.enemies.extend(spawned);
+        let in_dark_room = self
+            .cur_room_index
+            .is_some_and(|index| self.map.rooms[index].is_dark);
+        self.lighting.update(in_dark_room);
+        if player_was_alive && self.player.health.current == 0 {
+            self.request_slow_motion(PLAYER_DEATH_SLOW_MOTION.0, PLAYER_DEATH_SLOW_MOTION.1);
+        }
+
+        // The boss bar shows the first boss in the current room
+        let boss = self.cur_room_index.and_then(|cur_room_index| {
+            self.enemies.iter().find_map(|enemy| {
+                let info = enemy.boss_info()?;
+                let center = enemy.bb().center();
+                let (room_index, _) = self.map.get_room_at(center.x, center.y)?;
+                (room_index == cur_room_index).then(|| (info, enemy.get_health().ratio()))
+            })
+        });
+        if self.boss_bar.update(boss) {
+            sound_handler.play_stinger(Stinger::BossPhase);
+        }
+
+        if let Some(cur_room_index) = self.cur_room_index {
+            let (coins_left, enemies_left) = self.count_left_in_room(cur_room_index);
+            if self
+                .completion
+                .update_room(cur_room_index, coins_left, enemies_left)
+            {
+                sound_handler.play(Sound::RoomComplete);
+                self.award_medal(cur_room_index);
             }
         }
+
+        self.quest_log.update(&QuestProgress {
+            cur_room_index: self.cur_room_index,
+            cleared_encounter_rooms: &self.cleared_encounter_rooms,
+            coins: self.wallet.balance(),
+            rooms_completed: self.completion.completed_rooms().len(),
+            room_count: self.completion.room_count(),
+            cur_room_progress: self
+                .cur_room_index
+                .and_then(|index| self.completion.progress(index)),
+            secrets: self.completion.secrets(),
+        });
+        self.show_quest_log = input.show_quest_log;
+        if !self.player.is_dead()
+            && self.checkpoints.update(&self.player.bb)
+            && let Some(feet) = self.checkpoints.respawn_pos()
+        {
+            self.checkpoint = (feet.x - self.player.bb.w / 2.0, feet.y - self.player.bb.h);
+            sound_handler.play(Sound::OneUp);
+        }
+        self.compass.update(self.player.bb.center());
+        if !self.player.is_dead() {
+            self.breadcrumbs.record(self.player.bb.center());
+        }
+
+        // Handle doors
+        let locked_rooms: Vec<&Room> = self
+            .encounter_room_index
+            .iter()
+            .chain(&self.script_locked_rooms)
+            .chain(&self.boss_locked_rooms)
+            .chain(&self.fight_results.sealed_room())
+            .map(|&index| &self.map.rooms[index])
+            .collect();
+        for door in &mut self.map.doors {
+            door.set_locked(locked_rooms.iter().any(|room| door.is_in_room(room)));
+            door.update(true);
+        }
     }
 
     fn update_camera(&mut self, camera: &mut Camera, zoom_show_all: bool) {
+        if let Some((amount, frames)) = self.shake.take() {
+            camera.shake(amount, frames);
+        }
+        if std::mem::take(&mut self.room_slide) && !zoom_show_all {
+            camera.slide(ROOM_SLIDE_FRAMES);
+        }
         if zoom_show_all {
             let (x, y, w, h) = self.map.get_bounds();
 
@@ -453,6 +1755,41 @@ impl GameState for Game {
                 .min(camera.zoom_to_fit_vertical_tiles(h as u32));
 
             camera.slowly_follow(camera_x, camera_y, camera_zoom);
+        } else if let Some(escape) = &self.escape {
+            let (x, y) = escape.view_center();
+            let zoom = camera.zoom_to_fit_vertical_tiles(escape::VIEW_TILES);
+            camera.slowly_follow(x, y, zoom);
+        } else if let Some(hint) = self.camera_focus.current() {
+            let zoom = camera.zoom_to_fit_horizontal_tiles(hint.view_tiles);
+            camera.slowly_follow(hint.pos.x, hint.pos.y, zoom);
+        } else if self.camera_follows_player {
+            let center = self.player.bb.center();
+            let zoom = camera.zoom_to_fit_horizontal_tiles(FOLLOW_VIEW_TILES);
+            // The view stays in the room the player is in
+            let room_bounds = self.cur_room_index.map(|index| {
+                let room = &self.map.rooms[index];
+                let (x, y) = room.get_pos();
+                BoundingBox {
+                    x: x as f32,
+                    y: y as f32,
+                    w: room.w as f32,
+                    h: room.h as f32,
+                    vx: 0.0,
+                    vy: 0.0,
+                }
+            });
+            let targets = self.camera_targets();
+            if targets.len() > 1 {
+                camera.follow_targets(&targets, TARGETS_MARGIN_TILES, zoom, room_bounds.as_ref());
+            } else {
+                camera.follow(
+                    center.x,
+                    center.y,
+                    self.player.bb.vx,
+                    zoom,
+                    room_bounds.as_ref(),
+                );
+            }
         } else {
             let rooms = self.get_rooms_for_display();
             if let Some(room) = rooms.0 {
@@ -496,9 +1833,63 @@ impl GameState for Game {
                 renderer,
                 camera,
                 &self.map,
-                cur_room_index,
+                MinimapRooms {
+                    current: cur_room_index,
+                    visited: &self.visited_rooms,
+                    completed: self.completion.completed_rooms(),
+                    medals: &self.room_medals,
+                },
                 draw_big,
             )
         }
     }
+
+    fn time_scale(&self) -> f64 {
+        if self.time_scale_frames > 0 {
+            self.time_scale
+        } else {
+            1.0
+        }
+    }
+
+    fn restart_room(&mut self) {
+        self.player.respawn(self.checkpoint.0, self.checkpoint.1);
+        self.boss_retry = None;
+        self.dead_frames = 0;
+        self.encounter_room_index = None;
+        self.boss_locked_rooms.clear();
+        self.fight_results.abandon();
+        self.camera_focus.clear();
+        self.escape = None;
+
+        let Some(room_index) = self.cur_room_index else {
+            return;
+        };
+        let map = &self.map;
+        self.enemies.retain(|enemy| {
+            let center = enemy.bb().center();
+            !matches!(map.get_room_at(center.x, center.y), Some((index, _)) if index == room_index)
+        });
+        self.enemies
+            .extend(self.map.rooms[room_index].get_enemies_from_template());
+    }
+
+    fn record_telemetry(&self) {
+        telemetry::record_game(
+            self.enemies.len(),
+            self.items.len(),
+            self.particles.len(),
+            self.visited_rooms.len(),
+        );
+        // Only the records are kept, so recording them every frame is fine
+        let mut progress = save::progress_mut();
+        progress.most_rooms_visited = progress
+            .most_rooms_visited
+            .max(self.visited_rooms.len() as u32);
+        progress.most_coins = progress.most_coins.max(self.wallet.balance());
+    }
+
+    fn next_game_seed(&self) -> Option<u64> {
+        self.next_game_seed
+    }
 }
@@ -0,0 +1,146 @@
+use crate::render::Renderer;
+use crate::state::map_like::{BaseTile, MapLike};
+use std::collections::{HashMap, HashSet};
+
+const HITS_TO_BREAK: u32 = 3;
+// One swing only counts as one hit even if it stays in the wall for several frames
+const HIT_COOLDOWN_FRAMES: u32 = 20;
+// Flood filling a wall group stops here so that a badly placed wall can not break the level
+const MAX_GROUP_SIZE: usize = 64;
+
+pub enum HiddenWallHit {
+    NotHit,
+    Cracked,
+    /// The tiles of the whole wall that broke
+    Broke(Vec<(i32, i32)>),
+}
+
+// Walls that look like stone but break after a couple of hits, with the secret behind them.
+// Connected hidden wall tiles form one wall that breaks all at once.
+#[derive(Clone)]
+pub struct HiddenWalls {
+    tiles: HashSet<(i32, i32)>,
+    // Hits taken by the wall each tile is part of
+    hits: HashMap<(i32, i32), u32>,
+    cooldown: u32,
+}
+
+impl HiddenWalls {
+    pub fn new(map: &dyn MapLike) -> HiddenWalls {
+        let (x, y, w, h) = map.get_bounds();
+        let tiles = (x..x + w as i32)
+            .flat_map(|tx| (y..y + h as i32).map(move |ty| (tx, ty)))
+            .filter(|&(tx, ty)| map.get_at(tx, ty).0 == BaseTile::HiddenWall)
+            .collect();
+        HiddenWalls {
+            tiles,
+            hits: HashMap::new(),
+            cooldown: 0,
+        }
+    }
+
+    /// The separate walls, each one is a secret to find
+    pub fn walls(&self) -> Vec<Vec<(i32, i32)>> {
+        let mut seen = HashSet::new();
+        let mut walls = Vec::new();
+        let mut tiles: Vec<&(i32, i32)> = self.tiles.iter().collect();
+        tiles.sort();
+        for &tile in tiles {
+            if seen.contains(&tile) {
+                continue;
+            }
+            let wall = self.wall_at(tile);
+            seen.extend(wall.iter().copied());
+            walls.push(wall);
+        }
+        walls
+    }
+
+    fn wall_at(&self, start: (i32, i32)) -> Vec<(i32, i32)> {
+        let mut wall = vec![start];
+        let mut seen = HashSet::from([start]);
+        let mut i = 0;
+        while i < wall.len() && wall.len() < MAX_GROUP_SIZE {
+            let (x, y) = wall[i];
+            for next in [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)] {
+                if self.tiles.contains(&next) && seen.insert(next) {
+                    wall.push(next);
+                }
+            }
+            i += 1;
+        }
+        wall.sort();
+        wall
+    }
+
+    pub fn update(&mut self) {
+        self.cooldown = self.cooldown.saturating_sub(1);
+    }
+
+    pub fn hit(&mut self, tile: (i32, i32)) -> HiddenWallHit {
+        if self.cooldown > 0 || !self.tiles.contains(&tile) {
+            return HiddenWallHit::NotHit;
+        }
+        self.cooldown = HIT_COOLDOWN_FRAMES;
+
+        let wall = self.wall_at(tile);
+        for tile in &wall {
+            *self.hits.entry(*tile).or_insert(0) += 1;
+        }
+        if self.hits[&tile] < HITS_TO_BREAK {
+            return HiddenWallHit::Cracked;
+        }
+
+        for tile in &wall {
+            self.hits.remove(tile);
+            self.tiles.remove(tile);
+        }
+        HiddenWallHit::Broke(wall)
+    }
+
+    /// Untouched walls have a faint crack in them, it grows with every hit
+    pub fn draw(&self, renderer: &mut Renderer) {
+        for &tile in &self.tiles {
+            let wall_hits = self.hits.get(&tile).copied().unwrap_or(0);
+            renderer.draw_from_texture_atlas(
+                "wall_crack",
+                wall_hits.min(HITS_TO_BREAK - 1),
+                false,
+                tile.0 as f32,
+                tile.1 as f32,
+                1.0,
+                1.0,
+                1.0,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::map_like::Room;
+
+    #[test]
+    fn connected_walls_break_together() {
+        let mut room = Room::new_boxed(0, 0, 8, 8);
+        room.set_base_absolute(3, 5, BaseTile::HiddenWall);
+        room.set_base_absolute(3, 6, BaseTile::HiddenWall);
+        room.set_base_absolute(6, 6, BaseTile::HiddenWall);
+
+        let mut walls = HiddenWalls::new(&room);
+        assert_eq!(walls.walls().len(), 2);
+
+        for _ in 1..HITS_TO_BREAK {
+            assert!(matches!(walls.hit((3, 5)), HiddenWallHit::Cracked));
+            // The same swing does not hit again
+            assert!(matches!(walls.hit((3, 6)), HiddenWallHit::NotHit));
+            walls.cooldown = 0;
+        }
+        match walls.hit((3, 6)) {
+            HiddenWallHit::Broke(wall) => assert_eq!(wall, vec![(3, 5), (3, 6)]),
+            _ => panic!("The wall should have broken"),
+        }
+        assert_eq!(walls.walls(), vec![vec![(6, 6)]]);
+    }
+}
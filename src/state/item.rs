@@ -1,11 +1,32 @@
-use crate::physics::integrate_kinematic;
+use crate::physics::integrate_kinematic_with_platforms;
 use crate::render::Renderer;
 use crate::sound_handler::{Sound, SoundHandler};
+use crate::state::abilities::Ability;
+use crate::state::enemies::common::HitStrength;
+use crate::state::game_rng::GameRng;
+use crate::state::loot::{BOX_LOOT, VASE_LOOT};
 use crate::state::map_like::MapLike;
+use crate::state::shop::ForSale;
 use crate::state::{BoundingBox, Pos};
 use rand::Rng;
 use rand::seq::IndexedRandom;
 
+// Gibs lie around for a couple of seconds and fade out at the end
+const GIB_FRAMES: u32 = 150;
+const GIB_FADE_FRAMES: u32 = 40;
+// Every gib sprite has this many different chunks in it
+pub const GIB_SPRITE_FRAMES: u32 = 3;
+const KEY_COLOR: [f32; 4] = [0.95, 0.78, 0.25, 1.0];
+const KEY_HOLE_COLOR: [f32; 4] = [0.35, 0.25, 0.08, 1.0];
+const ARROW_SHAFT_COLOR: [f32; 4] = [0.6, 0.45, 0.3, 1.0];
+const ARROW_HEAD_COLOR: [f32; 4] = [0.75, 0.78, 0.8, 1.0];
+const ARROW_FLETCHING_COLOR: [f32; 4] = [0.9, 0.9, 0.85, 1.0];
+// Frames from dropping a bomb to it going off, the fuse blinks faster towards the end
+const BOMB_FUSE_FRAMES: u32 = 90;
+const BOMB_COLOR: [f32; 4] = [0.15, 0.15, 0.2, 1.0];
+const BOMB_SPARK_COLOR: [f32; 4] = [1.0, 0.85, 0.3, 1.0];
+const PICKUP_EDGE_COLOR: [f32; 4] = [0.1, 0.1, 0.1, 1.0];
+
 #[derive(Copy, Clone)]
 pub enum ItemType {
     Coin,
@@ -13,14 +34,30 @@ pub enum ItemType {
     LargeStone,
     // Sack,
     Box,
-    // Vase,
-    // Arrow,
+    Vase,
+    // Shot by archers, flies with gravity and breaks on whatever it hits
+    Arrow,
     GreenProjectile,
+    OneUp,
+    Heart,
+    // Carried to a locked door to open it
+    Key,
+    // Touching it gives the player the ability
+    Ability(Ability),
+    // Dropped by the player, goes off when the fuse runs out
+    Bomb,
+    // Chunk of a killed enemy, a frame of the enemy's gib sprite
+    Gib { sprite: &'static str, frame: u32 },
 }
 
+#[derive(Clone)]
 pub struct Item {
     bb: BoundingBox,
     item_type: ItemType,
+    // Items with a life time are removed when it runs out
+    frames_left: Option<u32>,
+    // Wares in a shop can not be touched or picked up until bought
+    for_sale: Option<ForSale>,
 }
 
 pub enum ItemInteractionResult {
@@ -28,6 +65,10 @@ pub enum ItemInteractionResult {
     IncreaseScore, // TODO: Add amount to increase by
     SpawnItem { item: Item },
     PlayerGotHit,
+    GainLife,
+    Heal,
+    GainAbility(Ability),
+    Explode { pos: Pos },
 }
 
 impl Item {
@@ -36,27 +77,32 @@ impl Item {
         self.bb.vy *= fraction;
     }
 
+    pub fn hit_strength(&self) -> HitStrength {
+        match self.item_type {
+            ItemType::LargeStone | ItemType::Box => HitStrength::Heavy,
+            _ => HitStrength::Normal,
+        }
+    }
+
     pub fn can_hit_enemy(&self) -> bool {
-        if let ItemType::GreenProjectile = self.item_type {
+        if let ItemType::Arrow | ItemType::GreenProjectile | ItemType::Gib { .. } = self.item_type {
             return false;
         }
         self.bb.vx.abs() > 0.001 || self.bb.vy.abs() > 0.001
     }
 
-    pub fn randomize_speed(&mut self) {
+    pub fn randomize_speed(&mut self, rng: &mut GameRng) {
         let min_max_speed = 0.95;
 
-        let mut rng = rand::rng();
         let speed = rng.random_range(min_max_speed..1.0 / min_max_speed);
         self.bb.vx *= speed;
         self.bb.vy *= speed;
     }
 
-    pub fn randomize_direction(&mut self) {
+    pub fn randomize_direction(&mut self, rng: &mut GameRng) {
         let min_max_degrees = 5.0_f32;
 
         let min_max_radians = min_max_degrees.to_radians();
-        let mut rng = rand::rng();
         let angle = rng.random_range(-min_max_radians..min_max_radians);
 
         let cos = angle.cos();
@@ -82,7 +128,15 @@ impl Item {
             ItemType::SmallStone => (4, 4),
             ItemType::LargeStone => (8, 8),
             ItemType::Box => (8, 10),
+            ItemType::Vase => (8, 8),
+            ItemType::Arrow => (10, 3),
             ItemType::GreenProjectile => (6, 6),
+            ItemType::OneUp => (7, 7),
+            ItemType::Heart => (7, 7),
+            ItemType::Key => (8, 5),
+            ItemType::Ability(_) => (8, 8),
+            ItemType::Bomb => (6, 6),
+            ItemType::Gib { .. } => (4, 4),
         };
 
         let width = width_px as f32 / 16.0;
@@ -98,9 +152,67 @@ impl Item {
                 vy,
             },
             item_type,
+            frames_left: None,
+            for_sale: None,
         }
     }
 
+    pub fn new_gib(
+        center_x: f32,
+        center_y: f32,
+        vx: f32,
+        vy: f32,
+        sprite: &'static str,
+        rng: &mut GameRng,
+    ) -> Self {
+        let frame = rng.random_range(0..GIB_SPRITE_FRAMES);
+        let mut item =
+            Self::new_with_velocity(center_x, center_y, vx, vy, ItemType::Gib { sprite, frame });
+        item.frames_left = Some(GIB_FRAMES);
+        item
+    }
+
+    pub fn new_bomb(center_x: f32, center_y: f32) -> Self {
+        let mut item = Self::new(center_x, center_y, ItemType::Bomb);
+        item.frames_left = Some(BOMB_FUSE_FRAMES);
+        item
+    }
+
+    pub fn new_for_sale(center_x: f32, center_y: f32, for_sale: ForSale) -> Self {
+        let mut item = Self::new(center_x, center_y, for_sale.ware.item_type());
+        item.for_sale = Some(for_sale);
+        item
+    }
+
+    pub fn is_coin(&self) -> bool {
+        matches!(self.item_type, ItemType::Coin)
+    }
+
+    pub fn is_key(&self) -> bool {
+        matches!(self.item_type, ItemType::Key)
+    }
+
+    /// The ability the item gives when touched
+    pub fn ability(&self) -> Option<Ability> {
+        match self.item_type {
+            ItemType::Ability(ability) => Some(ability),
+            _ => None,
+        }
+    }
+
+    // The fuse of a bomb would not burn in hand
+    pub fn can_be_picked_up(&self) -> bool {
+        !matches!(self.item_type, ItemType::Gib { .. } | ItemType::Bomb) && self.for_sale.is_none()
+    }
+
+    pub fn for_sale(&self) -> Option<ForSale> {
+        self.for_sale
+    }
+
+    pub fn mark_bought(&mut self) {
+        self.for_sale = None;
+    }
+
     pub fn bb(&self) -> &BoundingBox {
         &self.bb
     }
@@ -109,22 +221,99 @@ impl Item {
         Self::new_with_velocity(center_x, center_y, 0.0, 0.0, item_type)
     }
 
+    // There is no sprite for the key, it is a bow, a shaft and a tooth
+    fn draw_key(renderer: &mut Renderer, x: f32, y: f32) {
+        let pixel = 1.0 / 16.0;
+        renderer.draw_rect(x, y, pixel * 3.0, pixel * 5.0, KEY_COLOR);
+        renderer.draw_rect(x + pixel, y + pixel * 2.0, pixel * 7.0, pixel, KEY_COLOR);
+        renderer.draw_rect(
+            x + pixel * 6.0,
+            y + pixel * 3.0,
+            pixel,
+            pixel * 2.0,
+            KEY_COLOR,
+        );
+        renderer.draw_rect(x + pixel, y + pixel, pixel, pixel, KEY_HOLE_COLOR);
+    }
+
+    // No sprite for the arrow either, a shaft with the head towards where it is flying
+    fn draw_arrow(&self, renderer: &mut Renderer, x: f32, y: f32) {
+        let pixel = 1.0 / 16.0;
+        let (head_x, fletching_x) = if self.bb.vx < 0.0 {
+            (x, x + pixel * 8.0)
+        } else {
+            (x + pixel * 8.0, x)
+        };
+        renderer.draw_rect(x, y + pixel, pixel * 10.0, pixel, ARROW_SHAFT_COLOR);
+        renderer.draw_rect(head_x, y, pixel * 2.0, pixel * 3.0, ARROW_HEAD_COLOR);
+        renderer.draw_rect(fletching_x, y, pixel * 2.0, pixel, ARROW_FLETCHING_COLOR);
+        renderer.draw_rect(
+            fletching_x,
+            y + pixel * 2.0,
+            pixel * 2.0,
+            pixel,
+            ARROW_FLETCHING_COLOR,
+        );
+    }
+
+    // Pickups are a square of the color of the ability
+    fn draw_pickup(&self, renderer: &mut Renderer, x: f32, y: f32, ability: Ability) {
+        let pixel = 1.0 / 16.0;
+        renderer.draw_rect(x, y, self.bb.w, self.bb.h, PICKUP_EDGE_COLOR);
+        renderer.draw_rect(
+            x + pixel,
+            y + pixel,
+            self.bb.w - pixel * 2.0,
+            self.bb.h - pixel * 2.0,
+            ability.color(),
+        );
+    }
+
+    fn draw_bomb(&self, renderer: &mut Renderer, x: f32, y: f32) {
+        let pixel = 1.0 / 16.0;
+        renderer.draw_rect(x, y + pixel, self.bb.w, self.bb.h - pixel, BOMB_COLOR);
+        let frames_left = self.frames_left.unwrap_or(BOMB_FUSE_FRAMES);
+        let blink_frames = (frames_left / 8).max(2);
+        if frames_left % blink_frames < blink_frames / 2 {
+            renderer.draw_rect(
+                x + self.bb.w / 2.0,
+                y - pixel,
+                pixel,
+                pixel * 2.0,
+                BOMB_SPARK_COLOR,
+            );
+        }
+    }
+
     pub fn draw_fake_xy(&self, renderer: &mut Renderer, x: f32, y: f32) {
+        let (texture_index, atlas_index) = match self.item_type {
+            ItemType::Key => return Self::draw_key(renderer, x, y),
+            ItemType::Arrow => return self.draw_arrow(renderer, x, y),
+            ItemType::Ability(ability) => return self.draw_pickup(renderer, x, y, ability),
+            ItemType::Bomb => return self.draw_bomb(renderer, x, y),
+            ItemType::Coin => ("coin", 0),
+            ItemType::SmallStone => ("small_stone", 0),
+            ItemType::LargeStone => ("large_stone", 0),
+            ItemType::Box => ("box", 0),
+            ItemType::Vase => ("vase", 0),
+            ItemType::GreenProjectile => ("green_projectile", 0),
+            ItemType::OneUp => ("one_up", 0),
+            ItemType::Heart => ("heart", 0),
+            ItemType::Gib { sprite, frame } => (sprite, frame),
+        };
+        let alpha = match self.frames_left {
+            Some(frames_left) => (frames_left as f32 / GIB_FADE_FRAMES as f32).min(1.0),
+            None => 1.0,
+        };
         renderer.draw_from_texture_atlas(
-            match self.item_type {
-                ItemType::Coin => "coin",
-                ItemType::SmallStone => "small_stone",
-                ItemType::LargeStone => "large_stone",
-                ItemType::Box => "box",
-                ItemType::GreenProjectile => "green_projectile",
-            },
-            0,
+            texture_index,
+            atlas_index,
             false,
             x,
             y,
             self.bb.w,
             self.bb.h,
-            1.0,
+            alpha,
         );
     }
 
@@ -152,26 +341,40 @@ impl Item {
         self.draw_fake_xy(renderer, self.bb.x, self.bb.y);
     }
 
-    pub fn new_random(center_x: f32, center_y: f32) -> Self {
-        let mut rng = rand::rng();
-
+    pub fn new_random(center_x: f32, center_y: f32, rng: &mut GameRng) -> Self {
         let item_types = [
             ItemType::Coin,
             ItemType::SmallStone,
             ItemType::LargeStone,
             ItemType::Box,
+            ItemType::Vase,
         ];
 
-        let random_type = item_types.choose(&mut rng).unwrap();
+        let random_type = item_types.choose(rng).unwrap();
 
         Item::new(center_x, center_y, *random_type)
     }
 
     pub fn update(&mut self, map: &dyn MapLike) -> Vec<ItemInteractionResult> {
-        let res = integrate_kinematic(map, &self.bb, true);
+        if let Some(frames_left) = &mut self.frames_left {
+            *frames_left = frames_left.saturating_sub(1);
+            if *frames_left == 0 {
+                if let ItemType::Bomb = self.item_type {
+                    return vec![
+                        ItemInteractionResult::RemoveItem,
+                        ItemInteractionResult::Explode {
+                            pos: self.bb.center(),
+                        },
+                    ];
+                }
+                return vec![ItemInteractionResult::RemoveItem];
+            }
+        }
+
+        let res = integrate_kinematic_with_platforms(map, &self.bb, true);
 
         if res.on_something()
-            && let ItemType::GreenProjectile = self.item_type
+            && let ItemType::Arrow | ItemType::GreenProjectile = self.item_type
         {
             return vec![ItemInteractionResult::RemoveItem];
         }
@@ -201,20 +404,42 @@ impl Item {
         &mut self,
         sound_handler: &SoundHandler,
     ) -> Vec<ItemInteractionResult> {
+        if self.for_sale.is_some() {
+            return vec![];
+        }
         match self.item_type {
             ItemType::Coin => {
-                sound_handler.play(Sound::CollectCoin);
+                sound_handler.play_at(Sound::CollectCoin, self.bb().center());
                 vec![
                     ItemInteractionResult::RemoveItem,
                     ItemInteractionResult::IncreaseScore,
                 ]
             }
-            ItemType::GreenProjectile => {
+            ItemType::Arrow | ItemType::GreenProjectile => {
                 vec![
                     ItemInteractionResult::RemoveItem,
                     ItemInteractionResult::PlayerGotHit,
                 ]
             }
+            ItemType::OneUp => {
+                vec![
+                    ItemInteractionResult::RemoveItem,
+                    ItemInteractionResult::GainLife,
+                ]
+            }
+            ItemType::Heart => {
+                vec![
+                    ItemInteractionResult::RemoveItem,
+                    ItemInteractionResult::Heal,
+                ]
+            }
+            ItemType::Ability(ability) => {
+                sound_handler.play(Sound::OneUp);
+                vec![
+                    ItemInteractionResult::RemoveItem,
+                    ItemInteractionResult::GainAbility(ability),
+                ]
+            }
             _ => vec![],
         }
     }
@@ -222,22 +447,27 @@ impl Item {
     pub fn handle_being_swung(
         &mut self,
         sound_handler: &SoundHandler,
+        rng: &mut GameRng,
     ) -> Vec<ItemInteractionResult> {
+        if self.for_sale.is_some() {
+            return vec![];
+        }
         match self.item_type {
-            ItemType::Box => {
+            ItemType::Box | ItemType::Vase => {
+                let loot = match self.item_type {
+                    ItemType::Box => &BOX_LOOT,
+                    _ => &VASE_LOOT,
+                };
                 let mut results = vec![ItemInteractionResult::RemoveItem];
-                let mut rng = rand::rng();
-                for _ in 0..rng.random_range(1..5) {
-                    let vy = rng.random_range(-0.05..0.05);
-                    let vx = rng.random_range(-0.05..0.05);
+                let center = self.bb.center();
+                for item_type in loot.roll(rng) {
+                    // Extra lives pop straight up so that they are easy to spot
+                    let (vx, vy) = match item_type {
+                        ItemType::OneUp => (0.0, -0.1),
+                        _ => (rng.random_range(-0.05..0.05), rng.random_range(-0.05..0.05)),
+                    };
                     results.push(ItemInteractionResult::SpawnItem {
-                        item: Item::new_with_velocity(
-                            self.bb.x + self.bb.w * 0.5,
-                            self.bb.y + self.bb.h * 0.5,
-                            vx,
-                            vy,
-                            ItemType::Coin,
-                        ),
+                        item: Item::new_with_velocity(center.x, center.y, vx, vy, item_type),
                     })
                 }
                 sound_handler.play(Sound::Clink);
@@ -0,0 +1,54 @@
+use crate::state::common::Pos;
+
+// The lighting shader has room for this many lights, the ones furthest from the view are left out
+pub const MAX_LIGHTS: usize = 16;
+// How bright the parts of a dark room outside of every light are
+const DARK_AMBIENT: f32 = 0.06;
+// How much the ambient light changes per game update when moving between dark and lit rooms
+const AMBIENT_FADE: f32 = 0.04;
+
+// A round light in the world, it fades out towards its edge
+#[derive(Clone, Copy, Debug)]
+pub struct Light {
+    pub pos: Pos,
+    // In tiles
+    pub radius: f32,
+}
+
+// Dark rooms, rooms marked with Room::is_dark, are lit only by the lights around the player,
+// torches and glowing enemies. The radii are kept here so that items like a lantern can extend
+// them.
+#[derive(Clone)]
+pub struct Lighting {
+    pub player_radius: f32,
+    pub torch_radius: f32,
+    // Brightness outside of the lights, 1.0 is fully lit
+    ambient: f32,
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Lighting {
+            player_radius: 5.0,
+            torch_radius: 4.0,
+            ambient: 1.0,
+        }
+    }
+}
+
+impl Lighting {
+    /// Call once per game update, the darkness fades in and out instead of changing at once
+    pub fn update(&mut self, in_dark_room: bool) {
+        let target = if in_dark_room { DARK_AMBIENT } else { 1.0 };
+        self.ambient += (target - self.ambient).clamp(-AMBIENT_FADE, AMBIENT_FADE);
+    }
+
+    pub fn ambient(&self) -> f32 {
+        self.ambient
+    }
+
+    /// No lighting pass is needed when everything is lit
+    pub fn is_dark(&self) -> bool {
+        self.ambient < 1.0
+    }
+}
@@ -0,0 +1,67 @@
+use crate::state::game_rng::GameRng;
+use crate::state::item::ItemType;
+use rand::Rng;
+use std::ops::RangeInclusive;
+
+// One kind of item a container can drop
+pub struct LootDrop {
+    pub item_type: ItemType,
+    // Dropped with a chance of one in this many
+    pub one_in: u32,
+    pub count: RangeInclusive<u32>,
+}
+
+// What falls out of a container when it is broken
+pub struct LootTable {
+    pub drops: &'static [LootDrop],
+}
+
+impl LootTable {
+    pub fn roll(&self, rng: &mut GameRng) -> Vec<ItemType> {
+        let mut items = Vec::new();
+        for drop in self.drops {
+            if rng.random_range(0..drop.one_in) == 0 {
+                let count = rng.random_range(drop.count.clone());
+                items.extend(std::iter::repeat_n(drop.item_type, count as usize));
+            }
+        }
+        items
+    }
+}
+
+pub const BOX_LOOT: LootTable = LootTable {
+    drops: &[
+        LootDrop {
+            item_type: ItemType::Coin,
+            one_in: 1,
+            count: 1..=4,
+        },
+        // Rarely there is an extra life in the box
+        LootDrop {
+            item_type: ItemType::OneUp,
+            one_in: 10,
+            count: 1..=1,
+        },
+    ],
+};
+
+// Vases are easier to find, but have less in them
+pub const VASE_LOOT: LootTable = LootTable {
+    drops: &[
+        LootDrop {
+            item_type: ItemType::Coin,
+            one_in: 2,
+            count: 1..=2,
+        },
+        LootDrop {
+            item_type: ItemType::SmallStone,
+            one_in: 3,
+            count: 1..=1,
+        },
+        LootDrop {
+            item_type: ItemType::OneUp,
+            one_in: 25,
+            count: 1..=1,
+        },
+    ],
+};
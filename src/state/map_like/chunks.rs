@@ -0,0 +1,113 @@
+use super::common::{BaseTile, OverlayTile};
+use std::collections::HashMap;
+
+// Chunks are this many tiles wide and high
+pub const CHUNK_SIZE: i32 = 16;
+
+#[derive(Clone)]
+struct Chunk {
+    tiles: Vec<(BaseTile, OverlayTile)>,
+    // Indexes of the rooms that overlap the chunk, in order
+    rooms: Vec<usize>,
+}
+
+impl Chunk {
+    fn new() -> Self {
+        Chunk {
+            tiles: vec![(BaseTile::Stone, OverlayTile::None); (CHUNK_SIZE * CHUNK_SIZE) as usize],
+            rooms: Vec::new(),
+        }
+    }
+}
+
+// The tile and its index inside the chunk
+fn chunk_of(x: i32, y: i32) -> ((i32, i32), usize) {
+    let chunk = (x.div_euclid(CHUNK_SIZE), y.div_euclid(CHUNK_SIZE));
+    let index = x.rem_euclid(CHUNK_SIZE) + y.rem_euclid(CHUNK_SIZE) * CHUNK_SIZE;
+    (chunk, index as usize)
+}
+
+// The tiles of a map in fixed size chunks keyed by the chunk coordinates. Only the chunks that
+// have rooms in them exist, everything else is stone. Every chunk knows the rooms overlapping
+// it, so finding the room at a spot looks at a few rooms however many there are in the map.
+#[derive(Clone, Default)]
+pub struct ChunkedTiles {
+    chunks: HashMap<(i32, i32), Chunk>,
+}
+
+impl ChunkedTiles {
+    /// Adds the room to the chunks under its rectangle, add the rooms in the order of their
+    /// indexes
+    pub fn add_room(&mut self, room_index: usize, (x, y, w, h): (i32, i32, u32, u32)) {
+        if w == 0 || h == 0 {
+            return;
+        }
+        let (min_chunk, _) = chunk_of(x, y);
+        let (max_chunk, _) = chunk_of(x + w as i32 - 1, y + h as i32 - 1);
+        for chunk_y in min_chunk.1..=max_chunk.1 {
+            for chunk_x in min_chunk.0..=max_chunk.0 {
+                self.chunks
+                    .entry((chunk_x, chunk_y))
+                    .or_insert_with(Chunk::new)
+                    .rooms
+                    .push(room_index);
+            }
+        }
+    }
+
+    /// Sets every tile of the chunks from the tile position and the rooms overlapping its chunk
+    pub fn fill(&mut self, tile_at: impl Fn(i32, i32, &[usize]) -> (BaseTile, OverlayTile)) {
+        for (&(chunk_x, chunk_y), chunk) in &mut self.chunks {
+            for (index, tile) in chunk.tiles.iter_mut().enumerate() {
+                let x = chunk_x * CHUNK_SIZE + index as i32 % CHUNK_SIZE;
+                let y = chunk_y * CHUNK_SIZE + index as i32 / CHUNK_SIZE;
+                *tile = tile_at(x, y, &chunk.rooms);
+            }
+        }
+    }
+
+    pub fn get(&self, x: i32, y: i32) -> (BaseTile, OverlayTile) {
+        let (chunk, index) = chunk_of(x, y);
+        self.chunks
+            .get(&chunk)
+            .map_or((BaseTile::Stone, OverlayTile::None), |chunk| {
+                chunk.tiles[index]
+            })
+    }
+
+    /// Tiles outside of the chunks can not be changed
+    pub fn set_base(&mut self, x: i32, y: i32, base: BaseTile) {
+        let (chunk, index) = chunk_of(x, y);
+        if let Some(chunk) = self.chunks.get_mut(&chunk) {
+            chunk.tiles[index].0 = base;
+        }
+    }
+
+    /// The indexes of the rooms that could have the tile in them
+    pub fn rooms_near(&self, x: i32, y: i32) -> &[usize] {
+        let (chunk, _) = chunk_of(x, y);
+        self.chunks
+            .get(&chunk)
+            .map_or(&[], |chunk| chunk.rooms.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rooms_are_found_from_their_chunks() {
+        let mut tiles = ChunkedTiles::default();
+        tiles.add_room(0, (-20, -4, 10, 8));
+        tiles.add_room(1, (-12, 2, 30, 4));
+        tiles.fill(|_, _, _| (BaseTile::Empty, OverlayTile::None));
+
+        assert_eq!(tiles.rooms_near(-20, -4), &[0]);
+        assert_eq!(tiles.rooms_near(-1, 3), &[0, 1]);
+        assert_eq!(tiles.rooms_near(17, 5), &[1]);
+        assert!(tiles.rooms_near(40, 40).is_empty());
+        assert_eq!(tiles.get(-1, -1).0, BaseTile::Empty);
+        assert_eq!(tiles.get(40, 40).0, BaseTile::Stone);
+    }
+}
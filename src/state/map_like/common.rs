@@ -1,7 +1,14 @@
-use crate::state::common::BoundingBox;
-use crate::state::enemies::{Bat, Burrower, Enemy, Slime, Worm};
+use crate::state::abilities::Ability;
+use crate::state::checkpoints::flag_bb;
+use crate::state::common::{BoundingBox, Pos};
+use crate::state::enemies::{
+    Archer, Bat, BatFlock, Boss, Burrower, Enemy, ShieldBearer, Slime, Wisp, Worm,
+};
+use crate::state::item::{Item, ItemType};
 use crate::state::map_like::Room;
+use crate::state::training::switch_bb;
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 #[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Copy, Debug)]
 pub enum BaseTile {
@@ -9,6 +16,43 @@ pub enum BaseTile {
     Empty = 1,
     Stone = 2,
     Wood = 3,
+    // Looks like stone but breaks after a couple of hits, there is a secret behind it
+    HiddenWall = 4,
+    // 45 degree slopes of stone, the floor rises to the right or to the left
+    SlopeUpRight = 5,
+    SlopeUpLeft = 6,
+    // Stone with visible cracks in it, one swing breaks it
+    Cracked = 7,
+    // Wooden door that opens when the player touches it with a key in hand
+    LockedDoor = 8,
+    // Wooden barrier that breaks when the player dashes in to it
+    DashBarrier = 9,
+    // Stone that only a bomb breaks
+    BombWall = 10,
+}
+
+impl BaseTile {
+    /// What the tile is made of, for the sounds made on it. None for the tiles that are not solid.
+    pub fn material(self) -> Option<Material> {
+        match self {
+            BaseTile::Empty => None,
+            BaseTile::Wood | BaseTile::LockedDoor | BaseTile::DashBarrier => Some(Material::Wood),
+            BaseTile::NotPartOfRoom
+            | BaseTile::Stone
+            | BaseTile::HiddenWall
+            | BaseTile::SlopeUpRight
+            | BaseTile::SlopeUpLeft
+            | BaseTile::Cracked
+            | BaseTile::BombWall => Some(Material::Stone),
+        }
+    }
+}
+
+// The ground the footsteps and landings sound like, sand and ice come with their tiles
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Material {
+    Stone,
+    Wood,
 }
 
 #[derive(Serialize, Deserialize, Clone, Copy, Debug)]
@@ -18,8 +62,18 @@ pub enum OverlayTile {
     Platform = 2,
     LadderPlatform = 3,
     StartDoor = 4,
+    Spikes = 5,
+    // Decoration on the wall, it flickers and glows
+    Torch = 6,
+    // Decoration growing on the floor
+    Grass = 7,
+    // Climbed like a ladder but sways in the wind, carrying the player with it
+    Vine = 8,
 }
 
+// Damage dealt by touching the spikes, to the player and enemies alike
+pub const SPIKE_DAMAGE: u32 = 1;
+
 #[derive(Serialize, Deserialize, Clone, Copy, Debug)]
 pub struct OverlayInfo {
     pub tile: OverlayTile,
@@ -27,10 +81,19 @@ pub struct OverlayInfo {
     pub y: i32,
 }
 
+static REVISIONS: AtomicU64 = AtomicU64::new(0);
+
+/// A revision no map has had yet, maps take a new one whenever their tiles change
+pub fn next_revision() -> u64 {
+    REVISIONS.fetch_add(1, Ordering::Relaxed)
+}
+
 pub trait MapLike {
     /// Returns (origin_x, origin_y, width, height) in tile coordinates for the map bounds.
     fn get_bounds(&self) -> (i32, i32, u32, u32);
     fn get_at(&self, tx: i32, ty: i32) -> (BaseTile, OverlayTile);
+    /// Changes whenever the base tiles change, the renderer rebuilds its tile meshes then
+    fn revision(&self) -> u64;
     fn set_base(&mut self, x: i32, y: i32, tile: BaseTile);
     fn set_overlay(&mut self, x: i32, y: i32, tile: OverlayTile);
     fn get_overlays(&self) -> &Vec<OverlayInfo>;
@@ -42,12 +105,60 @@ pub trait MapLike {
             (_, OverlayTile::Ladder) | (_, OverlayTile::LadderPlatform)
         )
     }
+    fn is_vine_at(&self, tx: i32, ty: i32) -> bool {
+        matches!(self.get_at(tx, ty), (_, OverlayTile::Vine))
+    }
+    /// Ladders and vines
+    fn is_climbable_at(&self, tx: i32, ty: i32) -> bool {
+        self.is_ladder_at(tx, ty) || self.is_vine_at(tx, ty)
+    }
     fn is_platform_at(&self, tx: i32, ty: i32) -> bool {
         matches!(
             self.get_at(tx, ty),
             (_, OverlayTile::Platform) | (_, OverlayTile::LadderPlatform)
         )
     }
+    /// A ladder is supported when the chain of ladders it is part of ends on something below,
+    /// a solid tile, a slope, a platform or a door. Otherwise it is a stub that can not be
+    /// reached from below and is drawn broken.
+    fn is_ladder_supported(&self, tx: i32, ty: i32) -> bool {
+        let mut y = ty;
+        while self.is_ladder_at(tx, y) {
+            y += 1;
+        }
+        self.is_solid_at_tile(tx, y)
+            || self.is_slope_at(tx, y)
+            || self.is_platform_at(tx, y)
+            || self.is_door_at_i(tx, y)
+    }
+    /// Something to stand on, a solid tile, a slope or a platform
+    fn is_ground_at(&self, tx: i32, ty: i32) -> bool {
+        self.is_solid_at_tile(tx, ty) || self.is_slope_at(tx, ty) || self.is_platform_at(tx, ty)
+    }
+    fn is_spikes_at(&self, tx: i32, ty: i32) -> bool {
+        matches!(self.get_at(tx, ty), (_, OverlayTile::Spikes))
+    }
+    /// Damage from hazardous tiles touching the bounding box. Spikes only fill the lower half of
+    /// the tile and are a bit narrower than it, so that grazing them does not hurt.
+    fn hazard_damage(&self, bb: &BoundingBox) -> Option<u32> {
+        for tx in bb.x.floor() as i32..=(bb.x + bb.w).floor() as i32 {
+            for ty in bb.y.floor() as i32..=(bb.y + bb.h).floor() as i32 {
+                if self.is_spikes_at(tx, ty)
+                    && bb.overlaps(&BoundingBox {
+                        x: tx as f32 + 2.0 / 16.0,
+                        y: ty as f32 + 0.5,
+                        w: 12.0 / 16.0,
+                        h: 0.5,
+                        vx: 0.0,
+                        vy: 0.0,
+                    })
+                {
+                    return Some(SPIKE_DAMAGE);
+                }
+            }
+        }
+        None
+    }
     fn is_solid_at_tile(&self, tx: i32, ty: i32) -> bool {
         let (base, _overlay) = self.get_at(tx, ty);
         match base {
@@ -55,8 +166,20 @@ pub trait MapLike {
             BaseTile::Empty => false,
             BaseTile::Stone => true,
             BaseTile::Wood => true,
+            BaseTile::HiddenWall => true,
+            BaseTile::Cracked => true,
+            BaseTile::LockedDoor => true,
+            BaseTile::DashBarrier | BaseTile::BombWall => true,
+            // Only the part below the surface is solid, see _is_solid_at_f_tile
+            BaseTile::SlopeUpRight | BaseTile::SlopeUpLeft => false,
         }
     }
+    fn is_slope_at(&self, tx: i32, ty: i32) -> bool {
+        matches!(
+            self.get_at(tx, ty).0,
+            BaseTile::SlopeUpRight | BaseTile::SlopeUpLeft
+        )
+    }
     fn _overlaps_solid_tile(&self, x: f32, y: f32, w: f32, h: f32) -> bool {
         self._is_solid_at_f_tile(x, y)
             || self._is_solid_at_f_tile(x + w, y)
@@ -65,7 +188,13 @@ pub trait MapLike {
     }
 
     fn _is_solid_at_f_tile(&self, tx: f32, ty: f32) -> bool {
-        self.is_solid_at_tile(tx.floor() as i32, ty.floor() as i32)
+        let (x, y) = (tx.floor() as i32, ty.floor() as i32);
+        let (local_x, local_y) = (tx - x as f32, ty - y as f32);
+        match self.get_at(x, y).0 {
+            BaseTile::SlopeUpRight => local_y >= 1.0 - local_x,
+            BaseTile::SlopeUpLeft => local_y >= local_x,
+            _ => self.is_solid_at_tile(x, y),
+        }
     }
 
     fn is_room_border(&self, tx: i32, ty: i32) -> bool;
@@ -81,6 +210,18 @@ pub enum DoorDir {
     Down,
 }
 
+impl DoorDir {
+    /// The direction of the door on the other room that this one connects to
+    pub fn opposite(&self) -> DoorDir {
+        match self {
+            DoorDir::Left => DoorDir::Right,
+            DoorDir::Right => DoorDir::Left,
+            DoorDir::Up => DoorDir::Down,
+            DoorDir::Down => DoorDir::Up,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct RoomDoor {
     pub x: u32,
@@ -94,6 +235,102 @@ pub enum ObjectTemplateType {
     Slime = 1,
     Worm = 2,
     Burrower = 3,
+    BatFlock = 4,
+    ShieldBearer = 5,
+    Archer = 6,
+    Boss = 7,
+    Wisp = 8,
+}
+
+// Containers placed in rooms, they break open with loot in them. The shop slots of a room make
+// it a shop, the wares are picked when the game starts. Checkpoints are flags the player
+// respawns at, keys open the locked doors. The ability pickups give the player an ability.
+// Coins and stones are placed as they are, on top of the random ones. Training switches make the
+// room a training room, see state::training.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ItemTemplateType {
+    Box = 0,
+    Vase = 1,
+    ShopSlot = 2,
+    Checkpoint = 3,
+    Key = 4,
+    DoubleJumpPickup = 5,
+    DashPickup = 6,
+    BombPickup = 7,
+    Coin = 8,
+    SmallStone = 9,
+    LargeStone = 10,
+    TrainingSwitch = 11,
+}
+
+impl ItemTemplateType {
+    pub fn pickup_of(ability: Ability) -> Self {
+        match ability {
+            Ability::DoubleJump => ItemTemplateType::DoubleJumpPickup,
+            Ability::Dash => ItemTemplateType::DashPickup,
+            Ability::Bomb => ItemTemplateType::BombPickup,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ItemTemplate {
+    x: f32,
+    y: f32,
+    item_type: ItemTemplateType,
+}
+
+impl ItemTemplate {
+    pub fn new(x: f32, y: f32, item_type: ItemTemplateType) -> ItemTemplate {
+        ItemTemplate { x, y, item_type }
+    }
+
+    pub fn translate(&mut self, dx: f32, dy: f32) {
+        self.x += dx;
+        self.y += dy;
+    }
+
+    pub fn is_shop_slot(&self) -> bool {
+        self.item_type == ItemTemplateType::ShopSlot
+    }
+
+    pub fn is_checkpoint(&self) -> bool {
+        self.item_type == ItemTemplateType::Checkpoint
+    }
+
+    pub fn is_training_switch(&self) -> bool {
+        self.item_type == ItemTemplateType::TrainingSwitch
+    }
+
+    pub fn pos(&self) -> Pos {
+        Pos::new(self.x, self.y)
+    }
+
+    // Shop slots are shown with a heart in the editor, checkpoints and switches are not items
+    pub fn as_item(&self) -> Option<Item> {
+        let item_type = match self.item_type {
+            ItemTemplateType::Box => ItemType::Box,
+            ItemTemplateType::Vase => ItemType::Vase,
+            ItemTemplateType::ShopSlot => ItemType::Heart,
+            ItemTemplateType::Key => ItemType::Key,
+            ItemTemplateType::DoubleJumpPickup => ItemType::Ability(Ability::DoubleJump),
+            ItemTemplateType::DashPickup => ItemType::Ability(Ability::Dash),
+            ItemTemplateType::BombPickup => ItemType::Ability(Ability::Bomb),
+            ItemTemplateType::Coin => ItemType::Coin,
+            ItemTemplateType::SmallStone => ItemType::SmallStone,
+            ItemTemplateType::LargeStone => ItemType::LargeStone,
+            ItemTemplateType::Checkpoint | ItemTemplateType::TrainingSwitch => return None,
+        };
+        Some(Item::new(self.x, self.y, item_type))
+    }
+
+    pub fn bb(&self) -> BoundingBox {
+        match self.as_item() {
+            Some(item) => *item.bb(),
+            None if self.is_training_switch() => switch_bb(self.pos()),
+            None => flag_bb(self.pos()),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -117,13 +354,19 @@ impl ObjectTemplate {
         *self.as_object().bb()
     }
 
-    pub fn get_texture_index(&self) -> &str {
-        match self.object_type {
+    /// None for the enemies without a sprite, they are drawn as they are in the game
+    pub fn get_texture_index(&self) -> Option<&str> {
+        Some(match self.object_type {
             ObjectTemplateType::Bat => "bat",
             ObjectTemplateType::Slime => "slime",
             ObjectTemplateType::Worm => "worm",
             ObjectTemplateType::Burrower => "burrower",
-        }
+            ObjectTemplateType::BatFlock => "batling",
+            ObjectTemplateType::ShieldBearer => "shield_bearer",
+            ObjectTemplateType::Archer | ObjectTemplateType::Boss | ObjectTemplateType::Wisp => {
+                return None;
+            }
+        })
     }
 
     pub fn as_object(&self) -> Box<dyn Enemy> {
@@ -132,6 +375,11 @@ impl ObjectTemplate {
             ObjectTemplateType::Slime => Box::new(Slime::new(self.x, self.y)),
             ObjectTemplateType::Worm => Box::new(Worm::new(self.x, self.y)),
             ObjectTemplateType::Burrower => Box::new(Burrower::new(self.x, self.y)),
+            ObjectTemplateType::BatFlock => Box::new(BatFlock::new(self.x, self.y)),
+            ObjectTemplateType::ShieldBearer => Box::new(ShieldBearer::new(self.x, self.y)),
+            ObjectTemplateType::Archer => Box::new(Archer::new(self.x, self.y)),
+            ObjectTemplateType::Boss => Box::new(Boss::new(self.x, self.y)),
+            ObjectTemplateType::Wisp => Box::new(Wisp::new(self.x, self.y)),
         }
     }
 }
@@ -1,13 +1,34 @@
-use super::common::{BaseTile, DoorDir, MapLike, OverlayInfo, OverlayTile, RoomDoor};
+use super::chunks::ChunkedTiles;
+use super::common::{
+    BaseTile, DoorDir, ItemTemplate, ItemTemplateType, MapLike, OverlayInfo, OverlayTile, RoomDoor,
+    next_revision,
+};
 use super::room::Room;
+use crate::state::abilities::{Abilities, Ability};
+use crate::state::ai::{JumpReach, reachable_tiles};
 use crate::state::animation_handler::{AnimationConfig, AnimationConfigResult, AnimationHandler};
-use crate::state::common::BoundingBox;
+use crate::state::common::{BoundingBox, Pos};
 use crate::state::enemies::Enemy;
+use crate::state::game_rng::GameRng;
+use crate::state::item::Item;
+use crate::telemetry::log;
 use rand::Rng;
 use rand::seq::IndexedRandom;
-use std::collections::HashSet;
-
-#[derive(PartialEq)]
+use std::cmp::Reverse;
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+
+// Maps that can not be shown to be solvable are generated again, after this many attempts a map
+// of only the first room is used
+const MAX_GENERATION_ATTEMPTS: u32 = 10;
+// Rooms in a generated map, the first room included
+const ROOM_COUNT: usize = 10;
+// The critical path needs at least this many rooms for an ability lock to be put on it
+const ABILITY_LOCK_MIN_PATH: usize = 4;
+// Tries at connecting the training room to the first room before it is left out of the map
+const TRAINING_ROOM_TRIES: u32 = 100;
+
+#[derive(Clone, PartialEq)]
 enum DoorAnimationState {
     ClosedUpDown,
     OpenUpDown,
@@ -30,18 +51,34 @@ impl AnimationConfig for DoorAnimationState {
     }
 }
 
+#[derive(Clone)]
 pub struct MapDoor {
     pub x: i32,
     pub y: i32,
     pub goes_up_down: bool,
     open: bool,
+    locked: bool,
     closed_frames: i32,
     animation_handler: AnimationHandler<DoorAnimationState>,
 }
 
 impl MapDoor {
     pub fn is_open(&self) -> bool {
-        self.open && self.closed_frames == 0
+        self.open && !self.locked && self.closed_frames == 0
+    }
+
+    // Locked doors stay closed regardless of the open state and are drawn with a padlock
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    pub fn set_locked(&mut self, locked: bool) {
+        self.locked = locked
+    }
+
+    // Doors are on the border of the two rooms they connect, so they are part of both
+    pub fn is_in_room(&self, room: &Room) -> bool {
+        matches!(room.get_relative(self.x, self.y), Some((base, _)) if base != BaseTile::NotPartOfRoom)
     }
 
     pub fn new(x: i32, y: i32, goes_up_down: bool) -> MapDoor {
@@ -50,6 +87,7 @@ impl MapDoor {
             y,
             goes_up_down,
             open: false,
+            locked: false,
             closed_frames: 0,
             animation_handler: AnimationHandler::new(if goes_up_down {
                 DoorAnimationState::OpenUpDown
@@ -109,17 +147,37 @@ impl MapDoor {
     }
 }
 
+// How an attempt of the generator to add a room ended
+#[derive(Clone, Copy, PartialEq)]
+pub enum GenerationOutcome {
+    Placed,
+    NoMatchingDoor,
+    SamePosition,
+    // The tile where the candidate ran in to a room that was already placed
+    Overlaps { x: i32, y: i32 },
+}
+
+// One attempt to add a room, kept for the generation view of the debug window
+#[derive(Clone)]
+pub struct GenerationStep {
+    pub existing_room: usize,
+    // World position of the door of the existing room that was tried
+    pub door: (i32, i32),
+    // Bounds of the candidate moved to the door, None when it had no door to match
+    pub candidate: Option<(i32, i32, u32, u32)>,
+    pub outcome: GenerationOutcome,
+}
+
+#[derive(Clone)]
 pub struct GameMap {
     pub rooms: Vec<Room>,
     pub doors: Vec<MapDoor>,
 
-    base: Vec<BaseTile>,
-    overlay: Vec<OverlayTile>,
+    tiles: ChunkedTiles,
     all_overlays: Vec<OverlayInfo>,
-    x: i32,
-    y: i32,
-    w: u32,
-    h: u32,
+    revision: u64,
+    // Shared between the clones of the game, it does not change after generation
+    generation: Arc<Vec<GenerationStep>>,
 }
 
 impl GameMap {
@@ -149,7 +207,7 @@ impl GameMap {
     }
 
     pub fn get_room_at(&self, x: f32, y: f32) -> Option<(usize, &Room)> {
-        for room_index in 0..self.rooms.len() {
+        for &room_index in self.tiles.rooms_near(x.floor() as i32, y.floor() as i32) {
             let room = &self.rooms[room_index];
 
             let cent = room.get_relative(x.floor() as i32, y.floor() as i32);
@@ -179,6 +237,310 @@ impl GameMap {
         None
     }
 
+    /// For every room the rooms it has a door to, with the index of that door
+    pub fn room_neighbours(&self) -> Vec<Vec<(usize, usize)>> {
+        let mut neighbours = vec![Vec::new(); self.rooms.len()];
+        for (door_index, door) in self.doors.iter().enumerate() {
+            let rooms: Vec<usize> = (0..self.rooms.len())
+                .filter(|&index| door.is_in_room(&self.rooms[index]))
+                .collect();
+            for &a in &rooms {
+                for &b in &rooms {
+                    if a != b {
+                        neighbours[a].push((b, door_index));
+                    }
+                }
+            }
+        }
+        neighbours
+    }
+
+    /// How many doors away every room is from the given room, None for unreachable rooms
+    pub fn room_distances(&self, from: usize) -> Vec<Option<u32>> {
+        let neighbours = self.room_neighbours();
+        let mut distances = vec![None; self.rooms.len()];
+        distances[from] = Some(0);
+        let mut queue = VecDeque::from([from]);
+        while let Some(room) = queue.pop_front() {
+            let distance = distances[room].unwrap();
+            for &(next, _) in &neighbours[room] {
+                if distances[next].is_none() {
+                    distances[next] = Some(distance + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+        distances
+    }
+
+    /// The rooms on the way from the first room to the one furthest away from it
+    pub fn critical_path(&self) -> Vec<usize> {
+        let neighbours = self.room_neighbours();
+        let distances = self.room_distances(0);
+        let Some((mut distance, mut room)) = distances
+            .iter()
+            .enumerate()
+            .filter_map(|(index, distance)| Some((*distance.as_ref()?, index)))
+            .max()
+        else {
+            return Vec::new();
+        };
+        let mut path = vec![room];
+        while distance > 0 {
+            distance -= 1;
+            room = neighbours[room]
+                .iter()
+                .map(|&(next, _)| next)
+                .find(|&next| distances[next] == Some(distance))
+                .unwrap();
+            path.push(room);
+        }
+        path.reverse();
+        path
+    }
+
+    /// A standable tile in the room, the empty tile with a solid tile under it nearest to the
+    /// middle of the room
+    pub fn standable_tile_in_room(&self, room_index: usize) -> Option<(i32, i32)> {
+        let room = self.rooms.get(room_index)?;
+        let (room_x, room_y) = room.get_pos();
+        let middle = (room_x + room.w as i32 / 2, room_y + room.h as i32 / 2);
+        (room_y..room_y + room.h as i32)
+            .flat_map(|y| (room_x..room_x + room.w as i32).map(move |x| (x, y)))
+            .filter(|&(x, y)| {
+                !self.is_solid_at_tile(x, y)
+                    && self.is_solid_at_tile(x, y + 1)
+                    && matches!(self.get_room_at(x as f32 + 0.5, y as f32 + 0.5), Some((index, _)) if index == room_index)
+            })
+            .min_by_key(|&(x, y)| (x - middle.0).abs() + (y - middle.1).abs())
+    }
+
+    // Locks the door in to the deepest room, hides the key for it in the deepest dead end and
+    // leaves a box in the other dead ends. The deepest room has no rooms behind it, so the key is
+    // always reachable without going through the locked door. Long enough critical paths get an
+    // ability lock half way, the pickups of the abilities are placed after the locks.
+    fn place_progression(&mut self, rng: &mut GameRng) {
+        let path = self.critical_path();
+        let neighbours = self.room_neighbours();
+        let distances = self.room_distances(0);
+        let dead_ends: Vec<usize> = (1..self.rooms.len())
+            .filter(|&room| neighbours[room].len() == 1 && !path.contains(&room))
+            .collect();
+
+        let mut key_room = None;
+        if let [.., before_last, last] = path[..] {
+            let &(_, door_index) = neighbours[last]
+                .iter()
+                .find(|&&(next, _)| next == before_last)
+                .unwrap();
+            let (door_x, door_y) = (self.doors[door_index].x, self.doors[door_index].y);
+            let room = dead_ends
+                .iter()
+                .copied()
+                .max_by_key(|&room| distances[room])
+                .unwrap_or(before_last);
+            // Locked first so that the key is not placed in the doorway
+            self.set_base(door_x, door_y, BaseTile::LockedDoor);
+            match self.standable_tile_in_room(room) {
+                Some((x, y)) => {
+                    self.rooms[room].item_templates.push(ItemTemplate::new(
+                        x as f32 + 0.5,
+                        y as f32 + 0.5,
+                        ItemTemplateType::Key,
+                    ));
+                    key_room = Some(room);
+                    log!(
+                        "Locked the door to room {}, the key is in room {}",
+                        last,
+                        room
+                    );
+                }
+                None => self.set_base(door_x, door_y, BaseTile::Empty),
+            }
+        }
+
+        if path.len() >= ABILITY_LOCK_MIN_PATH {
+            let (previous, room) = (path[path.len() / 2 - 1], path[path.len() / 2]);
+            let &(_, door_index) = neighbours[room]
+                .iter()
+                .find(|&&(next, _)| next == previous)
+                .unwrap();
+            let lockable: Vec<Ability> = Ability::ALL
+                .into_iter()
+                .filter(|ability| ability.lock_tile().is_some())
+                .collect();
+            let ability = *lockable.choose(rng).unwrap();
+            let door = &self.doors[door_index];
+            let (door_x, door_y) = (door.x, door.y);
+            self.set_base(door_x, door_y, ability.lock_tile().unwrap());
+            log!("{} lock on the door to room {}", ability.name(), room);
+        }
+
+        let mut used_rooms: Vec<usize> = key_room.into_iter().collect();
+        self.place_ability_pickups(&mut used_rooms);
+
+        for &room in &dead_ends {
+            if used_rooms.contains(&room) {
+                continue;
+            }
+            if let Some((x, y)) = self.standable_tile_in_room(room) {
+                self.rooms[room].item_templates.push(ItemTemplate::new(
+                    x as f32 + 0.5,
+                    y as f32 + 0.5,
+                    ItemTemplateType::Box,
+                ));
+            }
+        }
+    }
+
+    // Places a pickup for each ability that the way to the deepest room needs, in a room that can
+    // be reached without it. The locks are the lock tiles and the ledges too high to get up on
+    // without the double jump, both put in by place_progression and drawn in the rooms.
+    fn place_ability_pickups(&mut self, used_rooms: &mut Vec<usize>) {
+        let neighbours = self.room_neighbours();
+        let distances = self.room_distances(0);
+        for _ in Ability::ALL {
+            let (reached, abilities) = self.reachable_with_progression(Abilities::default());
+            if self.reaches_goal(&reached) {
+                return;
+            }
+            // The ability that opens up the most of the map
+            let Some((ability, _)) = Ability::ALL
+                .into_iter()
+                .filter(|&ability| !abilities.has(ability))
+                .map(|ability| {
+                    let with_ability = abilities.with(ability);
+                    (
+                        ability,
+                        self.reachable_with_progression(with_ability).0.len(),
+                    )
+                })
+                .filter(|&(_, reached_with_ability)| reached_with_ability > reached.len())
+                .max_by_key(|&(_, reached_with_ability)| reached_with_ability)
+            else {
+                return;
+            };
+            // Dead ends first, the deeper rooms before the shallower ones
+            let mut rooms: Vec<usize> = (0..self.rooms.len())
+                .filter(|room| !used_rooms.contains(room))
+                .collect();
+            rooms.sort_by_key(|&room| (neighbours[room].len() != 1, Reverse(distances[room])));
+            let Some((room, (x, y))) = rooms.into_iter().find_map(|room| {
+                self.standable_tile_in_room(room)
+                    .filter(|tile| reached.contains(tile))
+                    .map(|tile| (room, tile))
+            }) else {
+                return;
+            };
+            self.rooms[room].item_templates.push(ItemTemplate::new(
+                x as f32 + 0.5,
+                y as f32 + 0.5,
+                ItemTemplateType::pickup_of(ability),
+            ));
+            used_rooms.push(room);
+            log!("{} pickup placed in room {}", ability.name(), room);
+        }
+    }
+
+    // Floods the map from the start like the player would get around it. Reaching a key opens
+    // the locked doors and reaching an ability pickup opens the locks of the ability, the double
+    // jump and the dash let the player jump higher and further. Returns the tiles reached in the
+    // end and the abilities picked up on the way.
+    fn reachable_with_progression(&self, given: Abilities) -> (HashSet<(i32, i32)>, Abilities) {
+        let (start_x, start_y) = self.player_start_pos();
+        let start = (start_x.floor() as i32, start_y.floor() as i32);
+        let items = self.get_items_from_templates();
+        let mut map = self.clone();
+        let mut abilities = given;
+        let mut key_found = false;
+        let mut to_open: Vec<BaseTile> = given.iter().filter_map(Ability::lock_tile).collect();
+        loop {
+            map.open_tiles(&to_open);
+            to_open.clear();
+            let reach = JumpReach {
+                height: if abilities.has(Ability::DoubleJump) {
+                    JumpReach::WALKER.height * 2
+                } else {
+                    JumpReach::WALKER.height
+                },
+                distance: if abilities.has(Ability::Dash) {
+                    JumpReach::WALKER.distance + 2
+                } else {
+                    JumpReach::WALKER.distance
+                },
+            };
+            let reached = reachable_tiles(&map, start, reach);
+            let mut progressed = false;
+            for item in &items {
+                let center = item.bb().center();
+                if !reached.contains(&(center.x.floor() as i32, center.y.floor() as i32)) {
+                    continue;
+                }
+                if item.is_key() && !key_found {
+                    key_found = true;
+                    progressed = true;
+                    to_open.push(BaseTile::LockedDoor);
+                }
+                if let Some(ability) = item.ability()
+                    && abilities.insert(ability)
+                {
+                    progressed = true;
+                    to_open.extend(ability.lock_tile());
+                }
+            }
+            if !progressed {
+                return (reached, abilities);
+            }
+        }
+    }
+
+    fn open_tiles(&mut self, tiles: &[BaseTile]) {
+        if tiles.is_empty() {
+            return;
+        }
+        let (x, y, w, h) = self.get_bounds();
+        for (x, y) in (x..x + w).flat_map(|x| (y..y + h).map(move |y| (x, y))) {
+            if tiles.contains(&self.get_at(x, y).0) {
+                self.set_base(x, y, BaseTile::Empty);
+            }
+        }
+    }
+
+    // Whether any of the tiles is in the deepest room
+    fn reaches_goal(&self, reached: &HashSet<(i32, i32)>) -> bool {
+        let Some(&goal_room) = self.critical_path().last() else {
+            return false;
+        };
+        reached.iter().any(|&(x, y)| {
+            matches!(self.get_room_at(x as f32 + 0.5, y as f32 + 0.5), Some((index, _)) if index == goal_room)
+        })
+    }
+
+    /// Every attempt to add a room while the map was generated, in order
+    pub fn generation_steps(&self) -> &[GenerationStep] {
+        &self.generation
+    }
+
+    /// The first door to go through on the shortest way from one room to another
+    pub fn next_door_towards(&self, from: usize, to: usize) -> Option<&MapDoor> {
+        let neighbours = self.room_neighbours();
+        // Walk back from the goal so that the first door of the route is found directly
+        let mut first_door: Vec<Option<usize>> = vec![None; self.rooms.len()];
+        let mut visited = vec![false; self.rooms.len()];
+        visited[to] = true;
+        let mut queue = VecDeque::from([to]);
+        while let Some(room) = queue.pop_front() {
+            for &(next, door_index) in &neighbours[room] {
+                if !visited[next] {
+                    visited[next] = true;
+                    first_door[next] = Some(door_index);
+                    queue.push_back(next);
+                }
+            }
+        }
+        first_door[from].map(|door_index| &self.doors[door_index])
+    }
+
     pub fn get_enemies_from_templates(&self) -> Vec<Box<dyn Enemy>> {
         let mut enemies = Vec::new();
 
@@ -190,25 +552,82 @@ impl GameMap {
         enemies
     }
 
-    pub fn new_random() -> GameMap {
+    pub fn get_items_from_templates(&self) -> Vec<Item> {
+        self.rooms
+            .iter()
+            .flat_map(|room| room.get_items_from_template())
+            .collect()
+    }
+
+    pub fn get_checkpoints(&self) -> Vec<Pos> {
+        self.rooms
+            .iter()
+            .flat_map(|room| room.get_checkpoints())
+            .collect()
+    }
+
+    /// The shop slots of every room that is a shop
+    pub fn get_shop_slots(&self) -> Vec<Vec<Pos>> {
+        self.rooms
+            .iter()
+            .map(|room| room.get_shop_slots())
+            .filter(|slots| !slots.is_empty())
+            .collect()
+    }
+
+    /// Whether the player can get from the start to the deepest room, with the key of the locked
+    /// door and the abilities for the ability locks if there are some. Uses the movement of the
+    /// walking enemies, which jump lower than the player can, so a map that passes is solvable
+    /// but some solvable maps do not pass.
+    pub fn is_solvable(&self) -> bool {
+        let (reached, _) = self.reachable_with_progression(Abilities::default());
+        self.reaches_goal(&reached)
+    }
+
+    /// Generates maps until one of them is solvable, see is_solvable. When none of them is, the
+    /// map is only the first room, which is solvable as the goal is where the player starts.
+    pub fn new_random(rng: &mut GameRng) -> GameMap {
         let room_candidates = Room::load_rooms_from_folder();
-        let mut rng = rand::rng();
+        for attempt in 1..=MAX_GENERATION_ATTEMPTS {
+            let map = GameMap::generate(rng, &room_candidates, ROOM_COUNT);
+            if map.is_solvable() {
+                return map;
+            }
+            log!("Generated map {} is not solvable", attempt);
+        }
+        eprintln!(
+            "No solvable map in {} attempts, using only the first room",
+            MAX_GENERATION_ATTEMPTS
+        );
+        GameMap::generate(rng, &room_candidates, 1)
+    }
 
+    fn generate(
+        rng: &mut GameRng,
+        room_candidates: &[(String, Room)],
+        max_rooms: usize,
+    ) -> GameMap {
         let first_room_candidates: Vec<Room> = room_candidates
             .iter()
             .map(|(_, room)| room.clone())
             .filter(|room| room.get_start_pos().is_some())
             .collect();
 
-        let non_first_room_candidates: Vec<Room> = room_candidates
+        let mut non_first_room_candidates: Vec<Room> = room_candidates
             .iter()
             .map(|(_, room)| room.clone())
             .filter(|room| room.get_start_pos().is_none())
             .collect();
+        // The training room is not placed at random, it hangs off the first room that is the hub
+        // of the map
+        let mut training_room = non_first_room_candidates
+            .iter()
+            .position(|room| room.is_training_room())
+            .map(|index| non_first_room_candidates.remove(index));
 
         let first_room = first_room_candidates[0].clone();
 
-        println!("First room: {:?}", first_room.get_start_pos());
+        log!("First room: {:?}", first_room.get_start_pos());
 
         assert!(
             first_room.get_start_pos().is_some(),
@@ -221,51 +640,76 @@ impl GameMap {
             rooms,
             doors: Vec::new(),
             all_overlays: Vec::new(),
-            x: 0,
-            y: 0,
-            h: 0,
-            w: 0,
-            overlay: Vec::new(),
-            base: Vec::new(),
+            tiles: ChunkedTiles::default(),
+            revision: next_revision(),
+            generation: Arc::default(),
         };
+        let mut steps = Vec::new();
+        // How many doors away from the first room each placed room is
+        let mut depths = vec![0];
 
         let mut room_count = 1;
         'room_loop: for i in 0..1000 {
-            println!("Iterating for adding a room {}", i);
-            println!(" a) Choosing a random room to try to connect a room to");
-            let random_existing_room_index = rng.random_range(0..game_map.rooms.len());
+            if room_count >= max_rooms {
+                break;
+            }
+            log!("Iterating for adding a room {}", i);
+            log!(" a) Choosing a random room to try to connect a room to");
+            let placing_training_room = training_room.is_some() && i < TRAINING_ROOM_TRIES;
+            let random_existing_room_index = if placing_training_room {
+                0
+            } else {
+                rng.random_range(0..game_map.rooms.len())
+            };
             let random_existing_room = &game_map.rooms[random_existing_room_index];
             if random_existing_room.get_doors().is_empty() {
                 continue;
             }
-            let random_door = random_existing_room.get_doors().choose(&mut rng).unwrap();
+            let random_door = random_existing_room.get_doors().choose(rng).unwrap();
             let random_door_x = random_door.x;
             let random_door_y = random_door.y;
             let door_world_pos = random_existing_room.rel_to_abs((random_door.x, random_door.y));
 
-            println!(" b) Choosing a random room to add");
-            let mut random_new_room = non_first_room_candidates.choose(&mut rng).unwrap().clone();
-            println!(" c) Choosing a random door");
+            log!(" b) Choosing a random room to add");
+            // Rooms only show up at their difficulty or deeper, all of them if none fit. Rooms
+            // close to their difficulty are favored, so easy rooms come up near the first room.
+            let depth = depths[random_existing_room_index] + 1;
+            let paced_candidates: Vec<&Room> = non_first_room_candidates
+                .iter()
+                .filter(|room| room.difficulty <= depth)
+                .collect();
+            let mut random_new_room = if placing_training_room {
+                training_room.clone().unwrap()
+            } else if paced_candidates.is_empty() {
+                non_first_room_candidates.choose(rng).unwrap().clone()
+            } else {
+                (*paced_candidates
+                    .choose_weighted(rng, |room| room.weight_at_depth(depth))
+                    .unwrap())
+                .clone()
+            };
+            log!(" c) Choosing a random door");
             let door_match_candidates: Vec<RoomDoor> = random_new_room
                 .get_doors()
                 .iter()
-                .filter(|door| match door.dir {
-                    DoorDir::Down => random_door.dir == DoorDir::Up,
-                    DoorDir::Up => random_door.dir == DoorDir::Down,
-                    DoorDir::Left => random_door.dir == DoorDir::Right,
-                    DoorDir::Right => random_door.dir == DoorDir::Left,
-                })
+                .filter(|door| door.dir == random_door.dir.opposite())
                 .cloned()
                 .collect();
 
+            let mut step = GenerationStep {
+                existing_room: random_existing_room_index,
+                door: door_world_pos,
+                candidate: None,
+                outcome: GenerationOutcome::NoMatchingDoor,
+            };
             if door_match_candidates.is_empty() {
-                println!(" ERR: Could not find door match from random room");
+                log!(" ERR: Could not find door match from random room");
+                steps.push(step);
                 continue;
             }
 
-            println!(" d) Checking if room overlaps with any other ones");
-            let random_door_where_trying_to_connect =
-                door_match_candidates.choose(&mut rng).unwrap();
+            log!(" d) Checking if room overlaps with any other ones");
+            let random_door_where_trying_to_connect = door_match_candidates.choose(rng).unwrap();
             let new_door_world_pos = random_new_room.rel_to_abs((
                 random_door_where_trying_to_connect.x,
                 random_door_where_trying_to_connect.y,
@@ -283,11 +727,14 @@ impl GameMap {
             let random_new_room_new_y =
                 random_new_room.get_pos().1 + (-new_door_world_pos.1 + door_world_pos.1);
             random_new_room.set_pos((random_new_room_new_x, random_new_room_new_y));
+            step.candidate = Some(random_new_room.get_bounds());
 
             if random_new_room.get_pos().0 == random_existing_room.get_pos().0
                 && random_new_room.get_pos().1 == random_existing_room.get_pos().1
             {
-                println!(" ERR: Room is a direct copy overlapping maybe");
+                log!(" ERR: Room is a direct copy overlapping maybe");
+                step.outcome = GenerationOutcome::SamePosition;
+                steps.push(step);
                 continue;
             }
 
@@ -305,10 +752,18 @@ impl GameMap {
                             (_, BaseTile::NotPartOfRoom) => {}
                             (tile1, tile2) => {
                                 if tile1 != tile2 {
-                                    println!(
+                                    log!(
                                         " ERR: Room overlaps with other one {:?} {:?} ({} {})",
-                                        tile1, tile2, x, y
+                                        tile1,
+                                        tile2,
+                                        x,
+                                        y
                                     );
+                                    step.outcome = GenerationOutcome::Overlaps {
+                                        x: new_room_x + x as i32,
+                                        y: new_room_y + y as i32,
+                                    };
+                                    steps.push(step);
                                     continue 'room_loop;
                                 }
                             }
@@ -317,7 +772,7 @@ impl GameMap {
                 }
             }
 
-            println!(" e) Adding room and clearing doors");
+            log!(" e) Adding room and clearing doors");
             random_new_room.set_base_absolute(
                 random_door_where_trying_to_connect.x,
                 random_door_where_trying_to_connect.y,
@@ -339,60 +794,42 @@ impl GameMap {
                 door_goes_up_down,
             ));
 
+            step.outcome = GenerationOutcome::Placed;
+            steps.push(step);
+            depths.push(depth);
             room_count += 1;
-
-            if room_count >= 10 {
-                break;
+            if placing_training_room {
+                log!(" Training room placed next to the first room");
+                training_room = None;
             }
         }
 
-        let (x, y, w, h) = game_map.get_bounds();
-
-        game_map.x = x;
-        game_map.y = y;
-        game_map.w = w as u32;
-        game_map.h = h as u32;
-
-        game_map.overlay.resize((w * h) as usize, OverlayTile::None);
-        game_map.base.resize((w * h) as usize, BaseTile::Stone);
-
-        for xx in 0..w {
-            for yy in 0..h {
-                let (base, overlay) = game_map.get_at_from_room(x + xx, y + yy);
-
-                game_map.overlay[(xx + yy * w) as usize] = overlay;
-                game_map.base[(xx + yy * w) as usize] = base;
-            }
+        for (room_index, room) in game_map.rooms.iter().enumerate() {
+            game_map.tiles.add_room(room_index, room.get_bounds());
         }
+        let rooms = &game_map.rooms;
+        game_map
+            .tiles
+            .fill(|x, y, room_indexes| get_at_from_rooms(rooms, room_indexes, x, y));
 
         game_map.all_overlays = game_map
             .rooms
             .iter()
             .flat_map(|room| room.get_overlays().clone())
             .collect();
+        game_map.generation = Arc::new(steps);
+        game_map.place_progression(rng);
 
         game_map
     }
 
-    fn get_at_from_room(&self, tx: i32, ty: i32) -> (BaseTile, OverlayTile) {
-        for room in &self.rooms {
-            match room.get_relative(tx, ty) {
-                None => {}
-                Some((BaseTile::NotPartOfRoom, _)) => {}
-                Some(res) => return res,
-            }
-        }
-
-        (BaseTile::Stone, OverlayTile::None)
-    }
-
     pub fn is_room_border_for_some_room(
         &self,
         x: i32,
         y: i32,
         room_indexes: &HashSet<usize>,
     ) -> bool {
-        for room_index in 0..self.rooms.len() {
+        for &room_index in self.tiles.rooms_near(x, y) {
             if !room_indexes.contains(&room_index) {
                 continue;
             }
@@ -474,23 +911,31 @@ impl MapLike for GameMap {
     }
 
     fn get_at(&self, tx: i32, ty: i32) -> (BaseTile, OverlayTile) {
-        if tx < self.x
-            || ty < self.y
-            || tx >= self.x + self.w as i32
-            || ty >= self.y + self.h as i32
-        {
-            return (BaseTile::Stone, OverlayTile::None);
-        }
-        let index = ((tx - self.x) + (ty - self.y) * self.w as i32) as usize;
-        (self.base[index], self.overlay[index])
+        self.tiles.get(tx, ty)
+    }
+
+    fn revision(&self) -> u64 {
+        self.revision
     }
 
     fn get_overlays(&self) -> &Vec<OverlayInfo> {
         &self.all_overlays
     }
 
-    fn set_base(&mut self, _x: i32, _y: i32, _tile: BaseTile) {
-        todo!()
+    fn set_base(&mut self, x: i32, y: i32, tile: BaseTile) {
+        self.tiles.set_base(x, y, tile);
+        self.revision = next_revision();
+
+        // The rooms are drawn on their own during the room transitions, keep them in sync
+        for &room_index in self.tiles.rooms_near(x, y) {
+            let room = &mut self.rooms[room_index];
+            if let Some((base, _)) = room.get_relative(x, y)
+                && base != BaseTile::NotPartOfRoom
+            {
+                let (room_x, room_y) = room.get_pos();
+                room.set_base_absolute((x - room_x) as u32, (y - room_y) as u32, tile);
+            }
+        }
     }
 
     fn set_overlay(&mut self, _x: i32, _y: i32, _tile: OverlayTile) {
@@ -505,7 +950,7 @@ impl MapLike for GameMap {
     }
 
     fn get_room_at_i(&self, x: i32, y: i32) -> Option<(usize, &Room)> {
-        for room_index in 0..self.rooms.len() {
+        for &room_index in self.tiles.rooms_near(x, y) {
             let room = &self.rooms[room_index];
             if let Some((base, _)) = room.get_relative(x, y)
                 && base != BaseTile::NotPartOfRoom
@@ -525,3 +970,63 @@ impl MapLike for GameMap {
         false
     }
 }
+
+// The tile of the first of the rooms that has it as a part of the room
+fn get_at_from_rooms(
+    rooms: &[Room],
+    room_indexes: &[usize],
+    tx: i32,
+    ty: i32,
+) -> (BaseTile, OverlayTile) {
+    for &room_index in room_indexes {
+        match rooms[room_index].get_relative(tx, ty) {
+            None => {}
+            Some((BaseTile::NotPartOfRoom, _)) => {}
+            Some(res) => return res,
+        }
+    }
+
+    (BaseTile::Stone, OverlayTile::None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_maps_have_one_locked_door_and_its_key() {
+        for seed in 0..5 {
+            let map = GameMap::new_random(&mut GameRng::new(seed));
+            let keys = map
+                .get_items_from_templates()
+                .iter()
+                .filter(|item| item.is_key())
+                .count();
+            let (x, y, w, h) = map.get_bounds();
+            let locked_doors = (x..x + w)
+                .flat_map(|x| (y..y + h).map(move |y| (x, y)))
+                .filter(|&(x, y)| map.get_at(x, y).0 == BaseTile::LockedDoor)
+                .count();
+            assert_eq!((keys, locked_doors), (1, 1), "seed {}", seed);
+            assert!(map.is_solvable(), "seed {}", seed);
+
+            // Every ability lock has a pickup for it
+            let pickups: Vec<Ability> = map
+                .get_items_from_templates()
+                .iter()
+                .filter_map(|item| item.ability())
+                .collect();
+            for ability in Ability::ALL {
+                let Some(lock_tile) = ability.lock_tile() else {
+                    continue;
+                };
+                let locked = (x..x + w)
+                    .flat_map(|x| (y..y + h).map(move |y| (x, y)))
+                    .any(|(x, y)| map.get_at(x, y).0 == lock_tile);
+                if locked {
+                    assert!(pickups.contains(&ability), "seed {} {:?}", seed, ability);
+                }
+            }
+        }
+    }
+}
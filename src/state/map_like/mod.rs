@@ -1,7 +1,11 @@
+pub mod chunks;
 pub mod common;
 pub mod game_map;
 pub mod room;
 
-pub use common::{BaseTile, DoorDir, MapLike, ObjectTemplate, ObjectTemplateType, OverlayTile};
+pub use common::{
+    BaseTile, DoorDir, ItemTemplate, ItemTemplateType, MapLike, Material, ObjectTemplate,
+    ObjectTemplateType, OverlayTile,
+};
 pub use game_map::GameMap;
 pub use room::Room;
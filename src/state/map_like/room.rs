@@ -1,7 +1,11 @@
 use super::common::{
-    BaseTile, DoorDir, MapLike, ObjectTemplate, OverlayInfo, OverlayTile, RoomDoor,
+    BaseTile, DoorDir, ItemTemplate, MapLike, ObjectTemplate, OverlayInfo, OverlayTile, RoomDoor,
+    next_revision,
 };
+use crate::state::common::Pos;
 use crate::state::enemies::Enemy;
+use crate::state::item::Item;
+use crate::telemetry::log;
 use serde::{Deserialize, Serialize};
 use std::fs::DirEntry;
 use std::{fs, io, path::Path};
@@ -17,10 +21,44 @@ pub struct Room {
     doors: Vec<RoomDoor>,
     pub object_templates: Vec<ObjectTemplate>,
     #[serde(default = "std::default::Default::default")]
+    pub item_templates: Vec<ItemTemplate>,
+    #[serde(default = "std::default::Default::default")]
     pub disabled: bool,
+    // Doors lock when the player enters until all the enemies in the room are defeated
+    #[serde(default = "std::default::Default::default")]
+    pub is_encounter: bool,
+    // The view scrolls up through the room by itself and lava rises behind the player, see
+    // state::escape
+    #[serde(default = "std::default::Default::default")]
+    pub is_escape: bool,
+    // Only lit by the lights around the player, torches and glowing enemies, see state::lighting
+    #[serde(default = "std::default::Default::default")]
+    pub is_dark: bool,
+    // The map generator only places the room at least this many doors away from the first room,
+    // harder rooms come later on the way through the map
+    #[serde(default = "std::default::Default::default")]
+    pub difficulty: u32,
+    // Clearing the room this many seconds after entering it earns a gold medal, 0 for no par time,
+    // see state::medals
+    #[serde(default = "std::default::Default::default")]
+    pub par_seconds: u32,
+    // Shown in the editor next to the file name
+    #[serde(default = "std::default::Default::default")]
+    pub name: Option<String>,
+    // Picks the background art of the room, see render::BIOME_BACKGROUNDS
+    #[serde(default = "std::default::Default::default")]
+    biome: u32,
+    // Name of the file the room was loaded from, the medals of the room are saved under it
+    #[serde(skip, default)]
+    pub file_name: Option<String>,
+    // Source of the Rhai script next to the room file, see state::scripting
+    #[serde(skip, default)]
+    pub script: Option<String>,
 
     #[serde(skip, default)]
     all_overlays: Vec<OverlayInfo>,
+    #[serde(skip, default = "next_revision")]
+    revision: u64,
 }
 
 impl Room {
@@ -28,6 +66,22 @@ impl Room {
         (self.x, self.y)
     }
 
+    /// Relative chance of the map generator picking the room this many doors away from the
+    /// first room, rooms are favored where their difficulty is close to the depth
+    pub fn weight_at_depth(&self, depth: u32) -> u32 {
+        4 - depth.saturating_sub(self.difficulty).min(3)
+    }
+
+    pub fn biome(&self) -> u32 {
+        self.biome
+    }
+
+    /// The background of the room is drawn again with the art of the biome
+    pub fn set_biome(&mut self, biome: u32) {
+        self.biome = biome;
+        self.revision = next_revision();
+    }
+
     pub fn get_doors(&self) -> &Vec<RoomDoor> {
         &self.doors
     }
@@ -39,9 +93,13 @@ impl Room {
         for t in &mut self.object_templates {
             t.translate(-diff_x as f32, -diff_y as f32);
         }
+        for t in &mut self.item_templates {
+            t.translate(-diff_x as f32, -diff_y as f32);
+        }
 
         self.x = pos.0;
         self.y = pos.1;
+        self.revision = next_revision();
     }
 
     pub fn new_empty(x: i32, y: i32, w: u32, h: u32, base: BaseTile, overlay: OverlayTile) -> Room {
@@ -57,8 +115,19 @@ impl Room {
             overlay,
             doors: Vec::new(),
             object_templates: Vec::new(),
+            item_templates: Vec::new(),
             all_overlays: Vec::new(),
             disabled: false,
+            is_encounter: false,
+            is_escape: false,
+            is_dark: false,
+            difficulty: 0,
+            par_seconds: 0,
+            name: None,
+            biome: 0,
+            file_name: None,
+            script: None,
+            revision: next_revision(),
         }
     }
 
@@ -69,6 +138,42 @@ impl Room {
             .collect()
     }
 
+    pub fn get_items_from_template(&self) -> Vec<Item> {
+        self.item_templates
+            .iter()
+            .filter(|t| !t.is_shop_slot())
+            .filter_map(|t| t.as_item())
+            .collect()
+    }
+
+    pub fn get_checkpoints(&self) -> Vec<Pos> {
+        self.item_templates
+            .iter()
+            .filter(|t| t.is_checkpoint())
+            .map(|t| t.pos())
+            .collect()
+    }
+
+    pub fn get_training_switches(&self) -> Vec<Pos> {
+        self.item_templates
+            .iter()
+            .filter(|t| t.is_training_switch())
+            .map(|t| t.pos())
+            .collect()
+    }
+
+    pub fn is_training_room(&self) -> bool {
+        self.item_templates.iter().any(|t| t.is_training_switch())
+    }
+
+    pub fn get_shop_slots(&self) -> Vec<Pos> {
+        self.item_templates
+            .iter()
+            .filter(|t| t.is_shop_slot())
+            .map(|t| t.pos())
+            .collect()
+    }
+
     pub fn add_object_template(&mut self, template: ObjectTemplate) {
         self.object_templates.push(template)
     }
@@ -166,11 +271,19 @@ impl Room {
 
             let mut room = Self::load_json(&path).unwrap();
             room.update_overlays_cache();
-
-            rooms.push((
-                String::from(path.file_name().unwrap().to_str().unwrap_or("<ERROR>")),
-                room,
-            ));
+            room.script = fs::read_to_string(path.with_extension("rhai")).ok();
+
+            let name = String::from(path.file_name().unwrap().to_str().unwrap_or("<ERROR>"));
+            let fixed = room.fix_unsupported_ladders();
+            if fixed > 0 {
+                log!(
+                    "Extended unsupported ladders of {} by {} tiles",
+                    name,
+                    fixed
+                );
+            }
+            room.file_name = Some(name.clone());
+            rooms.push((name, room));
         }
 
         rooms
@@ -194,6 +307,7 @@ impl Room {
 
     pub fn set_base_absolute(&mut self, x: u32, y: u32, tile: BaseTile) {
         self.base[(x + self.w * y) as usize] = tile;
+        self.revision = next_revision();
     }
 
     pub fn set_overlay_absolute(&mut self, x: u32, y: u32, tile: OverlayTile) {
@@ -249,6 +363,7 @@ impl Room {
         self.w = new_w;
         self.overlay = new_overlay;
         self.base = new_base;
+        self.revision = next_revision();
         for door in &mut self.doors {
             door.x += cols_to_add_left as u32;
             door.y += rows_to_add_top as u32;
@@ -340,6 +455,7 @@ impl Room {
         self.w = new_w;
         self.overlay = new_overlay;
         self.base = new_base;
+        self.revision = next_revision();
 
         for door in &mut self.doors {
             door.x = (door.x as i32 - cols_to_remove_left as i32)
@@ -364,6 +480,27 @@ impl Room {
         }
     }
 
+    /// The direction of a door on the tile, the one side of it that is outside the room. None
+    /// for tiles inside the room, outside it and on its corners.
+    pub fn door_dir_at(&self, x: i32, y: i32) -> Option<DoorDir> {
+        if self.get_at(x, y).0 == BaseTile::NotPartOfRoom {
+            return None;
+        }
+        let mut outside = [
+            (DoorDir::Left, -1, 0),
+            (DoorDir::Right, 1, 0),
+            (DoorDir::Up, 0, -1),
+            (DoorDir::Down, 0, 1),
+        ]
+        .into_iter()
+        .filter(|(_, dx, dy)| self.get_at(x + dx, y + dy).0 == BaseTile::NotPartOfRoom)
+        .map(|(dir, _, _)| dir);
+        match (outside.next(), outside.next()) {
+            (Some(dir), None) => Some(dir),
+            _ => None,
+        }
+    }
+
     pub fn remove_door(&mut self, x: i32, y: i32) {
         println!("Remove door");
         if let Some(rel_pos) = self.abs_to_rel((x, y)) {
@@ -382,6 +519,49 @@ impl Room {
         None
     }
 
+    /// Extends the ladders that end in the air down to the first tile that supports them, when
+    /// only empty tiles are on the way. Returns how many ladder tiles were added.
+    pub fn fix_unsupported_ladders(&mut self) -> usize {
+        let mut added = 0;
+        for info in self.all_overlays.clone() {
+            let (x, y) = (info.x, info.y);
+            // Only the bottoms of the chains
+            if !self.is_ladder_at(x, y) || self.is_ladder_at(x, y + 1) {
+                continue;
+            }
+            if self.is_ladder_supported(x, y) {
+                continue;
+            }
+            let mut bottom = y + 1;
+            while matches!(
+                self.get_relative(x, bottom),
+                Some((BaseTile::Empty, OverlayTile::None))
+            ) {
+                bottom += 1;
+            }
+            if bottom == y + 1 || self.get_relative(x, bottom).is_none() {
+                continue;
+            }
+            for ladder_y in y + 1..bottom {
+                let (rel_x, rel_y) = self.abs_to_rel((x, ladder_y)).unwrap();
+                self.set_overlay_absolute(rel_x, rel_y, OverlayTile::Ladder);
+            }
+            if self.is_ladder_supported(x, y) {
+                added += (bottom - y - 1) as usize;
+            } else {
+                // Did not reach anything to stand on, put it back the way it was
+                for ladder_y in y + 1..bottom {
+                    let (rel_x, rel_y) = self.abs_to_rel((x, ladder_y)).unwrap();
+                    self.set_overlay_absolute(rel_x, rel_y, OverlayTile::None);
+                }
+            }
+        }
+        if added > 0 {
+            self.update_overlays_cache();
+        }
+        added
+    }
+
     pub fn update_overlays_cache(&mut self) {
         let mut all_overlays = Vec::new();
 
@@ -430,6 +610,10 @@ impl MapLike for Room {
             .unwrap_or((BaseTile::NotPartOfRoom, OverlayTile::None))
     }
 
+    fn revision(&self) -> u64 {
+        self.revision
+    }
+
     fn get_overlays(&self) -> &Vec<OverlayInfo> {
         &self.all_overlays
     }
@@ -584,4 +768,32 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_fix_unsupported_ladders() {
+        let mut room = Room::new_empty(0, 0, 3, 6, BaseTile::Empty, OverlayTile::None);
+        for x in 0..3 {
+            room.set_base_absolute(x, 5, BaseTile::Stone);
+        }
+        room.set_overlay_absolute(1, 1, OverlayTile::Ladder);
+        room.update_overlays_cache();
+        assert!(!room.is_ladder_supported(1, 1));
+
+        assert_eq!(room.fix_unsupported_ladders(), 3);
+        assert!(room.is_ladder_supported(1, 1));
+        assert!(room.is_ladder_at(1, 4));
+        assert_eq!(room.fix_unsupported_ladders(), 0);
+    }
+
+    #[test]
+    fn test_door_dir_at() {
+        let room = Room::new_empty(0, 0, 5, 4, BaseTile::Stone, OverlayTile::None);
+        assert_eq!(room.door_dir_at(0, 2), Some(DoorDir::Left));
+        assert_eq!(room.door_dir_at(4, 1), Some(DoorDir::Right));
+        assert_eq!(room.door_dir_at(2, 0), Some(DoorDir::Up));
+        assert_eq!(room.door_dir_at(2, 3), Some(DoorDir::Down));
+        assert_eq!(room.door_dir_at(2, 2), None);
+        assert_eq!(room.door_dir_at(0, 0), None);
+        assert_eq!(room.door_dir_at(-1, 2), None);
+    }
 }
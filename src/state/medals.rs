@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+// Clearing a room within the par time gets gold, within these multiples of it silver and bronze
+const SILVER_PAR_MULTIPLIER: f32 = 1.5;
+const BRONZE_PAR_MULTIPLIER: f32 = 2.0;
+
+// For clearing a room with a par time fast, the best one of each room is kept in the save.
+// Better medals compare greater.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub enum Medal {
+    Bronze,
+    Silver,
+    Gold,
+}
+
+impl Medal {
+    /// The medal for clearing a room in the given game updates, None if it was too slow
+    pub fn for_clear_time(frames: u64, par_seconds: u32) -> Option<Medal> {
+        let seconds = frames as f32 / 60.0;
+        let par = par_seconds as f32;
+        if seconds <= par {
+            Some(Medal::Gold)
+        } else if seconds <= par * SILVER_PAR_MULTIPLIER {
+            Some(Medal::Silver)
+        } else if seconds <= par * BRONZE_PAR_MULTIPLIER {
+            Some(Medal::Bronze)
+        } else {
+            None
+        }
+    }
+
+    /// Color of the medal on the map
+    pub fn color(&self) -> [u8; 4] {
+        match self {
+            Medal::Bronze => [205, 127, 50, 255],
+            Medal::Silver => [220, 225, 235, 255],
+            Medal::Gold => [255, 215, 0, 255],
+        }
+    }
+}
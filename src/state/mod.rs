@@ -1,10 +1,35 @@
+pub mod abilities;
+pub mod ai;
 pub mod animation_handler;
+pub mod boss_bar;
+pub mod bot;
+pub mod breadcrumbs;
+pub mod camera_focus;
+pub mod checkpoints;
 pub mod common;
+pub mod compass;
+pub mod desync;
 pub mod enemies;
+pub mod escape;
+pub mod fight_results;
+pub mod game_rng;
 pub mod game_state;
+pub mod hidden_walls;
 pub mod item;
+pub mod lighting;
+pub mod loot;
 pub mod map_like;
+pub mod medals;
+pub mod particle;
 pub mod player;
+pub mod quest;
+pub mod room_completion;
+pub mod room_streaming;
+pub mod scripting;
+pub mod shop;
+pub mod spatial_grid;
+pub mod training;
+pub mod wallet;
 
 pub use common::{BoundingBox, Dir, Pos};
 pub use game_state::{GameState, InputState};
@@ -0,0 +1,114 @@
+use crate::render::Renderer;
+use crate::state::BoundingBox;
+use rand::Rng;
+
+// Short lived purely visual effect, does not interact with anything
+#[derive(Clone)]
+pub struct Particle {
+    x: f32,
+    y: f32,
+    vx: f32,
+    vy: f32,
+    size: f32,
+    color: [f32; 4],
+    frames_left: u32,
+    total_frames: u32,
+    gravity: f32,
+    drag: f32,
+}
+
+impl Particle {
+    pub fn new(x: f32, y: f32, vx: f32, vy: f32, size: f32, color: [f32; 4], frames: u32) -> Self {
+        Particle {
+            x,
+            y,
+            vx,
+            vy,
+            size,
+            color,
+            frames_left: frames,
+            total_frames: frames,
+            gravity: 0.0,
+            drag: 0.9,
+        }
+    }
+
+    pub fn with_physics(mut self, gravity: f32, drag: f32) -> Self {
+        self.gravity = gravity;
+        self.drag = drag;
+        self
+    }
+
+    // Small cloud of dust kicked up sideways from a point on the floor
+    pub fn dust_puff(x: f32, y: f32) -> Vec<Particle> {
+        let mut rng = rand::rng();
+        (0..8)
+            .map(|i| {
+                let dir = if i % 2 == 0 { 1.0 } else { -1.0 };
+                let shade = rng.random_range(0.65..0.8);
+                Particle::new(
+                    x + rng.random_range(-0.15..0.15),
+                    y - rng.random_range(0.0..0.1),
+                    dir * rng.random_range(0.01..0.04),
+                    -rng.random_range(0.0..0.015),
+                    rng.random_range(2.0..4.0) / 16.0,
+                    [shade, shade, shade * 0.95, 0.8],
+                    rng.random_range(16..28),
+                )
+            })
+            .collect()
+    }
+
+    // Chunks that burst out of a killed enemy, carrying on with the enemy's velocity
+    pub fn fragments(x: f32, y: f32, vx: f32, vy: f32, color: [f32; 4]) -> Vec<Particle> {
+        let mut rng = rand::rng();
+        (0..10)
+            .map(|_| {
+                Particle::new(
+                    x + rng.random_range(-0.2..0.2),
+                    y + rng.random_range(-0.2..0.2),
+                    vx + rng.random_range(-0.05..0.05),
+                    vy - rng.random_range(0.05..0.12),
+                    rng.random_range(1.0..3.0) / 16.0,
+                    color,
+                    rng.random_range(30..50),
+                )
+                .with_physics(0.007, 0.98)
+            })
+            .collect()
+    }
+
+    pub fn update(&mut self) {
+        self.vy += self.gravity;
+        self.x += self.vx;
+        self.y += self.vy;
+        self.vx *= self.drag;
+        self.vy *= self.drag;
+        self.frames_left = self.frames_left.saturating_sub(1);
+    }
+
+    pub fn should_remove(&self) -> bool {
+        self.frames_left == 0
+    }
+
+    pub fn bb(&self) -> BoundingBox {
+        BoundingBox {
+            x: self.x - self.size / 2.0,
+            y: self.y - self.size / 2.0,
+            w: self.size,
+            h: self.size,
+            vx: self.vx,
+            vy: self.vy,
+        }
+    }
+
+    pub fn draw(&self, renderer: &mut Renderer) {
+        // Shrink and fade out towards the end of the life time
+        let life = self.frames_left as f32 / self.total_frames as f32;
+        let size = self.size * (0.5 + 0.5 * life);
+        let mut color = self.color;
+        color[3] *= life;
+
+        renderer.draw_rect(self.x - size / 2.0, self.y - size / 2.0, size, size, color);
+    }
+}
@@ -1,13 +1,19 @@
 use super::common::{BoundingBox, Dir, Health, Pos};
 use super::game_state::InputState;
-use super::map_like::MapLike;
-use crate::camera::Camera;
-use crate::physics::{EPS, check_and_snap_hang, check_and_snap_platforms, integrate_kinematic};
+use super::map_like::{MapLike, Material};
+use crate::physics::{
+    EPS, check_and_snap_hang, check_and_snap_platforms, ground_material, integrate_kinematic,
+    integrate_kinematic_with_platforms,
+};
 use crate::render::{Renderer, TILE_SIZE};
 use crate::sound_handler::{Sound, SoundHandler};
+use crate::state::abilities::{Abilities, Ability};
 use crate::state::animation_handler::{AnimationConfig, AnimationConfigResult, AnimationHandler};
 use crate::state::item::{Item, ItemType};
+use crate::telemetry::log;
+use crate::tuning::tuning;
 
+#[derive(Clone)]
 pub enum PlayerState {
     Normal,
     // TODO: Swinging should be an optional part of Player struct so swinging can happen while on ladder etc.
@@ -17,6 +23,17 @@ pub enum PlayerState {
     Dead,
 }
 
+// Debug toggles for testing, set from the debug menu
+#[derive(Default, Clone)]
+pub struct Cheats {
+    // Nothing hurts the player
+    pub god_mode: bool,
+    // Flies through the walls with the arrow keys
+    pub noclip: bool,
+    // Jumping works in the air too
+    pub infinite_jump: bool,
+}
+
 pub struct SwingState {
     pub pivot: Pos,
     pub end: Pos,
@@ -24,11 +41,17 @@ pub struct SwingState {
     pub length: f32,
 }
 
+#[derive(Clone)]
 pub struct Player {
     pub bb: BoundingBox,
     pub health: Health,
+    // Extra lives, dying with none left is game over
+    pub lives: u32,
     pub immunity_frames: u32,
+    hurt_frames: u32,
     on_ground: bool,
+    // What the ground under the player is made of, for the footsteps
+    ground: Option<Material>,
     safe_edge_frames: u32,
     state: PlayerState,
     max_speed: f32,
@@ -36,9 +59,23 @@ pub struct Player {
     pub dir: Dir,
     animation_handler: AnimationHandler<PlayerAnimationState>,
     item: Option<Item>,
+    // Sprite scale (x, y) for squash and stretch, eases back to (1.0, 1.0)
+    squash: (f32, f32),
+    // Where the player last stood on the ground, used for recovering from falling out of the map
+    last_safe_pos: (f32, f32),
+    pub cheats: Cheats,
+    pub abilities: Abilities,
+    // The double jump and the dash can be used once before landing again
+    air_jumped: bool,
+    dashed: bool,
+    dash_frames: u32,
+    // Time until the next bomb can be dropped
+    bomb_frames: u32,
+    // Game updates spent climbing a vine, for its sway
+    vine_frames: u32,
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, PartialEq)]
 enum PlayerAnimationState {
     Walking,
     Standing,
@@ -48,6 +85,9 @@ enum PlayerAnimationState {
     Hanging,
     Dying,
     Crouching,
+    Swinging,
+    Falling,
+    Hurt,
 }
 
 impl AnimationConfig for PlayerAnimationState {
@@ -57,10 +97,13 @@ impl AnimationConfig for PlayerAnimationState {
             PlayerAnimationState::Standing => AnimationConfigResult::new(8, 9, 80),
             PlayerAnimationState::JumpingSide => AnimationConfigResult::new(10, 10, 15),
             PlayerAnimationState::JumpingDown => AnimationConfigResult::new(11, 11, 15),
-            PlayerAnimationState::Laddering => AnimationConfigResult::new(12, 15, 10),
+            PlayerAnimationState::Laddering => AnimationConfigResult::new_ping_pong(12, 14, 10),
             PlayerAnimationState::Hanging => AnimationConfigResult::new(16, 16, 5),
             PlayerAnimationState::Dying => AnimationConfigResult::new_no_loop(17, 20, 10),
             PlayerAnimationState::Crouching => AnimationConfigResult::new(22, 22, 10),
+            PlayerAnimationState::Swinging => AnimationConfigResult::new(10, 10, 10),
+            PlayerAnimationState::Falling => AnimationConfigResult::new(10, 11, 6),
+            PlayerAnimationState::Hurt => AnimationConfigResult::new(17, 17, 10),
         }
     }
 }
@@ -68,8 +111,39 @@ impl AnimationConfig for PlayerAnimationState {
 pub enum PlayerUpdateResult {
     AddItem { item: Item },
     PickUpItem,
+    // Landed from a large fall, x and y are the point between the feet
+    HardLanding { x: f32, y: f32 },
 }
 
+// Falling faster than this shows the falling animation instead of the jumping one
+const FALLING_VY: f32 = 0.2;
+// How long the hurt animation is shown after taking damage
+const HURT_FRAMES: u32 = 15;
+// Landing faster than this gets the dust puff and the thud sound
+const HARD_LANDING_VY: f32 = 0.25;
+const SQUASH_RECOVERY: f32 = 0.2;
+// How far outside the map bounds (in tiles) the player can go before being brought back
+const OUT_OF_BOUNDS_MARGIN: f32 = 2.0;
+const OUT_OF_BOUNDS_DAMAGE: u32 = 1;
+const NOCLIP_SPEED: f32 = 0.2;
+// (vx, vy) of the push away from whatever hurt the player
+const KNOCKBACK: (f32, f32) = (0.12, -0.1);
+// Frames of holding jump that extend the bounce after stomping
+const STOMP_MAX_JUMP_FRAMES: u32 = 14;
+const STARTING_LIVES: u32 = 3;
+// Frames of holding jump that extend the double jump, less than the jump from the ground gets
+const DOUBLE_JUMP_MAX_JUMP_FRAMES: u32 = 6;
+const DASH_FRAMES: u32 = 10;
+const DASH_SPEED: f32 = 0.22;
+const BOMB_COOLDOWN_FRAMES: u32 = 60;
+const MAX_LIVES: u32 = 9;
+// The wind swings a vine this many tiles to each side of its tile, and around once in this many
+// game updates
+const VINE_SWAY: f32 = 0.2;
+const VINE_SWAY_FRAMES: f32 = 150.0;
+// The feet hit the ground on these frames of the walking animation
+const STEP_FRAMES: [u32; 2] = [2, 6];
+
 impl Player {
     pub fn new(x: f32, y: f32) -> Self {
         Player {
@@ -82,8 +156,11 @@ impl Player {
                 vy: 0.0,
             },
             health: Health { current: 4, max: 4 },
+            lives: STARTING_LIVES,
             immunity_frames: 0,
+            hurt_frames: 0,
             on_ground: false,
+            ground: None,
             safe_edge_frames: 0,
             state: PlayerState::Normal,
             max_speed: 0.06,
@@ -92,6 +169,15 @@ impl Player {
             animation_handler: AnimationHandler::new(PlayerAnimationState::Standing),
             // item: None,
             item: Some(Item::new(0.0, 0.0, ItemType::Box)),
+            squash: (1.0, 1.0),
+            last_safe_pos: (x, y),
+            cheats: Cheats::default(),
+            abilities: Abilities::default(),
+            air_jumped: false,
+            dashed: false,
+            dash_frames: 0,
+            bomb_frames: 0,
+            vine_frames: 0,
         }
     }
 
@@ -99,7 +185,20 @@ impl Player {
         self.item = Some(item);
     }
 
-    pub fn draw(&self, renderer: &mut Renderer, camera: &Camera) {
+    pub fn holds_key(&self) -> bool {
+        self.item.as_ref().is_some_and(|item| item.is_key())
+    }
+
+    /// The key in hand is used up, returns false if there was no key
+    pub fn use_key(&mut self) -> bool {
+        if !self.holds_key() {
+            return false;
+        }
+        self.item = None;
+        true
+    }
+
+    pub fn draw(&self, renderer: &mut Renderer) {
         let px = self.bb.x;
         let py = self.bb.y;
         let pw = self.bb.w;
@@ -111,7 +210,7 @@ impl Player {
             1.0
         };
 
-        renderer.draw_from_texture_atlas(
+        renderer.draw_from_texture_atlas_scaled(
             "character",
             self.animation_handler.get_atlas_index(),
             match self.dir {
@@ -123,6 +222,7 @@ impl Player {
             pw + 2.0 / crate::render::TILE_SIZE,
             ph + 2.0 / crate::render::TILE_SIZE,
             alpha,
+            self.squash,
         );
 
         if let Some(item) = &self.item {
@@ -132,39 +232,118 @@ impl Player {
                 } else {
                     0.0
                 };
-            item.draw_fake_xy(renderer, self.bb.x, self.bb.y + crouch_offset_y);
-        }
-
-        // Draw the sword as the last step
-        if let Some(swing_info) = self.get_swing_info() {
-            renderer.draw_rect_rotated(
-                camera,
-                swing_info.pivot.x - 0.05,
-                swing_info.pivot.y - 0.15,
-                0.1,
-                swing_info.length + 0.15,
-                swing_info.pivot.x,
-                swing_info.pivot.y,
-                swing_info.angle_rad,
-                [0.5, 0.5, 0.5, 1.0],
+            // Carried above the head, resting a pixel into it
+            item.draw_fake_xy(
+                renderer,
+                self.bb.x + (self.bb.w - item.bb().w) / 2.0,
+                self.bb.y - item.bb().h + 1.0 / TILE_SIZE + crouch_offset_y,
             );
+        }
 
-            renderer.draw_rect(
-                camera,
-                swing_info.end.x - 0.05,
-                swing_info.end.y - 0.05,
-                0.1,
-                0.1,
-                [1.0, 0.5, 0.5, 1.0],
-            )
+        // Draw the sword as the last step. The previous positions of the swing are drawn
+        // first with the fainter sword frames as a trail.
+        if let PlayerState::Swinging {
+            total_frames,
+            frames_left,
+        } = self.state
+        {
+            for (atlas_index, frames_behind) in [(2, 4), (1, 2), (0, 0)] {
+                let swing_info = Self::swing_info_at(
+                    &self.bb,
+                    self.dir,
+                    total_frames,
+                    (frames_left + frames_behind).min(total_frames),
+                );
+                // Stretch the sprite so that the tip is at the end of the hit line
+                let handle_w = 2.0 / TILE_SIZE;
+                let sword_w = swing_info.length + handle_w;
+                let sword_h = 5.0 / TILE_SIZE * sword_w / (12.0 / TILE_SIZE);
+                // The sword sprite points right, the swing angle has the same quarter circle
+                // offset as the hit line
+                renderer.draw_from_texture_atlas_rotated(
+                    "sword",
+                    atlas_index,
+                    false,
+                    self.dir == Dir::Left,
+                    swing_info.pivot.x - handle_w,
+                    swing_info.pivot.y - sword_h / 2.0,
+                    sword_w,
+                    sword_h,
+                    (swing_info.pivot.x, swing_info.pivot.y),
+                    swing_info.angle_rad + std::f32::consts::PI / 2.0,
+                );
+            }
         }
     }
 
+    /// Dashing breaks the dash barriers the player runs in to
+    pub fn is_dashing(&self) -> bool {
+        self.dash_frames > 0
+    }
+
+    pub fn last_safe_pos(&self) -> (f32, f32) {
+        self.last_safe_pos
+    }
+
+    /// Returns false if the player already has the maximum amount of lives
+    pub fn add_life(&mut self) -> bool {
+        if self.lives >= MAX_LIVES {
+            return false;
+        }
+        self.lives += 1;
+        true
+    }
+
+    /// Returns false if the player already has full health
+    pub fn heal(&mut self) -> bool {
+        if self.health.current >= self.health.max {
+            return false;
+        }
+        self.health.current += 1;
+        true
+    }
+
+    pub fn is_dead(&self) -> bool {
+        matches!(self.state, PlayerState::Dead)
+    }
+
+    pub fn death_animation_finished(&self) -> bool {
+        self.is_dead() && self.animation_handler.finished()
+    }
+
+    // Brings the player back to life with full health at the given position
+    pub fn respawn(&mut self, x: f32, y: f32) {
+        self.bb.x = x;
+        self.bb.y = y;
+        self.bb.vx = 0.0;
+        self.bb.vy = 0.0;
+        self.health.current = self.health.max;
+        self.state = PlayerState::Normal;
+        self.immunity_frames = 60;
+        self.hurt_frames = 0;
+        self.last_safe_pos = (x, y);
+        self.animation_handler
+            .set_state(PlayerAnimationState::Standing);
+    }
+
+    /// Moves the player so that its feet are at the given spot, keeping the health
+    pub fn teleport(&mut self, x: f32, feet_y: f32) {
+        self.bb.x = x - self.bb.w / 2.0;
+        self.bb.y = feet_y - self.bb.h;
+        self.bb.vx = 0.0;
+        self.bb.vy = 0.0;
+        self.state = PlayerState::Normal;
+        self.last_safe_pos = (self.bb.x, self.bb.y);
+    }
+
     pub fn can_be_hit(&self) -> bool {
-        self.immunity_frames == 0
+        self.immunity_frames == 0 && !self.cheats.god_mode
     }
 
     pub fn got_hit(&mut self, damage: u32) {
+        if self.cheats.god_mode {
+            return;
+        }
         self.health.decrease_by(damage);
 
         // If no health set to dead
@@ -174,6 +353,24 @@ impl Player {
         // If we took damage set the immunity frmaes
         if damage > 0 {
             self.immunity_frames = 60;
+            self.hurt_frames = HURT_FRAMES;
+        }
+    }
+
+    /// Pushed away from the spot the hit came from, lets go of ladders and ledges
+    pub fn knock_back(&mut self, from_x: f32) {
+        let dir = if from_x < self.bb.center().x {
+            1.0
+        } else {
+            -1.0
+        };
+        self.bb.vx = dir * KNOCKBACK.0;
+        self.bb.vy = KNOCKBACK.1;
+        if matches!(
+            self.state,
+            PlayerState::Hanging { .. } | PlayerState::OnLadder
+        ) {
+            self.state = PlayerState::Normal;
         }
     }
 
@@ -184,9 +381,11 @@ impl Player {
         false
     }
 
+    // Bounces off the enemy, holding jump makes the bounce higher just like with a normal jump
     pub fn apply_stomping(&mut self, other_top_y: f32) {
         self.bb.y = other_top_y - self.bb.h - EPS;
         self.bb.vy = -0.12;
+        self.max_jump_frames = STOMP_MAX_JUMP_FRAMES;
     }
 
     pub fn get_swing_info(&self) -> Option<SwingState> {
@@ -194,44 +393,56 @@ impl Player {
             PlayerState::Swinging {
                 total_frames,
                 frames_left,
-            } => {
-                // Swing moves half a circle in total
-                let total_rads = std::f32::consts::PI;
+            } => Some(Self::swing_info_at(
+                &self.bb,
+                self.dir,
+                total_frames,
+                frames_left,
+            )),
+            _ => None,
+        }
+    }
 
-                let start_angle = match self.dir {
-                    Dir::Left => std::f32::consts::PI * 0.5 - 0.3,
-                    Dir::Right => std::f32::consts::PI * 0.5 + 0.3,
-                };
+    fn swing_info_at(
+        bb: &BoundingBox,
+        dir: Dir,
+        total_frames: u32,
+        frames_left: u32,
+    ) -> SwingState {
+        // Swing moves half a circle in total
+        let total_rads = std::f32::consts::PI;
+
+        let start_angle = match dir {
+            Dir::Left => std::f32::consts::PI * 0.5 - 0.3,
+            Dir::Right => std::f32::consts::PI * 0.5 + 0.3,
+        };
 
-                let fraction = match self.dir {
-                    Dir::Left => frames_left as f32 / total_frames as f32,
-                    Dir::Right => (total_frames - frames_left) as f32 / total_frames as f32,
-                };
+        let fraction = match dir {
+            Dir::Left => frames_left as f32 / total_frames as f32,
+            Dir::Right => (total_frames - frames_left) as f32 / total_frames as f32,
+        };
 
-                let angle_rad = start_angle + fraction * total_rads;
+        let angle_rad = start_angle + fraction * total_rads;
 
-                let dir_move = match self.dir {
-                    Dir::Left => -0.1,
-                    Dir::Right => 0.1,
-                };
+        let dir_move = match dir {
+            Dir::Left => -0.1,
+            Dir::Right => 0.1,
+        };
 
-                let pivot_x = self.bb.x + self.bb.w / 2.0 + dir_move;
-                let pivot_y = self.bb.y + self.bb.h / 2.0 + 0.05;
+        let pivot_x = bb.x + bb.w / 2.0 + dir_move;
+        let pivot_y = bb.y + bb.h / 2.0 + 0.05;
 
-                let length = 0.8;
+        let length = 0.8;
 
-                // Drawing uses angles weirdly. Adding 1/4 circle to the angle here as hacky fix
-                let end_x = pivot_x + (angle_rad + std::f32::consts::PI / 2.0).cos() * length;
-                let end_y = pivot_y + (angle_rad + std::f32::consts::PI / 2.0).sin() * length;
+        // Drawing uses angles weirdly. Adding 1/4 circle to the angle here as hacky fix
+        let end_x = pivot_x + (angle_rad + std::f32::consts::PI / 2.0).cos() * length;
+        let end_y = pivot_y + (angle_rad + std::f32::consts::PI / 2.0).sin() * length;
 
-                Some(SwingState {
-                    angle_rad,
-                    pivot: Pos::new(pivot_x, pivot_y),
-                    end: Pos::new(end_x, end_y),
-                    length,
-                })
-            }
-            _ => None,
+        SwingState {
+            angle_rad,
+            pivot: Pos::new(pivot_x, pivot_y),
+            end: Pos::new(end_x, end_y),
+            length,
         }
     }
 
@@ -276,18 +487,33 @@ impl Player {
         if self.on_ground {
             self.safe_edge_frames = 4;
             self.max_jump_frames = 10;
+            self.air_jumped = false;
+            self.dashed = false;
         } else if self.safe_edge_frames > 0 {
             self.safe_edge_frames -= 1;
         }
 
         // 1. Want to jump 2. Not trying to go down ledge 3. Can jump
-        if input.jump_pressed && !input.down && (self.on_ground || self.safe_edge_frames > 0) {
+        if input.jump_pressed
+            && !input.down
+            && (self.on_ground || self.safe_edge_frames > 0 || self.cheats.infinite_jump)
+        {
             sound_handler.play(Sound::Jump);
             self.safe_edge_frames = 0;
-            self.bb.vy = -0.125;
+            self.bb.vy = -tuning().jump_velocity;
+        } else if input.jump_pressed
+            && !input.down
+            && self.abilities.has(Ability::DoubleJump)
+            && !self.air_jumped
+        {
+            sound_handler.play(Sound::Jump);
+            self.air_jumped = true;
+            self.max_jump_frames = DOUBLE_JUMP_MAX_JUMP_FRAMES;
+            self.bb.vy = -tuning().jump_velocity;
+            self.squash = (0.85, 1.15);
         } else if input.jump_held && !input.down && self.max_jump_frames > 0 {
             self.max_jump_frames -= 1;
-            self.bb.vy = -0.125;
+            self.bb.vy = -tuning().jump_velocity;
         } else {
             self.max_jump_frames = 0; // if no input.jump reset to 0
         }
@@ -345,17 +571,47 @@ impl Player {
             }
         }
 
-        let res = integrate_kinematic(map, &self.bb, true);
+        if input.dash_pressed
+            && self.abilities.has(Ability::Dash)
+            && !self.dashed
+            && let PlayerState::Normal = self.state
+        {
+            sound_handler.play(Sound::Swing);
+            self.dashed = true;
+            self.dash_frames = DASH_FRAMES;
+            self.squash = (1.3, 0.8);
+        }
+        // Dashes straight forward without falling
+        let dashing = self.dash_frames > 0;
+        if dashing {
+            self.dash_frames -= 1;
+            self.bb.vx = match self.dir {
+                Dir::Left => -DASH_SPEED,
+                Dir::Right => DASH_SPEED,
+            };
+            self.bb.vy = 0.0;
+        }
+
+        if input.bomb_pressed && self.abilities.has(Ability::Bomb) && self.bomb_frames == 0 {
+            self.bomb_frames = BOMB_COOLDOWN_FRAMES;
+            let center = self.bb.center();
+            update_results.push(PlayerUpdateResult::AddItem {
+                item: Item::new_bomb(center.x, center.y),
+            });
+        }
+
+        let res = integrate_kinematic(map, &self.bb, !dashing);
         let mut new_bb = res.new_bb;
         let mut on_ground = res.on_bottom;
 
-        let could_ladder = map.is_ladder_at(
+        let could_ladder = map.is_climbable_at(
             (new_bb.x + new_bb.w * 0.5).floor() as i32,
             (new_bb.y + new_bb.h * 0.5).floor() as i32,
         );
 
         if could_ladder && (input.up || input.down) && !(input.down && res.on_bottom) {
             self.state = PlayerState::OnLadder;
+            self.dash_frames = 0;
             let middle_tx = (new_bb.x + new_bb.w * 0.5).floor() as i32;
             self.bb.x = (middle_tx as f32 + 0.5) - self.bb.w * 0.5;
             self.bb.vx = 0.0;
@@ -367,7 +623,8 @@ impl Player {
             on_ground |= check_and_snap_platforms(&self.bb, &mut new_bb, map);
         }
 
-        if self.bb.vy > 0.0 && (pressing_left || pressing_right) {
+        let wants_to_hang = input.grab_held || !tuning().hang_needs_grab_key;
+        if self.bb.vy > 0.0 && (pressing_left || pressing_right) && wants_to_hang {
             let dir: Dir = if pressing_right {
                 Dir::Right
             } else {
@@ -375,6 +632,7 @@ impl Player {
             };
             if let Some(hang_pos) = check_and_snap_hang(&self.bb, &new_bb, map, dir) {
                 self.state = PlayerState::Hanging { pos: hang_pos };
+                self.dash_frames = 0;
                 self.dir = dir;
                 self.bb.vy = 0.0;
                 self.on_ground = false;
@@ -382,10 +640,42 @@ impl Player {
             }
         }
 
+        // Platforms the player was snapped on are not in the result
+        let ground = if on_ground {
+            res.ground.or_else(|| ground_material(map, &new_bb))
+        } else {
+            None
+        };
+
+        if on_ground && !self.on_ground {
+            // Landing, squash by how hard we hit the ground
+            let impact_vy = self.bb.vy;
+            let amount = ((impact_vy - 0.05) * 1.2).clamp(0.0, 0.35);
+            self.squash = (1.0 + amount, 1.0 - amount);
+
+            if impact_vy > HARD_LANDING_VY {
+                sound_handler.play(Sound::Land.on(ground));
+                update_results.push(PlayerUpdateResult::HardLanding {
+                    x: new_bb.x + new_bb.w * 0.5,
+                    y: new_bb.y + new_bb.h,
+                });
+            }
+        } else if !on_ground && self.bb.vy < 0.0 && new_bb.vy >= 0.0 {
+            // Jump apex
+            self.squash = (0.85, 1.15);
+        }
+
         self.bb = new_bb;
         self.on_ground = on_ground;
+        self.ground = ground;
+        if on_ground {
+            self.last_safe_pos = (self.bb.x, self.bb.y);
+        }
 
-        if self.on_ground {
+        if let PlayerState::Swinging { .. } = self.state {
+            self.animation_handler
+                .set_state(PlayerAnimationState::Swinging);
+        } else if self.on_ground {
             if pressing_right || pressing_left {
                 self.animation_handler
                     .set_state(PlayerAnimationState::Walking);
@@ -396,6 +686,9 @@ impl Player {
                 self.animation_handler
                     .set_state(PlayerAnimationState::Standing);
             }
+        } else if self.bb.vy > FALLING_VY {
+            self.animation_handler
+                .set_state(PlayerAnimationState::Falling);
         } else if pressing_right || pressing_left {
             self.animation_handler
                 .set_state(PlayerAnimationState::JumpingSide);
@@ -407,6 +700,38 @@ impl Player {
         update_results
     }
 
+    // On a vine the player swings from side to side with it, unless that would push them in to a
+    // wall. On a ladder they stay in the middle of it.
+    fn sway_on_vine(&mut self, map: &dyn MapLike, middle_tx: i32) {
+        let middle_ty = (self.bb.y + self.bb.h * 0.5).floor() as i32;
+        if !map.is_vine_at(middle_tx, middle_ty) {
+            self.vine_frames = 0;
+            return;
+        }
+        self.vine_frames += 1;
+        let phase = self.vine_frames as f32 / VINE_SWAY_FRAMES * std::f32::consts::TAU;
+        let x = middle_tx as f32 + 0.5 - self.bb.w * 0.5 + VINE_SWAY * phase.sin();
+        if !map.overlaps_solid(x, self.bb.y, self.bb.w, self.bb.h) {
+            self.bb.x = x;
+        }
+    }
+
+    // Moves freely without the map or gravity, the map bounds do not bring the player back
+    fn update_noclip(&mut self, input: &InputState) {
+        let axis = |negative: bool, positive: bool| positive as i32 as f32 - negative as i32 as f32;
+        self.bb.vx = 0.0;
+        self.bb.vy = 0.0;
+        self.bb.x += axis(input.left, input.right) * NOCLIP_SPEED;
+        self.bb.y += axis(input.up, input.down) * NOCLIP_SPEED;
+        self.on_ground = false;
+        if !matches!(self.state, PlayerState::Dead) {
+            self.state = PlayerState::Normal;
+        }
+        self.animation_handler
+            .set_state(PlayerAnimationState::JumpingSide);
+        self.animation_handler.increment_frame();
+    }
+
     pub fn update(
         &mut self,
         input: &InputState,
@@ -417,6 +742,15 @@ impl Player {
 
         let mut increment_frame = true;
         self.immunity_frames = self.immunity_frames.saturating_sub(1);
+        self.bomb_frames = self.bomb_frames.saturating_sub(1);
+
+        self.squash.0 += (1.0 - self.squash.0) * SQUASH_RECOVERY;
+        self.squash.1 += (1.0 - self.squash.1) * SQUASH_RECOVERY;
+
+        if self.cheats.noclip {
+            self.update_noclip(input);
+            return update_results;
+        }
 
         match &self.state {
             PlayerState::Hanging { pos, .. } => {
@@ -454,7 +788,7 @@ impl Player {
                 update_results.append(&mut self._handle_normal(input, map, sound_handler));
             }
             PlayerState::Dead => {
-                let res = integrate_kinematic(map, &self.bb, true);
+                let res = integrate_kinematic_with_platforms(map, &self.bb, true);
                 self.bb = res.new_bb;
                 self.on_ground = res.on_bottom;
 
@@ -471,15 +805,16 @@ impl Player {
                 if input.jump_pressed && !input.up {
                     sound_handler.play(Sound::Jump);
                     self.state = PlayerState::Normal;
-                    self.bb.vy = -0.125;
+                    self.bb.vy = -tuning().jump_velocity;
                     self.bb.y += 0.05;
                     return update_results;
                 }
 
                 let middle_tx = (self.bb.x + self.bb.w * 0.5).floor() as i32;
                 let head_ty = (self.bb.y).floor() as i32;
-                let ladder_at_head = map.is_ladder_at(middle_tx, head_ty);
-                let ladder_at_below = map.is_ladder_at(middle_tx, head_ty + 1);
+                let ladder_at_head = map.is_climbable_at(middle_tx, head_ty);
+                let ladder_at_below = map.is_climbable_at(middle_tx, head_ty + 1);
+                self.sway_on_vine(map, middle_tx);
 
                 if input.up && !input.down {
                     if ladder_at_head {
@@ -510,9 +845,46 @@ impl Player {
             }
         }
 
+        // Fell below the map or otherwise escaped it, take damage and go back to solid ground
+        let (map_x, map_y, map_w, map_h) = map.get_bounds();
+        let center = self.bb.get_center();
+        if center.x < map_x as f32 - OUT_OF_BOUNDS_MARGIN
+            || center.x > (map_x + map_w as i32) as f32 + OUT_OF_BOUNDS_MARGIN
+            || center.y < map_y as f32 - OUT_OF_BOUNDS_MARGIN
+            || center.y > (map_y + map_h as i32) as f32 + OUT_OF_BOUNDS_MARGIN
+        {
+            log!(
+                "Player out of bounds, returning to {:?}",
+                self.last_safe_pos
+            );
+            self.bb.x = self.last_safe_pos.0;
+            self.bb.y = self.last_safe_pos.1;
+            self.bb.vx = 0.0;
+            self.bb.vy = 0.0;
+            if !matches!(self.state, PlayerState::Dead) {
+                self.state = PlayerState::Normal;
+                sound_handler.play(Sound::PlayerHit);
+                self.got_hit(OUT_OF_BOUNDS_DAMAGE);
+            }
+        }
+
+        // Getting hurt overrides the other animations for a moment
+        self.hurt_frames = self.hurt_frames.saturating_sub(1);
+        if self.hurt_frames > 0 && !matches!(self.state, PlayerState::Dead) {
+            self.animation_handler.set_state(PlayerAnimationState::Hurt);
+            increment_frame = true;
+        }
+
         if increment_frame {
             self.animation_handler.increment_frame();
         }
+        if *self.animation_handler.current_state() == PlayerAnimationState::Walking
+            && STEP_FRAMES
+                .iter()
+                .any(|&frame| self.animation_handler.entered_frame(frame))
+        {
+            sound_handler.play(Sound::Walk.on(self.ground));
+        }
 
         update_results
     }
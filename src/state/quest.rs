@@ -0,0 +1,288 @@
+use crate::camera::Camera;
+use crate::pixel_font;
+use crate::render::Renderer;
+use crate::state::common::Pos;
+use crate::state::compass;
+use crate::state::map_like::GameMap;
+use std::collections::HashSet;
+
+const COIN_OBJECTIVE: u32 = 30;
+const MAX_ENCOUNTER_OBJECTIVE: u32 = 3;
+// "Objective complete" stays on the screen this long
+const COMPLETED_FRAMES: u32 = 150;
+// How far from the player the tracker arrow is, in screen pixels
+const TRACKER_DISTANCE: f32 = 36.0;
+
+const TEXT_COLOR: [f32; 4] = [0.95, 0.92, 0.85, 1.0];
+const DONE_COLOR: [f32; 4] = [0.55, 0.55, 0.55, 1.0];
+const TRACKER_COLOR: [f32; 4] = [0.98, 0.8, 0.2, 1.0];
+
+#[derive(Clone)]
+pub enum Objective {
+    ReachRoom {
+        room_index: usize,
+        description: &'static str,
+    },
+    ClearEncounters {
+        count: u32,
+    },
+    CollectCoins {
+        count: u32,
+    },
+}
+
+/// What the objectives are checked against, updated by the game every frame
+pub struct QuestProgress<'a> {
+    pub cur_room_index: Option<usize>,
+    pub cleared_encounter_rooms: &'a HashSet<usize>,
+    pub coins: u32,
+    pub rooms_completed: usize,
+    pub room_count: usize,
+    pub cur_room_progress: Option<(u32, u32)>,
+    pub secrets: (u32, u32),
+}
+
+impl Objective {
+    fn is_done(&self, progress: &QuestProgress) -> bool {
+        match self {
+            Objective::ReachRoom { room_index, .. } => progress.cur_room_index == Some(*room_index),
+            Objective::ClearEncounters { count } => {
+                progress.cleared_encounter_rooms.len() as u32 >= *count
+            }
+            Objective::CollectCoins { count } => progress.coins >= *count,
+        }
+    }
+
+    fn description(&self, progress: &QuestProgress) -> String {
+        match self {
+            Objective::ReachRoom { description, .. } => description.to_string(),
+            Objective::ClearEncounters { count } => format!(
+                "Clear encounters {}/{}",
+                (progress.cleared_encounter_rooms.len() as u32).min(*count),
+                count
+            ),
+            Objective::CollectCoins { count } => {
+                format!("Collect coins {}/{}", progress.coins.min(*count), count)
+            }
+        }
+    }
+
+    // The room the tracker points towards, objectives without a place have none
+    fn target_room(&self, map: &GameMap, progress: &QuestProgress) -> Option<usize> {
+        match self {
+            Objective::ReachRoom { room_index, .. } => Some(*room_index),
+            Objective::ClearEncounters { .. } => {
+                let cur_room_index = progress.cur_room_index?;
+                let distances = map.room_distances(cur_room_index);
+                (0..map.rooms.len())
+                    .filter(|index| {
+                        map.rooms[*index].is_encounter
+                            && !progress.cleared_encounter_rooms.contains(index)
+                    })
+                    .filter_map(|index| Some((distances[index]?, index)))
+                    .min()
+                    .map(|(_, index)| index)
+            }
+            Objective::CollectCoins { .. } => None,
+        }
+    }
+}
+
+// The objectives of a run, done one at a time in order. The active one is shown with an arrow
+// next to the player pointing towards the door that leads to it.
+#[derive(Clone)]
+pub struct QuestLog {
+    objectives: Vec<Objective>,
+    active: usize,
+    completed_frames: u32,
+}
+
+impl QuestLog {
+    /// Picks the objectives from what there is in the map
+    pub fn new(map: &GameMap, start_room_index: Option<usize>) -> QuestLog {
+        let mut objectives = Vec::new();
+
+        if let Some(shop_room) = map
+            .rooms
+            .iter()
+            .position(|room| !room.get_shop_slots().is_empty())
+        {
+            objectives.push(Objective::ReachRoom {
+                room_index: shop_room,
+                description: "Find the shop",
+            });
+        }
+
+        let encounter_rooms = map.rooms.iter().filter(|room| room.is_encounter).count() as u32;
+        if encounter_rooms > 0 {
+            objectives.push(Objective::ClearEncounters {
+                count: encounter_rooms.min(MAX_ENCOUNTER_OBJECTIVE),
+            });
+        }
+
+        objectives.push(Objective::CollectCoins {
+            count: COIN_OBJECTIVE,
+        });
+
+        // The room furthest away from the start is the last thing to reach
+        if let Some(start_room_index) = start_room_index
+            && let Some(deepest_room) = map
+                .room_distances(start_room_index)
+                .iter()
+                .enumerate()
+                .filter_map(|(index, distance)| Some((distance.as_ref()?, index)))
+                .max()
+                .map(|(_, index)| index)
+                .filter(|&index| index != start_room_index)
+        {
+            objectives.push(Objective::ReachRoom {
+                room_index: deepest_room,
+                description: "Reach the deepest room",
+            });
+        }
+
+        QuestLog {
+            objectives,
+            active: 0,
+            completed_frames: 0,
+        }
+    }
+
+    pub fn update(&mut self, progress: &QuestProgress) {
+        self.completed_frames = self.completed_frames.saturating_sub(1);
+        while let Some(objective) = self.objectives.get(self.active)
+            && objective.is_done(progress)
+        {
+            self.active += 1;
+            self.completed_frames = COMPLETED_FRAMES;
+        }
+    }
+
+    /// The room of the active objective, if it has one
+    pub fn target_room(&self, map: &GameMap, progress: &QuestProgress) -> Option<usize> {
+        self.objectives
+            .get(self.active)
+            .and_then(|objective| objective.target_room(map, progress))
+    }
+
+    /// The objectives with the done ones greyed out and how much of the rooms is done, shown
+    /// while the quest log key is held
+    pub fn draw_log(&self, renderer: &mut Renderer, progress: &QuestProgress) {
+        let pixel_size = 3.0;
+        let line_height = (pixel_font::GLYPH_HEIGHT as f32 + 3.0) * pixel_size;
+        let box_size = pixel_font::GLYPH_HEIGHT as f32 * pixel_size;
+        let padding = 10.0;
+        let x = padding + 6.0;
+        let mut y = padding + 6.0;
+
+        let mut completion_lines = vec![
+            format!(
+                "Rooms complete {}/{}",
+                progress.rooms_completed, progress.room_count
+            ),
+            format!("Secrets {}/{}", progress.secrets.0, progress.secrets.1),
+        ];
+        if let Some((done, total)) = progress.cur_room_progress {
+            completion_lines.push(format!("This room {}/{}", done, total));
+        }
+
+        let objectives_width = self
+            .objectives
+            .iter()
+            .map(|objective| pixel_font::text_width(&objective.description(progress)))
+            .max()
+            .unwrap_or(0) as f32
+            * pixel_size
+            + box_size * 2.0;
+        let completion_width = completion_lines
+            .iter()
+            .map(|line| pixel_font::text_width(line))
+            .max()
+            .unwrap_or(0) as f32
+            * pixel_size;
+        let width = objectives_width.max(completion_width);
+        let lines = 1 + self.objectives.len() + 1 + completion_lines.len();
+        let height = line_height * lines as f32;
+        renderer.draw_rect_hud(
+            padding,
+            padding,
+            width + 12.0,
+            height + 12.0,
+            [0.05, 0.05, 0.08, 0.8],
+        );
+
+        renderer.draw_text_hud("Objectives", x, y, pixel_size, TEXT_COLOR);
+        y += line_height;
+        for (index, objective) in self.objectives.iter().enumerate() {
+            let done = index < self.active;
+            let color = if done { DONE_COLOR } else { TEXT_COLOR };
+            renderer.draw_rect_hud(x, y, box_size, box_size, color);
+            if !done {
+                renderer.draw_rect_hud(
+                    x + pixel_size,
+                    y + pixel_size,
+                    box_size - pixel_size * 2.0,
+                    box_size - pixel_size * 2.0,
+                    [0.05, 0.05, 0.08, 1.0],
+                );
+            }
+            renderer.draw_text_hud(
+                &objective.description(progress),
+                x + box_size * 2.0,
+                y,
+                pixel_size,
+                color,
+            );
+            y += line_height;
+        }
+
+        y += line_height;
+        for line in &completion_lines {
+            renderer.draw_text_hud(line, x, y, pixel_size, TEXT_COLOR);
+            y += line_height;
+        }
+    }
+
+    pub fn draw_hud(
+        &self,
+        renderer: &mut Renderer,
+        camera: &Camera,
+        map: &GameMap,
+        progress: &QuestProgress,
+        player_center: Pos,
+    ) {
+        if self.completed_frames > 0 {
+            let text = "Objective complete";
+            let pixel_size = 4.0;
+            let text_width = pixel_font::text_width(text) as f32 * pixel_size;
+            renderer.draw_text_hud(
+                text,
+                (camera.screen_w - text_width) / 2.0,
+                camera.screen_h * 0.2,
+                pixel_size,
+                TEXT_COLOR,
+            );
+        }
+
+        // The arrow points at the first door on the way to the objective
+        let Some(cur_room_index) = progress.cur_room_index else {
+            return;
+        };
+        let Some(target_room) = self.target_room(map, progress) else {
+            return;
+        };
+        let Some(door) = map.next_door_towards(cur_room_index, target_room) else {
+            return;
+        };
+
+        let (px, py) = camera.tile_to_screen(player_center.x, player_center.y);
+        let (dx, dy) = camera.tile_to_screen(door.x as f32 + 0.5, door.y as f32 + 0.5);
+        let length = ((dx - px).powi(2) + (dy - py).powi(2)).sqrt();
+        if length < TRACKER_DISTANCE * 2.0 {
+            return;
+        }
+        let dir = ((dx - px) / length, (dy - py) / length);
+        let tip = (px + dir.0 * TRACKER_DISTANCE, py + dir.1 * TRACKER_DISTANCE);
+        compass::draw_arrow(renderer, tip, dir, TRACKER_COLOR);
+    }
+}
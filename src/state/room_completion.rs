@@ -0,0 +1,113 @@
+use std::collections::HashSet;
+
+#[derive(Default, Clone, Copy)]
+struct RoomCompletion {
+    coins_total: u32,
+    enemies_total: u32,
+    secrets_total: u32,
+    coins_left: u32,
+    enemies_left: u32,
+    secrets_found: u32,
+}
+
+impl RoomCompletion {
+    fn total(&self) -> u32 {
+        self.coins_total + self.enemies_total + self.secrets_total
+    }
+
+    fn done(&self) -> u32 {
+        self.coins_total.saturating_sub(self.coins_left)
+            + self.enemies_total.saturating_sub(self.enemies_left)
+            + self.secrets_found.min(self.secrets_total)
+    }
+}
+
+// How much of every room the player has done: the coins placed in it collected, the enemies in
+// it defeated and its secrets found. A room stays complete once it gets there.
+#[derive(Clone)]
+pub struct CompletionTracker {
+    rooms: Vec<RoomCompletion>,
+    completed: HashSet<usize>,
+}
+
+impl CompletionTracker {
+    /// Takes what there is to do in each room at the start of the game
+    pub fn new(
+        coins_per_room: &[u32],
+        enemies_per_room: &[u32],
+        secrets_per_room: &[u32],
+    ) -> CompletionTracker {
+        let rooms = coins_per_room
+            .iter()
+            .zip(enemies_per_room)
+            .zip(secrets_per_room)
+            .map(|((&coins, &enemies), &secrets)| RoomCompletion {
+                coins_total: coins,
+                enemies_total: enemies,
+                secrets_total: secrets,
+                coins_left: coins,
+                enemies_left: enemies,
+                secrets_found: 0,
+            })
+            .collect();
+        CompletionTracker {
+            rooms,
+            completed: HashSet::new(),
+        }
+    }
+
+    /// Returns true when the room was just completed. Coins dropped after the start count too,
+    /// a room is only done when there is nothing left in it.
+    pub fn update_room(&mut self, room_index: usize, coins_left: u32, enemies_left: u32) -> bool {
+        let Some(room) = self.rooms.get_mut(room_index) else {
+            return false;
+        };
+        room.coins_left = coins_left;
+        room.enemies_left = enemies_left;
+
+        if room.total() > 0 && room.done() == room.total() && !self.completed.contains(&room_index)
+        {
+            self.completed.insert(room_index);
+            return true;
+        }
+        false
+    }
+
+    /// (done, total) of the room, None for rooms that had nothing to do in them
+    pub fn progress(&self, room_index: usize) -> Option<(u32, u32)> {
+        let room = self.rooms.get(room_index)?;
+        let total = room.total();
+        if total == 0 {
+            return None;
+        }
+        if self.completed.contains(&room_index) {
+            return Some((total, total));
+        }
+        Some((room.done(), total))
+    }
+
+    pub fn secret_found(&mut self, room_index: usize) {
+        if let Some(room) = self.rooms.get_mut(room_index) {
+            room.secrets_found += 1;
+        }
+    }
+
+    /// (found, total) of the secrets in all the rooms
+    pub fn secrets(&self) -> (u32, u32) {
+        self.rooms.iter().fold((0, 0), |(found, total), room| {
+            (
+                found + room.secrets_found.min(room.secrets_total),
+                total + room.secrets_total,
+            )
+        })
+    }
+
+    pub fn completed_rooms(&self) -> &HashSet<usize> {
+        &self.completed
+    }
+
+    /// Rooms that have something to complete in them
+    pub fn room_count(&self) -> usize {
+        self.rooms.iter().filter(|room| room.total() > 0).count()
+    }
+}
@@ -0,0 +1,87 @@
+use crate::state::common::BoundingBox;
+use crate::state::enemies::Enemy;
+use crate::state::item::Item;
+use crate::state::map_like::GameMap;
+use std::collections::BTreeMap;
+
+// Rooms this many doors away from the current room are kept loaded, so the next room is always
+// loaded before the player gets there
+const LOADED_ROOM_DISTANCE: u32 = 2;
+
+#[derive(Clone, Default)]
+struct UnloadedRoom {
+    enemies: Vec<Box<dyn Enemy>>,
+    items: Vec<Item>,
+}
+
+// The enemies and items of the rooms far from the player are taken out of the game and kept
+// here as they were, so that they are not updated, drawn or looked through. They are put back
+// once the player comes closer.
+#[derive(Clone, Default)]
+pub struct RoomStreamer {
+    // In room order so that the rooms are loaded back in the same order every time
+    unloaded: BTreeMap<usize, UnloadedRoom>,
+}
+
+impl RoomStreamer {
+    /// Call when the current room changes, unloads the rooms that are now too far and loads
+    /// back the ones that are close again
+    pub fn update(
+        &mut self,
+        map: &GameMap,
+        cur_room_index: usize,
+        enemies: &mut Vec<Box<dyn Enemy>>,
+        items: &mut Vec<Item>,
+    ) {
+        let distances = map.room_distances(cur_room_index);
+        let is_far = |room_index: usize| {
+            distances[room_index].is_none_or(|distance| distance > LOADED_ROOM_DISTANCE)
+        };
+        let far_room_of = |bb: &BoundingBox| {
+            let center = bb.center();
+            map.get_room_at(center.x, center.y)
+                .map(|(room_index, _)| room_index)
+                .filter(|&room_index| is_far(room_index))
+        };
+
+        let near: Vec<usize> = self
+            .unloaded
+            .keys()
+            .copied()
+            .filter(|&room_index| !is_far(room_index))
+            .collect();
+        for room_index in near {
+            let room = self.unloaded.remove(&room_index).unwrap();
+            enemies.extend(room.enemies);
+            items.extend(room.items);
+        }
+
+        // Things outside of the rooms stay loaded
+        for enemy in std::mem::take(enemies) {
+            match far_room_of(enemy.bb()) {
+                Some(room_index) => self
+                    .unloaded
+                    .entry(room_index)
+                    .or_default()
+                    .enemies
+                    .push(enemy),
+                None => enemies.push(enemy),
+            }
+        }
+        for item in std::mem::take(items) {
+            match far_room_of(item.bb()) {
+                Some(room_index) => self
+                    .unloaded
+                    .entry(room_index)
+                    .or_default()
+                    .items
+                    .push(item),
+                None => items.push(item),
+            }
+        }
+    }
+
+    pub fn unloaded_room_count(&self) -> usize {
+        self.unloaded.len()
+    }
+}
@@ -0,0 +1,177 @@
+use crate::state::item::ItemType;
+use crate::state::map_like::Room;
+use crate::telemetry::log;
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{AST, Engine, FuncArgs, Scope};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+// A script that runs longer than this is stopped, so a broken loop does not hang the game
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// What a script asked the game to do, done after the hook returns. Positions are in tiles
+/// from the top left corner of the room of the script.
+#[derive(Clone)]
+pub enum ScriptCommand {
+    SpawnItem { item_type: ItemType, x: f32, y: f32 },
+    OpenDoors,
+    LockDoors,
+    // The camera frames the spot for a moment, after what it was asked to frame before
+    FocusCamera { x: f32, y: f32 },
+}
+
+fn item_type_by_name(name: &str) -> Option<ItemType> {
+    Some(match name {
+        "coin" => ItemType::Coin,
+        "small_stone" => ItemType::SmallStone,
+        "large_stone" => ItemType::LargeStone,
+        "box" => ItemType::Box,
+        "vase" => ItemType::Vase,
+        "one_up" => ItemType::OneUp,
+        "heart" => ItemType::Heart,
+        "key" => ItemType::Key,
+        _ => return None,
+    })
+}
+
+fn new_engine(commands: &Arc<Mutex<Vec<ScriptCommand>>>) -> Engine {
+    let mut engine = Engine::new();
+    // The scripts can only use what is registered here, no files or other scripts
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+    engine.set_max_operations(MAX_OPERATIONS);
+
+    let queue = commands.clone();
+    let spawn_item = move |name: &str, x: f32, y: f32| match item_type_by_name(name) {
+        Some(item_type) => queue
+            .lock()
+            .unwrap()
+            .push(ScriptCommand::SpawnItem { item_type, x, y }),
+        None => log!("Script tried to spawn an unknown item {}", name),
+    };
+    // Both spawn_item("coin", 2, 3) and spawn_item("coin", 2.5, 3.0) work
+    let spawn_float = spawn_item.clone();
+    engine.register_fn("spawn_item", move |name: &str, x: f64, y: f64| {
+        spawn_float(name, x as f32, y as f32)
+    });
+    engine.register_fn("spawn_item", move |name: &str, x: i64, y: i64| {
+        spawn_item(name, x as f32, y as f32)
+    });
+    let queue = commands.clone();
+    engine.register_fn("open_doors", move || {
+        queue.lock().unwrap().push(ScriptCommand::OpenDoors)
+    });
+    let queue = commands.clone();
+    engine.register_fn("lock_doors", move || {
+        queue.lock().unwrap().push(ScriptCommand::LockDoors)
+    });
+    let queue = commands.clone();
+    let focus_float = move |x: f64, y: f64| {
+        queue.lock().unwrap().push(ScriptCommand::FocusCamera {
+            x: x as f32,
+            y: y as f32,
+        })
+    };
+    let focus_int = focus_float.clone();
+    engine.register_fn("focus_camera", focus_float);
+    engine.register_fn("focus_camera", move |x: i64, y: i64| {
+        focus_int(x as f64, y as f64)
+    });
+    engine.on_print(|text| log!("Script: {}", text));
+    engine
+}
+
+// Rooms can have a Rhai script next to their JSON file (rooms/room_0001.rhai next to
+// rooms/room_0001.json). The game calls the hooks the script defines:
+//   on_room_enter()           when the player enters the room
+//   on_enemy_killed(x, y)     when an enemy dies in the room, at the tile it died on
+// and the hooks can call spawn_item(name, x, y), open_doors(), lock_doors() and
+// focus_camera(x, y).
+#[derive(Clone)]
+pub struct RoomScripts {
+    engine: Arc<Engine>,
+    commands: Arc<Mutex<Vec<ScriptCommand>>>,
+    scripts: HashMap<usize, AST>,
+}
+
+impl RoomScripts {
+    /// Compiles the scripts of the rooms, the ones that do not compile are left out
+    pub fn new(rooms: &[Room]) -> Self {
+        let commands = Arc::new(Mutex::new(Vec::new()));
+        let engine = new_engine(&commands);
+        let mut scripts = HashMap::new();
+        for (room_index, room) in rooms.iter().enumerate() {
+            let Some(source) = &room.script else {
+                continue;
+            };
+            match engine.compile(source) {
+                Ok(ast) => {
+                    scripts.insert(room_index, ast);
+                }
+                Err(error) => log!("Script of room {} does not compile: {}", room_index, error),
+            }
+        }
+        RoomScripts {
+            engine: Arc::new(engine),
+            commands,
+            scripts,
+        }
+    }
+
+    /// Calls the hook of the room if it has one and returns what the script asked for
+    pub fn call(&self, room_index: usize, hook: &str, args: impl FuncArgs) -> Vec<ScriptCommand> {
+        let Some(ast) = self.scripts.get(&room_index) else {
+            return Vec::new();
+        };
+        if !ast.iter_functions().any(|function| function.name == hook) {
+            return Vec::new();
+        }
+        let result = self
+            .engine
+            .call_fn::<rhai::Dynamic>(&mut Scope::new(), ast, hook, args);
+        let commands = std::mem::take(&mut *self.commands.lock().unwrap());
+        if let Err(error) = result {
+            log!(
+                "Script of room {} failed in {}: {}",
+                room_index,
+                hook,
+                error
+            );
+        }
+        commands
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{BaseTile, OverlayTile};
+
+    #[test]
+    fn hooks_return_what_the_script_asked_for() {
+        let mut room = Room::new_empty(0, 0, 8, 8, BaseTile::Empty, OverlayTile::None);
+        room.script = Some(String::from(
+            "fn on_enemy_killed(x, y) { spawn_item(\"coin\", x, y - 1); open_doors(); }",
+        ));
+        let scripts = RoomScripts::new(&[room]);
+
+        let commands = scripts.call(0, "on_enemy_killed", (2.5_f64, 3.0_f64));
+        assert!(matches!(
+            commands.as_slice(),
+            [
+                ScriptCommand::SpawnItem {
+                    item_type: ItemType::Coin,
+                    x: 2.5,
+                    y: 2.0
+                },
+                ScriptCommand::OpenDoors
+            ]
+        ));
+        assert!(scripts.call(0, "on_room_enter", ()).is_empty());
+        assert!(
+            scripts
+                .call(1, "on_enemy_killed", (0.0_f64, 0.0_f64))
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use crate::state::common::Pos;
+use crate::state::game_rng::GameRng;
+use crate::state::item::{Item, ItemType};
+use rand::Rng;
+use rand::seq::IndexedRandom;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Ware {
+    Heart,
+    OneUp,
+}
+
+impl Ware {
+    pub fn item_type(self) -> ItemType {
+        match self {
+            Ware::Heart => ItemType::Heart,
+            Ware::OneUp => ItemType::OneUp,
+        }
+    }
+}
+
+#[derive(Clone, Deserialize)]
+struct WareConfig {
+    ware: Ware,
+    base_price: u32,
+    // Every one bought this run makes the next one this much (of the base price) pricier
+    demand_markup: f32,
+}
+
+#[derive(Clone, Deserialize)]
+struct ShopConfig {
+    // A shop of this kind has a sale with a chance of one in this many
+    sale_one_in: u32,
+    sale_discount: f32,
+    wares: Vec<WareConfig>,
+}
+
+// The kinds of shops and their prices, from assets/shops.json. The shops are named in the file
+// only to tell them apart.
+#[derive(Clone, Deserialize)]
+pub struct ShopData {
+    // Prices go up this much (of the base price) for every room visited
+    progress_markup_per_room: f32,
+    shops: Vec<ShopConfig>,
+}
+
+impl ShopData {
+    pub fn load_from_file() -> ShopData {
+        let s = fs::read_to_string(Path::new("assets/shops.json")).unwrap();
+        serde_json::from_str(&s)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            .unwrap()
+    }
+}
+
+/// A ware on display, the price is only decided when it is looked at
+#[derive(Clone, Copy, Debug)]
+pub struct ForSale {
+    shop: usize,
+    pub ware: Ware,
+}
+
+#[derive(Clone)]
+struct Shop {
+    config: usize,
+    on_sale: bool,
+}
+
+// The shops of a run and how many of each ware has been bought in them
+#[derive(Clone)]
+pub struct Shops {
+    data: ShopData,
+    shops: Vec<Shop>,
+    bought: HashMap<Ware, u32>,
+}
+
+impl Shops {
+    /// Opens a shop for every group of shop slots and stocks the slots with wares
+    pub fn open(data: ShopData, shop_slots: &[Vec<Pos>], rng: &mut GameRng) -> (Shops, Vec<Item>) {
+        let mut shops = Vec::new();
+        let mut items = Vec::new();
+        if !data.shops.is_empty() {
+            for slots in shop_slots {
+                let config = rng.random_range(0..data.shops.len());
+                let shop_config = &data.shops[config];
+                let on_sale = rng.random_range(0..shop_config.sale_one_in.max(1)) == 0;
+                let shop = shops.len();
+                shops.push(Shop { config, on_sale });
+
+                for pos in slots {
+                    let Some(ware) = shop_config.wares.choose(rng) else {
+                        continue;
+                    };
+                    let for_sale = ForSale {
+                        shop,
+                        ware: ware.ware,
+                    };
+                    items.push(Item::new_for_sale(pos.x, pos.y, for_sale));
+                }
+            }
+        }
+
+        let shops = Shops {
+            data,
+            shops,
+            bought: HashMap::new(),
+        };
+        (shops, items)
+    }
+
+    /// Price of the ware after visiting this many rooms
+    pub fn price(&self, for_sale: ForSale, rooms_visited: u32) -> u32 {
+        let shop = &self.shops[for_sale.shop];
+        let config = &self.data.shops[shop.config];
+        let Some(ware) = config.wares.iter().find(|ware| ware.ware == for_sale.ware) else {
+            return 0;
+        };
+
+        let bought = self.bought.get(&for_sale.ware).copied().unwrap_or(0);
+        let mut multiplier = 1.0
+            + self.data.progress_markup_per_room * rooms_visited as f32
+            + ware.demand_markup * bought as f32;
+        if shop.on_sale {
+            multiplier *= 1.0 - config.sale_discount;
+        }
+        ((ware.base_price as f32 * multiplier).round() as u32).max(1)
+    }
+
+    pub fn is_on_sale(&self, for_sale: ForSale) -> bool {
+        self.shops[for_sale.shop].on_sale
+    }
+
+    pub fn mark_bought(&mut self, ware: Ware) {
+        *self.bought.entry(ware).or_insert(0) += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_shops(on_sale: bool) -> Shops {
+        Shops {
+            data: ShopData {
+                progress_markup_per_room: 0.1,
+                shops: vec![ShopConfig {
+                    sale_one_in: 1,
+                    sale_discount: 0.5,
+                    wares: vec![WareConfig {
+                        ware: Ware::Heart,
+                        base_price: 10,
+                        demand_markup: 0.5,
+                    }],
+                }],
+            },
+            shops: vec![Shop { config: 0, on_sale }],
+            bought: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn prices_go_up_with_progress_and_demand() {
+        let heart = ForSale {
+            shop: 0,
+            ware: Ware::Heart,
+        };
+        let mut shops = test_shops(false);
+        assert_eq!(shops.price(heart, 0), 10);
+        assert_eq!(shops.price(heart, 5), 15);
+        shops.mark_bought(Ware::Heart);
+        shops.mark_bought(Ware::Heart);
+        assert_eq!(shops.price(heart, 5), 25);
+
+        assert_eq!(test_shops(true).price(heart, 0), 5);
+    }
+}
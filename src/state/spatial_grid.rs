@@ -0,0 +1,74 @@
+use crate::state::common::Pos;
+use std::collections::HashMap;
+
+// Buckets entity indexes by position so that "everything near this point" queries do not need
+// to go through all the entities. Rebuilt every frame as the entities move.
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<(usize, Pos)>>,
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> Self {
+        SpatialGrid {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+
+    fn cell_of(&self, x: f32, y: f32) -> (i32, i32) {
+        (
+            (x / self.cell_size).floor() as i32,
+            (y / self.cell_size).floor() as i32,
+        )
+    }
+
+    pub fn insert(&mut self, index: usize, pos: Pos) {
+        let cell = self.cell_of(pos.x, pos.y);
+        self.cells.entry(cell).or_default().push((index, pos));
+    }
+
+    // Indexes of everything within the radius of the point, in no particular order
+    pub fn query_radius(&self, pos: Pos, radius: f32) -> Vec<usize> {
+        let (min_x, min_y) = self.cell_of(pos.x - radius, pos.y - radius);
+        let (max_x, max_y) = self.cell_of(pos.x + radius, pos.y + radius);
+
+        let mut result = Vec::new();
+        for cell_x in min_x..=max_x {
+            for cell_y in min_y..=max_y {
+                let Some(cell) = self.cells.get(&(cell_x, cell_y)) else {
+                    continue;
+                };
+                for (index, other) in cell {
+                    let dx = other.x - pos.x;
+                    let dy = other.y - pos.y;
+                    if dx * dx + dy * dy <= radius * radius {
+                        result.push(*index);
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_radius_finds_only_close_entries() {
+        let mut grid = SpatialGrid::new(4.0);
+        grid.insert(0, Pos::new(0.5, 0.5));
+        grid.insert(1, Pos::new(3.9, 0.5));
+        grid.insert(2, Pos::new(4.1, 0.5));
+        grid.insert(3, Pos::new(-5.0, -5.0));
+
+        let mut found = grid.query_radius(Pos::new(2.0, 0.5), 2.15);
+        found.sort();
+        assert_eq!(found, vec![0, 1, 2]);
+
+        let found = grid.query_radius(Pos::new(-4.0, -4.0), 1.5);
+        assert_eq!(found, vec![3]);
+    }
+}
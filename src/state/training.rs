@@ -0,0 +1,214 @@
+use crate::camera::Camera;
+use crate::pixel_font;
+use crate::render::Renderer;
+use crate::state::common::{BoundingBox, Pos};
+use crate::state::enemies::Enemy;
+use crate::state::map_like::{GameMap, ObjectTemplate, ObjectTemplateType};
+use std::collections::VecDeque;
+
+// The switches of a training room spawn these in order from left to right, starting over when
+// there are more switches
+const TRAINING_ENEMIES: &[(&str, ObjectTemplateType)] = &[
+    ("Bat", ObjectTemplateType::Bat),
+    ("Slime", ObjectTemplateType::Slime),
+    ("Worm", ObjectTemplateType::Worm),
+    ("Burrower", ObjectTemplateType::Burrower),
+    ("Bat flock", ObjectTemplateType::BatFlock),
+    ("Shield bearer", ObjectTemplateType::ShieldBearer),
+    ("Archer", ObjectTemplateType::Archer),
+    ("Boss", ObjectTemplateType::Boss),
+    ("Wisp", ObjectTemplateType::Wisp),
+];
+// The damage per second is counted over this many game updates
+const DPS_WINDOW_FRAMES: usize = 300;
+// The enemy of a switch appears this far above it
+const SPAWN_HEIGHT: f32 = 3.0;
+
+const SWITCH_W: f32 = 12.0 / 16.0;
+const PLATE_H: f32 = 3.0 / 16.0;
+const BASE_COLOR: [f32; 4] = [0.4, 0.38, 0.36, 1.0];
+const PLATE_COLOR: [f32; 4] = [0.85, 0.25, 0.2, 1.0];
+const PRESSED_COLOR: [f32; 4] = [0.45, 0.15, 0.12, 1.0];
+const TITLE_COLOR: [f32; 4] = [0.98, 0.8, 0.2, 1.0];
+const TEXT_COLOR: [f32; 4] = [0.95, 0.95, 0.95, 1.0];
+
+/// The switch placed at the spot, the spot is the middle of the tile it is on the floor of
+pub fn switch_bb(pos: Pos) -> BoundingBox {
+    BoundingBox {
+        x: pos.x - SWITCH_W / 2.0,
+        y: pos.y - 0.5,
+        w: SWITCH_W,
+        h: 1.0,
+        vx: 0.0,
+        vy: 0.0,
+    }
+}
+
+/// A pressure plate on the floor, pressed down while stood on
+pub fn draw_switch(renderer: &mut Renderer, pos: Pos, pressed: bool) {
+    let bb = switch_bb(pos);
+    let pixel = 1.0 / 16.0;
+    let floor = bb.y + bb.h;
+    renderer.draw_rect(bb.x, floor - pixel * 2.0, bb.w, pixel * 2.0, BASE_COLOR);
+    let (plate_h, color) = if pressed {
+        (pixel, PRESSED_COLOR)
+    } else {
+        (PLATE_H, PLATE_COLOR)
+    };
+    renderer.draw_rect(
+        bb.x + pixel,
+        floor - pixel * 2.0 - plate_h,
+        bb.w - pixel * 2.0,
+        plate_h,
+        color,
+    );
+}
+
+#[derive(Clone)]
+struct Switch {
+    pos: Pos,
+    room_index: usize,
+    // Index in to TRAINING_ENEMIES
+    enemy: usize,
+    pressed: bool,
+}
+
+// The training rooms, rooms with training switches in them. Stepping on a switch spawns its
+// enemy, while the player is in the room a panel shows the damage they deal and take.
+#[derive(Clone, Default)]
+pub struct Training {
+    switches: Vec<Switch>,
+    // The training room the player is in
+    room_index: Option<usize>,
+    // Damage dealt on each of the last game updates, newest last
+    recent_damage: VecDeque<u32>,
+    damage_dealt: u32,
+    damage_taken: u32,
+    last_spawned: Option<usize>,
+}
+
+impl Training {
+    pub fn new(map: &GameMap) -> Self {
+        let mut switches = Vec::new();
+        for (room_index, room) in map.rooms.iter().enumerate() {
+            let mut positions = room.get_training_switches();
+            positions.sort_by(|a, b| a.x.total_cmp(&b.x));
+            switches.extend(positions.into_iter().enumerate().map(|(i, pos)| Switch {
+                pos,
+                room_index,
+                enemy: i % TRAINING_ENEMIES.len(),
+                pressed: false,
+            }));
+        }
+        Training {
+            switches,
+            ..Training::default()
+        }
+    }
+
+    pub fn is_training_room(&self, room_index: usize) -> bool {
+        self.switches
+            .iter()
+            .any(|switch| switch.room_index == room_index)
+    }
+
+    /// Call once per game update with the damage the player dealt with their hits and took on the
+    /// update. Returns the enemies the switches spawned.
+    pub fn update(
+        &mut self,
+        player_bb: &BoundingBox,
+        cur_room_index: Option<usize>,
+        damage_dealt: u32,
+        damage_taken: u32,
+    ) -> Vec<Box<dyn Enemy>> {
+        let room_index = cur_room_index.filter(|&index| self.is_training_room(index));
+        if room_index != self.room_index {
+            // The stats start over every time the room is entered
+            *self = Training {
+                switches: std::mem::take(&mut self.switches),
+                room_index,
+                ..Training::default()
+            };
+        }
+        let Some(room_index) = room_index else {
+            return Vec::new();
+        };
+
+        self.damage_dealt += damage_dealt;
+        self.damage_taken += damage_taken;
+        self.recent_damage.push_back(damage_dealt);
+        if self.recent_damage.len() > DPS_WINDOW_FRAMES {
+            self.recent_damage.pop_front();
+        }
+
+        let mut spawned: Vec<Box<dyn Enemy>> = Vec::new();
+        for switch in &mut self.switches {
+            if switch.room_index != room_index {
+                continue;
+            }
+            let stood_on = player_bb.overlaps(&switch_bb(switch.pos));
+            if stood_on && !switch.pressed {
+                let object_type = TRAINING_ENEMIES[switch.enemy].1.clone();
+                let (x, y) = (switch.pos.x, switch.pos.y - SPAWN_HEIGHT);
+                let bb = ObjectTemplate::new(x, y, object_type.clone()).get_bb();
+                let enemy =
+                    ObjectTemplate::new(x - bb.w / 2.0, y - bb.h / 2.0, object_type).as_object();
+                self.last_spawned = Some(switch.enemy);
+                spawned.push(enemy);
+            }
+            switch.pressed = stood_on;
+        }
+        spawned
+    }
+
+    pub fn draw(&self, renderer: &mut Renderer) {
+        for switch in &self.switches {
+            draw_switch(renderer, switch.pos, switch.pressed);
+        }
+    }
+
+    /// The readout panel, shown while the player is in a training room
+    pub fn draw_hud(&self, renderer: &mut Renderer, camera: &Camera) {
+        if self.room_index.is_none() {
+            return;
+        }
+        let seconds = self.recent_damage.len().max(1) as f32 / 60.0;
+        let dps = self.recent_damage.iter().sum::<u32>() as f32 / seconds;
+        let mut lines = vec![
+            format!("DPS {:.1}", dps),
+            format!("Damage dealt {}", self.damage_dealt),
+            format!("Damage taken {}", self.damage_taken),
+        ];
+        if let Some(enemy) = self.last_spawned {
+            lines.push(format!("Spawned {}", TRAINING_ENEMIES[enemy].0));
+        }
+        let title = "Training";
+
+        let pixel_size = 2.0;
+        let padding = 8.0;
+        let line_height = (pixel_font::GLYPH_HEIGHT as f32 + 3.0) * pixel_size;
+        let width = lines
+            .iter()
+            .map(|line| pixel_font::text_width(line))
+            .chain([pixel_font::text_width(title)])
+            .max()
+            .unwrap_or(0) as f32
+            * pixel_size
+            + padding * 2.0;
+        let height = line_height * (lines.len() + 1) as f32 + padding * 2.0 - 3.0 * pixel_size;
+        let x = camera.screen_w - width - padding;
+        let y = camera.screen_h * 0.3;
+
+        renderer.draw_rect_hud(x, y, width, height, [0.05, 0.05, 0.08, 0.75]);
+        renderer.draw_text_hud(title, x + padding, y + padding, pixel_size, TITLE_COLOR);
+        for (i, line) in lines.iter().enumerate() {
+            renderer.draw_text_hud(
+                line,
+                x + padding,
+                y + padding + line_height * (i + 1) as f32,
+                pixel_size,
+                TEXT_COLOR,
+            );
+        }
+    }
+}
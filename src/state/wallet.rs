@@ -0,0 +1,137 @@
+use crate::camera::Camera;
+use crate::pixel_font;
+use crate::render::Renderer;
+use crate::state::common::Pos;
+
+const FLY_FRAMES: u32 = 30;
+// How long the counter stays bigger after a coin lands in it
+const POP_FRAMES: u32 = 10;
+// How fast the counter ticks down when coins are spent
+const SPEND_FRAMES_PER_COIN: u32 = 2;
+
+const COIN_SIZE: f32 = 15.0;
+const PIXEL_SIZE: f32 = 3.0;
+// Below the health bar and the lives
+const COUNTER_X_FROM_RIGHT: f32 = 210.0;
+const COUNTER_Y: f32 = 54.0;
+
+const COIN_COLOR: [f32; 4] = [0.98, 0.8, 0.2, 1.0];
+const COIN_EDGE_COLOR: [f32; 4] = [0.55, 0.36, 0.08, 1.0];
+
+#[derive(Clone)]
+struct FlyingCoin {
+    // Where the coin was picked up, in tiles
+    from: Pos,
+    frame: u32,
+}
+
+// The coins of the player. Picked up coins fly to the counter on the HUD and are only added to
+// the balance once they get there.
+#[derive(Default, Clone)]
+pub struct Wallet {
+    balance: u32,
+    // Lags behind the balance when coins are spent so that the counter ticks down
+    displayed: u32,
+    spend_frames: u32,
+    pop_frames: u32,
+    flying: Vec<FlyingCoin>,
+}
+
+impl Wallet {
+    pub fn collect(&mut self, from: Pos) {
+        self.flying.push(FlyingCoin { from, frame: 0 });
+    }
+
+    /// Returns how many coins reached the counter this frame
+    pub fn update(&mut self) -> u32 {
+        self.pop_frames = self.pop_frames.saturating_sub(1);
+
+        for coin in self.flying.iter_mut() {
+            coin.frame += 1;
+        }
+        let before = self.flying.len();
+        self.flying.retain(|coin| coin.frame < FLY_FRAMES);
+        let landed = (before - self.flying.len()) as u32;
+        if landed > 0 {
+            self.balance += landed;
+            self.displayed += landed;
+            self.pop_frames = POP_FRAMES;
+        }
+
+        if self.displayed > self.balance {
+            self.spend_frames += 1;
+            if self.spend_frames >= SPEND_FRAMES_PER_COIN {
+                self.spend_frames = 0;
+                self.displayed -= 1;
+            }
+        }
+
+        landed
+    }
+
+    /// Returns false if there are not enough coins
+    pub fn spend(&mut self, amount: u32) -> bool {
+        if amount > self.balance {
+            return false;
+        }
+        self.balance -= amount;
+        true
+    }
+
+    /// Straight to the balance without flying to the counter
+    pub fn add(&mut self, amount: u32) {
+        self.balance += amount;
+        self.displayed += amount;
+    }
+
+    pub fn balance(&self) -> u32 {
+        self.balance
+    }
+
+    fn counter_pos(camera: &Camera) -> (f32, f32) {
+        (camera.screen_w - COUNTER_X_FROM_RIGHT, COUNTER_Y)
+    }
+
+    fn draw_coin(renderer: &mut Renderer, x: f32, y: f32, size: f32) {
+        renderer.draw_rect_hud(x, y, size, size, COIN_EDGE_COLOR);
+        let edge = (size / 6.0).max(1.0);
+        renderer.draw_rect_hud(
+            x + edge,
+            y + edge,
+            size - edge * 2.0,
+            size - edge * 2.0,
+            COIN_COLOR,
+        );
+    }
+
+    pub fn draw(&self, renderer: &mut Renderer, camera: &Camera) {
+        let (x, y) = Self::counter_pos(camera);
+
+        let pop = if self.pop_frames > 0 {
+            2.0 * self.pop_frames as f32 / POP_FRAMES as f32
+        } else {
+            0.0
+        };
+        Self::draw_coin(renderer, x - pop, y - pop, COIN_SIZE + pop * 2.0);
+        let text = format!("{}", self.displayed);
+        renderer.draw_text_hud(
+            &text,
+            x + COIN_SIZE + 6.0,
+            y + (COIN_SIZE - pixel_font::GLYPH_HEIGHT as f32 * PIXEL_SIZE) / 2.0,
+            PIXEL_SIZE,
+            [0.95, 0.92, 0.85, 1.0],
+        );
+
+        // Coins on their way speed up towards the counter and shrink from their size in the world
+        let world_size = 0.5 * 16.0 * camera.zoom;
+        for coin in &self.flying {
+            let t = coin.frame as f32 / FLY_FRAMES as f32;
+            let eased = t * t;
+            let (from_x, from_y) = camera.tile_to_screen(coin.from.x, coin.from.y);
+            let size = world_size + (COIN_SIZE - world_size) * eased;
+            let coin_x = from_x + (x + COIN_SIZE / 2.0 - from_x) * eased - size / 2.0;
+            let coin_y = from_y + (y + COIN_SIZE / 2.0 - from_y) * eased - size / 2.0;
+            Self::draw_coin(renderer, coin_x, coin_y, size);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::sync::{Mutex, MutexGuard, TryLockError};
+
+// Written next to the executable when the game is closed or panics, playtesters attach it to
+// their bug reports
+const SUMMARY_FILE: &str = "session_summary.txt";
+const LOG_LINES: usize = 100;
+
+// Prints the line and keeps it for the session summary
+macro_rules! log {
+    ($($arg:tt)*) => {{
+        let line = format!($($arg)*);
+        println!("{}", line);
+        $crate::telemetry::record_log(line);
+    }};
+}
+pub(crate) use log;
+
+struct Session {
+    frames: u64,
+    max_frame_time: f64,
+    // High-water marks of what there has been in the game at once
+    max_enemies: usize,
+    max_items: usize,
+    max_particles: usize,
+    max_rooms_visited: usize,
+    log: VecDeque<String>,
+    // Assets that could not be loaded and were replaced, shown on the screen as well
+    asset_warnings: Vec<String>,
+}
+
+static SESSION: Mutex<Session> = Mutex::new(Session {
+    frames: 0,
+    max_frame_time: 0.0,
+    max_enemies: 0,
+    max_items: 0,
+    max_particles: 0,
+    max_rooms_visited: 0,
+    log: VecDeque::new(),
+    asset_warnings: Vec::new(),
+});
+
+// A panic while the session was locked does not stop the rest from being recorded
+fn session() -> MutexGuard<'static, Session> {
+    SESSION
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+pub fn record_log(line: String) {
+    let mut session = session();
+    if session.log.len() == LOG_LINES {
+        session.log.pop_front();
+    }
+    session.log.push_back(line);
+}
+
+/// Call when an asset could not be loaded and a fallback is used instead
+pub fn record_asset_warning(warning: String) {
+    log!("{}", warning);
+    session().asset_warnings.push(warning);
+}
+
+pub fn asset_warnings() -> Vec<String> {
+    session().asset_warnings.clone()
+}
+
+/// Call once per frame with how long the frame took in seconds
+pub fn record_frame(frame_time: f64) {
+    let mut session = session();
+    session.frames += 1;
+    session.max_frame_time = session.max_frame_time.max(frame_time);
+}
+
+pub fn record_game(enemies: usize, items: usize, particles: usize, rooms_visited: usize) {
+    let mut session = session();
+    session.max_enemies = session.max_enemies.max(enemies);
+    session.max_items = session.max_items.max(items);
+    session.max_particles = session.max_particles.max(particles);
+    session.max_rooms_visited = session.max_rooms_visited.max(rooms_visited);
+}
+
+/// Writes the summary file, reason tells how the session ended
+pub fn write_summary(reason: &str) {
+    // The panic hook can run while the panicking thread holds the lock, waiting would hang
+    let session = match SESSION.try_lock() {
+        Ok(session) => session,
+        Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+        Err(TryLockError::WouldBlock) => return,
+    };
+
+    let mut summary = String::new();
+    let _ = writeln!(summary, "Session ended: {}", reason);
+    let _ = writeln!(summary, "Frames: {}", session.frames);
+    let _ = writeln!(
+        summary,
+        "Longest frame: {:.1} ms",
+        session.max_frame_time * 1000.0
+    );
+    let _ = writeln!(summary, "Most enemies: {}", session.max_enemies);
+    let _ = writeln!(summary, "Most items: {}", session.max_items);
+    let _ = writeln!(summary, "Most particles: {}", session.max_particles);
+    let _ = writeln!(summary, "Rooms visited: {}", session.max_rooms_visited);
+    for warning in &session.asset_warnings {
+        let _ = writeln!(summary, "Asset warning: {}", warning);
+    }
+    let _ = writeln!(summary, "\nLast {} log lines:", session.log.len());
+    for line in &session.log {
+        let _ = writeln!(summary, "{}", line);
+    }
+
+    match std::fs::write(SUMMARY_FILE, summary) {
+        Ok(()) => println!("Session summary written to {}", SUMMARY_FILE),
+        Err(error) => eprintln!("Could not write {}: {}", SUMMARY_FILE, error),
+    }
+}
+
+/// Writes the summary when the game panics, before the usual panic message
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        write_summary(&format!("panic, {}", info));
+        default_hook(info);
+    }));
+}
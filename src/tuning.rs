@@ -0,0 +1,86 @@
+use crate::telemetry::log;
+use serde::{Deserialize, Serialize};
+use std::sync::{RwLock, RwLockWriteGuard};
+use std::{fs, io};
+
+// Written by the debug window and read when the game starts, next to the executable
+const TUNING_FILE: &str = "tuning.json";
+
+// The physics and player feel values that can be changed from the debug window while playing.
+// The defaults are the tuned values, the ones in the tuning file replace them.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tuning {
+    pub gravity: f32,
+    pub terminal_velocity: f32,
+    // Upwards speed of the player while the jump key is held
+    pub jump_velocity: f32,
+    // How close to a wall the player has to be to grab its ledge
+    pub hang_epsilon: f32,
+    // Ledges are only grabbed while the grab key is held, so that falling next to a wall is not
+    // stopped by a ledge
+    pub hang_needs_grab_key: bool,
+    pub slime_idle_frames: u32,
+    pub slime_alerted_idle_frames: u32,
+    pub slime_jump_velocity: f32,
+    pub slime_jump_speed: f32,
+}
+
+impl Tuning {
+    const DEFAULT: Tuning = Tuning {
+        gravity: 0.0070,
+        terminal_velocity: 0.90,
+        jump_velocity: 0.125,
+        hang_epsilon: 0.0003,
+        hang_needs_grab_key: false,
+        slime_idle_frames: 60 * 5,
+        slime_alerted_idle_frames: 40,
+        slime_jump_velocity: 0.2,
+        slime_jump_speed: 0.06,
+    };
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Tuning::DEFAULT
+    }
+}
+
+static TUNING: RwLock<Tuning> = RwLock::new(Tuning::DEFAULT);
+
+/// The current values, cheap enough to call wherever they are used
+pub fn tuning() -> Tuning {
+    *TUNING
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// For the debug window to change the values in place
+pub fn tuning_mut() -> RwLockWriteGuard<'static, Tuning> {
+    TUNING
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Reads the tuning file if there is one, call before the game starts
+pub fn load() {
+    let Ok(json) = fs::read_to_string(TUNING_FILE) else {
+        return;
+    };
+    match serde_json::from_str(&json) {
+        Ok(tuning) => {
+            *tuning_mut() = tuning;
+            log!("Tuning read from {}", TUNING_FILE);
+        }
+        Err(error) => log!("Could not read {}: {}", TUNING_FILE, error),
+    }
+}
+
+pub fn save() -> io::Result<()> {
+    let json = serde_json::to_string_pretty(&tuning()).map_err(io::Error::other)?;
+    fs::write(TUNING_FILE, json)
+}
+
+pub fn reset() {
+    *tuning_mut() = Tuning::DEFAULT;
+}